@@ -31,14 +31,28 @@
 //!
 
 #[cfg(test)]
-mod test;
-
-use crate::common::{engines, PreBytes, RawKey, RawValue};
+pub(crate) mod test;
+
+use crate::common::{
+    engines, trash, AccessStats, BackendError, CacheAdapter, CompactionStats, PreBytes,
+    QuotaGroup, RawKey, RawValue, SizeLimits, Snapshot, TrashId, WriteError,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use ruc::*;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, ops::RangeBounds};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    ops::{Bound, RangeBounds},
+    result::Result as StdResult,
+    sync::Arc,
+    time::Duration,
+};
 
 pub type MapxRawIter<'a> = engines::MapxIter<'a>;
 pub type MapxRawIterMut<'a> = engines::MapxIterMut<'a>;
+pub type MapxRawKeys<'a> = engines::MapxKeys<'a>;
+pub type MapxRawSnapshotIter<'a> = engines::MapxSnapshotIter<'a>;
 pub type ValueMut<'a> = engines::ValueMut<'a>;
 pub type ValueIterMut<'a> = engines::ValueIterMut<'a>;
 
@@ -72,19 +86,47 @@ impl MapxRaw {
         self.inner.get(key.as_ref())
     }
 
+    /// Like `get`, but return an error instead of panicking
+    /// if the backend still fails after the retry policy is exhausted.
+    #[inline(always)]
+    pub fn try_get(
+        &self,
+        key: impl AsRef<[u8]>,
+    ) -> StdResult<Option<RawValue>, BackendError> {
+        self.inner.try_get(key.as_ref())
+    }
+
+    /// Read the value as of the time when the snapshot was taken,
+    /// check `vsdb_snapshot` for details.
+    #[inline(always)]
+    pub fn get_with_snapshot(
+        &self,
+        snapshot: &Snapshot,
+        key: impl AsRef<[u8]>,
+    ) -> Option<RawValue> {
+        self.inner.get_with_snapshot(snapshot, key.as_ref())
+    }
+
+    /// `None` if the instance is sealed, the guard could never be written back.
+    ///
+    /// The value is written back on dropping the guard only if it has been changed.
     #[inline(always)]
     pub fn get_mut(&mut self, key: impl AsRef<[u8]>) -> Option<ValueMut<'_>> {
         self.inner.get_mut(key.as_ref())
     }
 
     #[inline(always)]
-    pub fn mock_value_mut(&mut self, key: RawValue, value: RawValue) -> ValueMut {
+    pub fn mock_value_mut(&mut self, key: RawValue, value: RawValue) -> ValueMut<'_> {
         self.inner.mock_value_mut(key, value)
     }
 
+    /// Check the existence of a key, the value is not copied out.
+    ///
+    /// NOTE: the engines still read the value, e.g. the data block of rocksdb,
+    /// or the whole value on parity-db, so it is not cheaper than `get` on disk.
     #[inline(always)]
     pub fn contains_key(&self, key: impl AsRef<[u8]>) -> bool {
-        self.get(key.as_ref()).is_some()
+        self.inner.contains_key(key.as_ref())
     }
 
     #[inline(always)]
@@ -97,6 +139,20 @@ impl MapxRaw {
         self.range(Cow::Borrowed(key.as_ref())..).next()
     }
 
+    #[inline(always)]
+    pub fn get_lt(&self, key: impl AsRef<[u8]>) -> Option<(RawKey, RawValue)> {
+        self.range(..Cow::Borrowed(key.as_ref())).next_back()
+    }
+
+    #[inline(always)]
+    pub fn get_gt(&self, key: impl AsRef<[u8]>) -> Option<(RawKey, RawValue)> {
+        self.range((
+            Bound::Excluded(Cow::Borrowed(key.as_ref())),
+            Bound::Unbounded,
+        ))
+        .next()
+    }
+
     #[inline(always)]
     pub fn len(&self) -> usize {
         self.inner.len()
@@ -107,23 +163,113 @@ impl MapxRaw {
         self.inner.is_empty()
     }
 
+    /// An offset kept next to the len hint for the owner of the instance,
+    /// e.g. the index of the first element of a vector; 0 if never set.
+    ///
+    /// It is reset by `clear`, and copied by `clone_deep_to_new_instance`.
+    #[inline(always)]
+    pub fn base_offset(&self) -> u64 {
+        self.inner.base_offset()
+    }
+
+    /// Check `base_offset` for details,
+    /// it is rejected like the writes of the entries.
+    #[inline(always)]
+    pub fn set_base_offset(&mut self, offset: u64) {
+        self.inner.set_base_offset(offset);
+    }
+
     #[inline(always)]
     pub fn entry<'a>(&'a mut self, key: &'a [u8]) -> Entry<'a> {
         Entry { key, hdr: self }
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> MapxRawIter {
+    pub fn iter(&self) -> MapxRawIter<'_> {
         self.inner.iter()
     }
 
     #[inline(always)]
-    pub fn range<'a, R: RangeBounds<Cow<'a, [u8]>>>(&'a self, bounds: R) -> MapxRawIter {
+    pub fn range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        bounds: R,
+    ) -> MapxRawIter<'a> {
         self.inner.range(bounds)
     }
 
+    /// Iterate over the entries as of the time when the snapshot was taken.
+    ///
+    /// NOTE: the writes from other threads are blocked until it is dropped.
+    #[inline(always)]
+    pub fn iter_with_snapshot(&self, snapshot: &Snapshot) -> MapxRawSnapshotIter<'_> {
+        self.inner.range_with_snapshot(snapshot, ..)
+    }
+
+    /// Like `iter_with_snapshot`, but within the given range.
+    #[inline(always)]
+    pub fn range_with_snapshot<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        snapshot: &Snapshot,
+        bounds: R,
+    ) -> MapxRawSnapshotIter<'a> {
+        self.inner.range_with_snapshot(snapshot, bounds)
+    }
+
+    /// Iterate over the keys only, the values are not copied out.
+    ///
+    /// NOTE: on rocksdb the values are not read either,
+    /// but parity-db has no key-only iterators, it still reads them.
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxRawIterMut {
+    pub fn keys(&self) -> MapxRawKeys<'_> {
+        self.inner.keys()
+    }
+
+    /// Like `keys`, but within the given range.
+    #[inline(always)]
+    pub fn range_keys<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        bounds: R,
+    ) -> MapxRawKeys<'a> {
+        self.inner.range_keys(bounds)
+    }
+
+    /// Pick about `n` distinct keys at random, in ascending order,
+    /// the same `seed` gives the same sample if the data has not been changed.
+    ///
+    /// Each key is found by seeking to a random position between the first
+    /// and the last keys, so no full scans are needed. The sample is uniform
+    /// if the keys spread evenly in the key space, e.g. hashes or fixed-size integers,
+    /// otherwise it is biased toward the keys right after the sparse areas.
+    pub fn sample_keys(&self, n: usize, seed: u64) -> Vec<RawKey> {
+        if self.len() <= n {
+            return self.keys().collect();
+        }
+
+        let (Some(first), Some(last)) = (self.keys().next(), self.keys().next_back())
+        else {
+            return vec![];
+        };
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut sample = BTreeSet::new();
+
+        // the duplicate hits are retried for a limited number of times
+        for _ in 0..n.saturating_mul(4) {
+            if n == sample.len() {
+                break;
+            }
+            let k = random_key_between(&first, &last, &mut rng);
+            if let Some(k) = self.range_keys(Cow::Owned(k)..).next() {
+                sample.insert(k);
+            }
+        }
+
+        sample.into_iter().collect()
+    }
+
+    /// Empty if the instance is sealed, check `get_mut` for details.
+    #[inline(always)]
+    pub fn iter_mut(&mut self) -> MapxRawIterMut<'_> {
         self.inner.iter_mut()
     }
 
@@ -131,7 +277,7 @@ impl MapxRaw {
     pub fn range_mut<'a, R: RangeBounds<Cow<'a, [u8]>>>(
         &'a mut self,
         bounds: R,
-    ) -> MapxRawIterMut {
+    ) -> MapxRawIterMut<'a> {
         self.inner.range_mut(bounds)
     }
 
@@ -149,16 +295,192 @@ impl MapxRaw {
         self.inner.insert(key.as_ref(), value.as_ref())
     }
 
+    /// Like `insert`, but return an error instead of panicking
+    /// if the key or the value exceeds the size limits,
+    /// or the database is opened in read-only mode,
+    /// or the backend still fails after the retry policy is exhausted.
+    #[inline(always)]
+    pub fn try_insert(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        self.inner.try_insert(key.as_ref(), value.as_ref())
+    }
+
+    /// Like `try_insert`, but return `WriteError::WouldBlock` instead of waiting,
+    /// if the write is blocked by a snapshot being taken or exported,
+    /// by a locked quota group, by another write updating the length hints
+    /// of the same area, or by the write stalls of rocksdb.
+    ///
+    /// Useful for latency-critical paths that can retry later or elsewhere,
+    /// nothing is written if `WriteError::WouldBlock` is returned.
+    ///
+    /// NOTE: it is not wait-free,
+    /// - The failed backend operations are not retried, they panic at once
+    /// - Parity-db can not report its stalls,
+    ///   the commit waits if the commit queue of it is full
+    /// - The bound cache adapter is called as usual, it should not block
+    /// - The registries of the per-instance configs, e.g. the cache bindings,
+    ///   are still read-locked, they only wait for a concurrent bind or unbind
+    /// - The access statistics are skipped if they are being updated
+    #[inline(always)]
+    pub fn insert_nowait(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        self.inner.insert_nowait(key.as_ref(), value.as_ref())
+    }
+
+    #[inline(always)]
+    pub fn size_limits(&self) -> SizeLimits {
+        self.inner.size_limits()
+    }
+
+    /// Set the max sizes of keys and values of this handler,
+    /// writes beyond the limits will be rejected.
+    ///
+    /// NOTE: the limits are not persisted, they must be set again after decoding.
+    #[inline(always)]
+    pub fn set_size_limits(&mut self, limits: SizeLimits) {
+        self.inner.set_size_limits(limits);
+    }
+
     #[inline(always)]
     pub fn remove(&mut self, key: impl AsRef<[u8]>) -> Option<RawValue> {
         self.inner.remove(key.as_ref())
     }
 
+    /// Like `remove`, but return an error instead of panicking
+    /// if the database is opened in read-only mode,
+    /// or the backend still fails after the retry policy is exhausted.
+    #[inline(always)]
+    pub fn try_remove(
+        &mut self,
+        key: impl AsRef<[u8]>,
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        self.inner.try_remove(key.as_ref())
+    }
+
+    /// Like `try_remove`, but return `WriteError::WouldBlock` instead of waiting,
+    /// check `insert_nowait` for details.
+    #[inline(always)]
+    pub fn remove_nowait(
+        &mut self,
+        key: impl AsRef<[u8]>,
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        self.inner.remove_nowait(key.as_ref())
+    }
+
+    /// Remove all entries within the range,
+    /// in one batch if the backend supports it.
+    #[inline(always)]
+    pub fn remove_range<'a, R: RangeBounds<Cow<'a, [u8]>>>(&mut self, bounds: R) {
+        self.inner.remove_range(bounds);
+    }
+
+    /// Like `remove_range`, but return an error instead of panicking or blocking,
+    /// if the database is opened in read-only mode, is frozen, or the instance is sealed.
+    #[inline(always)]
+    pub fn try_remove_range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &mut self,
+        bounds: R,
+    ) -> StdResult<(), WriteError> {
+        self.inner.try_remove_range(bounds)
+    }
+
+    /// Apply the writes in one batch of the backend,
+    /// the `None` values remove the keys,
+    /// the last one wins if a key is written more than once.
+    #[inline(always)]
+    pub fn write_batch<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        ops: impl IntoIterator<Item = (K, Option<V>)>,
+    ) {
+        let ops = ops.into_iter().collect::<Vec<_>>();
+        self.inner.write_batch(&batch_ops(&ops));
+    }
+
+    /// Like `write_batch`, but return an error instead of panicking,
+    /// nothing is written if any of the writes is rejected.
+    #[inline(always)]
+    pub fn try_write_batch<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        ops: impl IntoIterator<Item = (K, Option<V>)>,
+    ) -> StdResult<(), WriteError> {
+        let ops = ops.into_iter().collect::<Vec<_>>();
+        self.inner.try_write_batch(&batch_ops(&ops))
+    }
+
+    /// Keep only the entries for which `f` returns `true`,
+    /// adjacent entries to be dropped are removed as a range.
+    #[inline(always)]
+    pub fn retain(&mut self, f: impl FnMut(&[u8], &[u8]) -> bool) {
+        self.inner.retain(f);
+    }
+
+    /// Remove all the entries, the instance itself is kept.
+    ///
+    /// NOTE:
+    /// - On rocksdb, it is one range deletion and nothing is read
+    /// - On parity-db, every key is deleted in batches, and the remaining entries
+    ///   are still visible to the concurrent readers until the last batch
+    /// - If there are alive snapshots, all the entries are copied into them
     #[inline(always)]
     pub fn clear(&mut self) {
         self.inner.clear();
     }
 
+    /// Like `clear`, but return an error instead of panicking or blocking,
+    /// check `try_remove_range` for details.
+    #[inline(always)]
+    pub fn try_clear(&mut self) -> StdResult<(), WriteError> {
+        self.inner.try_clear()
+    }
+
+    /// Join a quota group, the existing data will be counted in,
+    /// and the later inserts exceeding the quota will be rejected.
+    ///
+    /// An instance can only belong to one group,
+    /// it leaves the previous group first if there is one.
+    #[inline(always)]
+    pub fn join_quota_group(&self, group: &QuotaGroup) {
+        self.inner.join_quota_group(group)
+    }
+
+    #[inline(always)]
+    pub fn leave_quota_group(&self) {
+        self.inner.leave_quota_group()
+    }
+
+    #[inline(always)]
+    pub fn quota_group(&self) -> Option<QuotaGroup> {
+        self.inner.quota_group()
+    }
+
+    /// Bind an external cache, `get` will try it before the database,
+    /// and the writes will update or invalidate the cached entries.
+    ///
+    /// The iterators always read from the database.
+    #[inline(always)]
+    pub fn bind_cache(&self, adapter: Arc<dyn CacheAdapter>) {
+        self.inner.bind_cache(adapter)
+    }
+
+    #[inline(always)]
+    pub fn unbind_cache(&self) {
+        self.inner.unbind_cache()
+    }
+
+    /// Move the instance into the trash instead of deleting its data,
+    /// it can be restored by `vsdb_trash_restore` within the `retention` period,
+    /// and will be purged after that.
+    #[inline(always)]
+    pub fn soft_destroy(self, retention: Duration) -> Result<TrashId> {
+        trash::put(self, retention).c(d!())
+    }
+
     /// # Safety
     ///
     /// Do not use this API unless you know the internal details extremely well.
@@ -191,6 +513,167 @@ impl MapxRaw {
     pub fn is_the_same_instance(&self, other_hdr: &Self) -> bool {
         self.inner.is_the_same_instance(&other_hdr.inner)
     }
+
+    /// Copy all the entries into a new instance by the bulk writes of the backend,
+    /// it is much faster than inserting them one by one.
+    ///
+    /// The size limits are inherited,
+    /// but the quota group and the cache binding are not.
+    #[inline(always)]
+    pub fn clone_deep_to_new_instance(&self) -> Result<Self> {
+        self.inner
+            .clone_deep()
+            .map(|inner| Self { inner })
+            .map_err(|e| eg!(e))
+    }
+
+    /// Load pre-sorted entries after the existing ones by the bulk ingestion
+    /// of the backend, e.g. the SST ingestion of rocksdb,
+    /// it is designed for the initial loading of huge datasets,
+    /// an interrupted loading can be resumed from the last loaded key.
+    ///
+    /// Return the number of the loaded entries.
+    ///
+    /// NOTE:
+    /// - The keys must be strictly ascending and bigger than the existing ones,
+    ///   an error will be returned otherwise
+    /// - The entries that have been loaded before an error are kept
+    #[inline(always)]
+    pub fn bulk_load_sorted(
+        &mut self,
+        entries: impl IntoIterator<Item = (RawKey, RawValue)>,
+    ) -> Result<u64> {
+        self.inner.bulk_load_sorted(entries).c(d!())
+    }
+
+    /// A digest of all the entries, it does not depend on the order of the writes.
+    ///
+    /// It is computed by a full scan on every call,
+    /// unless the instance has opted in by `enable_digest`.
+    ///
+    /// NOTE: it is not a cryptographic hash, do not rely on it against malicious data.
+    #[inline(always)]
+    pub fn content_digest(&self) -> u64 {
+        self.inner.content_digest()
+    }
+
+    /// Maintain the digest incrementally by the writes,
+    /// so instances holding the same entries can be compared without full scans.
+    ///
+    /// Every write then pays one more read and write of the metadata,
+    /// so it is off by default. The opt-in is persisted,
+    /// and it is not inherited by `clone_deep_to_new_instance`.
+    ///
+    /// NOTE: after range removals, the digest is rebuilt by one scan on the next call.
+    #[inline(always)]
+    pub fn enable_digest(&self) -> StdResult<(), WriteError> {
+        self.inner.enable_digest()
+    }
+
+    /// Stop maintaining the digest, check `enable_digest` for details.
+    #[inline(always)]
+    pub fn disable_digest(&self) -> StdResult<(), WriteError> {
+        self.inner.disable_digest()
+    }
+
+    #[inline(always)]
+    pub fn is_digest_enabled(&self) -> bool {
+        self.inner.is_digest_enabled()
+    }
+
+    /// Compact the range of this instance on disk, e.g. the range compaction of rocksdb,
+    /// instead of the whole engine, useful for the instances that dominate the disk usage.
+    ///
+    /// Return the sizes of this instance, and the sizes on disk before and after it.
+    ///
+    /// NOTE:
+    /// - The sizes on disk are of the whole area that holds this instance
+    /// - Nothing will be compacted by the engines that reclaim the space in place,
+    ///   e.g. parity-db, the sizes on disk are `None` then
+    /// - The writes from other threads are not blocked,
+    ///   they may be counted in the returned sizes
+    /// - It fails if the database is frozen, the files must not be rewritten then
+    #[inline(always)]
+    pub fn compact(&self) -> StdResult<CompactionStats, WriteError> {
+        self.inner.compact()
+    }
+
+    /// Seal an archival instance, all of its entries are dumped into a flat file,
+    /// and the point reads(`get` and `contains_key`) are served by mapping the file into memory,
+    /// so they will not churn the block caches of the backend.
+    ///
+    /// All writes will be rejected with `WriteError::Sealed` until `unseal` is called.
+    ///
+    /// NOTE:
+    /// - The seal is persisted, the instance is still sealed after restarting
+    /// - The iterators are still served by the backend
+    /// - Sealing an instance that has been sealed does nothing
+    #[inline(always)]
+    pub fn seal(&self) -> Result<()> {
+        self.inner.seal().c(d!())
+    }
+
+    /// Remove the flat file, the instance will be writable again.
+    #[inline(always)]
+    pub fn unseal(&self) -> Result<()> {
+        self.inner.unseal().c(d!())
+    }
+
+    #[inline(always)]
+    pub fn is_sealed(&self) -> bool {
+        self.inner.is_sealed()
+    }
+
+    /// Count the reads and writes of this instance, for deciding
+    /// whether to bind a cache, build an index, etc. by the real workload.
+    ///
+    /// The keys are grouped into key ranges by their first `range_len` bytes,
+    /// the counters saved before will be resumed if `range_len` is not changed.
+    ///
+    /// NOTE: the counters are saved by `vsdb_flush`,
+    /// but the tracking itself must be started again after restarting.
+    #[inline(always)]
+    pub fn track_access(&self, range_len: usize) {
+        self.inner.track_access(range_len)
+    }
+
+    /// Stop counting and remove the saved counters.
+    #[inline(always)]
+    pub fn untrack_access(&self) {
+        self.inner.untrack_access()
+    }
+
+    /// `None` if this instance is not tracked.
+    #[inline(always)]
+    pub fn access_stats(&self) -> Option<AccessStats> {
+        self.inner.access_stats()
+    }
+}
+
+// Keep the last write of each key, in the order of the keys
+fn batch_ops<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+    ops: &[(K, Option<V>)],
+) -> Vec<(&[u8], Option<&[u8]>)> {
+    ops.iter()
+        .map(|(k, v)| (k.as_ref(), v.as_ref().map(|v| v.as_ref())))
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .collect()
+}
+
+// Interpolate the bytes after the common prefix of `lo` and `hi`,
+// only the first 16 of them are considered
+fn random_key_between(lo: &[u8], hi: &[u8], rng: &mut impl Rng) -> RawKey {
+    let common = lo.iter().zip(hi).take_while(|(a, b)| a == b).count();
+    let to_int = |s: &[u8]| {
+        let mut buf = [0u8; 16];
+        buf.iter_mut().zip(&s[common..]).for_each(|(b, x)| *b = *x);
+        u128::from_be_bytes(buf)
+    };
+
+    let mut k = lo[..common].to_vec();
+    k.extend_from_slice(&rng.gen_range(to_int(lo)..=to_int(hi)).to_be_bytes());
+    k
 }
 
 impl Default for MapxRaw {
@@ -199,6 +682,9 @@ impl Default for MapxRaw {
     }
 }
 
+// Used as the values of the other collections without `serde`
+vsdb_ende::impl_value_ende_by_bytes!(MapxRaw);
+
 pub struct Entry<'a> {
     key: &'a [u8],
     hdr: &'a mut MapxRaw,
@@ -2,6 +2,23 @@ use super::*;
 use ruc::*;
 use std::mem::size_of;
 
+pub(crate) fn to_u64(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(<[u8; size_of::<u64>()]>::try_from(bytes).unwrap())
+}
+
+pub(crate) fn to_bytes(i: u64) -> [u8; size_of::<u64>()] {
+    i.to_be_bytes()
+}
+
+// A new instance filled with `i => i` for each `i` in `0..n`
+pub(crate) fn filled(n: u64) -> MapxRaw {
+    let mut hdr = MapxRaw::new();
+    (0..n).for_each(|i| {
+        hdr.insert(to_bytes(i), to_bytes(i));
+    });
+    hdr
+}
+
 #[test]
 fn test_insert() {
     let mut hdr = MapxRaw::new();
@@ -16,6 +33,7 @@ fn test_insert() {
             assert_eq!(&pnk!(hdr.get(&key))[..], &value[..]);
             assert_eq!(&pnk!(hdr.remove(&key))[..], &value[..]);
             assert!(hdr.get(&key).is_none());
+            assert!(!hdr.contains_key(&key));
             assert!(hdr.insert(&key, &value).is_none());
         });
     hdr.clear();
@@ -43,13 +61,7 @@ fn test_len() {
 
 #[test]
 fn test_iter() {
-    let mut hdr = MapxRaw::new();
-    let max = 100;
-    (0..max)
-        .map(|i: u64| (to_bytes(i), to_bytes(i)))
-        .for_each(|(key, value)| {
-            assert!(hdr.insert(&key, &value).is_none());
-        });
+    let mut hdr = filled(100);
 
     hdr.iter_mut().for_each(|(k, mut v)| {
         *v = to_bytes(to_u64(&v) + 1).to_vec().into();
@@ -70,13 +82,8 @@ fn test_iter() {
 
 #[test]
 fn test_first_last() {
-    let mut hdr = MapxRaw::new();
     let max = 100;
-    (0..max)
-        .map(|i: u64| (to_bytes(i), to_bytes(i)))
-        .for_each(|(key, value)| {
-            assert!(hdr.insert(&key, &value).is_none());
-        });
+    let hdr = filled(max);
     let (_, value) = pnk!(hdr.iter().next());
     let val = to_u64(&value);
     assert_eq!(0, val);
@@ -86,10 +93,360 @@ fn test_first_last() {
     assert_eq!(max - 1, val);
 }
 
-fn to_u64(bytes: &[u8]) -> u64 {
-    u64::from_be_bytes(<[u8; size_of::<u64>()]>::try_from(bytes).unwrap())
+#[test]
+fn test_size_limits() {
+    let mut hdr = MapxRaw::new();
+    hdr.set_size_limits(SizeLimits {
+        max_key_len: Some(2),
+        max_value_len: Some(4),
+    });
+
+    let rejected = crate::vsdb_get_rejected_write_cnt();
+
+    assert!(pnk!(hdr.try_insert([1, 2], [0; 4])).is_none());
+    assert_eq!(
+        hdr.try_insert([1, 2, 3], [0]),
+        Err(WriteError::KeyTooLarge { len: 3, limit: 2 })
+    );
+    assert_eq!(
+        hdr.try_insert([1], [0; 5]),
+        Err(WriteError::ValueTooLarge { len: 5, limit: 4 })
+    );
+    assert!(crate::vsdb_get_rejected_write_cnt() >= rejected + 2);
+    assert_eq!(1, hdr.len());
+
+    // grown past the limits by a guard, the value is discarded on dropping
+    let mut v = pnk!(hdr.get_mut([1, 2]));
+    v.push(0);
+    assert_eq!(
+        v.commit(),
+        Err(WriteError::ValueTooLarge { len: 5, limit: 4 })
+    );
+    drop(v);
+    let mut it = hdr.iter_mut();
+    let (_, mut v) = pnk!(it.next());
+    v.push(0);
+    drop(v);
+    drop(it);
+    assert_eq!(Some(vec![0; 4]), hdr.get([1, 2]));
+    assert!(crate::vsdb_get_rejected_write_cnt() >= rejected + 5);
+
+    let mut shadow = unsafe { hdr.shadow() };
+    assert_eq!(hdr.size_limits(), shadow.size_limits());
+    assert!(shadow.try_insert([1, 2, 3], [0]).is_err());
+
+    hdr.set_size_limits(SizeLimits::default());
+    assert!(pnk!(hdr.try_insert([1, 2, 3], [0; 5])).is_none());
+    assert_eq!(2, hdr.len());
 }
 
-fn to_bytes(i: u64) -> [u8; size_of::<u64>()] {
-    i.to_be_bytes()
+#[test]
+fn test_remove_range_retain() {
+    let mut hdr = filled(100);
+
+    hdr.remove_range(
+        Cow::Owned(to_bytes(10).to_vec())..Cow::Owned(to_bytes(20).to_vec()),
+    );
+    assert_eq!(90, hdr.len());
+    assert!(!hdr.contains_key(to_bytes(10)));
+    assert!(hdr.contains_key(to_bytes(20)));
+
+    hdr.retain(|k, _| to_u64(k) >= 50);
+    assert_eq!(50, hdr.len());
+    assert_eq!(50, to_u64(&hdr.iter().next().unwrap().0));
+}
+
+#[test]
+fn test_write_batch() {
+    use crate::{Quota, QuotaGroup, QuotaUsage};
+
+    // `a` is written one by one, `b` in batches
+    let mut a = MapxRaw::new();
+    let mut b = MapxRaw::new();
+    pnk!(a.enable_digest());
+    pnk!(b.enable_digest());
+
+    (0..10u64).for_each(|i| {
+        a.insert(to_bytes(i), to_bytes(i));
+    });
+    b.write_batch((0..10u64).map(|i| (to_bytes(i), Some(to_bytes(i)))));
+    assert_eq!(10, b.len());
+    assert_eq!(a.content_digest(), b.content_digest());
+
+    // the last write of a key wins
+    (0..5u64).for_each(|i| {
+        a.remove(to_bytes(i));
+    });
+    a.insert(to_bytes(0), to_bytes(100));
+    b.write_batch(
+        [(to_bytes(0), Some(to_bytes(1)))]
+            .into_iter()
+            .chain((0..5u64).map(|i| (to_bytes(i), None)))
+            .chain([(to_bytes(0), Some(to_bytes(100)))]),
+    );
+    assert_eq!(6, b.len());
+    assert_eq!(Some(to_bytes(100).to_vec()), b.get(to_bytes(0)));
+    assert!(b.get(to_bytes(1)).is_none());
+    assert_eq!(a.content_digest(), b.content_digest());
+
+    // nothing is written if any of the writes is rejected
+    b.set_size_limits(SizeLimits {
+        max_key_len: Some(8),
+        max_value_len: None,
+    });
+    assert_eq!(
+        b.try_write_batch([(vec![1], Some(vec![1])), (vec![0; 9], Some(vec![]))]),
+        Err(WriteError::KeyTooLarge { len: 9, limit: 8 })
+    );
+    assert!(b.get([1]).is_none());
+
+    let group = QuotaGroup::new(Quota {
+        max_keys: Some(7),
+        max_bytes: None,
+    });
+    b.join_quota_group(&group);
+    assert_eq!(
+        b.try_write_batch([(to_bytes(100), Some([])), (to_bytes(101), Some([]))]),
+        Err(WriteError::KeyQuotaExceeded { limit: 7 })
+    );
+    assert_eq!(6, group.usage().keys);
+    pnk!(b.try_write_batch([(to_bytes(100), Some([])), (to_bytes(5), None)]));
+    assert_eq!(QuotaUsage { keys: 6, bytes: 88 }, group.usage());
+    assert_eq!(6, b.len());
+    b.leave_quota_group();
+}
+
+#[test]
+fn test_clear_keep_instance() {
+    let mut hdr = filled(100);
+
+    let reloaded = unsafe { MapxRaw::from_bytes(hdr.as_bytes()) };
+    hdr.clear();
+    assert!(reloaded.is_empty());
+    assert!(reloaded.iter().next().is_none());
+
+    hdr.insert(to_bytes(1), to_bytes(1));
+    assert_eq!(1, reloaded.len());
+    assert!(reloaded.is_the_same_instance(&hdr));
+}
+
+#[test]
+fn test_clear_in_batches() {
+    let mut hdr = filled(10_000);
+    let mut other = MapxRaw::new();
+    other.insert(to_bytes(0), to_bytes(0));
+
+    hdr.clear();
+    assert!(hdr.iter().next().is_none());
+    assert!(hdr.get(to_bytes(9999)).is_none());
+
+    // the neighbouring instances are untouched
+    assert_eq!(1, other.iter().count());
+}
+
+#[test]
+fn test_keys() {
+    let mut hdr = filled(100);
+
+    let keys = hdr.keys().map(|k| to_u64(&k)).collect::<Vec<_>>();
+    assert_eq!(keys, (0..100).collect::<Vec<_>>());
+    assert_eq!(99, to_u64(&pnk!(hdr.keys().next_back())));
+
+    let keys = hdr
+        .range_keys(
+            Cow::Owned(to_bytes(10).to_vec())..=Cow::Owned(to_bytes(20).to_vec()),
+        )
+        .map(|k| to_u64(&k))
+        .collect::<Vec<_>>();
+    assert_eq!(keys, (10..=20).collect::<Vec<_>>());
+    assert_eq!(
+        20,
+        to_u64(&pnk!(hdr
+            .range_keys(..Cow::Owned(to_bytes(21).to_vec()))
+            .next_back()))
+    );
+}
+
+#[test]
+fn test_clone_deep() {
+    let mut hdr = filled(10_000);
+    hdr.set_size_limits(SizeLimits {
+        max_key_len: Some(8),
+        max_value_len: None,
+    });
+
+    let mut new_hdr = pnk!(hdr.clone_deep_to_new_instance());
+    assert!(!new_hdr.is_the_same_instance(&hdr));
+    assert_eq!(hdr.len(), new_hdr.len());
+    assert_eq!(hdr.size_limits(), new_hdr.size_limits());
+    assert_eq!(hdr.content_digest(), new_hdr.content_digest());
+    assert!(hdr.iter().eq(new_hdr.iter()));
+
+    // independent of each other
+    new_hdr.remove(to_bytes(0));
+    assert!(hdr.contains_key(to_bytes(0)));
+    hdr.clear();
+    assert_eq!(9999, new_hdr.len());
+}
+
+#[test]
+fn test_sample_keys() {
+    let mut hdr = MapxRaw::new();
+    assert!(hdr.sample_keys(10, 0).is_empty());
+
+    (0..10u64).for_each(|i| {
+        hdr.insert(to_bytes(i), to_bytes(i));
+    });
+    assert_eq!(10, hdr.sample_keys(100, 0).len());
+
+    (10..10_000u64).for_each(|i| {
+        hdr.insert(to_bytes(i * 1000), to_bytes(i));
+    });
+
+    let sample = hdr.sample_keys(100, 7);
+    assert!(90 < sample.len() && sample.len() <= 100);
+    assert!(sample.windows(2).all(|w| w[0] < w[1]));
+    assert!(sample.iter().all(|k| hdr.contains_key(k)));
+    assert_eq!(sample, hdr.sample_keys(100, 7));
+    assert_ne!(sample, hdr.sample_keys(100, 8));
+
+    // roughly uniform over the evenly spread keys
+    let upper_half = sample.iter().filter(|k| to_u64(k) >= 5_000_000).count();
+    assert!((sample.len() / 4..sample.len() * 3 / 4).contains(&upper_half));
+}
+
+#[test]
+fn test_bulk_load_sorted() {
+    let entry = |i: u64| (to_bytes(i).to_vec(), to_bytes(i).to_vec());
+
+    let mut hdr = MapxRaw::new();
+    let n = pnk!(hdr.bulk_load_sorted((0..10_000u64).map(entry)));
+    assert_eq!(10_000, n);
+    assert_eq!(10_000, hdr.len());
+    assert!(hdr.iter().eq((0..10_000u64).map(entry)));
+
+    // the digest is rebuilt from the loaded entries
+    let other = filled(10_000);
+    assert_eq!(other.content_digest(), hdr.content_digest());
+
+    // appended after the existing keys
+    assert_eq!(1, pnk!(hdr.bulk_load_sorted([entry(10_000)])));
+    assert_eq!(10_001, hdr.len());
+    assert_eq!(
+        Some(to_bytes(10_000u64).to_vec()),
+        hdr.get(to_bytes(10_000u64))
+    );
+
+    // not after the existing keys
+    assert!(hdr.bulk_load_sorted([entry(0)]).is_err());
+    assert!(hdr.bulk_load_sorted([entry(10_000)]).is_err());
+    assert_eq!(10_001, hdr.len());
+
+    // not sorted, the entries before the error are kept
+    let mut hdr = MapxRaw::new();
+    let entries = [2u64, 3, 1].map(entry);
+    assert!(hdr.bulk_load_sorted(entries).is_err());
+    assert_eq!(2, hdr.len());
+    assert!(hdr.get(to_bytes(1)).is_none());
+}
+
+#[test]
+fn test_compact() {
+    let mut hdr = filled(100);
+    hdr.remove_range(Cow::Owned(to_bytes(50).to_vec())..);
+
+    let stats = pnk!(hdr.compact());
+    assert_eq!(50, stats.len);
+    assert_eq!(50 * 2 * size_of::<u64>() as u64, stats.data_bytes);
+    assert_eq!(50, hdr.len());
+
+    // parity-db reclaims the space in place
+    #[cfg(feature = "parity_backend")]
+    assert_eq!(
+        (None, None),
+        (stats.disk_bytes_before, stats.disk_bytes_after)
+    );
+    #[cfg(feature = "rocks_backend")]
+    assert!(stats.disk_bytes_before.is_some() && stats.disk_bytes_after.is_some());
+}
+
+#[test]
+fn test_nowait() {
+    use crate::vsdb_snapshot;
+    use std::{sync::mpsc, thread};
+
+    let mut hdr = MapxRaw::new();
+    hdr.insert([1], [1]);
+
+    let snapshot = vsdb_snapshot();
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel();
+
+    thread::scope(|s| {
+        let snapshot = &snapshot;
+        s.spawn(move || {
+            let _g = snapshot.lock();
+            locked_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+        locked_rx.recv().unwrap();
+
+        assert_eq!(Err(WriteError::WouldBlock), hdr.insert_nowait([2], [2]));
+        assert_eq!(Err(WriteError::WouldBlock), hdr.remove_nowait([1]));
+        assert!(hdr.get([2]).is_none());
+        assert_eq!(1, hdr.len());
+
+        release_tx.send(()).unwrap();
+    });
+
+    assert!(pnk!(hdr.insert_nowait([2], [2])).is_none());
+    assert_eq!(Some(vec![1]), pnk!(hdr.remove_nowait([1])));
+    assert_eq!(Some(vec![1]), hdr.get_with_snapshot(&snapshot, [1]));
+    assert!(hdr.get_with_snapshot(&snapshot, [2]).is_none());
+    drop(snapshot);
+
+    // the length hints of the area are being updated by another write
+    let lk = &engines::LEN_LK[0];
+    let lks = (0..engines::LEN_LK.len())
+        .map(|i| engines::LEN_LK[i].lock())
+        .collect::<Vec<_>>();
+    assert_eq!(Err(WriteError::WouldBlock), hdr.insert_nowait([3], [3]));
+    assert_eq!(Err(WriteError::WouldBlock), hdr.remove_nowait([2]));
+    drop(lks);
+
+    pnk!(hdr.enable_digest());
+    assert!(pnk!(hdr.insert_nowait([3], [3])).is_none());
+    assert_eq!(2, hdr.len());
+    assert_eq!(Some(vec![3]), pnk!(hdr.try_get([3])));
+
+    let mut other = MapxRaw::new();
+    other.insert([2], [2]);
+    other.insert([3], [3]);
+    assert_eq!(other.content_digest(), hdr.content_digest());
+}
+
+#[test]
+fn test_budgeted_iter() {
+    use std::time::Instant;
+
+    let mut hdr = filled(100);
+    let entry_size = 2 * size_of::<u64>() as u64;
+
+    let mut it = hdr.iter().take_bytes(10 * entry_size + 1);
+    assert_eq!(10, it.by_ref().count());
+    assert!(it.is_truncated());
+    assert!(it.next().is_none());
+
+    let it = hdr
+        .range(Cow::Owned(to_bytes(90).to_vec())..)
+        .take_bytes(100 * entry_size);
+    assert_eq!(10, it.count());
+
+    let mut it = hdr.iter().deadline(Instant::now());
+    assert!(it.next().is_none());
+    assert!(it.is_truncated());
+
+    let mut it = hdr.iter();
+    assert_eq!(100, it.by_ref().count());
+    assert!(!it.is_truncated());
 }
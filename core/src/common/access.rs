@@ -0,0 +1,273 @@
+//!
+//! # Access statistics
+//!
+//! The reads and writes of the tracked instances are counted,
+//! and grouped by the leading bytes of the keys to find out the hot key ranges.
+//!
+//! NOTE:
+//! - Like the cache bindings, the tracking must be started again after restarting,
+//!   the saved counters will be resumed then
+//! - The counters live in memory, they are saved by `vsdb_flush`
+//! - The non-blocking writes, e.g. `insert_nowait`, are not counted
+//!   if the counters are being updated by another thread
+//!
+
+use crate::{
+    basic::mapx_raw::MapxRaw,
+    common::{registry::Registry, Pre, PreBytes, RawKey, INTERNAL_ID_BASE},
+};
+use parking_lot::Mutex;
+use std::{cmp::Reverse, collections::BTreeMap, mem::size_of, sync::LazyLock};
+
+// An internal ID, it will never be allocated to normal instances
+const ACCESS_REGISTRY_ID: Pre = INTERNAL_ID_BASE + 1;
+
+// The max number of the distinct key ranges of an instance,
+// the accesses beyond it are counted in `AccessStats::others`
+const RANGE_CAP: usize = 1024;
+
+/// The accesses of a key range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RangeAccess {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// The accesses of an instance since it was tracked.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessStats {
+    /// The number of the leading bytes of the keys that identify a key range.
+    pub range_len: usize,
+    pub reads: u64,
+    pub writes: u64,
+    /// The number of the created iterators.
+    pub scans: u64,
+    /// The leading bytes of the keys => the accesses of them.
+    pub ranges: BTreeMap<RawKey, RangeAccess>,
+    /// The accesses of the key ranges that do not fit into `ranges`.
+    pub others: RangeAccess,
+}
+
+impl AccessStats {
+    fn new(range_len: usize) -> Self {
+        Self {
+            range_len,
+            ..Default::default()
+        }
+    }
+
+    /// The ratio of the reads in all the accesses, `None` if never accessed.
+    pub fn read_ratio(&self) -> Option<f64> {
+        let total = self.reads + self.writes;
+        (0 < total).then(|| self.reads as f64 / total as f64)
+    }
+
+    /// The `n` key ranges that have been accessed the most times,
+    /// in the descending order of the accesses.
+    pub fn hot_ranges(&self, n: usize) -> Vec<(RawKey, RangeAccess)> {
+        let mut ranges = self
+            .ranges
+            .iter()
+            .map(|(r, a)| (r.clone(), *a))
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|(_, a)| Reverse(a.reads + a.writes));
+        ranges.truncate(n);
+        ranges
+    }
+
+    fn record(&mut self, access: Access, key: Option<&[u8]>) {
+        match access {
+            Access::Read => self.reads += 1,
+            Access::Write => self.writes += 1,
+            Access::Scan => {
+                self.scans += 1;
+                return;
+            }
+        }
+
+        let Some(key) = key else {
+            return;
+        };
+        let range = &key[..key.len().min(self.range_len)];
+        let a = if let Some(a) = self.ranges.get_mut(range) {
+            a
+        } else if self.ranges.len() < RANGE_CAP {
+            self.ranges.entry(range.to_vec()).or_default()
+        } else {
+            &mut self.others
+        };
+        match access {
+            Access::Read => a.reads += 1,
+            _ => a.writes += 1,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut ret = vec![];
+        ret.extend_from_slice(&(self.range_len as u32).to_be_bytes());
+        [
+            self.reads,
+            self.writes,
+            self.scans,
+            self.others.reads,
+            self.others.writes,
+        ]
+        .iter()
+        .for_each(|n| ret.extend_from_slice(&n.to_be_bytes()));
+        self.ranges.iter().for_each(|(r, a)| {
+            ret.extend_from_slice(&(r.len() as u32).to_be_bytes());
+            ret.extend_from_slice(r);
+            ret.extend_from_slice(&a.reads.to_be_bytes());
+            ret.extend_from_slice(&a.writes.to_be_bytes());
+        });
+        ret
+    }
+
+    fn decode(mut bytes: &[u8]) -> Option<Self> {
+        let mut ret = Self::new(take_u32(&mut bytes)? as usize);
+        ret.reads = take_u64(&mut bytes)?;
+        ret.writes = take_u64(&mut bytes)?;
+        ret.scans = take_u64(&mut bytes)?;
+        ret.others.reads = take_u64(&mut bytes)?;
+        ret.others.writes = take_u64(&mut bytes)?;
+        while !bytes.is_empty() {
+            let len = take_u32(&mut bytes)? as usize;
+            let range = take(&mut bytes, len)?.to_vec();
+            let reads = take_u64(&mut bytes)?;
+            let writes = take_u64(&mut bytes)?;
+            ret.ranges.insert(range, RangeAccess { reads, writes });
+        }
+        Some(ret)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+    Scan,
+}
+
+// instance prefix => the encoded stats, saved by `vsdb_flush`
+static REGISTRY: LazyLock<Mutex<MapxRaw>> = LazyLock::new(|| {
+    Mutex::new(unsafe { MapxRaw::from_prefix_slice(ACCESS_REGISTRY_ID.to_be_bytes()) })
+});
+
+// instance prefix => the stats in memory
+static TRACKED: LazyLock<Registry<Mutex<AccessStats>>> =
+    LazyLock::new(Registry::default);
+
+#[inline(always)]
+pub(crate) fn record(prefix: PreBytes, access: Access, key: Option<&[u8]>) {
+    TRACKED.with(prefix, |s| s.lock().record(access, key));
+}
+
+// Like `record`, but skip the counting instead of waiting on the stats
+#[inline(always)]
+pub(crate) fn try_record(prefix: PreBytes, access: Access, key: Option<&[u8]>) {
+    TRACKED.with(prefix, |s| {
+        if let Some(mut s) = s.try_lock() {
+            s.record(access, key);
+        }
+    });
+}
+
+// The saved counters are resumed if they have the same `range_len`
+pub(crate) fn track(prefix: PreBytes, range_len: usize) {
+    let saved = REGISTRY
+        .lock()
+        .get(prefix)
+        .and_then(|s| AccessStats::decode(&s))
+        .filter(|s| s.range_len == range_len);
+    let stats = saved.unwrap_or_else(|| AccessStats::new(range_len));
+    TRACKED.insert(prefix, Mutex::new(stats));
+}
+
+// The saved counters are also removed if writable
+pub(crate) fn untrack(prefix: PreBytes) {
+    TRACKED.remove(prefix);
+
+    // read-only or frozen, the stale counters are only resumed if tracked again
+    let _ = REGISTRY.lock().try_remove(prefix);
+}
+
+#[inline(always)]
+pub(crate) fn stats_of(prefix: PreBytes) -> Option<AccessStats> {
+    TRACKED.with(prefix, |s| s.lock().clone())
+}
+
+// Skipped if read-only or frozen, the counters are kept in memory until the next call
+pub(crate) fn save() {
+    // the lock is released before writing, the writes will look up the tracked instances
+    let mut encoded = vec![];
+    TRACKED.for_each(|prefix, s| encoded.push((prefix, s.lock().encode())));
+
+    let mut registry = REGISTRY.lock();
+    for (prefix, s) in encoded {
+        if registry.try_insert(prefix, s).is_err() {
+            return;
+        }
+    }
+}
+
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if bytes.len() < n {
+        return None;
+    }
+    let (ret, rest) = bytes.split_at(n);
+    *bytes = rest;
+    Some(ret)
+}
+
+fn take_u32(bytes: &mut &[u8]) -> Option<u32> {
+    take(bytes, size_of::<u32>()).map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+}
+
+fn take_u64(bytes: &mut &[u8]) -> Option<u64> {
+    take(bytes, size_of::<u64>()).map(|b| u64::from_be_bytes(b.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::basic::mapx_raw::MapxRaw;
+    use ruc::*;
+
+    #[test]
+    fn test_access_stats() {
+        let mut hdr = MapxRaw::new();
+        assert!(hdr.access_stats().is_none());
+
+        hdr.track_access(1);
+        (0..10u8).for_each(|i| {
+            hdr.insert([i % 2, i], [i]);
+        });
+        (0..30u8).for_each(|i| {
+            hdr.get([0, i]);
+        });
+        hdr.iter().for_each(|_| {});
+        hdr.remove([1, 1]);
+
+        let stats = pnk!(hdr.access_stats());
+        assert_eq!(30, stats.reads);
+        assert_eq!(11, stats.writes);
+        assert_eq!(1, stats.scans);
+        assert_eq!(Some(30.0 / 41.0), stats.read_ratio());
+
+        let hot = stats.hot_ranges(1);
+        assert_eq!(1, hot.len());
+        assert_eq!(vec![0], hot[0].0);
+        assert_eq!(30, hot[0].1.reads);
+        assert_eq!(5, hot[0].1.writes);
+
+        // the saved counters are resumed
+        crate::vsdb_flush();
+        hdr.track_access(1);
+        assert_eq!(Some(stats), hdr.access_stats());
+
+        hdr.untrack_access();
+        assert!(hdr.access_stats().is_none());
+        hdr.track_access(1);
+        assert_eq!(0, pnk!(hdr.access_stats()).reads);
+        hdr.untrack_access();
+    }
+}
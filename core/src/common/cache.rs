@@ -0,0 +1,125 @@
+//!
+//! # External caches
+//!
+//! An instance can be bound to an external cache, e.g. memcached or redis,
+//! the cache is read through by `get` and written through by the writes.
+//!
+//! NOTE: like the quota groups, the bindings are runtime configs,
+//! they are not persisted and must be set again after restarting.
+//!
+
+use crate::common::{registry::Registry, PreBytes, RawValue};
+use std::sync::{Arc, LazyLock};
+
+/// The hooks called by the bound instances,
+/// `namespace` is the prefix of the instance,
+/// so one adapter can be shared by multiple instances.
+pub trait CacheAdapter: Send + Sync {
+    fn get(&self, namespace: PreBytes, key: &[u8]) -> Option<RawValue>;
+
+    /// Called by `contains_key`, override it if the value need not be copied out.
+    fn contains(&self, namespace: PreBytes, key: &[u8]) -> bool {
+        self.get(namespace, key).is_some()
+    }
+
+    fn put(&self, namespace: PreBytes, key: &[u8], value: &[u8]);
+
+    fn invalidate(&self, namespace: PreBytes, key: &[u8]);
+
+    /// Called by the writes that can not be tracked by keys,
+    /// e.g. range removals and clearing.
+    fn invalidate_all(&self, namespace: PreBytes);
+}
+
+// instance prefix => the cache that it is bound to
+static BINDINGS: LazyLock<Registry<Arc<dyn CacheAdapter>>> =
+    LazyLock::new(Registry::default);
+
+#[inline(always)]
+pub(crate) fn adapter_of(prefix: PreBytes) -> Option<Arc<dyn CacheAdapter>> {
+    BINDINGS.get(prefix)
+}
+
+pub(crate) fn bind(prefix: PreBytes, adapter: Arc<dyn CacheAdapter>) {
+    // the cached entries may be stale if it has been bound before
+    adapter.invalidate_all(prefix);
+    BINDINGS.insert(prefix, adapter);
+}
+
+pub(crate) fn unbind(prefix: PreBytes) {
+    BINDINGS.remove(prefix);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        basic::mapx_raw::{test::to_bytes, MapxRaw},
+        common::RawKey,
+    };
+    use parking_lot::Mutex;
+    use ruc::*;
+    use std::{
+        collections::HashMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    #[test]
+    fn test_cache() {
+        #[derive(Default)]
+        struct Cache(Mutex<HashMap<(PreBytes, RawKey), RawValue>>, AtomicUsize);
+
+        impl CacheAdapter for Cache {
+            fn get(&self, namespace: PreBytes, key: &[u8]) -> Option<RawValue> {
+                self.0.lock().get(&(namespace, key.to_vec())).cloned()
+            }
+            fn contains(&self, namespace: PreBytes, key: &[u8]) -> bool {
+                self.1.fetch_add(1, Ordering::Relaxed);
+                self.0.lock().contains_key(&(namespace, key.to_vec()))
+            }
+            fn put(&self, namespace: PreBytes, key: &[u8], value: &[u8]) {
+                self.0
+                    .lock()
+                    .insert((namespace, key.to_vec()), value.to_vec());
+            }
+            fn invalidate(&self, namespace: PreBytes, key: &[u8]) {
+                self.0.lock().remove(&(namespace, key.to_vec()));
+            }
+            fn invalidate_all(&self, namespace: PreBytes) {
+                self.0.lock().retain(|(ns, _), _| *ns != namespace);
+            }
+        }
+
+        let mut hdr = MapxRaw::new();
+        hdr.insert(to_bytes(0), to_bytes(0));
+
+        let cache = Arc::new(Cache::default());
+        hdr.bind_cache(cache.clone());
+        let ns = *hdr.as_prefix_slice();
+        assert!(cache.get(ns, &to_bytes(0)).is_none());
+
+        // read through
+        assert_eq!(&to_bytes(0)[..], &pnk!(hdr.get(to_bytes(0)))[..]);
+        assert_eq!(&to_bytes(0)[..], &pnk!(cache.get(ns, &to_bytes(0)))[..]);
+        assert!(hdr.contains_key(to_bytes(0)));
+        assert_eq!(1, cache.1.load(Ordering::Relaxed));
+
+        // write through
+        hdr.insert(to_bytes(1), to_bytes(1));
+        assert_eq!(&to_bytes(1)[..], &pnk!(cache.get(ns, &to_bytes(1)))[..]);
+        *pnk!(hdr.get_mut(to_bytes(1))) = to_bytes(2).to_vec();
+        assert_eq!(&to_bytes(2)[..], &pnk!(cache.get(ns, &to_bytes(1)))[..]);
+
+        hdr.remove(to_bytes(1));
+        assert!(cache.get(ns, &to_bytes(1)).is_none());
+        assert!(hdr.get(to_bytes(1)).is_none());
+
+        hdr.clear();
+        assert!(cache.0.lock().is_empty());
+        assert!(hdr.get(to_bytes(0)).is_none());
+
+        hdr.unbind_cache();
+        hdr.insert(to_bytes(3), to_bytes(3));
+        assert!(cache.get(ns, &to_bytes(3)).is_none());
+    }
+}
@@ -0,0 +1,118 @@
+//!
+//! # Content digests
+//!
+//! The digests of the opted-in instances are maintained incrementally by their writes,
+//! the other instances pay nothing for them, their digests are computed by full scans.
+//!
+//! NOTE: unlike the cache bindings, the opt-ins are persisted,
+//! the digests are still kept in step by the writes after restarting.
+//!
+
+use crate::{
+    basic::mapx_raw::MapxRaw,
+    common::{registry::Registry, Pre, PreBytes, WriteError, INTERNAL_ID_BASE},
+};
+use parking_lot::Mutex;
+use std::{collections::HashMap, result::Result as StdResult, sync::LazyLock};
+
+// An internal ID, it will never be allocated to normal instances
+const DIGEST_REGISTRY_ID: Pre = INTERNAL_ID_BASE + 2;
+
+// instance prefix => nothing
+static REGISTRY: LazyLock<Mutex<MapxRaw>> = LazyLock::new(|| {
+    Mutex::new(unsafe { MapxRaw::from_prefix_slice(DIGEST_REGISTRY_ID.to_be_bytes()) })
+});
+
+// Loaded from the registry at the first access
+static ENABLED: LazyLock<Registry<()>> = LazyLock::new(|| {
+    let enabled = REGISTRY
+        .lock()
+        .keys()
+        .filter_map(|k| PreBytes::try_from(&k[..]).ok())
+        .map(|prefix| (prefix, ()))
+        .collect::<HashMap<_, _>>();
+    Registry::from(enabled)
+});
+
+#[inline(always)]
+pub(crate) fn is_enabled(prefix: PreBytes) -> bool {
+    ENABLED.contains(prefix)
+}
+
+pub(crate) fn enable(prefix: PreBytes) -> StdResult<(), WriteError> {
+    // loaded before locking the registry, the writes to it will look up the opt-ins
+    LazyLock::force(&ENABLED);
+    REGISTRY.lock().try_insert(prefix, [])?;
+    ENABLED.insert(prefix, ());
+    Ok(())
+}
+
+pub(crate) fn disable(prefix: PreBytes) -> StdResult<(), WriteError> {
+    LazyLock::force(&ENABLED);
+    REGISTRY.lock().try_remove(prefix)?;
+    ENABLED.remove(prefix);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::basic::mapx_raw::{
+        test::{to_bytes, to_u64},
+        MapxRaw,
+    };
+    use ruc::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_content_digest() {
+        // `a` keeps its digest, `b` is scanned on every call
+        let mut a = MapxRaw::new();
+        let mut b = MapxRaw::new();
+        pnk!(a.enable_digest());
+        assert!(a.is_digest_enabled());
+        assert!(!b.is_digest_enabled());
+        assert_eq!(a.content_digest(), b.content_digest());
+
+        (0..100u64).for_each(|i| {
+            a.insert(to_bytes(i), to_bytes(i));
+        });
+        (0..100u64).rev().for_each(|i| {
+            b.insert(to_bytes(i), to_bytes(i + 1));
+            b.insert(to_bytes(i), to_bytes(i));
+        });
+        b.insert(to_bytes(1000), to_bytes(1000));
+        assert_ne!(a.content_digest(), b.content_digest());
+        b.remove(to_bytes(1000));
+        assert_eq!(a.content_digest(), b.content_digest());
+
+        // rebuilt after range removals
+        let d = a.content_digest();
+        a.insert(to_bytes(1000), to_bytes(1000));
+        a.remove_range(Cow::Owned(to_bytes(1000).to_vec())..);
+        assert_eq!(d, a.content_digest());
+        b.retain(|k, _| to_u64(k) < 50);
+        a.remove_range(Cow::Owned(to_bytes(50).to_vec())..);
+        assert_eq!(a.content_digest(), b.content_digest());
+
+        a.clear();
+        assert_eq!(MapxRaw::new().content_digest(), a.content_digest());
+
+        // not inherited by the clones
+        a.insert(to_bytes(1), to_bytes(1));
+        let c = pnk!(a.clone_deep_to_new_instance());
+        assert!(!c.is_digest_enabled());
+        assert_eq!(a.content_digest(), c.content_digest());
+
+        // the scans take over after opting out
+        pnk!(a.disable_digest());
+        a.insert(to_bytes(2), to_bytes(2));
+        b.clear();
+        b.insert(to_bytes(1), to_bytes(1));
+        b.insert(to_bytes(2), to_bytes(2));
+        assert_eq!(a.content_digest(), b.content_digest());
+
+        // rebuilt by one scan after opting in again
+        pnk!(a.enable_digest());
+        assert_eq!(a.content_digest(), b.content_digest());
+    }
+}
@@ -16,29 +16,54 @@ pub(crate) use rocks_backend::RocksEngine as RocksDB;
 #[cfg(feature = "rocks_backend")]
 type EngineIter = rocks_backend::RocksIter;
 
+#[cfg(feature = "rocks_backend")]
+type EngineKeyIter = rocks_backend::RocksKeyIter;
+
+#[cfg(feature = "rocks_backend")]
+pub use rocks_backend::{vsdb_finish_bulk_load, vsdb_tune, RocksProfile};
+
 #[cfg(feature = "parity_backend")]
 pub(crate) use parity_backend::ParityEngine as ParityDB;
 
 #[cfg(feature = "parity_backend")]
 type EngineIter = parity_backend::ParityIter;
 
+#[cfg(feature = "parity_backend")]
+type EngineKeyIter = parity_backend::ParityKeyIter;
+
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-use crate::common::{Pre, PreBytes, RawKey, RawValue, PREFIX_SIZE, VSDB};
-use parking_lot::Mutex;
+use crate::common::{
+    access::{self, Access, AccessStats},
+    cache::{self, CacheAdapter},
+    count_rejected_write, digest, entry_digest, is_labeled_prefix, next_labeled_prefix,
+    quota::{self, QuotaGroup, QuotaUsage},
+    retry::NoRetries,
+    seal, slow_op_timer,
+    snapshot::{self, PreimagesGuard, Snapshot},
+    trace_slow_op, vsdb_is_frozen, vsdb_is_read_only, wait_for_unfreeze, BackendError,
+    CompactionStats, OpKind, Pre, PreBytes, RawKey, RawValue, SizeLimits, WriteError,
+    INTERNAL_ID_BASE, PREFIX_SIZE, RESERVED_ID_CNT, VSDB,
+};
+use parking_lot::{Mutex, MutexGuard, RwLockReadGuard};
 use ruc::*;
 use serde::{de, Deserialize, Serialize};
 use std::{
     borrow::Cow,
     fmt,
+    iter::Peekable,
     mem::transmute,
-    ops::{Deref, DerefMut, RangeBounds},
+    ops::{Bound, Deref, DerefMut, RangeBounds},
     result::Result as StdResult,
-    sync::LazyLock,
+    sync::{Arc, LazyLock},
+    time::Instant,
 };
 
-static LEN_LK: LazyLock<Vec<Mutex<()>>> =
+// An internal ID, it will never be allocated to normal instances
+const READ_ONLY_PLACEHOLDER_ID: Pre = INTERNAL_ID_BASE + 3;
+
+pub(crate) static LEN_LK: LazyLock<Vec<Mutex<()>>> =
     LazyLock::new(|| (0..VSDB.db.area_count()).map(|_| Mutex::new(())).collect());
 
 /////////////////////////////////////////////////////////////////////////////
@@ -58,6 +83,25 @@ pub trait Engine: Sized {
 
     fn flush(&self);
 
+    // Reclaim the space of the removed and overwritten entries of an instance,
+    // nothing to do for the engines that reclaim the space in place
+    fn compact(&self, _meta_prefix: PreBytes) {}
+
+    // The bytes taken on disk by an area, shared by all the instances in it,
+    // `None` for the engines that do not compact, the sizes will not be changed by it
+    fn area_disk_size(&self, _area_idx: usize) -> Option<u64> {
+        None
+    }
+
+    // Release the memory that can be rebuilt from the disk, e.g. the write buffers
+    // and the block caches, return the released bytes;
+    // nothing is released by default, parity-db has no caches that can be shrunk
+    // from outside, its index and value tables are memory-mapped files
+    fn shrink_to_fit(&self) -> usize {
+        self.flush();
+        0
+    }
+
     fn iter(&self, meta_prefix: PreBytes) -> EngineIter;
 
     fn range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
@@ -66,38 +110,203 @@ pub trait Engine: Sized {
         bounds: R,
     ) -> EngineIter;
 
-    fn get(&self, meta_prefix: PreBytes, key: &[u8]) -> Option<RawValue>;
+    // Like `iter`, but the values are not copied out,
+    // the engines without key-only iterators may still read them
+    fn iter_keys(&self, meta_prefix: PreBytes) -> EngineKeyIter;
 
+    // Like `range`, but the values are not copied out,
+    // the engines without key-only iterators may still read them
+    fn range_keys<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        meta_prefix: PreBytes,
+        bounds: R,
+    ) -> EngineKeyIter;
+
+    // Panic if the retry policy is exhausted
+    fn get(&self, meta_prefix: PreBytes, key: &[u8]) -> Option<RawValue> {
+        self.try_get(meta_prefix, key)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // Like `get`, but return the error after the retry policy is exhausted
+    fn try_get(
+        &self,
+        meta_prefix: PreBytes,
+        key: &[u8],
+    ) -> StdResult<Option<RawValue>, BackendError>;
+
+    // Check the existence of a key without copying out its value,
+    // the engines may still read the value
+    fn contains_key(&self, meta_prefix: PreBytes, key: &[u8]) -> bool {
+        self.get(meta_prefix, key).is_some()
+    }
+
+    // Panic if the retry policy is exhausted
     fn insert(
         &self,
         meta_prefix: PreBytes,
         key: &[u8],
         value: &[u8],
-    ) -> Option<RawValue>;
+    ) -> Option<RawValue> {
+        self.try_insert(meta_prefix, key, value)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
 
-    fn remove(&self, meta_prefix: PreBytes, key: &[u8]) -> Option<RawValue>;
+    // Panic if the retry policy is exhausted
+    fn remove(&self, meta_prefix: PreBytes, key: &[u8]) -> Option<RawValue> {
+        self.try_remove(meta_prefix, key)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // Like `insert`, but return the error after the retry policy is exhausted
+    fn try_insert(
+        &self,
+        meta_prefix: PreBytes,
+        key: &[u8],
+        value: &[u8],
+    ) -> StdResult<Option<RawValue>, BackendError>;
+
+    // Like `remove`, but return the error after the retry policy is exhausted
+    fn try_remove(
+        &self,
+        meta_prefix: PreBytes,
+        key: &[u8],
+    ) -> StdResult<Option<RawValue>, BackendError>;
+
+    // Like `insert`, but return `None` instead of waiting
+    // if the write would be stalled by the engine,
+    // the default one can not tell the stalls, it waits like `insert`
+    fn insert_nowait(
+        &self,
+        meta_prefix: PreBytes,
+        key: &[u8],
+        value: &[u8],
+    ) -> Option<Option<RawValue>> {
+        Some(self.insert(meta_prefix, key, value))
+    }
+
+    // Like `remove`, but return `None` instead of waiting
+    // if the write would be stalled by the engine,
+    // the default one can not tell the stalls, it waits like `remove`
+    fn remove_nowait(
+        &self,
+        meta_prefix: PreBytes,
+        key: &[u8],
+    ) -> Option<Option<RawValue>> {
+        Some(self.remove(meta_prefix, key))
+    }
+
+    // Apply the writes in one batch of the backend, the `None` values remove the keys,
+    // the keys must be distinct,
+    // return the old values in the order of the writes;
+    // the default one can not batch them, they are applied one by one
+    fn try_write_batch(
+        &self,
+        meta_prefix: PreBytes,
+        ops: &[(&[u8], Option<&[u8]>)],
+    ) -> StdResult<Vec<Option<RawValue>>, BackendError> {
+        ops.iter()
+            .map(|(k, v)| match v {
+                Some(v) => self.try_insert(meta_prefix, k, v),
+                None => self.try_remove(meta_prefix, k),
+            })
+            .collect()
+    }
+
+    // Remove all entries within the range,
+    // return the number of the removed entries
+    fn remove_range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        meta_prefix: PreBytes,
+        bounds: R,
+    ) -> u64 {
+        let keys = self
+            .range(meta_prefix, bounds)
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+        keys.iter().for_each(|k| {
+            self.remove(meta_prefix, k);
+        });
+        keys.len() as u64
+    }
+
+    // Remove all entries of an instance,
+    // the number of them is not counted, the length hint is reset by the caller
+    fn clear_instance(&self, meta_prefix: PreBytes) {
+        self.remove_range(meta_prefix, ..);
+    }
+
+    // Copy all entries of an instance into another empty one,
+    // return the number of the copied entries
+    fn copy_instance(&self, src_prefix: PreBytes, dst_prefix: PreBytes) -> u64 {
+        self.iter(src_prefix).fold(0, |n, (k, v)| {
+            self.insert(dst_prefix, &k, &v);
+            n + 1
+        })
+    }
+
+    // Write the entries after the existing ones of an instance in the order of the keys,
+    // the keys must be strictly ascending and bigger than the existing ones,
+    // return the number of the written entries;
+    // the entries written before an error are kept
+    fn ingest_sorted(
+        &self,
+        meta_prefix: PreBytes,
+        entries: &mut dyn Iterator<Item = (RawKey, RawValue)>,
+    ) -> StdResult<u64, BackendError> {
+        let mut n = 0;
+        for (k, v) in entries {
+            self.try_insert(meta_prefix, &k, &v)?;
+            n += 1;
+        }
+        Ok(n)
+    }
 
     fn get_instance_len_hint(&self, instance_prefix: PreBytes) -> u64;
 
     fn set_instance_len_hint(&self, instance_prefix: PreBytes, new_len: u64);
 
-    fn increase_instance_len_hint(&self, instance_prefix: PreBytes) {
-        let x = LEN_LK[self.area_idx(instance_prefix)].lock();
+    // `None` means that the digest is unknown,
+    // and it must be rebuilt from the content of the instance
+    fn get_instance_digest(&self, instance_prefix: PreBytes) -> Option<u64>;
 
-        let l = self.get_instance_len_hint(instance_prefix);
-        self.set_instance_len_hint(instance_prefix, l + 1);
+    fn set_instance_digest(&self, instance_prefix: PreBytes, digest: Option<u64>);
 
-        drop(x);
-    }
+    // An offset kept for the owner of an instance, 0 if never set
+    fn get_instance_offset(&self, instance_prefix: PreBytes) -> u64;
 
-    fn decrease_instance_len_hint(&self, instance_prefix: PreBytes) {
-        let x = LEN_LK[self.area_idx(instance_prefix)].lock();
+    fn set_instance_offset(&self, instance_prefix: PreBytes, offset: u64);
+}
 
-        let l = self.get_instance_len_hint(instance_prefix);
-        self.set_instance_len_hint(instance_prefix, l.saturating_sub(1));
+// The keys of the other metadata of an instance in the meta area,
+// they can not conflict with the len hints, whose keys are the bare prefixes
+#[inline(always)]
+fn meta_key(instance_prefix: PreBytes, tag: u8) -> [u8; PREFIX_SIZE + 1] {
+    let mut k = [tag; PREFIX_SIZE + 1];
+    k[..PREFIX_SIZE].copy_from_slice(&instance_prefix);
+    k
+}
 
-        drop(x);
+// The labeled and the internal instances are used without being created,
+// so their len hints may be missing, the len hints of the others are written
+// when their prefixes are allocated, a missing one means a corrupted meta.
+fn missing_len_hint(instance_prefix: PreBytes) -> u64 {
+    let prefix = Pre::from_be_bytes(instance_prefix);
+    if is_labeled_prefix(prefix) || (INTERNAL_ID_BASE..RESERVED_ID_CNT).contains(&prefix)
+    {
+        return 0;
     }
+    panic!("the len hint of the instance {} is missing", prefix)
+}
+
+#[inline(always)]
+fn digest_key(instance_prefix: PreBytes) -> [u8; PREFIX_SIZE + 1] {
+    meta_key(instance_prefix, u8::MAX)
+}
+
+#[inline(always)]
+fn offset_key(instance_prefix: PreBytes) -> [u8; PREFIX_SIZE + 1] {
+    meta_key(instance_prefix, u8::MAX - 1)
 }
 
 /////////////////////////////////////////////////////////////////////////////
@@ -107,6 +316,8 @@ pub trait Engine: Sized {
 pub(crate) struct Mapx {
     // the unique ID of each instance
     prefix: Prefix,
+    // runtime config, not persisted
+    limits: SizeLimits,
 }
 
 #[derive(Debug)]
@@ -147,8 +358,21 @@ impl Prefix {
 
     #[inline(always)]
     fn create() -> Self {
+        // Labeled instances may have existing data, and the len hint must be kept,
+        // a missing len hint will be treated as 0, see `missing_len_hint`.
+        if let Some(prefix) = next_labeled_prefix() {
+            return Self::Recoverd(prefix.to_be_bytes());
+        }
+
         Self::Created(LazyLock::new(|| {
-            let prefix = VSDB.db.alloc_prefix();
+            // nothing can be allocated in read-only mode,
+            // the new instances share an empty placeholder, the writes to it are rejected
+            let db = &VSDB.db;
+            if vsdb_is_read_only() {
+                return READ_ONLY_PLACEHOLDER_ID.to_be_bytes();
+            }
+
+            let prefix = db.alloc_prefix();
             let prefix_bytes = prefix.to_be_bytes();
             debug_assert!(VSDB.db.iter(prefix_bytes).next().is_none());
             VSDB.db.set_instance_len_hint(prefix_bytes, 0);
@@ -165,6 +389,7 @@ impl Mapx {
     pub(crate) unsafe fn shadow(&self) -> Self {
         Self {
             prefix: Prefix::from_bytes(self.prefix.to_bytes()),
+            limits: self.limits,
         }
     }
 
@@ -172,30 +397,136 @@ impl Mapx {
     pub(crate) fn new() -> Self {
         Self {
             prefix: Prefix::create(),
+            limits: SizeLimits::default(),
         }
     }
 
     #[inline(always)]
     pub(crate) fn get(&self, key: &[u8]) -> Option<RawValue> {
-        VSDB.db.get(self.prefix.to_bytes(), key)
+        let prefix = self.prefix.to_bytes();
+        access::record(prefix, Access::Read, Some(key));
+
+        if let Some(f) = seal::file_of(prefix) {
+            return f.get(key);
+        }
+
+        let adapter = cache::adapter_of(prefix);
+        if let Some(v) = adapter.as_ref().and_then(|a| a.get(prefix, key)) {
+            return Some(v);
+        }
+
+        let t = slow_op_timer();
+        let ret = VSDB.db.get(prefix, key);
+        trace_slow_op(t, OpKind::Get, prefix, key.len());
+
+        if let (Some(a), Some(v)) = (adapter, ret.as_ref()) {
+            a.put(prefix, key, v);
+        }
+        ret
+    }
+
+    // Like `get`, but the backend is read by `try_get`
+    pub(crate) fn try_get(
+        &self,
+        key: &[u8],
+    ) -> StdResult<Option<RawValue>, BackendError> {
+        let prefix = self.prefix.to_bytes();
+        access::record(prefix, Access::Read, Some(key));
+
+        if let Some(f) = seal::file_of(prefix) {
+            return Ok(f.get(key));
+        }
+
+        let adapter = cache::adapter_of(prefix);
+        if let Some(v) = adapter.as_ref().and_then(|a| a.get(prefix, key)) {
+            return Ok(Some(v));
+        }
+
+        let t = slow_op_timer();
+        let ret = VSDB.db.try_get(prefix, key)?;
+        trace_slow_op(t, OpKind::Get, prefix, key.len());
+
+        if let (Some(a), Some(v)) = (adapter, ret.as_ref()) {
+            a.put(prefix, key, v);
+        }
+        Ok(ret)
+    }
+
+    #[inline(always)]
+    pub(crate) fn contains_key(&self, key: &[u8]) -> bool {
+        let prefix = self.prefix.to_bytes();
+        access::record(prefix, Access::Read, Some(key));
+        if let Some(f) = seal::file_of(prefix) {
+            return f.contains_key(key);
+        }
+        cache::adapter_of(prefix).is_some_and(|a| a.contains(prefix, key))
+            || VSDB.db.contains_key(prefix, key)
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_with_snapshot(
+        &self,
+        snapshot: &Snapshot,
+        key: &[u8],
+    ) -> Option<RawValue> {
+        let prefix = self.prefix.to_bytes();
+        snapshot.get(prefix, key, || VSDB.db.get(prefix, key))
     }
 
     #[inline(always)]
-    pub(crate) fn get_mut(&mut self, key: &[u8]) -> Option<ValueMut> {
-        let v = VSDB.db.get(self.prefix.hack_bytes(), key)?;
+    pub(crate) fn range_with_snapshot<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        snapshot: &Snapshot,
+        bounds: R,
+    ) -> MapxSnapshotIter<'a> {
+        let prefix = self.prefix.to_bytes();
+        let bounds = (
+            bounds.start_bound().map(|k| k.to_vec()),
+            bounds.end_bound().map(|k| k.to_vec()),
+        );
+        let db_bounds = (
+            bounds.0.clone().map(Cow::Owned),
+            bounds.1.clone().map(Cow::Owned),
+        );
+        let guard = snapshot.lock();
+        MapxSnapshotIter {
+            db_iter: VSDB.db.range(prefix, db_bounds).peekable(),
+            bounds,
+            last: None,
+            prefix,
+            guard,
+            _hdr: self,
+        }
+    }
+
+    // No guards for the sealed instances, they could never be written back;
+    // the guards are written back on dropping only if changed
+    #[inline(always)]
+    pub(crate) fn get_mut(&mut self, key: &[u8]) -> Option<ValueMut<'_>> {
+        let prefix = self.prefix.hack_bytes();
+        if seal::is_sealed(prefix) {
+            return None;
+        }
+        let v = VSDB.db.get(prefix, key)?;
 
         Some(ValueMut {
             key: key.to_vec(),
             value: v,
+            committed: true,
             hdr: self,
         })
     }
 
     #[inline(always)]
-    pub(crate) fn mock_value_mut(&mut self, key: RawValue, value: RawValue) -> ValueMut {
+    pub(crate) fn mock_value_mut(
+        &mut self,
+        key: RawValue,
+        value: RawValue,
+    ) -> ValueMut<'_> {
         ValueMut {
             key,
             value,
+            committed: false,
             hdr: self,
         }
     }
@@ -211,17 +542,64 @@ impl Mapx {
     }
 
     #[inline(always)]
-    pub(crate) fn iter(&self) -> MapxIter {
+    pub(crate) fn base_offset(&self) -> u64 {
+        VSDB.db.get_instance_offset(self.prefix.to_bytes())
+    }
+
+    #[inline(always)]
+    pub(crate) fn set_base_offset(&mut self, offset: u64) {
+        pnk!(self.set_base_offset_with(offset))
+    }
+
+    // Checked like the writes of the entries, so it keeps in step with them
+    fn set_base_offset_with(&mut self, offset: u64) -> StdResult<(), WriteError> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(WriteError::ReadOnly);
+        }
+
+        let prefix = self.prefix.hack_bytes();
+        let _w = write_guard(Mode::Wait)?;
+        check_sealed(prefix)?;
+        VSDB.db.set_instance_offset(prefix, offset);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn iter(&self) -> MapxIter<'_> {
+        access::record(self.prefix.to_bytes(), Access::Scan, None);
         MapxIter {
             db_iter: VSDB.db.iter(self.prefix.to_bytes()),
+            budget: Budget::default(),
+            _hdr: self,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn keys(&self) -> MapxKeys<'_> {
+        MapxKeys {
+            db_iter: VSDB.db.iter_keys(self.prefix.to_bytes()),
             _hdr: self,
         }
     }
 
     #[inline(always)]
-    pub(crate) fn iter_mut(&mut self) -> MapxIterMut {
+    pub(crate) fn range_keys<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        bounds: R,
+    ) -> MapxKeys<'a> {
+        MapxKeys {
+            db_iter: VSDB.db.range_keys(self.prefix.to_bytes(), bounds),
+            _hdr: self,
+        }
+    }
+
+    // Empty for the sealed instances, like `get_mut`
+    #[inline(always)]
+    pub(crate) fn iter_mut(&mut self) -> MapxIterMut<'_> {
+        let prefix = self.prefix.hack_bytes();
         MapxIterMut {
-            db_iter: VSDB.db.iter(self.prefix.hack_bytes()),
+            db_iter: (!seal::is_sealed(prefix)).then(|| VSDB.db.iter(prefix)),
             hdr: self,
         }
     }
@@ -231,50 +609,740 @@ impl Mapx {
         &'a self,
         bounds: R,
     ) -> MapxIter<'a> {
+        access::record(self.prefix.to_bytes(), Access::Scan, None);
         MapxIter {
             db_iter: VSDB.db.range(self.prefix.to_bytes(), bounds),
+            budget: Budget::default(),
             _hdr: self,
         }
     }
 
+    // Empty for the sealed instances, like `get_mut`
     #[inline(always)]
     pub(crate) fn range_mut<'a, R: RangeBounds<Cow<'a, [u8]>>>(
         &'a mut self,
         bounds: R,
     ) -> MapxIterMut<'a> {
+        let prefix = self.prefix.hack_bytes();
         MapxIterMut {
-            db_iter: VSDB.db.range(self.prefix.hack_bytes(), bounds),
+            db_iter: (!seal::is_sealed(prefix)).then(|| VSDB.db.range(prefix, bounds)),
             hdr: self,
         }
     }
 
     #[inline(always)]
     pub(crate) fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<RawValue> {
+        pnk!(self.insert_with(key, value, Mode::Wait))
+    }
+
+    #[inline(always)]
+    pub(crate) fn try_insert(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        self.insert_with(key, value, Mode::Try)
+    }
+
+    #[inline(always)]
+    pub(crate) fn insert_nowait(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        self.insert_with(key, value, Mode::NoWait)
+    }
+
+    // Check `Mode` for the differences between the write paths
+    fn insert_with(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        mode: Mode,
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(WriteError::ReadOnly);
+        }
+
+        if let Err(e) = self.limits.check(key, value) {
+            count_rejected_write();
+            return Err(e);
+        }
+
         let prefix = self.prefix.hack_bytes();
-        let ret = VSDB.db.insert(prefix, key, value);
-        if ret.is_none() {
-            VSDB.db.increase_instance_len_hint(prefix);
+        let current = || VSDB.db.get(prefix, key);
+
+        let _w = write_guard(mode)?;
+        check_sealed(prefix)?;
+        let nowait = Mode::NoWait == mode;
+        let _r = nowait.then(NoRetries::enter);
+        if nowait {
+            if !snapshot::try_preserve(prefix, key, current) {
+                return Err(would_block());
+            }
+        } else {
+            snapshot::preserve(prefix, key, current);
         }
-        ret
+        let mut group = if nowait {
+            quota::try_lock_group_of(prefix).ok_or_else(would_block)?
+        } else {
+            quota::lock_group_of(prefix)
+        };
+        let len_lk = if nowait {
+            Some(try_lock_len(prefix).ok_or_else(would_block)?)
+        } else {
+            None
+        };
+        let old_len = group
+            .as_ref()
+            .and_then(|_| VSDB.db.get(prefix, key).map(|v| v.len()));
+        if let Some(g) = group.as_mut() {
+            if let Err(e) = g.charge(prefix, key.len(), old_len, Some(value.len())) {
+                count_rejected_write();
+                return Err(e);
+            }
+        }
+
+        let t = slow_op_timer();
+        let ret = if nowait {
+            VSDB.db
+                .insert_nowait(prefix, key, value)
+                .ok_or_else(would_block)
+        } else {
+            VSDB.db
+                .try_insert(prefix, key, value)
+                .map_err(WriteError::Backend)
+        };
+        let ret = match ret {
+            Ok(ret) => ret,
+            Err(e) => {
+                if let Some(g) = group.as_mut() {
+                    g.refund(prefix, key.len(), old_len, Some(value.len()));
+                }
+                return Err(e);
+            }
+        };
+        trace_slow_op(t, OpKind::Insert, prefix, key.len());
+        if nowait {
+            access::try_record(prefix, Access::Write, Some(key));
+        } else {
+            access::record(prefix, Access::Write, Some(key));
+        }
+        update_meta(len_lk, prefix, key, ret.as_deref(), Some(value));
+        if let Some(a) = cache::adapter_of(prefix) {
+            a.put(prefix, key, value);
+        }
+        Ok(ret)
+    }
+
+    // The entries are copied by the bulk writes of the backend,
+    // the runtime configs except the size limits are not inherited
+    pub(crate) fn clone_deep(&self) -> StdResult<Self, WriteError> {
+        self.clone_deep_with(Mode::Try)
+    }
+
+    fn clone_deep_with(&self, mode: Mode) -> StdResult<Self, WriteError> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(WriteError::ReadOnly);
+        }
+
+        let _w = write_guard(mode)?;
+
+        let src = self.prefix.to_bytes();
+        let new_instance = Self {
+            prefix: Prefix::create(),
+            limits: self.limits,
+        };
+        let dst = new_instance.prefix.to_bytes();
+
+        let n = VSDB.db.copy_instance(src, dst);
+        VSDB.db.set_instance_len_hint(dst, n);
+        VSDB.db
+            .set_instance_offset(dst, VSDB.db.get_instance_offset(src));
+
+        Ok(new_instance)
+    }
+
+    // The entries are written by the bulk writes of the backend,
+    // the instance must be empty and the keys must be strictly ascending,
+    // the entries that have been written before an error are kept
+    pub(crate) fn bulk_load_sorted(
+        &mut self,
+        entries: impl IntoIterator<Item = (RawKey, RawValue)>,
+    ) -> Result<u64> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(eg!(WriteError::ReadOnly));
+        }
+
+        let prefix = self.prefix.hack_bytes();
+        let limits = self.limits;
+        let mut err = None;
+
+        // held until the write is done
+        let _w = write_guard(Mode::Try).c(d!())?;
+        check_sealed(prefix).c(d!())?;
+        let mut group = quota::lock_group_of(prefix);
+
+        // checked under the guard, the new keys must follow the existing ones
+        let mut last = VSDB.db.iter(prefix).next_back().map(|(k, _)| k);
+
+        let mut checked = entries.into_iter().map_while(|(k, v)| {
+            let checked = if last.as_ref().is_some_and(|l| l >= &k) {
+                Err(eg!(
+                    "the keys are not strictly ascending, or not after the existing ones"
+                ))
+            } else if let Err(e) = limits.check(&k, &v).and_then(|_| {
+                group
+                    .as_mut()
+                    .map_or(Ok(()), |g| g.charge(prefix, k.len(), None, Some(v.len())))
+            }) {
+                count_rejected_write();
+                Err(eg!(e))
+            } else {
+                Ok(())
+            };
+
+            match checked {
+                Ok(()) => {
+                    snapshot::preserve(prefix, &k, || None);
+                    last = Some(k.clone());
+                    Some((k, v))
+                }
+                Err(e) => {
+                    err = Some(e);
+                    None
+                }
+            }
+        });
+
+        let ret = VSDB.db.ingest_sorted(prefix, &mut checked);
+        drop(checked);
+
+        let x = LEN_LK[VSDB.db.area_idx(prefix)].lock();
+        let len = match ret.as_ref() {
+            Ok(n) => VSDB.db.get_instance_len_hint(prefix) + n,
+            // the number of the written entries is unknown,
+            // the charged but unwritten entries are also released from the group
+            Err(_) => {
+                let usage =
+                    VSDB.db
+                        .iter(prefix)
+                        .fold(QuotaUsage::default(), |u, (k, v)| {
+                            let e = entry_usage(&k, &v);
+                            QuotaUsage {
+                                keys: u.keys + e.keys,
+                                bytes: u.bytes + e.bytes,
+                            }
+                        });
+                if let Some(g) = group.as_mut() {
+                    g.reset_member(prefix, usage);
+                }
+                usage.keys
+            }
+        };
+        VSDB.db.set_instance_len_hint(prefix, len);
+        if digest::is_enabled(prefix) {
+            // rebuilt by one scan on the next call
+            VSDB.db.set_instance_digest(prefix, None);
+        }
+        drop(x);
+        if let Some(a) = cache::adapter_of(prefix) {
+            a.invalidate_all(prefix);
+        }
+
+        let n = ret.map_err(|e| eg!(WriteError::Backend(e)))?;
+        err.map_or(Ok(n), Err)
+    }
+
+    #[inline(always)]
+    pub(crate) fn size_limits(&self) -> SizeLimits {
+        self.limits
+    }
+
+    #[inline(always)]
+    pub(crate) fn set_size_limits(&mut self, limits: SizeLimits) {
+        self.limits = limits;
     }
 
     #[inline(always)]
     pub(crate) fn remove(&mut self, key: &[u8]) -> Option<RawValue> {
+        pnk!(self.remove_with(key, Mode::Wait))
+    }
+
+    #[inline(always)]
+    pub(crate) fn try_remove(
+        &mut self,
+        key: &[u8],
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        self.remove_with(key, Mode::Try)
+    }
+
+    #[inline(always)]
+    pub(crate) fn remove_nowait(
+        &mut self,
+        key: &[u8],
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        self.remove_with(key, Mode::NoWait)
+    }
+
+    // Check `Mode` for the differences between the write paths
+    fn remove_with(
+        &mut self,
+        key: &[u8],
+        mode: Mode,
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(WriteError::ReadOnly);
+        }
+
         let prefix = self.prefix.hack_bytes();
-        let ret = VSDB.db.remove(prefix, key);
-        if ret.is_some() {
-            VSDB.db.decrease_instance_len_hint(prefix);
+        let current = || VSDB.db.get(prefix, key);
+
+        let _w = write_guard(mode)?;
+        check_sealed(prefix)?;
+        let nowait = Mode::NoWait == mode;
+        let _r = nowait.then(NoRetries::enter);
+        if nowait {
+            if !snapshot::try_preserve(prefix, key, current) {
+                return Err(would_block());
+            }
+        } else {
+            snapshot::preserve(prefix, key, current);
         }
-        ret
+        let mut group = if nowait {
+            quota::try_lock_group_of(prefix).ok_or_else(would_block)?
+        } else {
+            quota::lock_group_of(prefix)
+        };
+        let len_lk = if nowait {
+            Some(try_lock_len(prefix).ok_or_else(would_block)?)
+        } else {
+            None
+        };
+        let t = slow_op_timer();
+        let ret = if nowait {
+            VSDB.db.remove_nowait(prefix, key).ok_or_else(would_block)?
+        } else {
+            VSDB.db
+                .try_remove(prefix, key)
+                .map_err(WriteError::Backend)?
+        };
+        trace_slow_op(t, OpKind::Remove, prefix, key.len());
+        if nowait {
+            access::try_record(prefix, Access::Write, Some(key));
+        } else {
+            access::record(prefix, Access::Write, Some(key));
+        }
+        if let (Some(g), Some(v)) = (group.as_mut(), ret.as_ref()) {
+            g.release(prefix, entry_usage(key, v));
+        }
+        update_meta(len_lk, prefix, key, ret.as_deref(), None);
+        if let Some(a) = cache::adapter_of(prefix) {
+            a.invalidate(prefix, key);
+        }
+        Ok(ret)
     }
 
     #[inline(always)]
-    pub(crate) fn clear(&mut self) {
+    pub(crate) fn write_batch(&mut self, ops: &[(&[u8], Option<&[u8]>)]) {
+        pnk!(self.write_batch_with(ops, Mode::Wait))
+    }
+
+    #[inline(always)]
+    pub(crate) fn try_write_batch(
+        &mut self,
+        ops: &[(&[u8], Option<&[u8]>)],
+    ) -> StdResult<(), WriteError> {
+        self.write_batch_with(ops, Mode::Try)
+    }
+
+    // Like `insert_with` and `remove_with`, but all the writes are checked
+    // before any of them is done, and they are done by one batch of the backend;
+    // the non-blocking mode is not supported
+    fn write_batch_with(
+        &mut self,
+        ops: &[(&[u8], Option<&[u8]>)],
+        mode: Mode,
+    ) -> StdResult<(), WriteError> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(WriteError::ReadOnly);
+        }
+
+        for (k, v) in ops.iter() {
+            if let Err(e) = v.map_or(Ok(()), |v| self.limits.check(k, v)) {
+                count_rejected_write();
+                return Err(e);
+            }
+        }
+
+        let prefix = self.prefix.hack_bytes();
+
+        let _w = write_guard(mode)?;
+        check_sealed(prefix)?;
+        for (k, _) in ops.iter() {
+            snapshot::preserve(prefix, k, || VSDB.db.get(prefix, k));
+        }
+
+        let mut group = quota::lock_group_of(prefix);
+        let mut charged = vec![];
+        if let Some(g) = group.as_mut() {
+            for (k, v) in ops.iter() {
+                let old_len = VSDB.db.get(prefix, k).map(|v| v.len());
+                let new_len = v.map(|v| v.len());
+                if let Err(e) = g.charge(prefix, k.len(), old_len, new_len) {
+                    for (key_len, old_len, new_len) in charged {
+                        g.refund(prefix, key_len, old_len, new_len);
+                    }
+                    count_rejected_write();
+                    return Err(e);
+                }
+                charged.push((k.len(), old_len, new_len));
+            }
+        }
+
+        let t = slow_op_timer();
+        let olds = match VSDB.db.try_write_batch(prefix, ops) {
+            Ok(olds) => olds,
+            Err(e) => {
+                if let Some(g) = group.as_mut() {
+                    for (key_len, old_len, new_len) in charged {
+                        g.refund(prefix, key_len, old_len, new_len);
+                    }
+                }
+                return Err(WriteError::Backend(e));
+            }
+        };
+        trace_slow_op(t, OpKind::WriteBatch, prefix, 0);
+
+        let adapter = cache::adapter_of(prefix);
+        for ((k, v), old) in ops.iter().zip(olds.iter()) {
+            access::record(prefix, Access::Write, Some(k));
+            update_meta(None, prefix, k, old.as_deref(), *v);
+            if let Some(a) = adapter.as_ref() {
+                match v {
+                    Some(v) => a.put(prefix, k, v),
+                    None => a.invalidate(prefix, k),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn remove_range<'a, R: RangeBounds<Cow<'a, [u8]>>>(&mut self, bounds: R) {
+        pnk!(self.remove_range_with(bounds, Mode::Wait))
+    }
+
+    #[inline(always)]
+    pub(crate) fn try_remove_range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &mut self,
+        bounds: R,
+    ) -> StdResult<(), WriteError> {
+        self.remove_range_with(bounds, Mode::Try)
+    }
+
+    fn remove_range_with<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &mut self,
+        bounds: R,
+        mode: Mode,
+    ) -> StdResult<(), WriteError> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(WriteError::ReadOnly);
+        }
+
         let prefix = self.prefix.hack_bytes();
-        VSDB.db.iter(prefix).for_each(|(k, _)| {
-            VSDB.db.remove(prefix, &k);
+        let bounds = (bounds.start_bound().cloned(), bounds.end_bound().cloned());
+
+        let _w = write_guard(mode)?;
+        check_sealed(prefix)?;
+        snapshot::preserve_all(prefix, || VSDB.db.range(prefix, bounds.clone()));
+
+        // the sizes of the removed entries are only needed by quota groups
+        let mut group = quota::lock_group_of(prefix);
+        if let Some(g) = group.as_mut() {
+            let removed = VSDB.db.range(prefix, bounds.clone()).fold(
+                QuotaUsage::default(),
+                |mut u, (k, v)| {
+                    let e = entry_usage(&k, &v);
+                    u.keys += e.keys;
+                    u.bytes += e.bytes;
+                    u
+                },
+            );
+            g.release(prefix, removed);
+        }
+
+        let t = slow_op_timer();
+        let n = VSDB.db.remove_range(prefix, bounds);
+        trace_slow_op(t, OpKind::RemoveRange, prefix, 0);
+        access::record(prefix, Access::Write, None);
+        if 0 < n {
+            if let Some(a) = cache::adapter_of(prefix) {
+                a.invalidate_all(prefix);
+            }
+            let x = LEN_LK[VSDB.db.area_idx(prefix)].lock();
+            let l = VSDB.db.get_instance_len_hint(prefix);
+            VSDB.db.set_instance_len_hint(prefix, l.saturating_sub(n));
+            if digest::is_enabled(prefix) {
+                // the removed values are unknown,
+                // the digest will be rebuilt when it is read next time
+                VSDB.db.set_instance_digest(prefix, None);
+            }
+            drop(x);
+        }
+
+        Ok(())
+    }
+
+    // Adjacent entries to be dropped are merged into one range deletion
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(&[u8], &[u8]) -> bool) {
+        let mut ranges = vec![];
+        let mut cur: Option<(RawKey, RawKey)> = None;
+        for (k, v) in self.iter() {
+            if f(&k, &v) {
+                ranges.extend(cur.take());
+            } else if let Some((_, last)) = cur.as_mut() {
+                *last = k;
+            } else {
+                cur = Some((k.clone(), k));
+            }
+        }
+        ranges.extend(cur);
+
+        ranges.into_iter().for_each(|(first, last)| {
+            self.remove_range(Cow::Owned(first)..=Cow::Owned(last));
         });
+    }
+
+    // NOTE:
+    // the prefix must be kept, it may have been serialized into other instances
+    #[inline(always)]
+    pub(crate) fn clear(&mut self) {
+        pnk!(self.clear_with(Mode::Wait))
+    }
+
+    #[inline(always)]
+    pub(crate) fn try_clear(&mut self) -> StdResult<(), WriteError> {
+        self.clear_with(Mode::Try)
+    }
+
+    fn clear_with(&mut self, mode: Mode) -> StdResult<(), WriteError> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(WriteError::ReadOnly);
+        }
+
+        let prefix = self.prefix.hack_bytes();
+        let _w = write_guard(mode)?;
+        check_sealed(prefix)?;
+        snapshot::preserve_all(prefix, || VSDB.db.iter(prefix));
+        let group = quota::lock_group_of(prefix);
+        VSDB.db.clear_instance(prefix);
+        access::record(prefix, Access::Write, None);
         VSDB.db.set_instance_len_hint(prefix, 0);
+        if digest::is_enabled(prefix) {
+            VSDB.db.set_instance_digest(prefix, Some(0));
+        }
+        VSDB.db.set_instance_offset(prefix, 0);
+        if let Some(mut g) = group {
+            g.release_all(prefix);
+        }
+        if let Some(a) = cache::adapter_of(prefix) {
+            a.invalidate_all(prefix);
+        }
+
+        Ok(())
+    }
+
+    // The sizes on disk are of the whole area that holds the instance,
+    // the concurrent writes are not blocked, they may be counted in
+    pub(crate) fn compact(&self) -> StdResult<CompactionStats, WriteError> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(WriteError::ReadOnly);
+        }
+
+        let prefix = self.prefix.to_bytes();
+        let area_idx = VSDB.db.area_idx(prefix);
+
+        // the files must not be rewritten when frozen
+        let _w = write_guard(Mode::Try)?;
+
+        let mut stats = CompactionStats {
+            disk_bytes_before: VSDB.db.area_disk_size(area_idx),
+            ..Default::default()
+        };
+        VSDB.db.compact(prefix);
+        stats.disk_bytes_after = stats
+            .disk_bytes_before
+            .and_then(|_| VSDB.db.area_disk_size(area_idx));
+
+        VSDB.db.iter(prefix).for_each(|(k, v)| {
+            stats.len += 1;
+            stats.data_bytes += (k.len() + v.len()) as u64;
+        });
+
+        Ok(stats)
+    }
+
+    // The instance is marked as sealed before the dumping,
+    // and the writes that have passed the check are waited for,
+    // so the sealed file never misses any writes
+    pub(crate) fn seal(&self) -> Result<()> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(eg!(WriteError::ReadOnly));
+        }
+
+        let prefix = self.prefix.to_bytes();
+
+        let w = write_guard(Mode::Try).c(d!())?;
+        if seal::is_sealed(prefix) {
+            return Ok(());
+        }
+        seal::begin(prefix);
+        drop(w);
+
+        snapshot::wait_for_writes();
+
+        // the files must not be written when frozen
+        let _w = match write_guard(Mode::Try) {
+            Ok(w) => w,
+            Err(e) => {
+                seal::abort(prefix);
+                return Err(eg!(e));
+            }
+        };
+        seal::seal(prefix, VSDB.db.iter(prefix)).c(d!())
+    }
+
+    pub(crate) fn unseal(&self) -> Result<()> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(eg!(WriteError::ReadOnly));
+        }
+
+        let _w = write_guard(Mode::Try).c(d!())?;
+        seal::unseal(self.prefix.to_bytes()).c(d!())
+    }
+
+    #[inline(always)]
+    pub(crate) fn is_sealed(&self) -> bool {
+        seal::is_sealed(self.prefix.to_bytes())
+    }
+
+    #[inline(always)]
+    pub(crate) fn track_access(&self, range_len: usize) {
+        access::track(self.prefix.to_bytes(), range_len);
+    }
+
+    #[inline(always)]
+    pub(crate) fn untrack_access(&self) {
+        access::untrack(self.prefix.to_bytes());
+    }
+
+    #[inline(always)]
+    pub(crate) fn access_stats(&self) -> Option<AccessStats> {
+        access::stats_of(self.prefix.to_bytes())
+    }
+
+    pub(crate) fn join_quota_group(&self, group: &QuotaGroup) {
+        let usage = self.iter().fold(QuotaUsage::default(), |mut u, (k, v)| {
+            let e = entry_usage(&k, &v);
+            u.keys += e.keys;
+            u.bytes += e.bytes;
+            u
+        });
+        quota::join(self.prefix.to_bytes(), group, usage);
+    }
+
+    #[inline(always)]
+    pub(crate) fn leave_quota_group(&self) {
+        quota::leave(self.prefix.to_bytes());
+    }
+
+    #[inline(always)]
+    pub(crate) fn quota_group(&self) -> Option<QuotaGroup> {
+        quota::group_of(self.prefix.to_bytes())
+    }
+
+    #[inline(always)]
+    pub(crate) fn bind_cache(&self, adapter: Arc<dyn CacheAdapter>) {
+        cache::bind(self.prefix.to_bytes(), adapter);
+    }
+
+    #[inline(always)]
+    pub(crate) fn unbind_cache(&self) {
+        cache::unbind(self.prefix.to_bytes());
+    }
+
+    // Only the opted-in instances keep their digests
+    pub(crate) fn enable_digest(&self) -> StdResult<(), WriteError> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(WriteError::ReadOnly);
+        }
+
+        let prefix = self.prefix.to_bytes();
+        let _w = write_guard(Mode::Try)?;
+        if digest::is_enabled(prefix) {
+            return Ok(());
+        }
+        // rebuilt by one scan on the next call
+        VSDB.db.set_instance_digest(prefix, None);
+        digest::enable(prefix)
+    }
+
+    // The kept digest is dropped, it will not be in step with the writes anymore
+    pub(crate) fn disable_digest(&self) -> StdResult<(), WriteError> {
+        if vsdb_is_read_only() {
+            count_rejected_write();
+            return Err(WriteError::ReadOnly);
+        }
+
+        let prefix = self.prefix.to_bytes();
+        let _w = write_guard(Mode::Try)?;
+        digest::disable(prefix)?;
+        VSDB.db.set_instance_digest(prefix, None);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn is_digest_enabled(&self) -> bool {
+        digest::is_enabled(self.prefix.to_bytes())
+    }
+
+    pub(crate) fn content_digest(&self) -> u64 {
+        let prefix = self.prefix.to_bytes();
+        let scan = || {
+            self.iter()
+                .fold(0u64, |d, (k, v)| d.wrapping_add(entry_digest(&k, &v)))
+        };
+
+        if !digest::is_enabled(prefix) {
+            return scan();
+        }
+
+        let x = LEN_LK[VSDB.db.area_idx(prefix)].lock();
+
+        if let Some(d) = VSDB.db.get_instance_digest(prefix) {
+            return d;
+        }
+
+        let d = scan();
+        if !vsdb_is_read_only() && !vsdb_is_frozen() {
+            VSDB.db.set_instance_digest(prefix, Some(d));
+        }
+
+        drop(x);
+        d
     }
 
     #[inline(always)]
@@ -284,6 +1352,7 @@ impl Mapx {
         prefix.copy_from_slice(s.as_ref());
         Self {
             prefix: Prefix::Recoverd(prefix),
+            limits: SizeLimits::default(),
         }
     }
 
@@ -298,13 +1367,118 @@ impl Mapx {
     }
 }
 
+#[inline(always)]
+fn would_block() -> WriteError {
+    count_rejected_write();
+    WriteError::WouldBlock
+}
+
+// How a write deals with the states that would block it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    // Return `WriteError::Frozen` when frozen, used by the `try_` writes
+    Try,
+    // Wait for `vsdb_unfreeze` when frozen,
+    // used by the writes that can not return errors, e.g. the drops of the guards
+    Wait,
+    // Like `Try`, and return `WriteError::WouldBlock`
+    // instead of waiting on the locks or the write stalls of the engine
+    NoWait,
+}
+
+// Held until the write is done, so no snapshots can be taken in the middle of it;
+// the frozen state is checked under it, so `vsdb_freeze` can wait for the writes in progress
+fn write_guard(mode: Mode) -> StdResult<RwLockReadGuard<'static, ()>, WriteError> {
+    loop {
+        let w = if Mode::NoWait == mode {
+            snapshot::try_write_guard().ok_or_else(would_block)?
+        } else {
+            snapshot::write_guard()
+        };
+        if !vsdb_is_frozen() {
+            return Ok(w);
+        }
+        if Mode::Wait != mode {
+            count_rejected_write();
+            return Err(WriteError::Frozen);
+        }
+        drop(w);
+        wait_for_unfreeze();
+    }
+}
+
+// Also checked under the write guard, so the sealed files never miss any writes
+#[inline(always)]
+fn check_sealed(prefix: PreBytes) -> StdResult<(), WriteError> {
+    if seal::is_sealed(prefix) {
+        count_rejected_write();
+        return Err(WriteError::Sealed);
+    }
+    Ok(())
+}
+
+#[inline(always)]
+fn entry_usage(key: &[u8], value: &[u8]) -> QuotaUsage {
+    QuotaUsage {
+        keys: 1,
+        bytes: (key.len() + value.len()) as u64,
+    }
+}
+
+#[inline(always)]
+fn try_lock_len(prefix: PreBytes) -> Option<MutexGuard<'static, ()>> {
+    LEN_LK[VSDB.db.area_idx(prefix)].try_lock()
+}
+
+// Keep the length hint and the digest in step with a single-entry write,
+// unknown digests are left to be rebuilt on reading.
+//
+// The lock of the area is taken here if not taken by the caller in advance,
+// it is skipped if there is nothing to update.
+fn update_meta(
+    len_lk: Option<MutexGuard<'static, ()>>,
+    prefix: PreBytes,
+    key: &[u8],
+    old: Option<&[u8]>,
+    new: Option<&[u8]>,
+) {
+    let len_changed = old.is_some() != new.is_some();
+    let digest_enabled = digest::is_enabled(prefix);
+    if !len_changed && !digest_enabled {
+        return;
+    }
+
+    let x = len_lk.unwrap_or_else(|| LEN_LK[VSDB.db.area_idx(prefix)].lock());
+
+    if len_changed {
+        let l = VSDB.db.get_instance_len_hint(prefix);
+        let l = if new.is_some() {
+            l + 1
+        } else {
+            l.saturating_sub(1)
+        };
+        VSDB.db.set_instance_len_hint(prefix, l);
+    }
+
+    if let Some(mut d) = digest_enabled
+        .then(|| VSDB.db.get_instance_digest(prefix))
+        .flatten()
+    {
+        if let Some(v) = old {
+            d = d.wrapping_sub(entry_digest(key, v));
+        }
+        if let Some(v) = new {
+            d = d.wrapping_add(entry_digest(key, v));
+        }
+        VSDB.db.set_instance_digest(prefix, Some(d));
+    }
+
+    drop(x);
+}
+
 impl Clone for Mapx {
     fn clone(&self) -> Self {
-        let mut new_instance = Self::new();
-        for (k, v) in self.iter() {
-            new_instance.insert(&k, &v);
-        }
-        new_instance
+        pnk!(self.clone_deep_with(Mode::Wait))
     }
 }
 
@@ -408,9 +1582,41 @@ impl<'de> Deserialize<'de> for Mapx {
 
 pub struct MapxIter<'a> {
     db_iter: EngineIter,
+    budget: Budget,
     _hdr: &'a Mapx,
 }
 
+impl<'a> MapxIter<'a> {
+    /// Stop before the entry that would make the total bytes
+    /// of the returned keys and values exceed `max_bytes`.
+    #[inline(always)]
+    pub fn take_bytes(mut self, max_bytes: u64) -> Self {
+        self.budget.bytes_left = Some(max_bytes);
+        self
+    }
+
+    /// Stop if the next entry is requested at or after the `deadline`.
+    #[inline(always)]
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.budget.deadline = Some(deadline);
+        self
+    }
+
+    /// Whether the iteration has been stopped by `take_bytes` or `deadline`,
+    /// instead of reaching the end of the range.
+    #[inline(always)]
+    pub fn is_truncated(&self) -> bool {
+        self.budget.truncated
+    }
+
+    fn charge(
+        &mut self,
+        entry: Option<(RawKey, RawValue)>,
+    ) -> Option<(RawKey, RawValue)> {
+        entry.filter(|(k, v)| self.budget.charge((k.len() + v.len()) as u64))
+    }
+}
+
 impl<'a> fmt::Debug for MapxIter<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("MapxIter").field(&self._hdr).finish()
@@ -420,18 +1626,147 @@ impl<'a> fmt::Debug for MapxIter<'a> {
 impl<'a> Iterator for MapxIter<'a> {
     type Item = (RawKey, RawValue);
     fn next(&mut self) -> Option<Self::Item> {
-        self.db_iter.next()
+        if self.budget.is_exhausted() {
+            return None;
+        }
+        let entry = self.db_iter.next();
+        self.charge(entry)
     }
 }
 
 impl<'a> DoubleEndedIterator for MapxIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.budget.is_exhausted() {
+            return None;
+        }
+        let entry = self.db_iter.next_back();
+        self.charge(entry)
+    }
+}
+
+// The limits are checked on the raw entries,
+// so the entries beyond them are never decoded or cloned by the upper layers
+#[derive(Clone, Copy, Debug, Default)]
+struct Budget {
+    bytes_left: Option<u64>,
+    deadline: Option<Instant>,
+    truncated: bool,
+}
+
+impl Budget {
+    #[inline(always)]
+    fn is_exhausted(&mut self) -> bool {
+        if !self.truncated && self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.truncated = true;
+        }
+        self.truncated
+    }
+
+    #[inline(always)]
+    fn charge(&mut self, n: u64) -> bool {
+        match self.bytes_left {
+            Some(left) if left < n => {
+                self.truncated = true;
+                false
+            }
+            Some(left) => {
+                self.bytes_left = Some(left - n);
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+pub struct MapxKeys<'a> {
+    db_iter: EngineKeyIter,
+    _hdr: &'a Mapx,
+}
+
+impl<'a> fmt::Debug for MapxKeys<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MapxKeys").field(&self._hdr).finish()
+    }
+}
+
+impl<'a> Iterator for MapxKeys<'a> {
+    type Item = RawKey;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.db_iter.next()
+    }
+}
+
+impl<'a> DoubleEndedIterator for MapxKeys<'a> {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.db_iter.next_back()
     }
 }
 
+// Merge the current entries with the saved old values of the snapshot,
+// the latter is checked on each step since it may grow during the iteration
+pub struct MapxSnapshotIter<'a> {
+    db_iter: Peekable<EngineIter>,
+    bounds: (Bound<RawKey>, Bound<RawKey>),
+    last: Option<RawKey>,
+    prefix: PreBytes,
+    guard: PreimagesGuard,
+    _hdr: &'a Mapx,
+}
+
+impl<'a> fmt::Debug for MapxSnapshotIter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MapxSnapshotIter").field(&self._hdr).finish()
+    }
+}
+
+impl<'a> Iterator for MapxSnapshotIter<'a> {
+    type Item = (RawKey, RawValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let preimages = self.guard.borrow();
+            let lo = match self.last.as_ref() {
+                Some(k) => Bound::Excluded(k.clone()),
+                None => self.bounds.0.clone(),
+            };
+            let saved = preimages
+                .entries
+                .get(&self.prefix)
+                .and_then(|m| m.range((lo, self.bounds.1.clone())).next())
+                .map(|(k, v)| (k.clone(), v.clone()));
+            drop(preimages);
+
+            let from_db = match (self.db_iter.peek(), saved.as_ref()) {
+                (None, None) => return None,
+                (Some((dk, _)), Some((sk, _))) => dk < sk,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+            };
+
+            if from_db {
+                let (k, v) = self.db_iter.next()?;
+                self.last = Some(k.clone());
+                return Some((k, v));
+            }
+
+            let (k, v) = saved?;
+            if self.db_iter.peek().is_some_and(|(dk, _)| *dk == k) {
+                self.db_iter.next();
+            }
+            self.last = Some(k.clone());
+            if let Some(v) = v {
+                return Some((k, v));
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
 pub struct MapxIterMut<'a> {
-    db_iter: EngineIter,
+    // `None` if the instance is sealed
+    db_iter: Option<EngineIter>,
     hdr: &'a mut Mapx,
 }
 
@@ -445,11 +1780,12 @@ impl<'a> Iterator for MapxIterMut<'a> {
     type Item = (RawKey, ValueIterMut<'a>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let (k, v) = self.db_iter.next()?;
+        let (k, v) = self.db_iter.as_mut()?.next()?;
 
         let vmut = ValueIterMut {
             key: k.clone(),
             value: v,
+            committed: true,
             iter_mut: unsafe { transmute::<&'_ mut Self, &'a mut Self>(self) },
         };
 
@@ -459,11 +1795,12 @@ impl<'a> Iterator for MapxIterMut<'a> {
 
 impl<'a> DoubleEndedIterator for MapxIterMut<'a> {
     fn next_back(&mut self) -> Option<Self::Item> {
-        let (k, v) = self.db_iter.next_back()?;
+        let (k, v) = self.db_iter.as_mut()?.next_back()?;
 
         let vmut = ValueIterMut {
             key: k.clone(),
             value: v,
+            committed: true,
             iter_mut: unsafe { transmute::<&'_ mut Self, &'a mut Self>(self) },
         };
 
@@ -475,12 +1812,31 @@ impl<'a> DoubleEndedIterator for MapxIterMut<'a> {
 pub struct ValueIterMut<'a> {
     key: RawKey,
     value: RawValue,
+    // no need to write again on dropping, until the value is borrowed mutably
+    committed: bool,
     iter_mut: &'a mut MapxIterMut<'a>,
 }
 
+impl<'a> ValueIterMut<'a> {
+    /// Write the value back now and return the errors,
+    /// instead of leaving it to the drop, which will block when frozen,
+    /// and discard the value if the write is rejected, e.g. over the size limits.
+    pub fn commit(&mut self) -> StdResult<(), WriteError> {
+        self.iter_mut
+            .hdr
+            .try_insert(&self.key[..], &self.value[..])?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+// Check the drop of `ValueMut` for the errors
 impl<'a> Drop for ValueIterMut<'a> {
     fn drop(&mut self) {
-        self.iter_mut.hdr.insert(&self.key[..], &self.value[..]);
+        if !self.committed {
+            let hdr = &mut self.iter_mut.hdr;
+            info_omit!(hdr.insert_with(&self.key, &self.value, Mode::Wait).c(d!()));
+        }
     }
 }
 
@@ -493,6 +1849,7 @@ impl<'a> Deref for ValueIterMut<'a> {
 
 impl<'a> DerefMut for ValueIterMut<'a> {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.committed = false;
         &mut self.value
     }
 }
@@ -504,12 +1861,33 @@ impl<'a> DerefMut for ValueIterMut<'a> {
 pub struct ValueMut<'a> {
     key: RawKey,
     value: RawValue,
+    // no need to write again on dropping, until the value is borrowed mutably
+    committed: bool,
     hdr: &'a mut Mapx,
 }
 
+impl<'a> ValueMut<'a> {
+    /// Write the value back now and return the errors,
+    /// instead of leaving it to the drop, which will block when frozen,
+    /// and discard the value if the write is rejected, e.g. over the size limits.
+    pub fn commit(&mut self) -> StdResult<(), WriteError> {
+        self.hdr.try_insert(&self.key[..], &self.value[..])?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+// Never panic here, it may run during unwinding;
+// a rejected write, e.g. over the size limits or the quota, is discarded,
+// `commit` should be used if the errors matter
 impl<'a> Drop for ValueMut<'a> {
     fn drop(&mut self) {
-        self.hdr.insert(&self.key[..], &self.value[..]);
+        if !self.committed {
+            info_omit!(self
+                .hdr
+                .insert_with(&self.key, &self.value, Mode::Wait)
+                .c(d!()));
+        }
     }
 }
 
@@ -522,6 +1900,7 @@ impl<'a> Deref for ValueMut<'a> {
 
 impl<'a> DerefMut for ValueMut<'a> {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.committed = false;
         &mut self.value
     }
 }
@@ -1,13 +1,17 @@
 use crate::common::{
-    vsdb_get_base_dir, vsdb_set_base_dir, Engine, Pre, PreBytes, RawKey, RawValue,
-    PREFIX_SIZE, RESERVED_ID_CNT,
+    detect_read_only,
+    retry::{retry, retry_with, with_retry, with_retry_input, BackendError},
+    vsdb_get_base_dir, vsdb_is_read_only, vsdb_set_base_dir, Engine, Pre, PreBytes,
+    RawKey, RawValue, PREFIX_SIZE, RESERVED_ID_CNT,
 };
 use parity_db::{BTreeIterator, CompressionType, Db as DB, Options};
 use parking_lot::Mutex;
 use ruc::*;
 use std::{
     borrow::Cow,
+    mem,
     ops::{Bound, RangeBounds},
+    result::Result as StdResult,
     sync::{
         atomic::{AtomicUsize, Ordering},
         LazyLock,
@@ -25,6 +29,10 @@ const META_KEY_MAX_KEYLEN: [u8; 1] = [u8::MAX];
 const META_KEY_PREFIX_ALLOCATOR: [u8; 1] = [u8::MIN];
 const META_KEY_NULL: [u8; 0] = [0; 0];
 
+// The number of entries committed in one transaction
+// when copying instances or ingesting sorted entries
+const COPY_BATCH_SIZE: usize = 4096;
+
 static HDR: LazyLock<DB> = LazyLock::new(|| paritydb_open().unwrap());
 
 pub struct ParityEngine {
@@ -42,13 +50,13 @@ impl ParityEngine {
     #[inline(always)]
     fn set_max_key_len(&self, len: usize) {
         self.max_keylen.store(len, Ordering::Relaxed);
-        self.hdr
-            .commit([(
+        retry("commit", || {
+            self.hdr.commit([(
                 META_COLID,
                 META_KEY_MAX_KEYLEN,
                 Some(len.to_be_bytes().to_vec()),
             )])
-            .unwrap();
+        });
     }
 
     #[inline(always)]
@@ -74,6 +82,18 @@ impl Engine for ParityEngine {
 
         let (prefix_allocator, initial_value) = PreAllocator::init();
 
+        if vsdb_is_read_only() {
+            let max_keylen = hdr
+                .get(META_COLID, &META_KEY_MAX_KEYLEN)
+                .c(d!())?
+                .c(d!("not a VSDB directory"))?;
+            return Ok(ParityEngine {
+                hdr,
+                prefix_allocator,
+                max_keylen: AtomicUsize::new(crate::parse_int!(max_keylen, usize)),
+            });
+        }
+
         if hdr.get(META_COLID, &META_KEY_MAX_KEYLEN).c(d!())?.is_none() {
             hdr.commit([(
                 META_COLID,
@@ -114,25 +134,26 @@ impl Engine for ParityEngine {
     #[allow(unused_variables)]
     fn alloc_prefix(&self) -> Pre {
         static LK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
-        let x = LK.lock();
-
-        // step 1
-        let ret = crate::parse_prefix!(self
-            .hdr
-            .get(META_COLID, &self.prefix_allocator.key)
-            .unwrap()
-            .unwrap());
-
-        // step 2
-        self.hdr
-            .commit([(
+
+        // the lock is released before sleeping between the attempts
+        retry("alloc_prefix", || {
+            let x = LK.lock();
+
+            // step 1
+            let ret = crate::parse_prefix!(self
+                .hdr
+                .get(META_COLID, &self.prefix_allocator.key)?
+                .unwrap());
+
+            // step 2
+            self.hdr.commit([(
                 META_COLID,
                 self.prefix_allocator.key,
                 Some((1 + ret).to_be_bytes().to_vec()),
-            )])
-            .unwrap();
+            )])?;
 
-        ret
+            Ok::<_, parity_db::Error>(ret)
+        })
     }
 
     fn area_count(&self) -> usize {
@@ -226,20 +247,50 @@ impl Engine for ParityEngine {
         }
     }
 
-    fn get(&self, hdr_prefix: PreBytes, key: &[u8]) -> Option<RawValue> {
+    // NOTE:
+    // parity-db has no key-only iterators, `BTreeIterator` still reads every value,
+    // they are only dropped here, so nothing is saved except the copies to the callers
+    fn iter_keys(&self, hdr_prefix: PreBytes) -> ParityKeyIter {
+        ParityKeyIter(self.iter(hdr_prefix))
+    }
+
+    fn range_keys<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        hdr_prefix: PreBytes,
+        bounds: R,
+    ) -> ParityKeyIter {
+        ParityKeyIter(self.range(hdr_prefix, bounds))
+    }
+
+    fn try_get(
+        &self,
+        hdr_prefix: PreBytes,
+        key: &[u8],
+    ) -> StdResult<Option<RawValue>, BackendError> {
+        let area_idx = self.area_idx(hdr_prefix);
+
+        let mut k = hdr_prefix.to_vec();
+        k.extend_from_slice(key);
+        with_retry("get", || self.hdr.get(area_idx as u8, &k))
+    }
+
+    // NOTE:
+    // `get_size` still reads the whole value from the btree of the column,
+    // it only saves the copy to the caller, parity-db has no cheaper existence check
+    fn contains_key(&self, hdr_prefix: PreBytes, key: &[u8]) -> bool {
         let area_idx = self.area_idx(hdr_prefix);
 
         let mut k = hdr_prefix.to_vec();
         k.extend_from_slice(key);
-        self.hdr.get(area_idx as u8, &k).unwrap()
+        retry("get", || self.hdr.get_size(area_idx as u8, &k)).is_some()
     }
 
-    fn insert(
+    fn try_insert(
         &self,
         hdr_prefix: PreBytes,
         key: &[u8],
         value: &[u8],
-    ) -> Option<RawValue> {
+    ) -> StdResult<Option<RawValue>, BackendError> {
         let area_idx = self.area_idx(hdr_prefix);
 
         let mut k = hdr_prefix.to_vec();
@@ -249,38 +300,203 @@ impl Engine for ParityEngine {
             self.set_max_key_len(key.len());
         }
 
-        let old_v = self.hdr.get(area_idx as u8, &k).unwrap();
-        self.hdr
-            .commit([(area_idx as u8, k, Some(value.to_vec()))])
-            .unwrap();
-        old_v
+        let old_v = with_retry("get", || self.hdr.get(area_idx as u8, &k))?;
+        with_retry("commit", || {
+            self.hdr
+                .commit([(area_idx as u8, &k, Some(value.to_vec()))])
+        })?;
+        Ok(old_v)
     }
 
-    fn remove(&self, hdr_prefix: PreBytes, key: &[u8]) -> Option<RawValue> {
+    fn try_remove(
+        &self,
+        hdr_prefix: PreBytes,
+        key: &[u8],
+    ) -> StdResult<Option<RawValue>, BackendError> {
         let area_idx = self.area_idx(hdr_prefix);
 
         let mut k = hdr_prefix.to_vec();
         k.extend_from_slice(key);
-        let old_v = self.hdr.get(area_idx as u8, &k).unwrap();
-        self.hdr.commit([(area_idx as u8, k, None)]).unwrap();
-        old_v
+        let old_v = with_retry("get", || self.hdr.get(area_idx as u8, &k))?;
+        with_retry("commit", || self.hdr.commit([(area_idx as u8, &k, None)]))?;
+        Ok(old_v)
+    }
+
+    fn try_write_batch(
+        &self,
+        hdr_prefix: PreBytes,
+        ops: &[(&[u8], Option<&[u8]>)],
+    ) -> StdResult<Vec<Option<RawValue>>, BackendError> {
+        let area_idx = self.area_idx(hdr_prefix);
+
+        let mut olds = Vec::with_capacity(ops.len());
+        let mut batch = Vec::with_capacity(ops.len());
+        for (key, value) in ops.iter() {
+            if value.is_some() && key.len() > self.get_max_keylen() {
+                self.set_max_key_len(key.len());
+            }
+            let mut k = hdr_prefix.to_vec();
+            k.extend_from_slice(key);
+            olds.push(with_retry("get", || self.hdr.get(area_idx as u8, &k))?);
+            batch.push((area_idx as u8, k, value.map(|v| v.to_vec())));
+        }
+        with_retry("commit", || {
+            self.hdr
+                .commit(batch.iter().map(|(c, k, v)| (*c, k, v.clone())))
+        })?;
+        Ok(olds)
+    }
+
+    fn remove_range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        hdr_prefix: PreBytes,
+        bounds: R,
+    ) -> u64 {
+        let area_idx = self.area_idx(hdr_prefix);
+
+        let ops = self
+            .range_keys(hdr_prefix, bounds)
+            .map(|k| {
+                let mut full_k = hdr_prefix.to_vec();
+                full_k.extend_from_slice(&k);
+                (area_idx as u8, full_k, None)
+            })
+            .collect::<Vec<_>>();
+        let n = ops.len() as u64;
+
+        // all in one transaction
+        retry_with("commit", ops, |ops| self.hdr.commit(ops));
+        n
+    }
+
+    // There are no range deletions in parity-db, so every key is deleted,
+    // they are committed in batches to keep the memory bounded.
+    //
+    // NOTE: unlike `remove_range`, it is not atomic,
+    // the remaining entries are still visible to the readers until the last batch
+    fn clear_instance(&self, hdr_prefix: PreBytes) {
+        let area_idx = self.area_idx(hdr_prefix);
+
+        loop {
+            // the committed deletions are skipped by the next walk
+            let ops = self
+                .iter_keys(hdr_prefix)
+                .take(COPY_BATCH_SIZE)
+                .map(|k| {
+                    let mut full_k = hdr_prefix.to_vec();
+                    full_k.extend_from_slice(&k);
+                    (area_idx as u8, full_k, None)
+                })
+                .collect::<Vec<_>>();
+            if ops.is_empty() {
+                return;
+            }
+            retry_with("commit", ops, |ops| self.hdr.commit(ops));
+        }
+    }
+
+    fn copy_instance(&self, src_prefix: PreBytes, dst_prefix: PreBytes) -> u64 {
+        let area_idx = self.area_idx(dst_prefix);
+
+        let mut n = 0;
+        let mut ops = Vec::with_capacity(COPY_BATCH_SIZE);
+        for (k, v) in self.iter(src_prefix) {
+            let mut full_k = dst_prefix.to_vec();
+            full_k.extend_from_slice(&k);
+            ops.push((area_idx as u8, full_k, Some(v)));
+            if COPY_BATCH_SIZE == ops.len() {
+                n += ops.len() as u64;
+                let batch = mem::replace(&mut ops, Vec::with_capacity(COPY_BATCH_SIZE));
+                retry_with("commit", batch, |ops| self.hdr.commit(ops));
+            }
+        }
+        n += ops.len() as u64;
+        retry_with("commit", ops, |ops| self.hdr.commit(ops));
+
+        n
+    }
+
+    fn ingest_sorted(
+        &self,
+        hdr_prefix: PreBytes,
+        entries: &mut dyn Iterator<Item = (RawKey, RawValue)>,
+    ) -> StdResult<u64, BackendError> {
+        let area_idx = self.area_idx(hdr_prefix);
+
+        let mut n = 0;
+        let mut ops = Vec::with_capacity(COPY_BATCH_SIZE);
+        for (k, v) in entries {
+            if k.len() > self.get_max_keylen() {
+                self.set_max_key_len(k.len());
+            }
+            let mut full_k = hdr_prefix.to_vec();
+            full_k.extend_from_slice(&k);
+            ops.push((area_idx as u8, full_k, Some(v)));
+            if COPY_BATCH_SIZE == ops.len() {
+                let cnt = ops.len() as u64;
+                let batch = mem::replace(&mut ops, Vec::with_capacity(COPY_BATCH_SIZE));
+                with_retry_input("commit", batch, |ops| self.hdr.commit(ops))?;
+                n += cnt;
+            }
+        }
+        n += ops.len() as u64;
+        with_retry_input("commit", ops, |ops| self.hdr.commit(ops))?;
+
+        Ok(n)
     }
 
     fn get_instance_len_hint(&self, instance_prefix: PreBytes) -> u64 {
-        crate::parse_int!(
-            self.hdr.get(META_COLID, &instance_prefix).unwrap().unwrap(),
-            u64
-        )
+        retry("get", || self.hdr.get(META_COLID, &instance_prefix))
+            .map(|l| crate::parse_int!(l, u64))
+            .unwrap_or_else(|| super::missing_len_hint(instance_prefix))
     }
 
     fn set_instance_len_hint(&self, instance_prefix: PreBytes, new_len: u64) {
-        self.hdr
-            .commit([(
+        retry("commit", || {
+            self.hdr.commit([(
                 META_COLID,
                 instance_prefix,
                 Some(new_len.to_be_bytes().to_vec()),
             )])
-            .unwrap();
+        });
+    }
+
+    fn get_instance_digest(&self, instance_prefix: PreBytes) -> Option<u64> {
+        retry("get", || {
+            self.hdr
+                .get(META_COLID, &super::digest_key(instance_prefix))
+        })
+        .map(|d| crate::parse_int!(d, u64))
+    }
+
+    fn set_instance_digest(&self, instance_prefix: PreBytes, digest: Option<u64>) {
+        retry("commit", || {
+            self.hdr.commit([(
+                META_COLID,
+                super::digest_key(instance_prefix),
+                digest.map(|d| d.to_be_bytes().to_vec()),
+            )])
+        });
+    }
+
+    fn get_instance_offset(&self, instance_prefix: PreBytes) -> u64 {
+        retry("get", || {
+            self.hdr
+                .get(META_COLID, &super::offset_key(instance_prefix))
+        })
+        .map(|o| crate::parse_int!(o, u64))
+        .unwrap_or(0)
+    }
+
+    // The default value is not stored
+    fn set_instance_offset(&self, instance_prefix: PreBytes, offset: u64) {
+        retry("commit", || {
+            self.hdr.commit([(
+                META_COLID,
+                super::offset_key(instance_prefix),
+                (0 != offset).then(|| offset.to_be_bytes().to_vec()),
+            )])
+        });
     }
 }
 
@@ -354,6 +570,21 @@ impl DoubleEndedIterator for ParityIter {
     }
 }
 
+pub struct ParityKeyIter(ParityIter);
+
+impl Iterator for ParityKeyIter {
+    type Item = RawKey;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+}
+
+impl DoubleEndedIterator for ParityKeyIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, _)| k)
+    }
+}
+
 // key of the prefix allocator in the 'hdr'
 struct PreAllocator {
     key: [u8; 1],
@@ -395,7 +626,11 @@ fn paritydb_open() -> Result<DB> {
         c.compression = CompressionType::NoCompression;
     });
 
-    let db = DB::open_or_create(&cfg).c(d!())?;
+    let db = if detect_read_only(&dir) {
+        with_retry("open", || DB::open_read_only(&cfg)).c(d!())?
+    } else {
+        with_retry("open", || DB::open_or_create(&cfg)).c(d!())?
+    };
 
     Ok(db)
 }
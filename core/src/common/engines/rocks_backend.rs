@@ -1,20 +1,24 @@
 use crate::common::{
-    vsdb_get_base_dir, vsdb_set_base_dir, Engine, Pre, PreBytes, RawKey, RawValue, GB,
-    MB, PREFIX_SIZE, RESERVED_ID_CNT,
+    detect_read_only,
+    retry::{retry, with_retry, BackendError},
+    vsdb_get_base_dir, vsdb_is_read_only, vsdb_set_base_dir, Engine, Pre, PreBytes,
+    RawKey, RawValue, GB, KB, MB, PREFIX_SIZE, RESERVED_ID_CNT,
 };
 use parking_lot::Mutex;
 use rocksdb::{
-    ColumnFamily, ColumnFamilyDescriptor, DBCompressionType, DBIterator, Direction,
-    IteratorMode, Options, ReadOptions, SliceTransform, DB,
+    BlockBasedOptions, Cache, ColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle,
+    DBCompressionType, DBIterator, DBRawIterator, Direction, ErrorKind, IteratorMode,
+    Options, ReadOptions, SliceTransform, SstFileWriter, WriteBatch, WriteOptions, DB,
 };
 use ruc::*;
 use std::{
     borrow::Cow,
     fs,
-    mem::size_of,
+    mem::{self, size_of},
     ops::{Bound, RangeBounds},
+    result::Result as StdResult,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
         LazyLock,
     },
     thread::available_parallelism,
@@ -27,8 +31,130 @@ const DATA_SET_NUM: usize = 2;
 const META_KEY_MAX_KEYLEN: [u8; 1] = [u8::MAX];
 const META_KEY_PREFIX_ALLOCATOR: [u8; 1] = [u8::MIN];
 
+// The number of entries written in one batch when copying instances
+const COPY_BATCH_SIZE: u64 = 4096;
+
+// The number of entries written into one SST file when ingesting sorted entries
+const INGEST_FILE_ENTRIES: usize = 1 << 20;
+
 static HDR: LazyLock<(DB, Vec<String>)> = LazyLock::new(|| rocksdb_open().unwrap());
 
+// The default capacity of the block cache of rocksdb
+const DEFAULT_BLOCK_CACHE_CAP: usize = 32 * MB as usize;
+
+static TUNING: LazyLock<Mutex<Tuning>> = LazyLock::new(|| {
+    Mutex::new(Tuning {
+        profile: None,
+        opened: false,
+        bulk_loading: false,
+        block_cache: None,
+    })
+});
+
+struct Tuning {
+    profile: Option<RocksProfile>,
+    opened: bool,
+    // The auto compactions are disabled by `RocksProfile::BulkLoad` until
+    // `vsdb_finish_bulk_load` is called
+    bulk_loading: bool,
+    // Shared by all the column families, kept to be emptied by `shrink_to_fit`,
+    // along with its capacity
+    block_cache: Option<(Cache, usize)>,
+}
+
+/// Curated option sets of rocksdb for common workloads.
+///
+/// NOTE: the options are shared by all the instances,
+/// rocksdb can only be tuned per column family, not per instance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RocksProfile {
+    /// Random reads of single keys: bloom filters and a big block cache.
+    PointLookup,
+    /// Ordered iterations: big data blocks and a big block cache.
+    RangeScan,
+    /// Mass writes of an initial import: auto compactions are disabled,
+    /// call `vsdb_finish_bulk_load` after the loading to compact the data
+    /// and to enable them again.
+    BulkLoad,
+}
+
+impl RocksProfile {
+    // Return the capacity of the block cache
+    fn apply(self, cfg: &mut Options, table: &mut BlockBasedOptions) -> usize {
+        match self {
+            Self::PointLookup => {
+                table.set_cache_index_and_filter_blocks(true);
+                table.set_bloom_filter(10.0, false);
+                table.set_block_size(4 * KB as usize);
+                // the prefix extractor is always set
+                cfg.set_memtable_prefix_bloom_ratio(0.1);
+                cfg.set_compaction_style(DBCompactionStyle::Level);
+                cfg.set_level_compaction_dynamic_level_bytes(true);
+                GB as usize
+            }
+            Self::RangeScan => {
+                table.set_block_size(64 * KB as usize);
+                cfg.set_compaction_style(DBCompactionStyle::Level);
+                cfg.set_level_compaction_dynamic_level_bytes(true);
+                GB as usize
+            }
+            Self::BulkLoad => {
+                // only the mutable options are changed,
+                // so they can be restored without reopening
+                table.set_block_size(64 * KB as usize);
+                cfg.set_disable_auto_compactions(true);
+                cfg.set_level_zero_file_num_compaction_trigger(1 << 30);
+                cfg.set_level_zero_slowdown_writes_trigger(1 << 30);
+                cfg.set_level_zero_stop_writes_trigger(1 << 30);
+                cfg.set_soft_pending_compaction_bytes_limit(0);
+                cfg.set_hard_pending_compaction_bytes_limit(0);
+                DEFAULT_BLOCK_CACHE_CAP
+            }
+        }
+    }
+}
+
+// The defaults of rocksdb for the options changed by `RocksProfile::BulkLoad`
+const COMPACTION_DEFAULTS: [(&str, &str); 6] = [
+    ("disable_auto_compactions", "false"),
+    ("level0_file_num_compaction_trigger", "4"),
+    ("level0_slowdown_writes_trigger", "20"),
+    ("level0_stop_writes_trigger", "36"),
+    ("soft_pending_compaction_bytes_limit", "68719476736"),
+    ("hard_pending_compaction_bytes_limit", "274877906944"),
+];
+
+/// Tune rocksdb with a curated profile,
+/// it must be called before the first use of VSDB,
+/// and it applies to all column families.
+pub fn vsdb_tune(profile: RocksProfile) -> Result<()> {
+    let mut t = TUNING.lock();
+    if t.opened {
+        return Err(eg!("VSDB has been initialized !!"));
+    }
+    t.profile = Some(profile);
+    Ok(())
+}
+
+/// Compact all the data written under `RocksProfile::BulkLoad`,
+/// and enable the auto compactions again, the other options of the profile are kept.
+pub fn vsdb_finish_bulk_load() -> Result<()> {
+    let mut t = TUNING.lock();
+    if !t.bulk_loading {
+        return Err(eg!("not in the bulk load mode"));
+    }
+
+    let (db, cfs) = &*HDR;
+    for cf in cfs.iter().map(|cf| db.cf_handle(cf).c(d!())) {
+        let cf = cf?;
+        db.set_options_cf(cf, &COMPACTION_DEFAULTS).c(d!())?;
+        db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+    }
+    t.bulk_loading = false;
+
+    Ok(())
+}
+
 pub struct RocksEngine {
     meta: &'static DB,
     areas: Vec<&'static str>,
@@ -47,15 +173,82 @@ impl RocksEngine {
         self.max_keylen.load(Ordering::Relaxed)
     }
 
+    // The batch is rebuilt from its data for retries
+    fn write(&self, batch: WriteBatch) {
+        retry("write", || {
+            self.meta.write(WriteBatch::from_data(batch.data()))
+        });
+    }
+
     #[inline(always)]
     fn set_max_key_len(&self, len: usize) {
         self.max_keylen.store(len, Ordering::Relaxed);
-        self.meta
-            .put(META_KEY_MAX_KEYLEN, len.to_be_bytes())
-            .unwrap();
+        retry("put", || {
+            self.meta.put(META_KEY_MAX_KEYLEN, len.to_be_bytes())
+        });
+    }
+
+    // Like `set_max_key_len`, but return `None` if the write would be stalled
+    fn set_max_key_len_nowait(&self, len: usize) -> Option<()> {
+        no_stall(self.meta.put_opt(
+            META_KEY_MAX_KEYLEN,
+            len.to_be_bytes(),
+            &no_slowdown(),
+        ))?;
+        self.max_keylen.store(len, Ordering::Relaxed);
+        Some(())
     }
 
     #[inline(always)]
+    // (forward options, reverse options, lower key, upper key)
+    fn range_opts<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &self,
+        meta_prefix: PreBytes,
+        bounds: R,
+    ) -> (ReadOptions, ReadOptions, Vec<u8>, Vec<u8>) {
+        let mut opt = ReadOptions::default();
+        let mut opt_rev = ReadOptions::default();
+
+        let mut b_lo = meta_prefix.to_vec();
+        match bounds.start_bound() {
+            Bound::Included(lo) => {
+                b_lo.extend_from_slice(lo);
+                opt.set_iterate_lower_bound(b_lo.as_slice());
+                opt_rev.set_iterate_lower_bound(b_lo.as_slice());
+            }
+            Bound::Excluded(lo) => {
+                b_lo.extend_from_slice(lo);
+                b_lo.push(0u8);
+                opt.set_iterate_lower_bound(b_lo.as_slice());
+                opt_rev.set_iterate_lower_bound(b_lo.as_slice());
+            }
+            _ => {}
+        };
+
+        let mut b_hi = meta_prefix.to_vec();
+        let h = match bounds.end_bound() {
+            Bound::Included(hi) => {
+                b_hi.extend_from_slice(hi);
+                b_hi.push(0u8);
+                opt.set_iterate_upper_bound(b_hi.as_slice());
+                opt_rev.set_iterate_upper_bound(b_hi.as_slice());
+                b_hi
+            }
+            Bound::Excluded(hi) => {
+                b_hi.extend_from_slice(hi);
+                opt.set_iterate_upper_bound(b_hi.as_slice());
+                opt_rev.set_iterate_upper_bound(b_hi.as_slice());
+                b_hi
+            }
+            _ => self.get_upper_bound_value(meta_prefix),
+        };
+
+        opt.set_prefix_same_as_start(true);
+        opt_rev.set_prefix_same_as_start(true);
+
+        (opt, opt_rev, b_lo, h)
+    }
+
     fn get_upper_bound_value(&self, meta_prefix: PreBytes) -> Vec<u8> {
         const BUF: [u8; 256] = [u8::MAX; 256];
 
@@ -79,6 +272,19 @@ impl Engine for RocksEngine {
 
         let (prefix_allocator, initial_value) = PreAllocator::init();
 
+        if vsdb_is_read_only() {
+            let max_keylen = meta
+                .get(META_KEY_MAX_KEYLEN)
+                .c(d!())?
+                .c(d!("not a VSDB directory"))?;
+            return Ok(RocksEngine {
+                meta,
+                areas,
+                prefix_allocator,
+                max_keylen: AtomicUsize::new(crate::parse_int!(max_keylen, usize)),
+            });
+        }
+
         if meta.get(META_KEY_MAX_KEYLEN).c(d!())?.is_none() {
             meta.put(META_KEY_MAX_KEYLEN, 0_usize.to_be_bytes())
                 .c(d!())?;
@@ -107,21 +313,21 @@ impl Engine for RocksEngine {
     #[allow(unused_variables)]
     fn alloc_prefix(&self) -> Pre {
         static LK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
-        let x = LK.lock();
 
-        // step 1
-        let ret = crate::parse_prefix!(self
-            .meta
-            .get(self.prefix_allocator.key)
-            .unwrap()
-            .unwrap());
+        // the lock is released before sleeping between the attempts
+        retry("alloc_prefix", || {
+            let x = LK.lock();
+
+            // step 1
+            let ret =
+                crate::parse_prefix!(self.meta.get(self.prefix_allocator.key)?.unwrap());
 
-        // step 2
-        self.meta
-            .put(self.prefix_allocator.key, (1 + ret).to_be_bytes())
-            .unwrap();
+            // step 2
+            self.meta
+                .put(self.prefix_allocator.key, (1 + ret).to_be_bytes())?;
 
-        ret
+            Ok::<_, rocksdb::Error>(ret)
+        })
     }
 
     fn area_count(&self) -> usize {
@@ -135,6 +341,60 @@ impl Engine for RocksEngine {
         });
     }
 
+    // The memtables are released by flushing them,
+    // and the block cache is emptied by shrinking its capacity to 0 for a moment
+    fn shrink_to_fit(&self) -> usize {
+        // only used to report the released bytes,
+        // an unreadable property is counted as nothing
+        let memtables = || {
+            (0..DATA_SET_NUM)
+                .map(|i| {
+                    self.meta
+                        .property_int_value_cf(
+                            self.cf_hdr(i),
+                            "rocksdb.size-all-mem-tables",
+                        )
+                        .ok()
+                        .flatten()
+                        .unwrap_or(0) as usize
+                })
+                .sum::<usize>()
+        };
+
+        let memtables_before = memtables();
+        self.flush();
+        retry("flush_wal", || self.meta.flush_wal(true));
+        let mut released = memtables_before.saturating_sub(memtables());
+
+        if let Some((cache, cap)) = TUNING.lock().block_cache.as_mut() {
+            let used = cache.get_usage();
+            cache.set_capacity(0);
+            cache.set_capacity(*cap);
+            released += used.saturating_sub(cache.get_usage());
+        }
+
+        released
+    }
+
+    fn compact(&self, meta_prefix: PreBytes) {
+        let area_idx = self.area_idx(meta_prefix);
+        self.meta.compact_range_cf(
+            self.cf_hdr(area_idx),
+            Some(meta_prefix),
+            Some(self.get_upper_bound_value(meta_prefix)),
+        );
+    }
+
+    fn area_disk_size(&self, area_idx: usize) -> Option<u64> {
+        let size = self
+            .meta
+            .property_int_value_cf(self.cf_hdr(area_idx), "rocksdb.total-sst-files-size")
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+        Some(size)
+    }
+
     fn iter(&self, meta_prefix: PreBytes) -> RocksIter {
         let area_idx = self.area_idx(meta_prefix);
 
@@ -163,53 +423,12 @@ impl Engine for RocksEngine {
         bounds: R,
     ) -> RocksIter {
         let area_idx = self.area_idx(meta_prefix);
-
-        let mut opt = ReadOptions::default();
-        let mut opt_rev = ReadOptions::default();
-
-        let mut b_lo = meta_prefix.to_vec();
-        let l = match bounds.start_bound() {
-            Bound::Included(lo) => {
-                b_lo.extend_from_slice(lo);
-                opt.set_iterate_lower_bound(b_lo.as_slice());
-                opt_rev.set_iterate_lower_bound(b_lo.as_slice());
-                b_lo.as_slice()
-            }
-            Bound::Excluded(lo) => {
-                b_lo.extend_from_slice(lo);
-                b_lo.push(0u8);
-                opt.set_iterate_lower_bound(b_lo.as_slice());
-                opt_rev.set_iterate_lower_bound(b_lo.as_slice());
-                b_lo.as_slice()
-            }
-            _ => meta_prefix.as_slice(),
-        };
-
-        let mut b_hi = meta_prefix.to_vec();
-        let h = match bounds.end_bound() {
-            Bound::Included(hi) => {
-                b_hi.extend_from_slice(hi);
-                b_hi.push(0u8);
-                opt.set_iterate_upper_bound(b_hi.as_slice());
-                opt_rev.set_iterate_upper_bound(b_hi.as_slice());
-                b_hi
-            }
-            Bound::Excluded(hi) => {
-                b_hi.extend_from_slice(hi);
-                opt.set_iterate_upper_bound(b_hi.as_slice());
-                opt_rev.set_iterate_upper_bound(b_hi.as_slice());
-                b_hi
-            }
-            _ => self.get_upper_bound_value(meta_prefix),
-        };
-
-        opt.set_prefix_same_as_start(true);
-        opt_rev.set_prefix_same_as_start(true);
+        let (opt, opt_rev, l, h) = self.range_opts(meta_prefix, bounds);
 
         let inner = self.meta.iterator_cf_opt(
             self.cf_hdr(area_idx),
             opt,
-            IteratorMode::From(l, Direction::Forward),
+            IteratorMode::From(&l, Direction::Forward),
         );
 
         let inner_rev = self.meta.iterator_cf_opt(
@@ -221,20 +440,58 @@ impl Engine for RocksEngine {
         RocksIter { inner, inner_rev }
     }
 
-    fn get(&self, meta_prefix: PreBytes, key: &[u8]) -> Option<RawValue> {
+    fn iter_keys(&self, meta_prefix: PreBytes) -> RocksKeyIter {
+        self.range_keys(meta_prefix, ..)
+    }
+
+    // Use the raw iterators, the values will not be copied out
+    fn range_keys<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        meta_prefix: PreBytes,
+        bounds: R,
+    ) -> RocksKeyIter {
+        let area_idx = self.area_idx(meta_prefix);
+        let (opt, opt_rev, l, h) = self.range_opts(meta_prefix, bounds);
+
+        let mut inner = self.meta.raw_iterator_cf_opt(self.cf_hdr(area_idx), opt);
+        inner.seek(&l);
+
+        let mut inner_rev = self
+            .meta
+            .raw_iterator_cf_opt(self.cf_hdr(area_idx), opt_rev);
+        inner_rev.seek_for_prev(&h);
+
+        RocksKeyIter { inner, inner_rev }
+    }
+
+    fn try_get(
+        &self,
+        meta_prefix: PreBytes,
+        key: &[u8],
+    ) -> StdResult<Option<RawValue>, BackendError> {
+        let area_idx = self.area_idx(meta_prefix);
+
+        let mut k = meta_prefix.to_vec();
+        k.extend_from_slice(key);
+        with_retry("get", || self.meta.get_cf(self.cf_hdr(area_idx), &k))
+    }
+
+    // The value is pinned in the block cache instead of being copied out,
+    // but the block holding it is still read
+    fn contains_key(&self, meta_prefix: PreBytes, key: &[u8]) -> bool {
         let area_idx = self.area_idx(meta_prefix);
 
         let mut k = meta_prefix.to_vec();
         k.extend_from_slice(key);
-        self.meta.get_cf(self.cf_hdr(area_idx), k).unwrap()
+        retry("get", || self.meta.get_pinned_cf(self.cf_hdr(area_idx), &k)).is_some()
     }
 
-    fn insert(
+    fn try_insert(
         &self,
         meta_prefix: PreBytes,
         key: &[u8],
         value: &[u8],
-    ) -> Option<RawValue> {
+    ) -> StdResult<Option<RawValue>, BackendError> {
         let area_idx = self.area_idx(meta_prefix);
 
         let mut k = meta_prefix.to_vec();
@@ -244,29 +501,260 @@ impl Engine for RocksEngine {
             self.set_max_key_len(key.len());
         }
 
-        let old_v = self.meta.get_cf(self.cf_hdr(area_idx), &k).unwrap();
-        self.meta.put_cf(self.cf_hdr(area_idx), k, value).unwrap();
-        old_v
+        let old_v = with_retry("get", || self.meta.get_cf(self.cf_hdr(area_idx), &k))?;
+        with_retry("put", || self.meta.put_cf(self.cf_hdr(area_idx), &k, value))?;
+        Ok(old_v)
+    }
+
+    fn try_remove(
+        &self,
+        meta_prefix: PreBytes,
+        key: &[u8],
+    ) -> StdResult<Option<RawValue>, BackendError> {
+        let area_idx = self.area_idx(meta_prefix);
+
+        let mut k = meta_prefix.to_vec();
+        k.extend_from_slice(key);
+        let old_v = with_retry("get", || self.meta.get_cf(self.cf_hdr(area_idx), &k))?;
+        with_retry("delete", || self.meta.delete_cf(self.cf_hdr(area_idx), &k))?;
+        Ok(old_v)
+    }
+
+    fn insert_nowait(
+        &self,
+        meta_prefix: PreBytes,
+        key: &[u8],
+        value: &[u8],
+    ) -> Option<Option<RawValue>> {
+        let area_idx = self.area_idx(meta_prefix);
+
+        let mut k = meta_prefix.to_vec();
+        k.extend_from_slice(key);
+
+        if key.len() > self.get_max_keylen() {
+            self.set_max_key_len_nowait(key.len())?;
+        }
+
+        let old_v = retry("get", || self.meta.get_cf(self.cf_hdr(area_idx), &k));
+        no_stall(
+            self.meta
+                .put_cf_opt(self.cf_hdr(area_idx), &k, value, &no_slowdown()),
+        )
+        .map(|_| old_v)
     }
 
-    fn remove(&self, meta_prefix: PreBytes, key: &[u8]) -> Option<RawValue> {
+    fn remove_nowait(
+        &self,
+        meta_prefix: PreBytes,
+        key: &[u8],
+    ) -> Option<Option<RawValue>> {
         let area_idx = self.area_idx(meta_prefix);
 
         let mut k = meta_prefix.to_vec();
         k.extend_from_slice(key);
-        let old_v = self.meta.get_cf(self.cf_hdr(area_idx), &k).unwrap();
-        self.meta.delete_cf(self.cf_hdr(area_idx), k).unwrap();
-        old_v
+        let old_v = retry("get", || self.meta.get_cf(self.cf_hdr(area_idx), &k));
+        no_stall(
+            self.meta
+                .delete_cf_opt(self.cf_hdr(area_idx), &k, &no_slowdown()),
+        )
+        .map(|_| old_v)
+    }
+
+    fn try_write_batch(
+        &self,
+        meta_prefix: PreBytes,
+        ops: &[(&[u8], Option<&[u8]>)],
+    ) -> StdResult<Vec<Option<RawValue>>, BackendError> {
+        let area_idx = self.area_idx(meta_prefix);
+        let cf = self.cf_hdr(area_idx);
+
+        let mut olds = Vec::with_capacity(ops.len());
+        let mut batch = WriteBatch::default();
+        for (key, value) in ops.iter() {
+            if value.is_some() && key.len() > self.get_max_keylen() {
+                self.set_max_key_len(key.len());
+            }
+            let mut k = meta_prefix.to_vec();
+            k.extend_from_slice(key);
+            olds.push(with_retry("get", || self.meta.get_cf(cf, &k))?);
+            match value {
+                Some(v) => batch.put_cf(cf, &k, v),
+                None => batch.delete_cf(cf, &k),
+            }
+        }
+        with_retry("write", || {
+            self.meta.write(WriteBatch::from_data(batch.data()))
+        })?;
+        Ok(olds)
+    }
+
+    fn remove_range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        meta_prefix: PreBytes,
+        bounds: R,
+    ) -> u64 {
+        let area_idx = self.area_idx(meta_prefix);
+
+        let mut n = 0;
+        let mut first_last: Option<(RawKey, RawKey)> = None;
+        for (k, _) in self.range(meta_prefix, bounds) {
+            n += 1;
+            if let Some((_, last)) = first_last.as_mut() {
+                *last = k;
+            } else {
+                first_last = Some((k.clone(), k));
+            }
+        }
+
+        if let Some((first, mut last)) = first_last {
+            let mut lo = meta_prefix.to_vec();
+            lo.extend_from_slice(&first);
+
+            // the end bound of `delete_range_cf` is exclusive
+            last.push(0u8);
+            let mut hi = meta_prefix.to_vec();
+            hi.extend_from_slice(&last);
+
+            retry("delete_range", || {
+                self.meta.delete_range_cf(self.cf_hdr(area_idx), &lo, &hi)
+            });
+        }
+
+        n
+    }
+
+    // One range deletion covers the whole instance,
+    // nothing is read, the tombstone is dropped by the later compactions
+    fn clear_instance(&self, meta_prefix: PreBytes) {
+        let area_idx = self.area_idx(meta_prefix);
+
+        // the next prefix is the exclusive end bound of the instance,
+        // the last prefix has no next one, its keys are deleted by their own range
+        let Some(next) = Pre::from_be_bytes(meta_prefix).checked_add(1) else {
+            self.remove_range(meta_prefix, ..);
+            return;
+        };
+        let hi = next.to_be_bytes();
+        retry("delete_range", || {
+            self.meta
+                .delete_range_cf(self.cf_hdr(area_idx), meta_prefix, hi)
+        });
+    }
+
+    fn copy_instance(&self, src_prefix: PreBytes, dst_prefix: PreBytes) -> u64 {
+        let cf = self.cf_hdr(self.area_idx(dst_prefix));
+
+        let mut n = 0;
+        let mut batch = WriteBatch::default();
+        for (k, v) in self.iter(src_prefix) {
+            let mut full_k = dst_prefix.to_vec();
+            full_k.extend_from_slice(&k);
+            batch.put_cf(cf, full_k, v);
+            n += 1;
+            if 0 == n % COPY_BATCH_SIZE {
+                self.write(mem::take(&mut batch));
+            }
+        }
+        self.write(batch);
+
+        n
+    }
+
+    // The entries are written into SST files and then ingested,
+    // which bypasses the memtables and the WAL
+    fn ingest_sorted(
+        &self,
+        meta_prefix: PreBytes,
+        entries: &mut dyn Iterator<Item = (RawKey, RawValue)>,
+    ) -> StdResult<u64, BackendError> {
+        // unique among the processes sharing the base dir and the concurrent calls
+        static INGEST_SEQ: AtomicU64 = AtomicU64::new(0);
+
+        let cf = self.cf_hdr(self.area_idx(meta_prefix));
+        let opts = Options::default();
+        let path = vsdb_get_base_dir().join(format!(
+            "ingest_{}_{}_{}.sst",
+            std::process::id(),
+            Pre::from_be_bytes(meta_prefix),
+            INGEST_SEQ.fetch_add(1, Ordering::Relaxed)
+        ));
+        let to_err = |op, e: rocksdb::Error| BackendError {
+            op,
+            attempts: 1,
+            cause: e.to_string(),
+        };
+
+        let mut n = 0;
+        let mut entries = entries.peekable();
+        while entries.peek().is_some() {
+            let mut cnt = 0;
+            let mut writer = SstFileWriter::create(&opts);
+            let written = writer.open(&path).and_then(|_| {
+                for (k, v) in entries.by_ref().take(INGEST_FILE_ENTRIES) {
+                    if k.len() > self.get_max_keylen() {
+                        self.set_max_key_len(k.len());
+                    }
+                    let mut full_k = meta_prefix.to_vec();
+                    full_k.extend_from_slice(&k);
+                    writer.put(full_k, v)?;
+                    cnt += 1;
+                }
+                writer.finish()
+            });
+            let ret = written.map_err(|e| to_err("write_sst", e)).and_then(|_| {
+                with_retry("ingest", || {
+                    self.meta.ingest_external_file_cf(cf, vec![&path])
+                })
+            });
+            // removed on every path, a failed write may leave a partial file
+            let _ = fs::remove_file(&path);
+            ret?;
+            n += cnt;
+        }
+
+        Ok(n)
     }
 
     fn get_instance_len_hint(&self, instance_prefix: PreBytes) -> u64 {
-        crate::parse_int!(self.meta.get(instance_prefix).unwrap().unwrap(), u64)
+        retry("get", || self.meta.get(instance_prefix))
+            .map(|l| crate::parse_int!(l, u64))
+            .unwrap_or_else(|| super::missing_len_hint(instance_prefix))
     }
 
     fn set_instance_len_hint(&self, instance_prefix: PreBytes, new_len: u64) {
-        self.meta
-            .put(instance_prefix, new_len.to_be_bytes())
-            .unwrap();
+        retry("put", || {
+            self.meta.put(instance_prefix, new_len.to_be_bytes())
+        });
+    }
+
+    fn get_instance_digest(&self, instance_prefix: PreBytes) -> Option<u64> {
+        retry("get", || self.meta.get(super::digest_key(instance_prefix)))
+            .map(|d| crate::parse_int!(d, u64))
+    }
+
+    fn set_instance_digest(&self, instance_prefix: PreBytes, digest: Option<u64>) {
+        let k = super::digest_key(instance_prefix);
+        if let Some(d) = digest {
+            retry("put", || self.meta.put(k, d.to_be_bytes()));
+        } else {
+            retry("delete", || self.meta.delete(k));
+        }
+    }
+
+    fn get_instance_offset(&self, instance_prefix: PreBytes) -> u64 {
+        retry("get", || self.meta.get(super::offset_key(instance_prefix)))
+            .map(|o| crate::parse_int!(o, u64))
+            .unwrap_or(0)
+    }
+
+    // The default value is not stored
+    fn set_instance_offset(&self, instance_prefix: PreBytes, offset: u64) {
+        let k = super::offset_key(instance_prefix);
+        if 0 == offset {
+            retry("delete", || self.meta.delete(k));
+        } else {
+            retry("put", || self.meta.put(k, offset.to_be_bytes()));
+        }
     }
 }
 
@@ -294,6 +782,28 @@ impl DoubleEndedIterator for RocksIter {
     }
 }
 
+pub struct RocksKeyIter {
+    inner: DBRawIterator<'static>,
+    inner_rev: DBRawIterator<'static>,
+}
+
+impl Iterator for RocksKeyIter {
+    type Item = RawKey;
+    fn next(&mut self) -> Option<Self::Item> {
+        let k = self.inner.key()?[PREFIX_SIZE..].to_vec();
+        self.inner.next();
+        Some(k)
+    }
+}
+
+impl DoubleEndedIterator for RocksKeyIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let k = self.inner_rev.key()?[PREFIX_SIZE..].to_vec();
+        self.inner_rev.prev();
+        Some(k)
+    }
+}
+
 // key of the prefix allocator in the 'meta'
 struct PreAllocator {
     key: [u8; 1],
@@ -314,6 +824,21 @@ impl PreAllocator {
     // }
 }
 
+fn no_slowdown() -> WriteOptions {
+    let mut opts = WriteOptions::default();
+    opts.set_no_slowdown(true);
+    opts
+}
+
+// With `no_slowdown`, the stalled writes fail as `Incomplete`
+fn no_stall(ret: std::result::Result<(), rocksdb::Error>) -> Option<()> {
+    match ret {
+        Ok(()) => Some(()),
+        Err(e) if ErrorKind::Incomplete == e.kind() => None,
+        Err(e) => panic!("{}", e),
+    }
+}
+
 fn rocksdb_open() -> Result<(DB, Vec<String>)> {
     let dir = vsdb_get_base_dir();
 
@@ -376,14 +901,37 @@ fn rocksdb_open() -> Result<(DB, Vec<String>)> {
     #[cfg(not(feature = "compress"))]
     cfg.set_compression_type(DBCompressionType::None);
 
+    let mut t = TUNING.lock();
+    t.opened = true;
+    t.bulk_loading = Some(RocksProfile::BulkLoad) == t.profile;
+    let mut table = BlockBasedOptions::default();
+    let cache_cap = t
+        .profile
+        .map(|p| p.apply(&mut cfg, &mut table))
+        .unwrap_or(DEFAULT_BLOCK_CACHE_CAP);
+    let cache = Cache::new_lru_cache(cache_cap);
+    table.set_block_cache(&cache);
+    cfg.set_block_based_table_factory(&table);
+    t.block_cache = Some((cache, cache_cap));
+    drop(t);
+
     let cfhdrs = (0..DATA_SET_NUM).map(|i| i.to_string()).collect::<Vec<_>>();
 
-    let cfs = cfhdrs
-        .iter()
-        .map(|i| ColumnFamilyDescriptor::new(i, cfg.clone()))
-        .collect::<Vec<_>>();
+    let cfs = || {
+        cfhdrs
+            .iter()
+            .map(|i| ColumnFamilyDescriptor::new(i, cfg.clone()))
+            .collect::<Vec<_>>()
+    };
 
-    let db = DB::open_cf_descriptors(&cfg, &dir, cfs).c(d!())?;
+    let db = if detect_read_only(&dir) {
+        with_retry("open", || {
+            DB::open_cf_descriptors_read_only(&cfg, &dir, cfs(), false)
+        })
+        .c(d!())?
+    } else {
+        with_retry("open", || DB::open_cf_descriptors(&cfg, &dir, cfs())).c(d!())?
+    };
 
     Ok((db, cfhdrs))
 }
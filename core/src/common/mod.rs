@@ -2,21 +2,48 @@
 //! # Common components
 //!
 
+pub(crate) mod access;
+pub(crate) mod cache;
+pub(crate) mod digest;
 pub(crate) mod engines;
+pub(crate) mod quota;
+pub(crate) mod registry;
+pub(crate) mod retry;
+pub(crate) mod seal;
+pub(crate) mod snapshot;
+pub(crate) mod trash;
 
+#[cfg(test)]
+mod test;
+
+pub use access::{AccessStats, RangeAccess};
+pub use cache::CacheAdapter;
 use engines::Engine;
-use parking_lot::Mutex;
+
+#[cfg(feature = "rocks_backend")]
+pub use engines::{vsdb_finish_bulk_load, vsdb_tune, RocksProfile};
+use parking_lot::{Condvar, Mutex};
+pub use quota::{Quota, QuotaGroup, QuotaUsage};
+pub use retry::{
+    vsdb_get_retry_policy, vsdb_set_retry_policy, BackendError, RetryPolicy,
+};
 use ruc::*;
+pub use snapshot::{vsdb_snapshot, Snapshot};
 use std::{
-    env, fs,
+    cell::RefCell,
+    collections::VecDeque,
+    env, error, fmt, fs,
     mem::size_of,
     path::{Path, PathBuf},
+    result::Result as StdResult,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         LazyLock,
     },
+    time::{Duration, Instant},
 };
 use threadpool::ThreadPool;
+pub use trash::{vsdb_trash_purge, vsdb_trash_restore, TrashId};
 
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
@@ -36,7 +63,14 @@ pub const MB: u64 = 1 << 20;
 pub const GB: u64 = 1 << 30;
 
 const RESERVED_ID_CNT: Pre = 4096_0000;
-pub const BIGGEST_RESERVED_ID: Pre = RESERVED_ID_CNT - 1;
+
+// The tail of the reserved IDs is taken by the internal registries
+const INTERNAL_ID_CNT: Pre = 1024;
+pub(crate) const INTERNAL_ID_BASE: Pre = RESERVED_ID_CNT - INTERNAL_ID_CNT;
+
+/// The IDs in `[0, BIGGEST_RESERVED_ID]` will never be allocated to normal instances,
+/// they are free for the users, e.g. for the well-known instances built by `from_prefix_slice`.
+pub const BIGGEST_RESERVED_ID: Pre = INTERNAL_ID_BASE - 1;
 
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
@@ -72,6 +106,277 @@ pub static TRASH_CLEANER: LazyLock<Mutex<ThreadPool>> = LazyLock::new(|| {
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
+/// The max sizes of keys and values accepted by an instance,
+/// `None` means no limit.
+///
+/// NOTE: the limits only live in memory, they are not persisted with the instance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SizeLimits {
+    pub max_key_len: Option<usize>,
+    pub max_value_len: Option<usize>,
+}
+
+impl SizeLimits {
+    pub(crate) fn check(&self, key: &[u8], value: &[u8]) -> StdResult<(), WriteError> {
+        if let Some(limit) = self.max_key_len.filter(|l| key.len() > *l) {
+            return Err(WriteError::KeyTooLarge {
+                len: key.len(),
+                limit,
+            });
+        }
+        if let Some(limit) = self.max_value_len.filter(|l| value.len() > *l) {
+            return Err(WriteError::ValueTooLarge {
+                len: value.len(),
+                limit,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Sizes reported by the compaction of an instance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+    /// The number of the entries.
+    pub len: u64,
+    /// The total bytes of the keys and the values.
+    pub data_bytes: u64,
+    /// The bytes on disk of the area that holds the instance,
+    /// NOTE: an area is shared by many instances.
+    ///
+    /// `None` if the engine does not compact, e.g. parity-db.
+    pub disk_bytes_before: Option<u64>,
+    pub disk_bytes_after: Option<u64>,
+}
+
+/// Reasons for rejecting a write operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WriteError {
+    KeyTooLarge {
+        len: usize,
+        limit: usize,
+    },
+    ValueTooLarge {
+        len: usize,
+        limit: usize,
+    },
+    ReadOnly,
+    Frozen,
+    Sealed,
+    KeyQuotaExceeded {
+        limit: u64,
+    },
+    ByteQuotaExceeded {
+        used: u64,
+        limit: u64,
+    },
+    WouldBlock,
+    /// The backend failed after the retry policy was exhausted.
+    Backend(BackendError),
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeyTooLarge { len, limit } => {
+                write!(f, "key too large: {} bytes, limit: {} bytes", len, limit)
+            }
+            Self::ValueTooLarge { len, limit } => {
+                write!(f, "value too large: {} bytes, limit: {} bytes", len, limit)
+            }
+            Self::ReadOnly => {
+                write!(f, "the database is opened in read-only mode")
+            }
+            Self::Frozen => {
+                write!(f, "the database is frozen")
+            }
+            Self::Sealed => {
+                write!(f, "the instance is sealed")
+            }
+            Self::KeyQuotaExceeded { limit } => {
+                write!(f, "key quota exceeded, limit: {} keys", limit)
+            }
+            Self::ByteQuotaExceeded { used, limit } => {
+                write!(
+                    f,
+                    "byte quota exceeded: {} bytes, limit: {} bytes",
+                    used, limit
+                )
+            }
+            Self::WouldBlock => {
+                write!(f, "the write would block")
+            }
+            Self::Backend(e) => {
+                write!(f, "{}", e)
+            }
+        }
+    }
+}
+
+impl error::Error for WriteError {}
+
+static REJECTED_WRITE_CNT: AtomicU64 = AtomicU64::new(0);
+
+#[inline(always)]
+pub(crate) fn count_rejected_write() {
+    REJECTED_WRITE_CNT.fetch_add(1, Ordering::Relaxed);
+}
+
+// Set once when the backend is opened
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+// Open the database in read-only mode if the base dir is not writable,
+// e.g. archived snapshots that are mounted read-only in containers.
+pub(crate) fn detect_read_only(dir: &Path) -> bool {
+    let mut probe = dir.to_path_buf();
+    probe.push(".__vsdb_probe__");
+    let ro = fs::write(&probe, []).is_err();
+    if !ro {
+        omit!(fs::remove_file(&probe));
+    }
+    READ_ONLY.store(ro, Ordering::Relaxed);
+    ro
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+// Labeled prefixes live in the upper half of the ID space,
+// the sequential allocator will never grow into it.
+const LABELED_PREFIX_FLAG: Pre = 1 << (Pre::BITS - 1);
+
+thread_local! {
+    // (label, the number of prefixes derived from it)
+    static NAMESPACE_LABEL: RefCell<Option<(Vec<u8>, u64)>> = const { RefCell::new(None) };
+}
+
+// Restore the outer label when leaving a labeled scope, even on panics
+struct LabelGuard(Option<(Vec<u8>, u64)>);
+
+impl Drop for LabelGuard {
+    fn drop(&mut self) {
+        NAMESPACE_LABEL.with(|l| *l.borrow_mut() = self.0.take());
+    }
+}
+
+// The next prefix derived from the label of the current scope,
+// `None` if not in a labeled scope.
+pub(crate) fn next_labeled_prefix() -> Option<Pre> {
+    NAMESPACE_LABEL.with(|l| {
+        l.borrow_mut().as_mut().map(|(label, n)| {
+            let mut h = fnv1a(label);
+            h = fnv1a_extend(h, &n.to_be_bytes());
+            *n += 1;
+            h | LABELED_PREFIX_FLAG
+        })
+    })
+}
+
+#[inline(always)]
+pub(crate) fn is_labeled_prefix(prefix: Pre) -> bool {
+    0 != prefix & LABELED_PREFIX_FLAG
+}
+
+// The digest of an instance is the wrapping sum of the digests of its entries,
+// so it does not depend on the order of writes.
+#[inline(always)]
+pub(crate) fn entry_digest(key: &[u8], value: &[u8]) -> u64 {
+    let h = fnv1a(&(key.len() as u64).to_be_bytes());
+    fnv1a_extend(fnv1a_extend(h, key), value)
+}
+
+// A stable hash, it must never change between versions and platforms
+const fn fnv1a(bytes: &[u8]) -> u64 {
+    fnv1a_extend(0xcbf29ce484222325, bytes)
+}
+
+const fn fnv1a_extend(mut h: u64, bytes: &[u8]) -> u64 {
+    let mut i = 0;
+    while i < bytes.len() {
+        h ^= bytes[i] as u64;
+        h = h.wrapping_mul(0x100000001b3);
+        i += 1;
+    }
+    h
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+/// The kinds of operations traced by the slow operation log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OpKind {
+    Get,
+    Insert,
+    Remove,
+    RemoveRange,
+    WriteBatch,
+}
+
+/// An operation that took longer than the threshold.
+#[derive(Clone, Debug)]
+pub struct SlowOp {
+    pub kind: OpKind,
+    /// The prefix of the instance that the operation was issued to.
+    pub namespace: Pre,
+    /// The size of the key, 0 for range and batch operations.
+    pub key_len: usize,
+    pub elapsed: Duration,
+}
+
+// The oldest records will be dropped when the log is full
+const SLOW_OP_LOG_CAP: usize = 1024;
+
+// In milliseconds
+static SLOW_OP_THRESHOLD: AtomicU64 = AtomicU64::new(SLOW_OP_LOG_DISABLED);
+
+const SLOW_OP_LOG_DISABLED: u64 = u64::MAX;
+
+static SLOW_OP_LOG: LazyLock<Mutex<VecDeque<SlowOp>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::with_capacity(SLOW_OP_LOG_CAP)));
+
+// Start timing an operation, `None` if the slow operation log is disabled
+#[inline(always)]
+pub(crate) fn slow_op_timer() -> Option<Instant> {
+    alt!(
+        SLOW_OP_LOG_DISABLED == SLOW_OP_THRESHOLD.load(Ordering::Relaxed),
+        None,
+        Some(Instant::now())
+    )
+}
+
+#[inline(always)]
+pub(crate) fn trace_slow_op(
+    timer: Option<Instant>,
+    kind: OpKind,
+    prefix: PreBytes,
+    key_len: usize,
+) {
+    if let Some(t) = timer {
+        let elapsed = t.elapsed();
+        let threshold = SLOW_OP_THRESHOLD.load(Ordering::Relaxed);
+        if SLOW_OP_LOG_DISABLED != threshold
+            && elapsed >= Duration::from_millis(threshold)
+        {
+            let mut log = SLOW_OP_LOG.lock();
+            if SLOW_OP_LOG_CAP == log.len() {
+                log.pop_front();
+            }
+            log.push_back(SlowOp {
+                kind,
+                namespace: Pre::from_be_bytes(prefix),
+                key_len,
+                elapsed,
+            });
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
 /// Parse bytes to a specified integer type.
 #[macro_export]
 macro_rules! parse_int {
@@ -106,7 +411,15 @@ impl<T: Engine> VsDB<T> {
 
     #[inline(always)]
     fn flush(&self) {
-        self.db.flush()
+        if !vsdb_is_read_only() {
+            self.db.flush()
+        }
+    }
+
+    #[inline(always)]
+    fn shrink_to_fit(&self) -> usize {
+        SLOW_OP_LOG.lock().shrink_to_fit();
+        alt!(vsdb_is_read_only(), 0, self.db.shrink_to_fit())
     }
 }
 
@@ -152,5 +465,120 @@ pub fn vsdb_set_base_dir(dir: impl AsRef<Path>) -> Result<()> {
 /// Flush data to disk, may take a long time.
 #[inline(always)]
 pub fn vsdb_flush() {
+    access::save();
     VSDB.flush();
 }
+
+/// Release the memory held by the engine, e.g. the write buffers and the block cache,
+/// useful for long-lived processes between bursts of writes,
+/// return the number of the released bytes.
+///
+/// NOTE:
+/// - The data will be flushed to disk, it may take a long time
+/// - Nothing can be released on parity-db, 0 is always returned,
+///   its tables are memory-mapped files managed by the OS
+#[inline(always)]
+pub fn vsdb_shrink_to_fit() -> usize {
+    VSDB.shrink_to_fit()
+}
+
+/// Whether the database has been opened in read-only mode,
+/// all writes will be rejected with `WriteError::ReadOnly`.
+#[inline(always)]
+pub fn vsdb_is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+// Set by `vsdb_freeze`, checked by the writes under the write lock of snapshots
+static FROZEN: AtomicBool = AtomicBool::new(false);
+
+// Notified by `vsdb_unfreeze`, waited on by the writes that can not return errors
+static THAW: LazyLock<(Mutex<()>, Condvar)> =
+    LazyLock::new(|| (Mutex::new(()), Condvar::new()));
+
+/// Stop all writes until `vsdb_unfreeze` is called,
+/// the `try_` writes are rejected and the others wait,
+/// e.g. during maintenance windows or hot backups of the base dir.
+///
+/// The writes in progress are waited for,
+/// so no data will be changed after this function returns.
+///
+/// NOTE:
+/// - The `try_` writes return `WriteError::Frozen`
+/// - The non-`try_` writes, including the drops of the mutable guards,
+///   block until `vsdb_unfreeze` is called,
+///   so calling them on the thread that will unfreeze will deadlock
+pub fn vsdb_freeze() {
+    FROZEN.store(true, Ordering::Relaxed);
+    snapshot::wait_for_writes();
+}
+
+/// Accept writes again after `vsdb_freeze`,
+/// the blocked non-`try_` writes are woken up.
+pub fn vsdb_unfreeze() {
+    let (lk, cv) = &*THAW;
+    let x = lk.lock();
+    FROZEN.store(false, Ordering::Relaxed);
+    cv.notify_all();
+    drop(x);
+}
+
+#[inline(always)]
+pub fn vsdb_is_frozen() -> bool {
+    FROZEN.load(Ordering::Relaxed)
+}
+
+// Return after `vsdb_unfreeze` is called, at once if not frozen
+pub(crate) fn wait_for_unfreeze() {
+    let (lk, cv) = &*THAW;
+    let mut x = lk.lock();
+    while vsdb_is_frozen() {
+        cv.wait(&mut x);
+    }
+}
+
+/// Derive the prefixes of the instances created in `f` from `label`,
+/// instead of allocating them from the global sequence.
+///
+/// The n-th instance created in `f` always gets the same prefix for the same label,
+/// so processes that construct the same structures in the same order
+/// will map to identical on-disk data, e.g. replicas and shared read-only mounts.
+///
+/// NOTE:
+/// - The prefixes are 63-bit hashes, use distinct labels for distinct structures
+/// - Instances created in other threads are not affected
+/// - The inner label wins in nested calls
+pub fn vsdb_with_namespace_label<T>(
+    label: impl AsRef<[u8]>,
+    f: impl FnOnce() -> T,
+) -> T {
+    let outer =
+        NAMESPACE_LABEL.with(|l| l.borrow_mut().replace((label.as_ref().to_vec(), 0)));
+    let _guard = LabelGuard(outer);
+    f()
+}
+
+/// Record operations that take at least `ms` milliseconds,
+/// `None` disables the slow operation log, and it is disabled by default;
+/// `Some(0)` records all operations.
+///
+/// At most 1024 records are kept, the oldest ones are dropped first.
+#[inline(always)]
+pub fn vsdb_set_slow_op_threshold(ms: Option<u64>) {
+    SLOW_OP_THRESHOLD.store(
+        ms.map_or(SLOW_OP_LOG_DISABLED, |ms| ms.min(SLOW_OP_LOG_DISABLED - 1)),
+        Ordering::Relaxed,
+    );
+}
+
+/// Take out all the recorded slow operations.
+#[inline(always)]
+pub fn vsdb_take_slow_ops() -> Vec<SlowOp> {
+    SLOW_OP_LOG.lock().drain(..).collect()
+}
+
+/// The number of write operations rejected since the process started.
+#[inline(always)]
+pub fn vsdb_get_rejected_write_cnt() -> u64 {
+    REJECTED_WRITE_CNT.load(Ordering::Relaxed)
+}
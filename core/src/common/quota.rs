@@ -0,0 +1,309 @@
+//!
+//! # Quota
+//!
+//! Instances can be grouped, e.g. per tenant,
+//! and the total number of keys and bytes of a group can be limited.
+//!
+//! NOTE: like the size limits, the memberships are runtime configs,
+//! they are not persisted and must be set again after restarting.
+//!
+
+use crate::common::{registry::Registry, PreBytes, WriteError};
+use parking_lot::{ArcMutexGuard, Mutex, RawMutex};
+use std::{
+    collections::HashMap,
+    mem,
+    result::Result as StdResult,
+    sync::{Arc, LazyLock},
+};
+
+/// The limits of a quota group, `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quota {
+    pub max_keys: Option<u64>,
+    /// The total length of all keys and values.
+    pub max_bytes: Option<u64>,
+}
+
+/// The resources used by a quota group or one of its members.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub keys: u64,
+    pub bytes: u64,
+}
+
+/// A group of instances that share the same quota.
+#[derive(Clone, Debug, Default)]
+pub struct QuotaGroup(Arc<Mutex<GroupState>>);
+
+#[derive(Debug, Default)]
+pub(crate) struct GroupState {
+    quota: Quota,
+    usage: QuotaUsage,
+    members: HashMap<PreBytes, QuotaUsage>,
+}
+
+pub(crate) type GroupGuard = ArcMutexGuard<RawMutex, GroupState>;
+
+// instance prefix => the group that it belongs to
+static MEMBERSHIPS: LazyLock<Registry<QuotaGroup>> = LazyLock::new(Registry::default);
+
+impl QuotaGroup {
+    pub fn new(quota: Quota) -> Self {
+        Self(Arc::new(Mutex::new(GroupState {
+            quota,
+            ..Default::default()
+        })))
+    }
+
+    #[inline(always)]
+    pub fn quota(&self) -> Quota {
+        self.0.lock().quota
+    }
+
+    /// The new limits only apply to the later writes,
+    /// existing data will not be touched even if it exceeds them.
+    #[inline(always)]
+    pub fn set_quota(&self, quota: Quota) {
+        self.0.lock().quota = quota;
+    }
+
+    /// The total usage of all members.
+    #[inline(always)]
+    pub fn usage(&self) -> QuotaUsage {
+        self.0.lock().usage
+    }
+
+    /// The number of the member instances.
+    #[inline(always)]
+    pub fn member_cnt(&self) -> usize {
+        self.0.lock().members.len()
+    }
+
+    #[inline(always)]
+    pub fn is_the_same_group(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl GroupState {
+    // Apply the change of a single entry,
+    // an increasing usage must not exceed the quota.
+    pub(crate) fn charge(
+        &mut self,
+        prefix: PreBytes,
+        key_len: usize,
+        old_value_len: Option<usize>,
+        new_value_len: Option<usize>,
+    ) -> StdResult<(), WriteError> {
+        let mut new = self.usage;
+        if let Some(l) = old_value_len {
+            new.keys = new.keys.saturating_sub(1);
+            new.bytes = new.bytes.saturating_sub((key_len + l) as u64);
+        }
+        if let Some(l) = new_value_len {
+            new.keys += 1;
+            new.bytes += (key_len + l) as u64;
+        }
+
+        if let Some(limit) = self.quota.max_keys {
+            if new.keys > self.usage.keys && new.keys > limit {
+                return Err(WriteError::KeyQuotaExceeded { limit });
+            }
+        }
+        if let Some(limit) = self.quota.max_bytes {
+            if new.bytes > self.usage.bytes && new.bytes > limit {
+                return Err(WriteError::ByteQuotaExceeded {
+                    used: new.bytes,
+                    limit,
+                });
+            }
+        }
+
+        let m = self.members.entry(prefix).or_default();
+        m.keys = (m.keys + new.keys).saturating_sub(self.usage.keys);
+        m.bytes = (m.bytes + new.bytes).saturating_sub(self.usage.bytes);
+        self.usage = new;
+
+        Ok(())
+    }
+
+    // Revert a successful `charge` whose write has not been done
+    pub(crate) fn refund(
+        &mut self,
+        prefix: PreBytes,
+        key_len: usize,
+        old_value_len: Option<usize>,
+        new_value_len: Option<usize>,
+    ) {
+        // a decreasing usage is never rejected without limits
+        let quota = mem::take(&mut self.quota);
+        let ret = self.charge(prefix, key_len, new_value_len, old_value_len);
+        self.quota = quota;
+        debug_assert!(ret.is_ok());
+    }
+
+    // Release some entries of a member that have been removed
+    pub(crate) fn release(&mut self, prefix: PreBytes, removed: QuotaUsage) {
+        if let Some(m) = self.members.get_mut(&prefix) {
+            let removed = QuotaUsage {
+                keys: removed.keys.min(m.keys),
+                bytes: removed.bytes.min(m.bytes),
+            };
+            m.keys -= removed.keys;
+            m.bytes -= removed.bytes;
+            self.usage.keys -= removed.keys;
+            self.usage.bytes -= removed.bytes;
+        }
+    }
+
+    // Release all entries of a member
+    pub(crate) fn release_all(&mut self, prefix: PreBytes) {
+        if let Some(m) = self.members.get(&prefix).copied() {
+            self.release(prefix, m);
+        }
+    }
+
+    // Replace the usage of a member by a recounted one,
+    // used when the number of the written entries is unknown
+    pub(crate) fn reset_member(&mut self, prefix: PreBytes, usage: QuotaUsage) {
+        if self.members.contains_key(&prefix) {
+            self.release_all(prefix);
+            self.usage.keys += usage.keys;
+            self.usage.bytes += usage.bytes;
+            self.members.insert(prefix, usage);
+        }
+    }
+}
+
+/// Lock the state of the group that the instance belongs to,
+/// the lock should be held until the write has been done.
+#[inline(always)]
+pub(crate) fn lock_group_of(prefix: PreBytes) -> Option<GroupGuard> {
+    group_of(prefix).map(|g| g.0.lock_arc())
+}
+
+// Like `lock_group_of`, but return `None` instead of waiting on a locked group
+pub(crate) fn try_lock_group_of(prefix: PreBytes) -> Option<Option<GroupGuard>> {
+    match group_of(prefix) {
+        Some(g) => g.0.try_lock_arc().map(Some),
+        None => Some(None),
+    }
+}
+
+pub(crate) fn group_of(prefix: PreBytes) -> Option<QuotaGroup> {
+    MEMBERSHIPS.get(prefix)
+}
+
+/// `usage` is the existing data of the instance.
+pub(crate) fn join(prefix: PreBytes, group: &QuotaGroup, usage: QuotaUsage) {
+    leave(prefix);
+
+    let mut st = group.0.lock();
+    st.usage.keys += usage.keys;
+    st.usage.bytes += usage.bytes;
+    st.members.insert(prefix, usage);
+    drop(st);
+
+    MEMBERSHIPS.insert(prefix, group.clone());
+}
+
+pub(crate) fn leave(prefix: PreBytes) {
+    let g = MEMBERSHIPS.remove(prefix);
+    if let Some(g) = g {
+        let mut st = g.0.lock();
+        st.release_all(prefix);
+        st.members.remove(&prefix);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::basic::mapx_raw::{test::to_bytes, MapxRaw};
+    use ruc::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_quota() {
+        let mut a = MapxRaw::new();
+        let mut b = MapxRaw::new();
+        a.insert(to_bytes(0), to_bytes(0));
+
+        let group = QuotaGroup::new(Quota {
+            max_keys: Some(4),
+            max_bytes: Some(64),
+        });
+        a.join_quota_group(&group);
+        b.join_quota_group(&group);
+        assert!(pnk!(a.quota_group()).is_the_same_group(&group));
+        assert_eq!(QuotaUsage { keys: 1, bytes: 16 }, group.usage());
+
+        assert!(pnk!(b.try_insert(to_bytes(1), to_bytes(1))).is_none());
+        assert!(pnk!(b.try_insert(to_bytes(2), to_bytes(2))).is_none());
+        // overwriting does not increase the usage
+        assert!(pnk!(b.try_insert(to_bytes(2), to_bytes(3))).is_some());
+        assert_eq!(
+            a.try_insert(to_bytes(1), [0; 24]),
+            Err(WriteError::ByteQuotaExceeded {
+                used: 80,
+                limit: 64
+            })
+        );
+        assert!(pnk!(a.try_insert(to_bytes(1), to_bytes(1))).is_none());
+        assert_eq!(
+            b.try_insert(to_bytes(3), []),
+            Err(WriteError::KeyQuotaExceeded { limit: 4 })
+        );
+        assert_eq!(QuotaUsage { keys: 4, bytes: 64 }, group.usage());
+
+        b.remove(to_bytes(1));
+        assert_eq!(QuotaUsage { keys: 3, bytes: 48 }, group.usage());
+        a.remove_range(Cow::Owned(to_bytes(1).to_vec())..);
+        assert_eq!(QuotaUsage { keys: 2, bytes: 32 }, group.usage());
+        b.clear();
+        assert_eq!(QuotaUsage { keys: 1, bytes: 16 }, group.usage());
+
+        a.leave_quota_group();
+        assert!(a.quota_group().is_none());
+        assert_eq!(QuotaUsage::default(), group.usage());
+        assert_eq!(1, group.member_cnt());
+    }
+
+    #[test]
+    fn test_reset_member() {
+        let group = QuotaGroup::new(Quota::default());
+        let a = MapxRaw::new();
+        let b = MapxRaw::new();
+        join(
+            *a.as_prefix_slice(),
+            &group,
+            QuotaUsage { keys: 1, bytes: 16 },
+        );
+        join(
+            *b.as_prefix_slice(),
+            &group,
+            QuotaUsage { keys: 2, bytes: 32 },
+        );
+
+        // charged more than written
+        let usage = QuotaUsage { keys: 1, bytes: 10 };
+        let mut st = group.0.lock();
+        pnk!(st.charge(*a.as_prefix_slice(), 8, None, Some(8)));
+        st.reset_member(*a.as_prefix_slice(), usage);
+        assert_eq!(usage, st.members[a.as_prefix_slice()]);
+        drop(st);
+        assert_eq!(QuotaUsage { keys: 3, bytes: 42 }, group.usage());
+
+        // not a member
+        group
+            .0
+            .lock()
+            .reset_member(*MapxRaw::new().as_prefix_slice(), usage);
+        assert_eq!(2, group.member_cnt());
+
+        leave(*a.as_prefix_slice());
+        leave(*b.as_prefix_slice());
+        assert_eq!(QuotaUsage::default(), group.usage());
+    }
+}
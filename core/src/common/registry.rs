@@ -0,0 +1,82 @@
+//!
+//! # Per-instance registries
+//!
+//! The runtime states of the instances, e.g. the cache bindings and the quota groups,
+//! are kept in memory by the prefixes of the instances.
+//!
+
+use crate::common::PreBytes;
+use parking_lot::RwLock;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+pub(crate) struct Registry<T> {
+    entries: RwLock<HashMap<PreBytes, T>>,
+    // Skip the lookups in the common case that the registry is empty,
+    // it is only changed under the write lock of `entries`
+    enabled: AtomicBool,
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self::from(HashMap::new())
+    }
+}
+
+impl<T> From<HashMap<PreBytes, T>> for Registry<T> {
+    fn from(entries: HashMap<PreBytes, T>) -> Self {
+        Self {
+            enabled: AtomicBool::new(!entries.is_empty()),
+            entries: RwLock::new(entries),
+        }
+    }
+}
+
+impl<T> Registry<T> {
+    #[inline(always)]
+    pub(crate) fn with<R>(
+        &self,
+        prefix: PreBytes,
+        f: impl FnOnce(&T) -> R,
+    ) -> Option<R> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.entries.read().get(&prefix).map(f)
+    }
+
+    #[inline(always)]
+    pub(crate) fn contains(&self, prefix: PreBytes) -> bool {
+        self.with(prefix, |_| ()).is_some()
+    }
+
+    pub(crate) fn insert(&self, prefix: PreBytes, value: T) -> Option<T> {
+        let mut entries = self.entries.write();
+        self.enabled.store(true, Ordering::Relaxed);
+        entries.insert(prefix, value)
+    }
+
+    pub(crate) fn remove(&self, prefix: PreBytes) -> Option<T> {
+        let mut entries = self.entries.write();
+        let ret = entries.remove(&prefix);
+        self.enabled.store(!entries.is_empty(), Ordering::Relaxed);
+        ret
+    }
+
+    // Hold the read lock during the whole iteration
+    pub(crate) fn for_each(&self, mut f: impl FnMut(PreBytes, &T)) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        self.entries.read().iter().for_each(|(p, v)| f(*p, v));
+    }
+}
+
+impl<T: Clone> Registry<T> {
+    #[inline(always)]
+    pub(crate) fn get(&self, prefix: PreBytes) -> Option<T> {
+        self.with(prefix, T::clone)
+    }
+}
@@ -0,0 +1,231 @@
+//!
+//! # Retries of the backend operations
+//!
+//! Transient errors of the backends, e.g. lock contention on opening
+//! or a temporary `ENOSPC` before the cleanup finishes,
+//! can be retried by a policy instead of failing at the first time.
+//!
+//! NOTE:
+//! - No retries by default, that is the behavior of older versions
+//! - The policy is not persisted, it must be set again after restarting
+//! - The errors of the backends are not classified, all of them will be retried
+//! - After the policy is exhausted, the `try_*` writes return `WriteError::Backend`,
+//!   the other operations panic
+//!
+
+use parking_lot::RwLock;
+use std::{cell::Cell, error, fmt, thread, time::Duration, time::Instant};
+
+/// How to retry the failed backend operations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The max number of attempts, including the first one,
+    /// `1` disables retries.
+    pub max_attempts: u32,
+    /// The wait before the first retry, it is doubled for each of the following ones.
+    pub backoff: Duration,
+    /// Stop retrying when the total time exceeds it, `None` means no limit.
+    pub timeout: Option<Duration>,
+}
+
+const DEFAULT_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: 1,
+    backoff: Duration::from_millis(10),
+    timeout: None,
+};
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        DEFAULT_POLICY
+    }
+}
+
+/// The error of a backend operation after all the attempts have failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackendError {
+    /// The name of the failed operation.
+    pub op: &'static str,
+    pub attempts: u32,
+    /// The error of the last attempt.
+    pub cause: String,
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "backend operation `{}` failed after {} attempt(s): {}",
+            self.op, self.attempts, self.cause
+        )
+    }
+}
+
+impl error::Error for BackendError {}
+
+// Only read after a failure, so the successful operations do not pay for it
+static POLICY: RwLock<RetryPolicy> = RwLock::new(DEFAULT_POLICY);
+
+/// Set the retry policy of all the backend operations.
+#[inline(always)]
+pub fn vsdb_set_retry_policy(policy: RetryPolicy) {
+    *POLICY.write() = policy;
+}
+
+#[inline(always)]
+pub fn vsdb_get_retry_policy() -> RetryPolicy {
+    *POLICY.read()
+}
+
+thread_local! {
+    static NO_RETRIES: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The operations of the current thread are not retried until it is dropped,
+/// used by the writes that must not sleep, e.g. `insert_nowait`.
+pub(crate) struct NoRetries(bool);
+
+impl NoRetries {
+    pub(crate) fn enter() -> Self {
+        Self(NO_RETRIES.replace(true))
+    }
+}
+
+impl Drop for NoRetries {
+    fn drop(&mut self) {
+        NO_RETRIES.set(self.0);
+    }
+}
+
+/// Run `f` until it succeeds or the policy is exhausted.
+pub(crate) fn with_retry<T, E: fmt::Display>(
+    op: &'static str,
+    mut f: impl FnMut() -> Result<T, E>,
+) -> Result<T, BackendError> {
+    let e = match f() {
+        Ok(v) => return Ok(v),
+        Err(e) => e,
+    };
+
+    let mut policy = vsdb_get_retry_policy();
+    if NO_RETRIES.get() {
+        policy.max_attempts = 1;
+    }
+    let start = Instant::now();
+    let mut backoff = policy.backoff;
+    let mut attempts = 1;
+    let mut cause = e.to_string();
+
+    while attempts < policy.max_attempts
+        && policy
+            .timeout
+            .is_none_or(|t| start.elapsed() + backoff <= t)
+    {
+        thread::sleep(backoff);
+        backoff = backoff.saturating_mul(2);
+        attempts += 1;
+        match f() {
+            Ok(v) => return Ok(v),
+            Err(e) => cause = e.to_string(),
+        }
+    }
+
+    Err(BackendError {
+        op,
+        attempts,
+        cause,
+    })
+}
+
+/// Like `with_retry`, but panic after the policy is exhausted,
+/// used by the operations that can not return errors.
+#[inline(always)]
+pub(crate) fn retry<T, E: fmt::Display>(
+    op: &'static str,
+    f: impl FnMut() -> Result<T, E>,
+) -> T {
+    with_retry(op, f).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like `retry`, but `input` is moved into the only attempt if retries are disabled,
+/// it is cloned for every attempt only if they may be needed.
+pub(crate) fn retry_with<I: Clone, T, E: fmt::Display>(
+    op: &'static str,
+    input: I,
+    f: impl FnMut(I) -> Result<T, E>,
+) -> T {
+    with_retry_input(op, input, f).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like `retry_with`, but return the error after the retry policy is exhausted.
+pub(crate) fn with_retry_input<I: Clone, T, E: fmt::Display>(
+    op: &'static str,
+    input: I,
+    mut f: impl FnMut(I) -> Result<T, E>,
+) -> Result<T, BackendError> {
+    if 1 < vsdb_get_retry_policy().max_attempts && !NO_RETRIES.get() {
+        return with_retry(op, || f(input.clone()));
+    }
+
+    f(input).map_err(|e| BackendError {
+        op,
+        attempts: 1,
+        cause: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ruc::*;
+
+    #[test]
+    fn test_retry_policy() {
+        let mut failures = 2;
+        let mut flaky = || {
+            if 0 == failures {
+                Ok(failures)
+            } else {
+                failures -= 1;
+                Err("busy")
+            }
+        };
+
+        let e = with_retry("flaky", &mut flaky).unwrap_err();
+        assert_eq!(1, e.attempts);
+        assert_eq!("busy", e.cause);
+
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            backoff: Duration::from_millis(1),
+            timeout: None,
+        };
+        vsdb_set_retry_policy(policy);
+        assert_eq!(policy, vsdb_get_retry_policy());
+        assert_eq!(0, pnk!(with_retry("flaky", &mut flaky)));
+
+        let e = with_retry("always", || Err::<(), _>("down")).unwrap_err();
+        assert_eq!(3, e.attempts);
+        assert_eq!("always", e.op);
+
+        // the input is cloned for each attempt
+        let mut failures = 2;
+        let n = retry_with("flaky", vec![1u8], |v| {
+            if 0 == failures {
+                Ok(v.len())
+            } else {
+                failures -= 1;
+                Err("busy")
+            }
+        });
+        assert_eq!(1, n);
+
+        vsdb_set_retry_policy(RetryPolicy {
+            timeout: Some(Duration::ZERO),
+            ..policy
+        });
+        let e = with_retry("always", || Err::<(), _>("down")).unwrap_err();
+        assert_eq!(1, e.attempts);
+
+        vsdb_set_retry_policy(RetryPolicy::default());
+    }
+}
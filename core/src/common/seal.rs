@@ -0,0 +1,353 @@
+//!
+//! # Sealed instances
+//!
+//! An archival instance can be sealed, all of its entries are dumped into a flat file,
+//! and then the point reads are served by mapping the file into memory,
+//! the block caches of the backend will not be churned by them.
+//!
+//! The layout:
+//! - `[ MAGIC ]`
+//! - `[ key length, u32 ][ key ][ value length, u32 ][ value ]`, repeated
+//! - `[ offset of the entry, u64 ]`, repeated
+//! - `[ number of the entries, u64 ]`
+//!
+//! The integers are in big endian, the entries are in the order of the keys.
+//!
+//! NOTE:
+//! - Unlike the quota groups, the seals are persisted,
+//!   the instances are still sealed after restarting
+//! - The entries are still kept in the backend,
+//!   so the iterators and the snapshots are served as before
+//! - A damaged file is skipped on loading, its instance is served by the backend
+//!   as if it had never been sealed
+//!
+
+use crate::common::{registry::Registry, vsdb_get_base_dir, Pre, PreBytes, RawValue};
+use memmap2::Mmap;
+use ruc::*;
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    mem::size_of,
+    path::PathBuf,
+    sync::{Arc, LazyLock},
+};
+
+const MAGIC: [u8; 8] = *b"VSDBSEAL";
+
+const LEN_SIZE: usize = size_of::<u32>();
+const OFFSET_SIZE: usize = size_of::<u64>();
+
+// instance prefix => the mapped file, loaded from the disk at the first access
+static SEALED: LazyLock<Registry<Arc<FlatFile>>> =
+    LazyLock::new(|| Registry::from(pnk!(load_all())));
+
+// The instances being dumped, the writes are rejected,
+// but the reads are still served by the backend
+static SEALING: LazyLock<Registry<()>> = LazyLock::new(Registry::default);
+
+#[inline(always)]
+pub(crate) fn file_of(prefix: PreBytes) -> Option<Arc<FlatFile>> {
+    SEALED.get(prefix)
+}
+
+// The instances being sealed are also treated as sealed
+#[inline(always)]
+pub(crate) fn is_sealed(prefix: PreBytes) -> bool {
+    SEALED.contains(prefix) || SEALING.contains(prefix)
+}
+
+/// Reject the writes to the instance from now on,
+/// must be called before the entries are dumped by `seal`.
+pub(crate) fn begin(prefix: PreBytes) {
+    SEALING.insert(prefix, ());
+}
+
+/// Accept the writes again without sealing the instance.
+pub(crate) fn abort(prefix: PreBytes) {
+    SEALING.remove(prefix);
+}
+
+/// Dump the entries into a new flat file and map it,
+/// the entries must be in the order of the keys.
+///
+/// The writes are accepted again if failed.
+pub(crate) fn seal(
+    prefix: PreBytes,
+    entries: impl Iterator<Item = (Vec<u8>, RawValue)>,
+) -> Result<()> {
+    let ret = dump(prefix, entries);
+    abort(prefix);
+    ret
+}
+
+fn dump(
+    prefix: PreBytes,
+    entries: impl Iterator<Item = (Vec<u8>, RawValue)>,
+) -> Result<()> {
+    let path = path_of(prefix);
+    let tmp = path.with_extension("tmp");
+
+    let mut w = BufWriter::new(File::create(&tmp).c(d!())?);
+    w.write_all(&MAGIC).c(d!())?;
+
+    let mut offset = MAGIC.len() as u64;
+    let mut offsets = vec![];
+    for (k, v) in entries {
+        offsets.push(offset);
+        for bytes in [&k[..], &v[..]] {
+            let len = u32::try_from(bytes.len()).c(d!())?;
+            w.write_all(&len.to_be_bytes()).c(d!())?;
+            w.write_all(bytes).c(d!())?;
+            offset += (LEN_SIZE + bytes.len()) as u64;
+        }
+    }
+    for o in offsets.iter() {
+        w.write_all(&o.to_be_bytes()).c(d!())?;
+    }
+    w.write_all(&(offsets.len() as u64).to_be_bytes()).c(d!())?;
+
+    let f = w.into_inner().c(d!())?;
+    f.sync_all().c(d!())?;
+    fs::rename(&tmp, &path).c(d!())?;
+
+    let file = FlatFile::open(&path).c(d!())?;
+    SEALED.insert(prefix, Arc::new(file));
+
+    Ok(())
+}
+
+/// The mapped file is released after the last reader drops it.
+pub(crate) fn unseal(prefix: PreBytes) -> Result<()> {
+    if SEALED.remove(prefix).is_some() {
+        fs::remove_file(path_of(prefix)).c(d!())?;
+    }
+    Ok(())
+}
+
+fn dir() -> PathBuf {
+    let mut d = vsdb_get_base_dir();
+    d.push("__SEALED__");
+    d
+}
+
+fn path_of(prefix: PreBytes) -> PathBuf {
+    let d = dir();
+    pnk!(fs::create_dir_all(&d));
+    d.join(Pre::from_be_bytes(prefix).to_string())
+}
+
+fn load_all() -> Result<HashMap<PreBytes, Arc<FlatFile>>> {
+    let mut files = HashMap::new();
+
+    let d = dir();
+    if !d.exists() {
+        return Ok(files);
+    }
+
+    for entry in fs::read_dir(&d).c(d!())? {
+        let path = entry.c(d!())?.path();
+        // the leftovers of an interrupted sealing are ignored
+        let Some(id) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.parse::<Pre>().ok())
+        else {
+            continue;
+        };
+        // a damaged file only unseals its own instance,
+        // which is then served by the backend, where the entries are still kept
+        match FlatFile::open(&path).c(d!()) {
+            Ok(file) => {
+                files.insert(id.to_be_bytes(), Arc::new(file));
+            }
+            Err(e) => e.print(None),
+        }
+    }
+
+    Ok(files)
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub(crate) struct FlatFile {
+    mmap: Mmap,
+    // the position of the offsets
+    index: usize,
+    cnt: usize,
+}
+
+impl FlatFile {
+    fn open(path: &PathBuf) -> Result<Self> {
+        let f = File::open(path).c(d!())?;
+        // SAFETY: the sealed files are never modified after being renamed into place
+        let mmap = unsafe { Mmap::map(&f) }.c(d!())?;
+
+        if mmap.len() < MAGIC.len() + OFFSET_SIZE || MAGIC != mmap[..MAGIC.len()] {
+            return Err(eg!("invalid sealed file: {}", path.display()));
+        }
+
+        let cnt = read_u64(&mmap, mmap.len() - OFFSET_SIZE) as usize;
+        let index = cnt
+            .checked_mul(OFFSET_SIZE)
+            .and_then(|n| (mmap.len() - OFFSET_SIZE).checked_sub(n))
+            .filter(|&i| i >= MAGIC.len())
+            .c(d!("corrupted sealed file: {}", path.display()))?;
+
+        Ok(Self { mmap, index, cnt })
+    }
+
+    pub(crate) fn get(&self, key: &[u8]) -> Option<RawValue> {
+        let (mut lo, mut hi) = (0, self.cnt);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (k, v) = self.entry(mid);
+            match k.cmp(key) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return Some(v.to_vec()),
+            }
+        }
+        None
+    }
+
+    #[inline(always)]
+    pub(crate) fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    fn entry(&self, idx: usize) -> (&[u8], &[u8]) {
+        let offset = read_u64(&self.mmap, self.index + idx * OFFSET_SIZE) as usize;
+        let (k, pos) = pnk!(read_bytes(&self.mmap[..self.index], offset));
+        let (v, _) = pnk!(read_bytes(&self.mmap[..self.index], pos));
+        (k, v)
+    }
+}
+
+#[inline(always)]
+fn read_u64(buf: &[u8], pos: usize) -> u64 {
+    let mut b = [0; OFFSET_SIZE];
+    b.copy_from_slice(&buf[pos..pos + OFFSET_SIZE]);
+    u64::from_be_bytes(b)
+}
+
+// Return the bytes and the position after them
+fn read_bytes(buf: &[u8], pos: usize) -> Result<(&[u8], usize)> {
+    let len = buf
+        .get(pos..pos + LEN_SIZE)
+        .c(d!("corrupted sealed file"))?;
+    let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    let start = pos + LEN_SIZE;
+    let bytes = buf.get(start..start + len).c(d!("corrupted sealed file"))?;
+    Ok((bytes, start + len))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        basic::mapx_raw::{test::to_bytes, MapxRaw},
+        common::WriteError,
+    };
+    use ruc::*;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn test_seal() {
+        let mut hdr = MapxRaw::new();
+        (0..100u64).for_each(|i| {
+            hdr.insert(to_bytes(i), to_bytes(i * 2));
+        });
+        assert!(!hdr.is_sealed());
+
+        pnk!(hdr.seal());
+        assert!(hdr.is_sealed());
+        pnk!(hdr.seal());
+
+        (0..100u64).for_each(|i| {
+            assert!(hdr.contains_key(to_bytes(i)));
+            assert_eq!(&to_bytes(i * 2)[..], &pnk!(hdr.get(to_bytes(i)))[..]);
+        });
+        assert!(hdr.get(to_bytes(100)).is_none());
+        assert!(!hdr.contains_key([]));
+        assert_eq!(100, hdr.iter().count());
+
+        assert_eq!(
+            Err(WriteError::Sealed),
+            hdr.try_insert(to_bytes(100), to_bytes(0))
+        );
+        assert_eq!(Err(WriteError::Sealed), hdr.try_remove(to_bytes(0)));
+        assert_eq!(Err(WriteError::Sealed), hdr.try_remove_range(..));
+        assert_eq!(Err(WriteError::Sealed), hdr.try_clear());
+        assert_eq!(100, hdr.len());
+
+        // no guards, they could never be written back
+        assert!(hdr.get_mut(to_bytes(1)).is_none());
+        assert!(hdr.iter_mut().next().is_none());
+        assert!(hdr.range_mut(..).next_back().is_none());
+
+        pnk!(hdr.unseal());
+        assert!(!hdr.is_sealed());
+        assert!(pnk!(hdr.try_remove(to_bytes(0))).is_some());
+        assert!(hdr.get(to_bytes(0)).is_none());
+
+        // sealed while a guard is held, the unchanged value is not written back
+        let shadow = unsafe { hdr.shadow() };
+        let v = pnk!(hdr.get_mut(to_bytes(1)));
+        pnk!(shadow.seal());
+        assert_eq!(&to_bytes(2)[..], &v[..]);
+        drop(v);
+        pnk!(shadow.unseal());
+
+        let empty = MapxRaw::new();
+        pnk!(empty.seal());
+        assert!(empty.get([0]).is_none());
+        pnk!(empty.unseal());
+    }
+
+    #[test]
+    fn test_damaged_file() {
+        let hdr = MapxRaw::new();
+        let prefix = *hdr.as_prefix_slice();
+        let path = dir().join(Pre::from_be_bytes(prefix).to_string());
+        pnk!(fs::create_dir_all(dir()));
+        pnk!(fs::write(&path, &MAGIC[..4]));
+
+        // skipped instead of failing the loading of all the files
+        let files = pnk!(load_all());
+        assert!(!files.contains_key(&prefix));
+        pnk!(fs::remove_file(&path));
+    }
+
+    // The writes racing with the sealing are either in the sealed file or rejected
+    #[test]
+    fn test_seal_concurrent_writes() {
+        let hdr = MapxRaw::new();
+
+        let writers = (0..4u64)
+            .map(|n| {
+                let mut h = unsafe { hdr.shadow() };
+                thread::spawn(move || {
+                    (0..).map(|i| n * 1_000_000 + i).all(|i| {
+                        h.try_insert(to_bytes(i), to_bytes(i))
+                            .is_ok_and(|old| old.is_none())
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        thread::sleep(Duration::from_millis(50));
+        pnk!(hdr.seal());
+        writers
+            .into_iter()
+            .for_each(|w| assert!(!w.join().unwrap()));
+
+        hdr.iter().for_each(|(k, v)| {
+            assert_eq!(Some(v), hdr.get(&k));
+        });
+        pnk!(hdr.unseal());
+    }
+}
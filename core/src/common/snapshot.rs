@@ -0,0 +1,274 @@
+//!
+//! # Snapshots
+//!
+//! A snapshot is a point-in-time view of all instances,
+//! the reads through the same snapshot observe the same state.
+//!
+//! The backends are not required to support snapshots natively,
+//! instead, the first write to each key after a snapshot has been taken
+//! saves the old value into the snapshot, in memory.
+//! The native snapshots of rocksdb are not used, parity-db has none,
+//! and the same semantics are kept for both engines.
+//!
+//! NOTE:
+//! - Snapshots are not persisted, they are released when dropped
+//! - **The memory is not bounded**, every alive snapshot keeps a copy of
+//!   the old value of each key written after it was taken,
+//!   and `clear`/`remove_range` copy all the removed entries,
+//!   check `Snapshot::memory_usage` and drop the long-lived snapshots
+//! - While a snapshot is alive, the writes are serialized on it
+//! - **The writes from other threads wait for the snapshot iterators to be dropped**
+//! - Do not take new snapshots while holding snapshot iterators, it may deadlock
+//!
+
+use crate::common::{PreBytes, RawKey, RawValue};
+use parking_lot::{
+    ArcReentrantMutexGuard, Mutex, RawMutex, RawThreadId, ReentrantMutex, RwLock,
+    RwLockReadGuard,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, LazyLock, Weak,
+    },
+};
+
+pub(crate) type Preimages = RefCell<PreimageMap>;
+
+pub(crate) type PreimagesGuard =
+    ArcReentrantMutexGuard<RawMutex, RawThreadId, Preimages>;
+
+#[derive(Default)]
+pub(crate) struct PreimageMap {
+    // instance prefix => key => the value when the snapshot was taken
+    pub(crate) entries: HashMap<PreBytes, BTreeMap<RawKey, Option<RawValue>>>,
+    // the bytes of the saved keys and values
+    bytes: usize,
+}
+
+impl PreimageMap {
+    // Only the first value of each key is kept
+    fn save(
+        &mut self,
+        prefix: PreBytes,
+        key: &[u8],
+        value: impl FnOnce() -> Option<RawValue>,
+    ) {
+        let m = self.entries.entry(prefix).or_default();
+        if !m.contains_key(key) {
+            let v = value();
+            self.bytes += key.len() + v.as_ref().map_or(0, |v| v.len());
+            m.insert(key.to_vec(), v);
+        }
+    }
+}
+
+/// A point-in-time view of the database, see `vsdb_snapshot`.
+///
+/// NOTE: the old values are kept in memory until it is dropped,
+/// see `Snapshot::memory_usage`.
+#[derive(Clone, Default)]
+pub struct Snapshot(Arc<ReentrantMutex<Preimages>>);
+
+static SNAPSHOTS: LazyLock<Mutex<Vec<Weak<ReentrantMutex<Preimages>>>>> =
+    LazyLock::new(|| Mutex::new(vec![]));
+
+// Skip the registry in the common case that no snapshots are alive,
+// it is only increased by `vsdb_snapshot`, so it can not be stale in a write
+static ALIVE_CNT: AtomicUsize = AtomicUsize::new(0);
+
+// Held by the writes, so no snapshots can be taken in the middle of a write
+static WRITE_LK: LazyLock<RwLock<()>> = LazyLock::new(|| RwLock::new(()));
+
+/// Take a snapshot of all instances.
+///
+/// NOTE:
+/// - The memory is not bounded, the old value of each key written after this call
+///   is kept in memory until the snapshot is dropped
+/// - The writes from other threads wait for the iterators of the snapshot to be dropped
+pub fn vsdb_snapshot() -> Snapshot {
+    let x = WRITE_LK.write();
+
+    let s = Snapshot::default();
+    let mut snapshots = SNAPSHOTS.lock();
+    snapshots.retain(|w| 0 < w.strong_count());
+    snapshots.push(Arc::downgrade(&s.0));
+    ALIVE_CNT.store(snapshots.len(), Ordering::Relaxed);
+
+    drop(snapshots);
+    drop(x);
+    s
+}
+
+impl Snapshot {
+    // The current value is read under the lock,
+    // so it can not be changed by other threads before being checked
+    pub(crate) fn get(
+        &self,
+        prefix: PreBytes,
+        key: &[u8],
+        current: impl FnOnce() -> Option<RawValue>,
+    ) -> Option<RawValue> {
+        let g = self.0.lock();
+        if let Some(v) = g.borrow().entries.get(&prefix).and_then(|m| m.get(key)) {
+            return v.clone();
+        }
+        current()
+    }
+
+    /// The bytes of the old keys and values kept by this snapshot,
+    /// the bookkeeping overhead is not included.
+    pub fn memory_usage(&self) -> usize {
+        self.0.lock().borrow().bytes
+    }
+
+    // The writes from other threads are blocked until it is dropped
+    #[inline(always)]
+    pub(crate) fn lock(&self) -> PreimagesGuard {
+        self.0.lock_arc()
+    }
+}
+
+#[inline(always)]
+pub(crate) fn write_guard() -> RwLockReadGuard<'static, ()> {
+    WRITE_LK.read_recursive()
+}
+
+// Like `write_guard`, but return `None` instead of waiting for a snapshot being taken
+#[inline(always)]
+pub(crate) fn try_write_guard() -> Option<RwLockReadGuard<'static, ()>> {
+    WRITE_LK.try_read_recursive()
+}
+
+// Return after all the writes in progress are done
+#[inline(always)]
+pub(crate) fn wait_for_writes() {
+    drop(WRITE_LK.write());
+}
+
+fn alive() -> Vec<Arc<ReentrantMutex<Preimages>>> {
+    if 0 == ALIVE_CNT.load(Ordering::Relaxed) {
+        return vec![];
+    }
+
+    let mut snapshots = SNAPSHOTS.lock();
+    snapshots.retain(|w| 0 < w.strong_count());
+    ALIVE_CNT.store(snapshots.len(), Ordering::Relaxed);
+    snapshots.iter().filter_map(Weak::upgrade).collect()
+}
+
+/// Save the value of a key before it is overwritten or removed,
+/// each snapshot only keeps the first one.
+pub(crate) fn preserve(
+    prefix: PreBytes,
+    key: &[u8],
+    current: impl Fn() -> Option<RawValue>,
+) {
+    for s in alive() {
+        let g = s.lock();
+        g.borrow_mut().save(prefix, key, &current);
+    }
+}
+
+// Like `preserve`, but return `false` instead of waiting on a locked snapshot,
+// the preimages recorded before that are still valid, as nothing has been written
+pub(crate) fn try_preserve(
+    prefix: PreBytes,
+    key: &[u8],
+    current: impl Fn() -> Option<RawValue>,
+) -> bool {
+    for s in alive() {
+        let Some(g) = s.try_lock() else {
+            return false;
+        };
+        g.borrow_mut().save(prefix, key, &current);
+    }
+    true
+}
+
+/// Like `preserve`, but for the entries that will be removed in batch.
+///
+/// NOTE: the entries are streamed into every alive snapshot,
+/// so the memory of the snapshots grows with the size of the removed range,
+/// nothing is read if there are no snapshots.
+pub(crate) fn preserve_all<I: Iterator<Item = (RawKey, RawValue)>>(
+    prefix: PreBytes,
+    current: impl Fn() -> I,
+) {
+    for s in alive() {
+        let g = s.lock();
+        let mut preimages = g.borrow_mut();
+        current().for_each(|(k, v)| {
+            preimages.save(prefix, &k, || Some(v));
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::basic::mapx_raw::test::{filled, to_bytes, to_u64};
+    use ruc::*;
+    use std::{borrow::Cow, mem::size_of};
+
+    #[test]
+    fn test_snapshot() {
+        let mut a = filled(100);
+        let mut b = filled(100);
+
+        let snapshot = crate::vsdb_snapshot();
+        let used = snapshot.memory_usage();
+
+        a.insert(to_bytes(0), to_bytes(1000));
+        a.insert(to_bytes(1000), to_bytes(1000));
+        a.remove(to_bytes(1));
+        a.remove_range(Cow::Owned(to_bytes(50).to_vec())..);
+        b.clear();
+
+        for hdr in [&a, &b] {
+            (0..100u64).for_each(|i| {
+                assert_eq!(
+                    &to_bytes(i)[..],
+                    &pnk!(hdr.get_with_snapshot(&snapshot, to_bytes(i)))[..]
+                );
+            });
+            assert!(hdr.get_with_snapshot(&snapshot, to_bytes(1000)).is_none());
+            assert!(hdr
+                .iter_with_snapshot(&snapshot)
+                .map(|(k, v)| (to_u64(&k), to_u64(&v)))
+                .eq((0..100).map(|i| (i, i))));
+        }
+        assert!(a
+            .range_with_snapshot(
+                &snapshot,
+                Cow::Owned(to_bytes(40).to_vec())..Cow::Owned(to_bytes(60).to_vec())
+            )
+            .map(|(k, _)| to_u64(&k))
+            .eq(40..60));
+
+        // the old values of `a` and all the entries of `b`
+        assert!(used + (2 + 50 + 100) * 2 * size_of::<u64>() <= snapshot.memory_usage());
+
+        // the current state
+        assert_eq!(1000, to_u64(&pnk!(a.get(to_bytes(0)))));
+        assert_eq!(49, a.len());
+        assert!(b.is_empty());
+
+        // writes in the same thread during the iteration
+        let mut shadow = unsafe { a.shadow() };
+        let mut iter = a.iter_with_snapshot(&snapshot);
+        assert_eq!(0, to_u64(&pnk!(iter.next()).1));
+        shadow.insert(to_bytes(10), to_bytes(1000));
+        shadow.remove(to_bytes(20));
+        assert!(iter.map(|(_, v)| to_u64(&v)).eq(1..100));
+
+        drop(snapshot);
+        let snapshot = crate::vsdb_snapshot();
+        assert!(b.iter_with_snapshot(&snapshot).next().is_none());
+        assert_eq!(
+            1000,
+            to_u64(&pnk!(a.get_with_snapshot(&snapshot, to_bytes(10))))
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use super::*;
+use crate::basic::mapx_raw::{
+    test::{filled, to_bytes},
+    MapxRaw,
+};
+use ruc::*;
+use std::mem::size_of;
+
+#[test]
+fn test_slow_op_log() {
+    let mut hdr = MapxRaw::new();
+    let prefix = *hdr.as_prefix_slice();
+    let taken = || {
+        crate::vsdb_take_slow_ops()
+            .into_iter()
+            .filter(|op| op.namespace.to_be_bytes() == prefix)
+            .collect::<Vec<_>>()
+    };
+
+    // disabled by default
+    hdr.insert(to_bytes(1), to_bytes(1));
+
+    // faster than the threshold
+    crate::vsdb_set_slow_op_threshold(Some(60_000));
+    hdr.insert(to_bytes(2), to_bytes(2));
+    assert!(taken().is_empty());
+
+    // every operation is recorded
+    crate::vsdb_set_slow_op_threshold(Some(0));
+    hdr.insert(to_bytes(3), to_bytes(3));
+    hdr.get(to_bytes(3));
+    crate::vsdb_set_slow_op_threshold(None);
+    hdr.remove(to_bytes(3));
+
+    let ops = taken();
+    assert_eq!(
+        vec![OpKind::Insert, OpKind::Get],
+        ops.iter().map(|op| op.kind).collect::<Vec<_>>()
+    );
+    assert!(ops.iter().all(|op| size_of::<u64>() == op.key_len));
+}
+
+#[test]
+fn test_namespace_label() {
+    let label = rand::random::<u64>().to_be_bytes();
+    let new_pair =
+        || crate::vsdb_with_namespace_label(label, || (MapxRaw::new(), MapxRaw::new()));
+
+    let (mut a0, a1) = new_pair();
+    assert_ne!(a0.as_bytes(), a1.as_bytes());
+    assert!(a0.is_empty());
+    a0.insert(to_bytes(1), to_bytes(1));
+
+    let (b0, b1) = new_pair();
+    assert_eq!(a0.as_bytes(), b0.as_bytes());
+    assert_eq!(a1.as_bytes(), b1.as_bytes());
+    assert_eq!(1, b0.len());
+    assert_eq!(&to_bytes(1)[..], &pnk!(b0.get(to_bytes(1)))[..]);
+
+    // out of the labeled scope
+    assert_ne!(a0.as_bytes(), MapxRaw::new().as_bytes());
+
+    // only the labeled instances may miss the len hint
+    let unknown = unsafe {
+        MapxRaw::from_prefix_slice(crate::common::BIGGEST_RESERVED_ID.to_be_bytes())
+    };
+    assert!(std::panic::catch_unwind(|| unknown.len()).is_err());
+}
+
+#[test]
+fn test_shrink_to_fit() {
+    let hdr = filled(100);
+    crate::vsdb_shrink_to_fit();
+    assert_eq!(100, hdr.len());
+    assert_eq!(&to_bytes(99)[..], &pnk!(hdr.get(to_bytes(99)))[..]);
+
+    // the flushed data blocks are loaded into the block cache by the reads
+    assert_eq!(100, hdr.iter().count());
+    let released = crate::vsdb_shrink_to_fit();
+    #[cfg(feature = "parity_backend")]
+    assert_eq!(0, released);
+    #[cfg(feature = "rocks_backend")]
+    assert!(0 < released);
+}
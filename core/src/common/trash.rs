@@ -0,0 +1,124 @@
+//!
+//! # Trash
+//!
+//! Soft-destroyed instances are kept here until their retention periods end,
+//! they can be restored before being purged.
+//!
+//! NOTE: nothing is purged when the database is frozen or read-only,
+//! the expired instances are purged by the next call after that.
+//!
+
+use crate::{
+    basic::mapx_raw::MapxRaw,
+    common::{
+        vsdb_is_frozen, vsdb_is_read_only, Pre, PreBytes, INTERNAL_ID_BASE,
+        TRASH_CLEANER,
+    },
+};
+use parking_lot::Mutex;
+use ruc::*;
+use std::{
+    sync::LazyLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The ID of a soft-destroyed instance.
+pub type TrashId = Pre;
+
+// An internal ID, it will never be allocated to normal instances
+const TRASH_REGISTRY_ID: Pre = INTERNAL_ID_BASE;
+
+// trash id(the prefix of the instance) => deadline(unix timestamp in seconds)
+static TRASH: LazyLock<Mutex<MapxRaw>> = LazyLock::new(|| {
+    Mutex::new(unsafe { MapxRaw::from_prefix_slice(TRASH_REGISTRY_ID.to_be_bytes()) })
+});
+
+#[inline(always)]
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+pub(crate) fn put(hdr: MapxRaw, retention: Duration) -> Result<TrashId> {
+    let id = *hdr.as_prefix_slice();
+    let deadline = now().saturating_add(retention.as_secs());
+
+    TRASH
+        .lock()
+        .try_insert(id, deadline.to_be_bytes())
+        .map_err(|e| eg!(e))?;
+
+    // purge the expired ones in background
+    TRASH_CLEANER.lock().execute(|| {
+        info_omit!(vsdb_trash_purge());
+    });
+
+    Ok(Pre::from_be_bytes(id))
+}
+
+/// Restore a soft-destroyed instance,
+/// the instances of other types can be rebuilt from its prefix.
+pub fn vsdb_trash_restore(id: TrashId) -> Result<MapxRaw> {
+    let id: PreBytes = id.to_be_bytes();
+    TRASH
+        .lock()
+        .try_remove(id)
+        .map_err(|e| eg!(e))?
+        .c(d!("not found in the trash"))?;
+    Ok(unsafe { MapxRaw::from_prefix_slice(id) })
+}
+
+/// Physically delete the data of the soft-destroyed instances
+/// whose retention periods have ended, return the number of them.
+///
+/// It is postponed and `0` is returned when the database is frozen or read-only.
+pub fn vsdb_trash_purge() -> Result<usize> {
+    if vsdb_is_frozen() || vsdb_is_read_only() {
+        return Ok(0);
+    }
+
+    let mut trash = TRASH.lock();
+    let now = now();
+
+    let expired = trash
+        .iter()
+        .filter(|(_, deadline)| crate::parse_int!(deadline, u64) <= now)
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+
+    // the ones failed to be purged are kept in the trash, and retried next time
+    for id in expired.iter() {
+        let mut hdr = unsafe { MapxRaw::from_prefix_slice(id) };
+        // the sealed instances can not be cleared
+        hdr.unseal().c(d!())?;
+        hdr.try_clear().map_err(|e| eg!(e))?;
+        trash.try_remove(id).map_err(|e| eg!(e))?;
+    }
+
+    Ok(expired.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::basic::mapx_raw::test::filled;
+
+    #[test]
+    fn test_soft_destroy() {
+        let hdr = filled(100);
+        let shadow = unsafe { hdr.shadow() };
+
+        let id = pnk!(hdr.soft_destroy(Duration::from_secs(3600)));
+        let hdr = pnk!(vsdb_trash_restore(id));
+        assert!(hdr.is_the_same_instance(&shadow));
+        assert_eq!(100, hdr.len());
+        assert!(vsdb_trash_restore(id).is_err());
+
+        let id = pnk!(hdr.soft_destroy(Duration::ZERO));
+        pnk!(vsdb_trash_purge());
+        assert!(vsdb_trash_restore(id).is_err());
+        assert!(shadow.is_empty());
+    }
+}
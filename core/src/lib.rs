@@ -10,6 +10,15 @@ pub mod basic;
 pub use basic::mapx_raw::MapxRaw;
 
 pub use common::{
-    vsdb_flush, vsdb_get_base_dir, vsdb_get_custom_dir, vsdb_set_base_dir, RawBytes,
-    RawKey, RawValue, GB, KB, MB, NULL,
+    vsdb_flush, vsdb_freeze, vsdb_get_base_dir, vsdb_get_custom_dir,
+    vsdb_get_rejected_write_cnt, vsdb_get_retry_policy, vsdb_is_frozen,
+    vsdb_is_read_only, vsdb_set_base_dir, vsdb_set_retry_policy,
+    vsdb_set_slow_op_threshold, vsdb_shrink_to_fit, vsdb_snapshot, vsdb_take_slow_ops,
+    vsdb_trash_purge, vsdb_trash_restore, vsdb_unfreeze, vsdb_with_namespace_label,
+    AccessStats, BackendError, CacheAdapter, CompactionStats, OpKind, Quota, QuotaGroup,
+    QuotaUsage, RangeAccess, RawBytes, RawKey, RawValue, RetryPolicy, SizeLimits,
+    SlowOp, Snapshot, TrashId, WriteError, GB, KB, MB, NULL,
 };
+
+#[cfg(feature = "rocks_backend")]
+pub use common::{vsdb_finish_bulk_load, vsdb_tune, RocksProfile};
@@ -0,0 +1,78 @@
+use ruc::*;
+use std::{thread, time::Duration};
+use vsdb_core::{
+    vsdb_flush, vsdb_freeze, vsdb_is_frozen, vsdb_set_base_dir, vsdb_trash_purge,
+    vsdb_trash_restore, vsdb_unfreeze, MapxRaw, WriteError,
+};
+
+// The switch is global, so it is tested in its own process
+#[test]
+fn basic_cases() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr = MapxRaw::new();
+    hdr.insert([1], [1]);
+    let mut blocked = MapxRaw::new();
+    blocked.insert([1], [1]);
+    let mut trashed = MapxRaw::new();
+    trashed.insert([1], [1]);
+    let trash_id = pnk!(trashed.soft_destroy(Duration::from_secs(1)));
+    hdr.track_access(1);
+
+    vsdb_freeze();
+    assert!(vsdb_is_frozen());
+
+    assert_eq!(Err(WriteError::Frozen), hdr.try_insert([2], [2]));
+    assert_eq!(Err(WriteError::Frozen), hdr.try_remove([1]));
+    assert!(hdr.clone_deep_to_new_instance().is_err());
+    assert!(MapxRaw::new()
+        .bulk_load_sorted([(vec![1], vec![1])])
+        .is_err());
+    assert_eq!(Err(WriteError::Frozen), hdr.try_clear());
+    assert_eq!(Err(WriteError::Frozen), hdr.try_remove_range(..));
+
+    // the non-`try_` writes wait for `vsdb_unfreeze`
+    let writer = thread::spawn(move || {
+        let mut v = pnk!(blocked.get_mut([1]));
+        assert_eq!(Err(WriteError::Frozen), v.commit());
+        v[0] = 9;
+        drop(v);
+        blocked.insert([2], [2]);
+        blocked
+    });
+    thread::sleep(Duration::from_millis(200));
+    assert!(!writer.is_finished());
+
+    // the purge is postponed
+    thread::sleep(Duration::from_secs(1));
+    assert_eq!(0, pnk!(vsdb_trash_purge()));
+
+    // the access statistics are not saved
+    vsdb_flush();
+
+    // reads are not affected
+    assert_eq!(1, hdr.len());
+    assert_eq!(vec![1], pnk!(hdr.get([1])));
+
+    vsdb_unfreeze();
+    assert!(!vsdb_is_frozen());
+
+    let blocked = writer.join().unwrap();
+    assert_eq!(vec![9], pnk!(blocked.get([1])));
+    assert_eq!(vec![2], pnk!(blocked.get([2])));
+
+    assert_eq!(1, pnk!(vsdb_trash_purge()));
+    assert!(vsdb_trash_restore(trash_id).is_err());
+
+    assert!(pnk!(hdr.try_insert([2], [2])).is_none());
+    let mut v = pnk!(hdr.get_mut([2]));
+    v[0] = 3;
+    pnk!(v.commit());
+    drop(v);
+    assert_eq!(vec![3], pnk!(hdr.get([2])));
+    pnk!(hdr.try_clear());
+    assert!(hdr.is_empty());
+}
@@ -0,0 +1,84 @@
+use ruc::*;
+use std::{env, fs, panic, process::Command, thread, time::Duration};
+use vsdb_core::{vsdb_flush, vsdb_is_read_only, vsdb_set_base_dir, MapxRaw, WriteError};
+
+const ROLE_VAR: &str = "VSDB_READ_ONLY_TEST_ROLE";
+const DIR_VAR: &str = "VSDB_READ_ONLY_TEST_DIR";
+
+// The mode is detected when the database is opened,
+// so the writer and the reader run in their own processes
+#[test]
+fn basic_cases() {
+    match env::var(ROLE_VAR).as_deref() {
+        Ok("writer") => return writer(),
+        Ok("reader") => return reader(),
+        _ => {}
+    }
+
+    let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u64>());
+    run("writer", &dir);
+
+    // the probe can not be written even by root
+    pnk!(fs::create_dir_all(format!("{}/.__vsdb_probe__", dir)));
+    run("reader", &dir);
+}
+
+fn run(role: &str, dir: &str) {
+    let out = pnk!(Command::new(pnk!(env::current_exe()))
+        .args(["basic_cases", "--exact", "--test-threads=1"])
+        .env(ROLE_VAR, role)
+        .env(DIR_VAR, dir)
+        .output());
+    assert!(
+        out.status.success(),
+        "{}: {}",
+        role,
+        String::from_utf8_lossy(&out.stdout)
+    );
+}
+
+fn instance_file(dir: &str) -> String {
+    format!("{}.instance", dir)
+}
+
+fn writer() {
+    let dir = pnk!(env::var(DIR_VAR));
+    pnk!(vsdb_set_base_dir(&dir));
+
+    let mut hdr = MapxRaw::new();
+    (0..10u8).for_each(|i| {
+        hdr.insert([i], [i]);
+    });
+    assert!(!vsdb_is_read_only());
+    vsdb_flush();
+    // parity-db commits in background, the queued commits are lost on exiting
+    thread::sleep(Duration::from_secs(2));
+
+    pnk!(fs::write(instance_file(&dir), hdr.as_bytes()));
+}
+
+fn reader() {
+    let dir = pnk!(env::var(DIR_VAR));
+    pnk!(vsdb_set_base_dir(&dir));
+
+    let bytes = pnk!(fs::read(instance_file(&dir)));
+    let mut hdr = unsafe { MapxRaw::from_bytes(bytes) };
+    assert_eq!(10, hdr.len());
+    assert!(vsdb_is_read_only());
+    assert_eq!(Some(vec![9]), hdr.get([9]));
+    assert_eq!(10, hdr.iter().count());
+    assert_eq!(Err(WriteError::ReadOnly), hdr.try_insert([10], [10]));
+    assert_eq!(Err(WriteError::ReadOnly), hdr.try_remove([0]));
+    assert_eq!(10, hdr.len());
+
+    // nothing is allocated for the new instances
+    let mut new = MapxRaw::new();
+    assert!(new.get([1]).is_none());
+    assert!(new.iter().next().is_none());
+    assert_eq!(0, new.len());
+    assert_eq!(Err(WriteError::ReadOnly), new.try_insert([1], [1]));
+    assert!(panic::catch_unwind(move || {
+        new.insert([1], [1]);
+    })
+    .is_err());
+}
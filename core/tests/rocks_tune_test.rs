@@ -0,0 +1,45 @@
+#![cfg(feature = "rocks_backend")]
+
+use ruc::*;
+use vsdb_core::{
+    vsdb_finish_bulk_load, vsdb_flush, vsdb_set_base_dir, vsdb_tune, MapxRaw,
+    RocksProfile,
+};
+
+// The profile is applied when the database is opened,
+// so the whole process is taken by one test
+#[test]
+fn bulk_load_profile() {
+    let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u64>());
+    pnk!(vsdb_set_base_dir(&dir));
+    pnk!(vsdb_tune(RocksProfile::BulkLoad));
+
+    // not opened yet
+    assert!(vsdb_finish_bulk_load().is_err());
+
+    let mut hdr = MapxRaw::new();
+    let n = pnk!(hdr.bulk_load_sorted(
+        (0..10_000u64).map(|i| (i.to_be_bytes().to_vec(), i.to_be_bytes().to_vec()))
+    ));
+    assert_eq!(10_000, n);
+    (10_000..20_000u64).for_each(|i| {
+        hdr.insert(i.to_be_bytes(), i.to_be_bytes());
+    });
+    vsdb_flush();
+
+    // too late to change the profile
+    assert!(vsdb_tune(RocksProfile::PointLookup).is_err());
+
+    pnk!(vsdb_finish_bulk_load());
+    // restored only once
+    assert!(vsdb_finish_bulk_load().is_err());
+
+    // the data survives the compaction, and the writes go on as usual
+    assert_eq!(20_000, hdr.len());
+    assert!(hdr
+        .iter()
+        .map(|(k, _)| u64::from_be_bytes(k.try_into().unwrap()))
+        .eq(0..20_000));
+    hdr.insert(20_000u64.to_be_bytes(), [0]);
+    assert_eq!(20_001, hdr.len());
+}
@@ -0,0 +1,190 @@
+#![doc = include_str!("../README.md")]
+#![deny(warnings)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Fields, LitStr, Result};
+
+/// Generate a `<Name>Store` for a struct with named fields,
+/// each field is persisted into its own slot of a `vsdb::ConfigStore`.
+///
+/// The store provides:
+/// - `<field>()` and `set_<field>(&value)` for every field
+/// - `load()`, read all fields into a new struct
+/// - `update_all(&struct)`, replace all fields atomically
+/// - `reset()`, fall back to the defaults
+///
+/// The getters and `load()` return a `vsdb::DecodeError`
+/// if a saved value can not be decoded, e.g. after the type of the field has been changed.
+///
+/// Fields that have never been set return `Default::default()`,
+/// or the expression given by `#[vsconfig(default = <expr>)]`.
+///
+/// A renamed field can keep its data by `#[vsconfig(renamed_from = "<old name>")]`,
+/// the old slots are read if the field has never been set under the new name,
+/// and they are removed by the first write under the new name.
+/// The attribute can be repeated for a field that has been renamed more than once.
+///
+/// ```ignore
+/// #[derive(VsConfig)]
+/// struct AppConfig {
+///     #[vsconfig(default = 8)]
+///     threads: u32,
+///     #[vsconfig(renamed_from = "app_name")]
+///     name: String,
+/// }
+///
+/// let mut store = AppConfigStore::new();
+/// assert_eq!(8, store.threads().unwrap());
+/// store.set_name(&"app".to_owned());
+/// ```
+#[proc_macro_derive(VsConfig, attributes(vsconfig))]
+pub fn vs_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_vs_config(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_vs_config(input: DeriveInput) -> Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "VsConfig can not be derived for generic structs",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(f) => &f.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &s.fields,
+                    "VsConfig can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "VsConfig can only be derived for structs",
+            ));
+        }
+    };
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let store = format_ident!("{}Store", name);
+
+    let mut accessors = vec![];
+    let mut idents = vec![];
+    let mut keys = vec![];
+
+    for f in fields.iter() {
+        // named fields always have idents
+        let ident = f.ident.as_ref().unwrap();
+        let ty = &f.ty;
+        let key = ident.to_string();
+        let setter = format_ident!("set_{}", ident);
+
+        let attrs = field_attrs(&f.attrs)?;
+        let default = match attrs.default {
+            Some(e) => quote! { #e },
+            None => quote! { ::core::default::Default::default() },
+        };
+        let old_keys = attrs.renamed_from;
+
+        accessors.push(quote! {
+            pub fn #ident(&self) -> ::core::result::Result<#ty, ::vsdb::DecodeError> {
+                let v = self.inner.get::<#ty>(#key)?;
+                #(let v = match v {
+                    Some(v) => Some(v),
+                    None => self.inner.get::<#ty>(#old_keys)?,
+                };)*
+                Ok(v.unwrap_or_else(|| #default))
+            }
+
+            // The old slots are removed after the new one has been written,
+            // so the value is never lost, it is a no-op after the first write
+            #[inline(always)]
+            pub fn #setter(&mut self, value: &#ty) {
+                self.inner.set(#key, value);
+                #(self.inner.remove(#old_keys);)*
+            }
+        });
+
+        idents.push(ident);
+        keys.push(key);
+    }
+
+    Ok(quote! {
+        #[derive(::vsdb::serde::Serialize, ::vsdb::serde::Deserialize, Debug)]
+        #[serde(crate = "::vsdb::serde")]
+        #vis struct #store {
+            inner: ::vsdb::ConfigStore,
+        }
+
+        impl #store {
+            #[inline(always)]
+            pub fn new() -> Self {
+                Self {
+                    inner: ::vsdb::ConfigStore::new(),
+                }
+            }
+
+            #(#accessors)*
+
+            pub fn load(&self) -> ::core::result::Result<#name, ::vsdb::DecodeError> {
+                Ok(#name {
+                    #(#idents: self.#idents()?,)*
+                })
+            }
+
+            pub fn update_all(&mut self, config: &#name) {
+                self.inner.update_all([
+                    #((#keys, ::vsdb::ValueEnDe::encode(&config.#idents)),)*
+                ]);
+            }
+
+            #[inline(always)]
+            pub fn reset(&mut self) {
+                self.inner.clear();
+            }
+        }
+
+        impl ::core::default::Default for #store {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    })
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    default: Option<Expr>,
+    renamed_from: Vec<String>,
+}
+
+// `#[vsconfig(default = <expr>)]`, `#[vsconfig(renamed_from = "<old name>")]`
+fn field_attrs(attrs: &[syn::Attribute]) -> Result<FieldAttrs> {
+    let mut ret = FieldAttrs::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("vsconfig")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                ret.default = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else if meta.path.is_ident("renamed_from") {
+                let old = meta.value()?.parse::<LitStr>()?;
+                ret.renamed_from.push(old.value());
+                Ok(())
+            } else {
+                Err(meta.error(
+                    "unsupported vsconfig attribute, expect `default` or `renamed_from`",
+                ))
+            }
+        })?;
+    }
+    Ok(ret)
+}
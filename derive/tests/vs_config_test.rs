@@ -0,0 +1,115 @@
+use ruc::*;
+use vsdb::{vsdb_set_base_dir, ValueEnDe};
+use vsdb_derive::VsConfig;
+
+#[derive(VsConfig, Debug, PartialEq, Eq)]
+pub struct AppConfig {
+    #[vsconfig(default = 8)]
+    threads: u32,
+    #[vsconfig(default = "app".to_owned())]
+    name: String,
+    peers: Vec<String>,
+}
+
+#[test]
+fn basic_cases() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut store = AppConfigStore::new();
+    assert_eq!(8, pnk!(store.threads()));
+    assert_eq!("app", pnk!(store.name()));
+    assert!(pnk!(store.peers()).is_empty());
+
+    store.set_threads(&16);
+    store.set_peers(&vec!["a".to_owned()]);
+
+    let reloaded = pnk!(AppConfigStore::decode(&store.encode()));
+    assert_eq!(
+        AppConfig {
+            threads: 16,
+            name: "app".to_owned(),
+            peers: vec!["a".to_owned()],
+        },
+        pnk!(reloaded.load())
+    );
+
+    let cfg = AppConfig {
+        threads: 1,
+        name: "x".to_owned(),
+        peers: vec![],
+    };
+    store.update_all(&cfg);
+    assert_eq!(cfg, pnk!(store.load()));
+    assert_eq!(cfg, pnk!(reloaded.load()));
+
+    store.reset();
+    assert_eq!(8, pnk!(store.threads()));
+    assert_eq!("app", pnk!(store.name()));
+}
+
+#[derive(VsConfig)]
+pub struct OldConfig {
+    app_name: String,
+}
+
+#[derive(VsConfig)]
+pub struct NewConfig {
+    #[vsconfig(renamed_from = "app_name")]
+    name: String,
+}
+
+#[test]
+fn renamed_fields() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut old = OldConfigStore::new();
+    old.set_app_name(&"app".to_owned());
+
+    let mut new = pnk!(NewConfigStore::decode(&old.encode()));
+    assert_eq!("app", pnk!(new.name()));
+
+    new.set_name(&"x".to_owned());
+    assert_eq!("x", pnk!(new.name()));
+    // the old slot is removed by the first write under the new name
+    assert!(pnk!(old.app_name()).is_empty());
+
+    new.reset();
+    assert!(pnk!(new.name()).is_empty());
+}
+
+#[derive(VsConfig)]
+pub struct FlagConfig {
+    enabled: bool,
+}
+
+#[derive(VsConfig)]
+pub struct LevelConfig {
+    #[vsconfig(renamed_from = "enabled")]
+    level: u64,
+}
+
+#[test]
+fn changed_types() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut old = FlagConfigStore::new();
+    old.set_enabled(&true);
+
+    let mut new = pnk!(LevelConfigStore::decode(&old.encode()));
+    assert!(new.level().is_err());
+    assert!(new.load().is_err());
+
+    // fixed by writing a value of the new type
+    new.set_level(&3);
+    assert_eq!(3, pnk!(new.level()));
+    assert!(!pnk!(old.enabled()));
+}
@@ -0,0 +1,126 @@
+//!
+//! The `serde`-based codec of keys and values.
+//!
+//! With the `stable_codec` feature, the msgpack options are pinned
+//! and each encoded value starts with a header of the format version,
+//! so the bytes will not change along with the upgrades of the dependencies.
+//!
+//! The values written by the plain `msgpack_codec` have no header,
+//! they are still readable as the version 0 of the stable wire format.
+//!
+
+use crate::{Error, RawBytes, Result};
+use alloc::string::ToString;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The version of the stable wire format,
+/// it is the second byte of each encoded value.
+#[cfg(feature = "stable_codec")]
+pub const STABLE_FORMAT_VERSION: u8 = 1;
+
+// The first byte of each encoded value,
+// it is never used by msgpack, so the values without headers can be told apart
+#[cfg(feature = "stable_codec")]
+const STABLE_FORMAT_MARKER: u8 = 0xc1;
+
+/// The name of the codec selected by features,
+/// it is recorded along with the exported data.
+#[cfg(feature = "json_codec")]
+pub const CODEC_NAME: &str = "json";
+
+/// The name of the codec selected by features,
+/// it is recorded along with the exported data.
+#[cfg(all(feature = "msgpack_codec", not(feature = "stable_codec")))]
+pub const CODEC_NAME: &str = "msgpack";
+
+/// The name of the codec selected by features,
+/// it is recorded along with the exported data.
+#[cfg(feature = "stable_codec")]
+pub const CODEC_NAME: &str = "msgpack-stable";
+
+/// Encode a value to bytes with the codec selected by features.
+#[cfg(feature = "json_codec")]
+pub fn encode<T: Serialize + ?Sized>(v: &T) -> Result<RawBytes> {
+    serde_json::to_vec(v).map_err(|e| Error::Codec(e.to_string()))
+}
+
+/// Encode a value to bytes with the codec selected by features.
+#[cfg(all(feature = "msgpack_codec", not(feature = "stable_codec")))]
+pub fn encode<T: Serialize + ?Sized>(v: &T) -> Result<RawBytes> {
+    msgpack::to_vec(v).map_err(|e| Error::Codec(e.to_string()))
+}
+
+/// Like `encode`, but the bytes are appended to `buf`,
+/// which is left unchanged on errors.
+#[cfg(feature = "json_codec")]
+pub fn encode_into<T: Serialize + ?Sized>(v: &T, buf: &mut RawBytes) -> Result<()> {
+    // `serde_json` can only write into `std::io::Write` with the `std` feature
+    encode(v).map(|b| buf.extend_from_slice(&b))
+}
+
+/// Like `encode`, but the bytes are appended to `buf`,
+/// which is left unchanged on errors.
+#[cfg(all(feature = "msgpack_codec", not(feature = "stable_codec")))]
+pub fn encode_into<T: Serialize + ?Sized>(v: &T, buf: &mut RawBytes) -> Result<()> {
+    let len = buf.len();
+    v.serialize(&mut msgpack::Serializer::new(&mut *buf))
+        .map_err(|e| {
+            buf.truncate(len);
+            Error::Codec(e.to_string())
+        })
+}
+
+/// Encode a value to bytes in the stable wire format.
+///
+/// Structs are encoded as maps keyed by the field names,
+/// so reordering the fields will not change the bytes.
+///
+/// NOTE: the encoding of unordered collections, e.g. `HashMap`,
+/// follows their iteration order, use ordered ones for verifiable bytes.
+#[cfg(feature = "stable_codec")]
+pub fn encode<T: Serialize + ?Sized>(v: &T) -> Result<RawBytes> {
+    let mut buf = alloc::vec![];
+    encode_into(v, &mut buf)?;
+    Ok(buf)
+}
+
+/// Like `encode`, but the bytes are appended to `buf`,
+/// which is left unchanged on errors.
+#[cfg(feature = "stable_codec")]
+pub fn encode_into<T: Serialize + ?Sized>(v: &T, buf: &mut RawBytes) -> Result<()> {
+    let len = buf.len();
+    buf.extend_from_slice(&[STABLE_FORMAT_MARKER, STABLE_FORMAT_VERSION]);
+    v.serialize(&mut msgpack::Serializer::new(&mut *buf).with_struct_map())
+        .map_err(|e| {
+            buf.truncate(len);
+            Error::Codec(e.to_string())
+        })
+}
+
+/// Decode bytes to a value with the codec selected by features.
+#[cfg(feature = "json_codec")]
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes).map_err(|e| Error::Codec(e.to_string()))
+}
+
+/// Decode bytes to a value with the codec selected by features.
+#[cfg(all(feature = "msgpack_codec", not(feature = "stable_codec")))]
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    msgpack::from_slice(bytes).map_err(|e| Error::Codec(e.to_string()))
+}
+
+/// Decode bytes in the stable wire format to a value,
+/// the bytes without headers are decoded as the version 0,
+/// that is the plain msgpack format.
+#[cfg(feature = "stable_codec")]
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let b = match bytes {
+        [STABLE_FORMAT_MARKER, STABLE_FORMAT_VERSION, b @ ..] => b,
+        [STABLE_FORMAT_MARKER, v, ..] => return Err(Error::UnsupportedVersion(*v)),
+        [STABLE_FORMAT_MARKER] => {
+            return Err(Error::Codec("missing format version".to_string()));
+        }
+        b => b,
+    };
+    msgpack::from_slice(b).map_err(|e| Error::Codec(e.to_string()))
+}
@@ -0,0 +1,78 @@
+//!
+//! # vsdb_ende
+//!
+//! The (en)Encode and (de)Decode rules of vsdb,
+//! without any dependency on the storage engines.
+//!
+//! Only `alloc` is required, so the exact same rules can be used
+//! in `no_std` environments (e.g. wasm light clients) to verify exported data.
+//!
+//! The codec traits are defined here and re-exported by `vsdb`,
+//! so a type implementing them works with both crates.
+//!
+
+#![no_std]
+#![deny(warnings)]
+#![cfg_attr(test, allow(warnings))]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "serde_ende")]
+mod codec;
+mod ordered;
+mod traits;
+
+#[cfg(test)]
+mod test;
+
+#[cfg(feature = "serde_ende")]
+pub use codec::{decode, encode, encode_into, CODEC_NAME};
+
+#[cfg(feature = "stable_codec")]
+pub use codec::STABLE_FORMAT_VERSION;
+pub use ordered::{KeyEnDeOrdered, OrderedF32, OrderedF64};
+pub use traits::{KeyDe, KeyEn, KeyEnDe, ValueDe, ValueEn, ValueEnDe};
+
+#[cfg(all(
+    feature = "serde_ende",
+    not(any(feature = "msgpack_codec", feature = "json_codec"))
+))]
+compile_error!("`serde_ende` requires a codec, enable `msgpack_codec` or `json_codec`");
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+pub type RawBytes = Vec<u8>;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Errors that may occur during decoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The length of the bytes does not match the target type.
+    InvalidLength,
+    /// The bytes are not a valid UTF-8 string.
+    InvalidUtf8,
+    /// Errors from the underlying `serde` codec.
+    Codec(String),
+    /// The bytes are written in an unknown version of the stable wire format.
+    UnsupportedVersion(u8),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength => write!(f, "invalid length"),
+            Self::InvalidUtf8 => write!(f, "invalid utf-8 bytes"),
+            Self::Codec(e) => write!(f, "codec error: {}", e),
+            Self::UnsupportedVersion(v) => {
+                write!(f, "unsupported format version: {}", v)
+            }
+        }
+    }
+}
+
+impl core::error::Error for Error {}
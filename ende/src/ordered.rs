@@ -0,0 +1,337 @@
+//!
+//! Keys whose encoded bytes keep the order of the original values.
+//!
+
+use crate::{Error, RawBytes, Result};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{cmp::Ordering, fmt, mem::size_of};
+
+/// For keys that their serialized order keep consistent with their original format.
+/// When using this kind of keys, we can do some ordered operations, such as: `get_le/get_be ...`
+pub trait KeyEnDeOrdered: Clone + Eq + Ord + fmt::Debug {
+    /// &key => bytes
+    fn to_bytes(&self) -> RawBytes;
+
+    /// &key => bytes, appended to `buf` without allocating a new vector
+    fn write_bytes(&self, buf: &mut RawBytes) {
+        buf.extend_from_slice(&self.to_bytes());
+    }
+
+    /// key => bytes
+    fn into_bytes(self) -> RawBytes {
+        self.to_bytes()
+    }
+
+    /// &bytes => key
+    fn from_slice(b: &[u8]) -> Result<Self>;
+
+    /// bytes => key
+    fn from_bytes(b: RawBytes) -> Result<Self> {
+        Self::from_slice(&b)
+    }
+}
+
+impl KeyEnDeOrdered for RawBytes {
+    #[inline(always)]
+    fn to_bytes(&self) -> RawBytes {
+        self.clone()
+    }
+
+    #[inline(always)]
+    fn write_bytes(&self, buf: &mut RawBytes) {
+        buf.extend_from_slice(self);
+    }
+
+    #[inline(always)]
+    fn into_bytes(self) -> RawBytes {
+        self
+    }
+
+    #[inline(always)]
+    fn from_slice(b: &[u8]) -> Result<Self> {
+        Ok(b.to_vec())
+    }
+
+    #[inline(always)]
+    fn from_bytes(b: RawBytes) -> Result<Self> {
+        Ok(b)
+    }
+}
+
+impl KeyEnDeOrdered for Box<[u8]> {
+    #[inline(always)]
+    fn to_bytes(&self) -> RawBytes {
+        self.to_vec()
+    }
+
+    #[inline(always)]
+    fn write_bytes(&self, buf: &mut RawBytes) {
+        buf.extend_from_slice(self);
+    }
+
+    #[inline(always)]
+    fn into_bytes(self) -> RawBytes {
+        self.into_vec()
+    }
+
+    #[inline(always)]
+    fn from_slice(b: &[u8]) -> Result<Self> {
+        Ok(b.into())
+    }
+
+    #[inline(always)]
+    fn from_bytes(b: RawBytes) -> Result<Self> {
+        Ok(b.into())
+    }
+}
+
+impl KeyEnDeOrdered for String {
+    #[inline(always)]
+    fn to_bytes(&self) -> RawBytes {
+        self.as_bytes().to_vec()
+    }
+
+    #[inline(always)]
+    fn write_bytes(&self, buf: &mut RawBytes) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    #[inline(always)]
+    fn into_bytes(self) -> RawBytes {
+        self.into_bytes()
+    }
+
+    #[inline(always)]
+    fn from_slice(b: &[u8]) -> Result<Self> {
+        String::from_utf8(b.to_vec()).map_err(|_| Error::InvalidUtf8)
+    }
+
+    #[inline(always)]
+    fn from_bytes(b: RawBytes) -> Result<Self> {
+        String::from_utf8(b).map_err(|_| Error::InvalidUtf8)
+    }
+}
+
+macro_rules! impl_type {
+    ($int: ty) => {
+        impl KeyEnDeOrdered for $int {
+            #[inline(always)]
+            fn to_bytes(&self) -> RawBytes {
+                self.wrapping_sub(<$int>::MIN).to_be_bytes().to_vec()
+            }
+            #[inline(always)]
+            fn write_bytes(&self, buf: &mut RawBytes) {
+                buf.extend_from_slice(&self.wrapping_sub(<$int>::MIN).to_be_bytes());
+            }
+            #[inline(always)]
+            fn from_slice(b: &[u8]) -> Result<Self> {
+                <[u8; size_of::<$int>()]>::try_from(b)
+                    .map_err(|_| Error::InvalidLength)
+                    .map(|bytes| <$int>::from_be_bytes(bytes).wrapping_add(<$int>::MIN))
+            }
+        }
+    };
+    (@$int: ty) => {
+        impl KeyEnDeOrdered for Vec<$int> {
+            #[inline(always)]
+            fn to_bytes(&self) -> RawBytes {
+                self.iter()
+                    .flat_map(|i| i.wrapping_sub(<$int>::MIN).to_be_bytes())
+                    .collect::<Vec<_>>()
+            }
+            #[inline(always)]
+            fn write_bytes(&self, buf: &mut RawBytes) {
+                buf.extend(
+                    self.iter()
+                        .flat_map(|i| i.wrapping_sub(<$int>::MIN).to_be_bytes()),
+                );
+            }
+            #[inline(always)]
+            fn from_slice(b: &[u8]) -> Result<Self> {
+                if 0 != b.len() % size_of::<$int>() {
+                    return Err(Error::InvalidLength);
+                }
+                Ok(b.chunks(size_of::<$int>())
+                    .map(|i| {
+                        let bytes = <[u8; size_of::<$int>()]>::try_from(i).unwrap();
+                        <$int>::from_be_bytes(bytes).wrapping_add(<$int>::MIN)
+                    })
+                    .collect())
+            }
+        }
+    };
+    (^$int: ty) => {
+        impl KeyEnDeOrdered for Box<[$int]> {
+            #[inline(always)]
+            fn to_bytes(&self) -> RawBytes {
+                KeyEnDeOrdered::to_bytes(&self.to_vec())
+            }
+            #[inline(always)]
+            fn write_bytes(&self, buf: &mut RawBytes) {
+                buf.extend(
+                    self.iter()
+                        .flat_map(|i| i.wrapping_sub(<$int>::MIN).to_be_bytes()),
+                );
+            }
+            #[inline(always)]
+            fn into_bytes(self) -> RawBytes {
+                KeyEnDeOrdered::into_bytes(self.into_vec())
+            }
+            #[inline(always)]
+            fn from_slice(b: &[u8]) -> Result<Self> {
+                <Vec<$int> as KeyEnDeOrdered>::from_slice(b).map(|b| b.into())
+            }
+            #[inline(always)]
+            fn from_bytes(b: RawBytes) -> Result<Self> {
+                <Vec<$int> as KeyEnDeOrdered>::from_bytes(b).map(|b| b.into())
+            }
+        }
+    };
+    (#$int: ty) => {
+        impl<const N: usize> KeyEnDeOrdered for [$int; N] {
+            #[inline(always)]
+            fn to_bytes(&self) -> RawBytes {
+                self.iter()
+                    .flat_map(|i| i.wrapping_sub(<$int>::MIN).to_be_bytes())
+                    .collect::<Vec<_>>()
+            }
+            #[inline(always)]
+            fn write_bytes(&self, buf: &mut RawBytes) {
+                buf.extend(
+                    self.iter()
+                        .flat_map(|i| i.wrapping_sub(<$int>::MIN).to_be_bytes()),
+                );
+            }
+            #[inline(always)]
+            fn from_slice(b: &[u8]) -> Result<Self> {
+                if N * size_of::<$int>() != b.len() {
+                    return Err(Error::InvalidLength);
+                }
+                let mut res = [0; N];
+                b.chunks(size_of::<$int>())
+                    .zip(res.iter_mut())
+                    .for_each(|(i, r)| {
+                        let bytes = <[u8; size_of::<$int>()]>::try_from(i).unwrap();
+                        *r = <$int>::from_be_bytes(bytes).wrapping_add(<$int>::MIN);
+                    });
+                Ok(res)
+            }
+        }
+    };
+}
+
+macro_rules! impl_float {
+    ($name: ident, $float: ty, $bits: ty) => {
+        /// A float key in the IEEE 754 total order,
+        /// `-NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN`.
+        ///
+        /// NOTE: `-0.0` and `0.0` are different keys,
+        /// NaNs with the same bits are equal.
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $name(pub $float);
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                Ordering::Equal == self.cmp(other)
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
+        }
+
+        impl From<$float> for $name {
+            fn from(f: $float) -> Self {
+                Self(f)
+            }
+        }
+
+        #[cfg(feature = "fuzz")]
+        impl<'a> arbitrary::Arbitrary<'a> for $name {
+            #[inline(always)]
+            fn arbitrary(
+                u: &mut arbitrary::Unstructured<'a>,
+            ) -> arbitrary::Result<Self> {
+                <$float>::arbitrary(u).map(Self)
+            }
+
+            #[inline(always)]
+            fn size_hint(depth: usize) -> (usize, Option<usize>) {
+                <$float>::size_hint(depth)
+            }
+        }
+
+        impl From<$name> for $float {
+            fn from(f: $name) -> Self {
+                f.0
+            }
+        }
+
+        impl KeyEnDeOrdered for $name {
+            #[inline(always)]
+            fn to_bytes(&self) -> RawBytes {
+                let mut buf = Vec::with_capacity(size_of::<$bits>());
+                self.write_bytes(&mut buf);
+                buf
+            }
+            // flip all bits of negative numbers, and the sign bit of positive ones
+            #[inline(always)]
+            fn write_bytes(&self, buf: &mut RawBytes) {
+                const SIGN: $bits = 1 << (<$bits>::BITS - 1);
+                let bits = self.0.to_bits();
+                let bits = if 0 == bits & SIGN { bits | SIGN } else { !bits };
+                buf.extend_from_slice(&bits.to_be_bytes());
+            }
+            #[inline(always)]
+            fn from_slice(b: &[u8]) -> Result<Self> {
+                const SIGN: $bits = 1 << (<$bits>::BITS - 1);
+                <[u8; size_of::<$bits>()]>::try_from(b)
+                    .map_err(|_| Error::InvalidLength)
+                    .map(|bytes| {
+                        let bits = <$bits>::from_be_bytes(bytes);
+                        let bits = if 0 == bits & SIGN { !bits } else { bits ^ SIGN };
+                        Self(<$float>::from_bits(bits))
+                    })
+            }
+        }
+    };
+}
+
+impl_float!(OrderedF32, f32, u32);
+impl_float!(OrderedF64, f64, u64);
+
+macro_rules! impl_all {
+    ($($t: ty),+) => {
+        $(impl_type!($t);)+
+    };
+    ($(@$t: ty),+) => {
+        $(impl_type!(@$t);)+
+    };
+    ($(^$t: ty),+) => {
+        $(impl_type!(^$t);)+
+    };
+    ($(#$t: ty),+) => {
+        $(impl_type!(#$t);)+
+    };
+}
+
+impl_all!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_all!(
+    @i8, @i16, @i32, @i64, @i128, @isize, @u16, @u32, @u64, @u128, @usize
+);
+impl_all!(
+    ^i8, ^i16, ^i32, ^i64, ^i128, ^isize, ^u16, ^u32, ^u64, ^u128, ^usize
+);
+impl_all!(
+    #i8, #i16, #i32, #i64, #i128, #isize, #u8, #u16, #u32, #u64, #u128, #usize
+);
@@ -0,0 +1,125 @@
+use super::*;
+use alloc::{boxed::Box, string::ToString, vec, vec::Vec};
+
+fn assert_order<K: KeyEnDeOrdered>(mut keys: Vec<K>) {
+    keys.sort();
+    let encoded = keys.iter().map(|k| k.to_bytes()).collect::<Vec<_>>();
+    let mut sorted = encoded.clone();
+    sorted.sort();
+    assert_eq!(encoded, sorted);
+    let mut buf = vec![0xff];
+    for (k, b) in keys.into_iter().zip(encoded) {
+        buf.truncate(1);
+        k.write_bytes(&mut buf);
+        assert_eq!(&buf[1..], &b[..]);
+        assert_eq!(K::from_slice(&b).unwrap(), k);
+        assert_eq!(K::from_bytes(b.clone()).unwrap(), k.clone());
+        assert_eq!(k.into_bytes(), b);
+    }
+}
+
+#[test]
+fn ordered_ints() {
+    assert_order(vec![i8::MIN, -1, 0, 1, i8::MAX]);
+    assert_order(vec![i64::MIN, -100, -1, 0, 1, 100, i64::MAX]);
+    assert_order(vec![u32::MIN, 1, 256, u32::MAX]);
+    assert_order(vec![i128::MIN, -1, 0, i128::MAX]);
+}
+
+#[test]
+fn ordered_floats() {
+    assert_order(
+        [
+            f64::NEG_INFINITY,
+            f64::MIN,
+            -1.5,
+            -f64::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.0,
+            f64::MAX,
+            f64::INFINITY,
+            f64::NAN,
+        ]
+        .into_iter()
+        .map(OrderedF64)
+        .collect(),
+    );
+    assert_order(
+        [
+            -f32::NAN,
+            f32::NEG_INFINITY,
+            -1.0,
+            -0.0,
+            0.0,
+            0.5,
+            f32::INFINITY,
+        ]
+        .into_iter()
+        .map(OrderedF32)
+        .collect(),
+    );
+    assert_eq!(OrderedF32::from_slice(&[0; 3]), Err(Error::InvalidLength));
+}
+
+#[test]
+fn ordered_collections() {
+    assert_order(vec![
+        vec![-1i32, 0],
+        vec![-1, 1],
+        vec![0, i32::MIN],
+        vec![1],
+    ]);
+    assert_order::<Box<[i16]>>(vec![
+        Box::from([-1i16, 0]),
+        Box::from([0i16, 0]),
+        Box::from([0i16, i16::MAX]),
+    ]);
+    assert_order(vec![[-1i64, 0], [-1, 1], [0, i64::MIN], [i64::MAX, 0]]);
+    assert_order(vec!["".to_string(), "a".to_string(), "ab".to_string()]);
+}
+
+#[test]
+fn invalid_bytes() {
+    assert_eq!(u32::from_slice(&[0; 3]), Err(Error::InvalidLength));
+    assert_eq!(<Vec<u16>>::from_slice(&[0; 3]), Err(Error::InvalidLength));
+    assert_eq!(<[u16; 2]>::from_slice(&[0; 2]), Err(Error::InvalidLength));
+    assert!(matches!(
+        alloc::string::String::from_slice(&[0xff]),
+        Err(Error::InvalidUtf8)
+    ));
+}
+
+#[cfg(feature = "serde_ende")]
+#[test]
+fn codec() {
+    let v = vec![(1u8, "a".to_string()), (2, "b".to_string())];
+    let b = encode(&v).unwrap();
+    assert_eq!(decode::<Vec<(u8, alloc::string::String)>>(&b).unwrap(), v);
+    assert!(matches!(decode::<u64>(&[]), Err(Error::Codec(_))));
+}
+
+#[cfg(feature = "stable_codec")]
+#[test]
+fn stable_codec() {
+    use alloc::{collections::BTreeMap, string::String};
+
+    let v = BTreeMap::from([(1u8, String::from("x"))]);
+    let b = encode(&v).unwrap();
+    assert_eq!(&b[..2], &[0xc1, STABLE_FORMAT_VERSION]);
+    assert_eq!(&b[2..], &[0x81, 1, 0xa1, b'x']);
+    assert_eq!(v, decode::<BTreeMap<u8, String>>(&b).unwrap());
+
+    // written by the plain msgpack codec, without headers
+    assert_eq!(v, decode::<BTreeMap<u8, String>>(&b[2..]).unwrap());
+    assert_eq!(1u8, decode::<u8>(&[1]).unwrap());
+
+    let mut b = b;
+    b[1] = STABLE_FORMAT_VERSION + 1;
+    assert_eq!(
+        Err(Error::UnsupportedVersion(STABLE_FORMAT_VERSION + 1)),
+        decode::<BTreeMap<u8, String>>(&b)
+    );
+    assert!(matches!(decode::<u8>(&[0xc1]), Err(Error::Codec(_))));
+}
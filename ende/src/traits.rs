@@ -0,0 +1,246 @@
+//!
+//! The traits used to encode and decode the keys and values.
+//!
+//! With `serde_ende`, all `serde` types are encoded by the codec selected by features,
+//! otherwise the keys are encoded by `KeyEnDeOrdered`,
+//! and the values must implement the traits by themselves.
+//!
+
+use crate::{RawBytes, Result};
+
+#[cfg(feature = "serde_ende")]
+use serde::{de::DeserializeOwned, Serialize};
+
+#[cfg(not(feature = "serde_ende"))]
+use crate::{Error, KeyEnDeOrdered};
+
+/// Methods used to encode the KEY.
+pub trait KeyEn: Sized {
+    /// Encode original key type to bytes.
+    fn try_encode_key(&self) -> Result<RawBytes>;
+
+    fn encode_key(&self) -> RawBytes {
+        must(self.try_encode_key())
+    }
+
+    /// Append the encoded key to `buf`.
+    fn encode_key_into(&self, buf: &mut RawBytes) {
+        buf.extend_from_slice(&self.encode_key());
+    }
+}
+
+/// Methods used to decode the KEY.
+pub trait KeyDe: Sized {
+    /// Decode from bytes to the original key type.
+    fn decode_key(bytes: &[u8]) -> Result<Self>;
+}
+
+/// Methods used to encode and decode the KEY.
+pub trait KeyEnDe: Sized {
+    /// Encode original key type to bytes.
+    fn try_encode(&self) -> Result<RawBytes>;
+
+    fn encode(&self) -> RawBytes {
+        must(self.try_encode())
+    }
+
+    /// Append the encoded key to `buf`,
+    /// no new vectors are allocated for the ordered keys.
+    fn encode_into(&self, buf: &mut RawBytes) {
+        buf.extend_from_slice(&self.encode());
+    }
+
+    /// Decode from bytes to the original key type.
+    fn decode(bytes: &[u8]) -> Result<Self>;
+}
+
+/// Methods used to encode the VALUE.
+pub trait ValueEn: Sized {
+    /// Encode original key type to bytes.
+    fn try_encode_value(&self) -> Result<RawBytes>;
+
+    fn encode_value(&self) -> RawBytes {
+        must(self.try_encode_value())
+    }
+}
+
+/// Methods used to decode the VALUE.
+pub trait ValueDe: Sized {
+    /// Decode from bytes to the original key type.
+    fn decode_value(bytes: &[u8]) -> Result<Self>;
+}
+
+/// Methods used to encode and decode the VALUE.
+pub trait ValueEnDe: Sized {
+    /// Encode original key type to bytes.
+    fn try_encode(&self) -> Result<RawBytes>;
+
+    fn encode(&self) -> RawBytes {
+        must(self.try_encode())
+    }
+
+    /// Decode from bytes to the original key type.
+    fn decode(bytes: &[u8]) -> Result<Self>;
+}
+
+// The infallible methods panic on errors, like the ones of the storage engines
+#[inline(always)]
+fn must<T>(r: Result<T>) -> T {
+    r.unwrap_or_else(|e| panic!("{}", e))
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "serde_ende")]
+impl<T: Serialize> KeyEn for T {
+    fn try_encode_key(&self) -> Result<RawBytes> {
+        crate::encode(self)
+    }
+
+    fn encode_key_into(&self, buf: &mut RawBytes) {
+        must(crate::encode_into(self, buf))
+    }
+}
+
+#[cfg(feature = "serde_ende")]
+impl<T: DeserializeOwned> KeyDe for T {
+    fn decode_key(bytes: &[u8]) -> Result<Self> {
+        crate::decode(bytes)
+    }
+}
+
+#[cfg(feature = "serde_ende")]
+impl<T: Serialize> ValueEn for T {
+    fn try_encode_value(&self) -> Result<RawBytes> {
+        crate::encode(self)
+    }
+}
+
+#[cfg(feature = "serde_ende")]
+impl<T: DeserializeOwned> ValueDe for T {
+    fn decode_value(bytes: &[u8]) -> Result<Self> {
+        crate::decode(bytes)
+    }
+}
+
+impl<T: KeyEn + KeyDe> KeyEnDe for T {
+    fn try_encode(&self) -> Result<RawBytes> {
+        <Self as KeyEn>::try_encode_key(self)
+    }
+
+    fn encode(&self) -> RawBytes {
+        <Self as KeyEn>::encode_key(self)
+    }
+
+    fn encode_into(&self, buf: &mut RawBytes) {
+        <Self as KeyEn>::encode_key_into(self, buf)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        <Self as KeyDe>::decode_key(bytes)
+    }
+}
+
+impl<T: ValueEn + ValueDe> ValueEnDe for T {
+    fn try_encode(&self) -> Result<RawBytes> {
+        <Self as ValueEn>::try_encode_value(self)
+    }
+
+    fn encode(&self) -> RawBytes {
+        <Self as ValueEn>::encode_value(self)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        <Self as ValueDe>::decode_value(bytes)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(not(feature = "serde_ende"))]
+impl<T: KeyEnDeOrdered> KeyEn for T {
+    fn try_encode_key(&self) -> Result<RawBytes> {
+        Ok(self.encode_key())
+    }
+
+    fn encode_key(&self) -> RawBytes {
+        <T as KeyEnDeOrdered>::to_bytes(self)
+    }
+
+    fn encode_key_into(&self, buf: &mut RawBytes) {
+        <T as KeyEnDeOrdered>::write_bytes(self, buf)
+    }
+}
+
+#[cfg(not(feature = "serde_ende"))]
+impl<T: KeyEnDeOrdered> KeyDe for T {
+    fn decode_key(bytes: &[u8]) -> Result<Self> {
+        <T as KeyEnDeOrdered>::from_slice(bytes)
+    }
+}
+
+// Without `serde`, the keys are encoded by `KeyEnDeOrdered`,
+// which keeps the arrays as they are, so do the values.
+#[cfg(not(feature = "serde_ende"))]
+impl<const N: usize> ValueEn for [u8; N] {
+    fn try_encode_value(&self) -> Result<RawBytes> {
+        Ok(self.to_vec())
+    }
+}
+
+#[cfg(not(feature = "serde_ende"))]
+impl<const N: usize> ValueDe for [u8; N] {
+    fn decode_value(bytes: &[u8]) -> Result<Self> {
+        <[u8; N]>::try_from(bytes).map_err(|_| Error::InvalidLength)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+/// Implement `ValueEnDe` for a type stored as its own handle bytes,
+/// by its `as_bytes` and `unsafe from_bytes` methods,
+/// e.g. the collections of `vsdb` used as values of other collections.
+///
+/// It expands to nothing with `serde_ende`,
+/// the type is expected to be encoded by the codec then.
+/// The generic parameters are given in brackets:
+///
+/// ```ignore
+/// vsdb_ende::impl_value_ende_by_bytes!(MapxRaw);
+/// vsdb_ende::impl_value_ende_by_bytes!([V: ValueEnDe] Vecx<V>);
+/// ```
+#[cfg(not(feature = "serde_ende"))]
+#[macro_export]
+macro_rules! impl_value_ende_by_bytes {
+    ([$($g: tt)*] $t: ty) => {
+        impl<$($g)*> $crate::ValueEnDe for $t {
+            fn try_encode(&self) -> $crate::Result<$crate::RawBytes> {
+                Ok(self.as_bytes().into())
+            }
+            fn encode(&self) -> $crate::RawBytes {
+                self.as_bytes().into()
+            }
+            fn decode(bytes: &[u8]) -> $crate::Result<Self> {
+                unsafe { Ok(<$t>::from_bytes(bytes)) }
+            }
+        }
+    };
+    ($t: ty) => {
+        $crate::impl_value_ende_by_bytes!([] $t);
+    };
+}
+
+/// Implement `ValueEnDe` for a type stored as its own handle bytes,
+/// by its `as_bytes` and `unsafe from_bytes` methods,
+/// e.g. the collections of `vsdb` used as values of other collections.
+///
+/// It expands to nothing with `serde_ende`,
+/// the type is expected to be encoded by the codec then.
+#[cfg(feature = "serde_ende")]
+#[macro_export]
+macro_rules! impl_value_ende_by_bytes {
+    ($($t: tt)*) => {};
+}
@@ -6,9 +6,19 @@ pub use hash_db as sp_hash_db;
 pub use vsdb;
 
 use hash_db::{AsHashDB, HashDB, HashDBRef, Hasher as KeyHasher, Prefix};
+use lru::LruCache;
+use parking_lot::Mutex;
 use ruc::*;
 use serde::{Deserialize, Serialize};
-use vsdb::{DagMapRaw, DagMapRawKey as Map, Orphan, RawBytes, ValueEnDe};
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{Arc, LazyLock, Weak},
+};
+use vsdb::{
+    vsdb_ende::{Error as EndeError, Result as EndeResult},
+    DagMapRaw, DagMapRawKey as Map, Orphan, RawBytes, ValueEnDe,
+};
 
 pub use keccak_hasher::KeccakHasher;
 
@@ -18,6 +28,36 @@ pub trait TrieVar: Clone + AsRef<[u8]> + for<'a> From<&'a [u8]> {}
 
 impl<T> TrieVar for T where T: Clone + AsRef<[u8]> + for<'a> From<&'a [u8]> {}
 
+/// The default number of nodes kept in the node cache of a backend.
+pub const DEFAULT_NODE_CACHE_CAP: usize = 1024;
+
+// prefixed key => the data of a live node
+type NodeLru = Mutex<Option<LruCache<Vec<u8>, RawBytes>>>;
+
+// Shared by all the handles of an instance
+type NodeCache = Arc<NodeLru>;
+
+// the encoded instance => its node cache,
+// so the shadows and the decoded handles are attached to the same cache
+static NODE_CACHES: LazyLock<Mutex<HashMap<RawBytes, Weak<NodeLru>>>> =
+    LazyLock::new(Default::default);
+
+// `cap` is only used if the instance has no cache yet
+fn node_cache_of<T: TrieVar>(data: &Map<Value<T>>, cap: usize) -> NodeCache {
+    let id = data.encode();
+    let mut caches = NODE_CACHES.lock();
+    if let Some(c) = caches.get(&id).and_then(Weak::upgrade) {
+        return c;
+    }
+    caches.retain(|_, c| 0 < c.strong_count());
+    let c = Arc::new(Mutex::new(NonZeroUsize::new(cap).map(LruCache::new)));
+    caches.insert(id, Arc::downgrade(&c));
+    c
+}
+
+// prefixed key => the new state of a node, `None` if it does not exist
+type NodeChanges<T> = HashMap<Vec<u8>, Option<Value<T>>>;
+
 // NOTE: make it `!Clone`
 pub struct MmBackend<H, T>
 where
@@ -27,6 +67,13 @@ where
     data: Map<Value<T>>,
     hashed_null_key: H::Out,
     null_node_data: T,
+
+    // hot nodes, mostly the upper levels of the trie,
+    // are served from here without reading and decoding them again
+    node_cache: NodeCache,
+
+    // the node writes staged by `begin_batch`, written by `commit_batch`
+    batch: Option<NodeChanges<T>>,
 }
 
 impl<H, T> MmBackend<H, T>
@@ -36,10 +83,13 @@ where
 {
     /// Create a new `MmBackend` from the default null key/data
     pub fn new(raw_parent: &mut Orphan<Option<DagMapRaw>>) -> Result<Self> {
+        let data = Map::new(raw_parent).c(d!())?;
         Ok(MmBackend {
-            data: Map::new(raw_parent).c(d!())?,
+            node_cache: node_cache_of(&data, DEFAULT_NODE_CACHE_CAP),
+            data,
             hashed_null_key: Self::hashed_null_node(),
             null_node_data: [0u8].as_slice().into(),
+            batch: None,
         })
     }
 
@@ -52,12 +102,43 @@ where
     ///
     /// This API breaks the semantic safety guarantees,
     /// but it is safe to use in a race-free environment.
+    ///
+    /// NOTE: the shadow shares the node cache with this handle,
+    /// but not the operations staged by `begin_batch`.
     #[inline(always)]
     pub unsafe fn shadow(&self) -> Self {
         Self {
             data: self.data.shadow(),
             hashed_null_key: self.hashed_null_key,
             null_node_data: self.null_node_data.clone(),
+            node_cache: Arc::clone(&self.node_cache),
+            batch: None,
+        }
+    }
+
+    /// The max number of nodes kept in the node cache.
+    #[inline(always)]
+    pub fn node_cache_cap(&self) -> usize {
+        self.node_cache
+            .lock()
+            .as_ref()
+            .map(|c| c.cap().get())
+            .unwrap_or(0)
+    }
+
+    /// Resize the node cache, `0` means disabling it.
+    ///
+    /// The cached nodes are dropped,
+    /// the cache is shared by all the handles of this instance.
+    #[inline(always)]
+    pub fn set_node_cache_cap(&self, cap: usize) {
+        *self.node_cache.lock() = NonZeroUsize::new(cap).map(LruCache::new);
+    }
+
+    #[inline(always)]
+    fn uncache(&self, key: &[u8]) {
+        if let Some(c) = self.node_cache.lock().as_mut() {
+            c.pop(key);
         }
     }
 
@@ -82,7 +163,11 @@ where
 
     #[inline(always)]
     pub fn clear(&mut self) {
+        self.batch = None;
         self.data.destroy();
+        if let Some(c) = self.node_cache.lock().as_mut() {
+            c.clear();
+        }
     }
 
     #[inline(always)]
@@ -93,11 +178,22 @@ where
     /// Return a new backend instance
     #[inline(always)]
     pub fn prune(self) -> Result<Self> {
+        let cap = self.node_cache_cap();
         let data = self.data.prune().c(d!())?;
+
+        // the nodes are merged into the genesis instance without going through
+        // its handles, so the ones cached by them may be stale
+        let node_cache = node_cache_of(&data, cap);
+        if let Some(c) = node_cache.lock().as_mut() {
+            c.clear();
+        }
+
         Ok(Self {
             data,
             hashed_null_key: Self::hashed_null_node(),
             null_node_data: [0u8].as_slice().into(),
+            node_cache,
+            batch: None,
         })
     }
 }
@@ -112,8 +208,23 @@ where
             return Some(self.null_node_data.clone());
         }
         let key = prefixed_key::<H>(key, prefix);
-        match self.data.get(key) {
-            Some(Value { v, rc }) if rc > 0 => Some(v),
+        if let Some(staged) = self.batch.as_ref().and_then(|b| b.get(&key)) {
+            return staged.as_ref().filter(|v| v.rc > 0).map(|v| v.v.clone());
+        }
+
+        if let Some(v) = self.node_cache.lock().as_mut().and_then(|c| c.get(&key)) {
+            return Some(T::from(v));
+        }
+
+        // the cache is not locked during the disk read,
+        // the other handles of this instance can still be served from it
+        match self.data.get(&key) {
+            Some(Value { v, rc }) if rc > 0 => {
+                if let Some(c) = self.node_cache.lock().as_mut() {
+                    c.put(key, v.as_ref().to_vec());
+                }
+                Some(v)
+            }
             _ => None,
         }
     }
@@ -123,6 +234,14 @@ where
             return true;
         }
         let key = prefixed_key::<H>(key, prefix);
+        if let Some(staged) = self.batch.as_ref().and_then(|b| b.get(&key)) {
+            return matches!(staged, Some(Value { v: _, rc }) if *rc > 0);
+        }
+        if let Some(c) = self.node_cache.lock().as_mut() {
+            if c.contains(&key) {
+                return true;
+            }
+        }
         matches!(self.data.get(key), Some(Value { v: _, rc }) if rc > 0)
     }
 
@@ -132,6 +251,24 @@ where
         }
 
         let key = prefixed_key::<H>(&key, prefix);
+        self.uncache(&key);
+
+        if let Some(batch) = self.batch.as_mut() {
+            let slot = batch.entry(key).or_insert_with_key(|k| self.data.get(k));
+            match slot {
+                Some(old) if old.rc == 0 => {
+                    old.v = value;
+                    old.rc = 1;
+                }
+                Some(old) => {
+                    old.rc += 1;
+                }
+                None => {
+                    *slot = Some(Value { v: value, rc: 1 });
+                }
+            }
+            return;
+        }
 
         if let Some(mut old) = self.data.get_mut(&key) {
             if old.rc == 0 {
@@ -163,6 +300,18 @@ where
         }
 
         let key = prefixed_key::<H>(key, prefix);
+        self.uncache(&key);
+
+        if let Some(batch) = self.batch.as_mut() {
+            let slot = batch.entry(key).or_insert_with_key(|k| self.data.get(k));
+            if let Some(v) = slot {
+                if v.rc > 0 {
+                    v.rc -= 1;
+                }
+            }
+            return;
+        }
+
         if let Some(mut v) = self.data.get_mut(&key) {
             if v.rc > 0 {
                 v.rc -= 1;
@@ -171,6 +320,75 @@ where
     }
 }
 
+impl<H, T> MmBackend<H, T>
+where
+    H: KeyHasher,
+    T: TrieVar + Clone + Sync + Send + PartialEq + Default,
+{
+    /// Apply a batch of node operations, such as the ones produced by one trie commit.
+    ///
+    /// The result is the same as calling `emplace/remove` one by one,
+    /// but each node is read and written at most once,
+    /// and all of them are written by one batch of the backend.
+    pub fn apply_batch<'a>(&mut self, ops: impl IntoIterator<Item = BatchOp<'a, H, T>>) {
+        let nested = self.batch.is_some();
+        self.begin_batch();
+
+        for op in ops.into_iter() {
+            match op {
+                BatchOp::Emplace(key, prefix, value) => {
+                    HashDB::emplace(self, key, prefix, value);
+                }
+                BatchOp::Remove(key, prefix) => {
+                    HashDB::remove(self, &key, prefix);
+                }
+            }
+        }
+
+        if !nested {
+            self.commit_batch();
+        }
+    }
+
+    /// Stage the following `emplace/remove` operations in memory,
+    /// until they are written by one batch of the backend in `commit_batch`.
+    ///
+    /// NOTE: the staged operations are lost if the backend is dropped before committing.
+    #[inline(always)]
+    pub fn begin_batch(&mut self) {
+        self.batch.get_or_insert_with(HashMap::new);
+    }
+
+    /// Write the operations staged since `begin_batch`, do nothing if not staging.
+    pub fn commit_batch(&mut self) {
+        if let Some(changes) = self.batch.take() {
+            self.data.write_batch(
+                changes
+                    .iter()
+                    .filter_map(|(k, v)| v.as_ref().map(|v| (k, Some(v)))),
+            );
+
+            // they may have been cached by the other handles before committing
+            if let Some(c) = self.node_cache.lock().as_mut() {
+                changes.keys().for_each(|k| {
+                    c.pop(k);
+                });
+            }
+        }
+    }
+}
+
+/// A node operation used by `MmBackend::apply_batch`.
+pub enum BatchOp<'a, H, T>
+where
+    H: KeyHasher,
+{
+    /// The same as `HashDB::emplace`
+    Emplace(H::Out, Prefix<'a>, T),
+    /// The same as `HashDB::remove`
+    Remove(H::Out, Prefix<'a>),
+}
+
 impl<H, T> HashDBRef<H, T> for MmBackend<H, T>
 where
     H: KeyHasher,
@@ -219,7 +437,7 @@ impl<T> ValueEnDe for Value<T>
 where
     T: TrieVar,
 {
-    fn try_encode(&self) -> Result<RawBytes> {
+    fn try_encode(&self) -> EndeResult<RawBytes> {
         Ok(self.encode())
     }
 
@@ -231,9 +449,9 @@ where
         r
     }
 
-    fn decode(bytes: &[u8]) -> Result<Self> {
+    fn decode(bytes: &[u8]) -> EndeResult<Self> {
         if bytes.len() < RC_BYTES_NUM {
-            return Err(eg!("invalid length"));
+            return Err(EndeError::InvalidLength);
         }
         let rcbytes = <[u8; RC_BYTES_NUM]>::try_from(&bytes[..RC_BYTES_NUM]).unwrap();
         Ok(Self {
@@ -260,9 +478,12 @@ where
 {
     fn from(vbs: MmBackendSerde<T>) -> Self {
         Self {
+            // re-attached to the cache of the instance if it has other live handles
+            node_cache: node_cache_of(&vbs.data, DEFAULT_NODE_CACHE_CAP),
             data: vbs.data,
             hashed_null_key: Self::hashed_null_node(),
             null_node_data: T::from(&vbs.null_node_data),
+            batch: None,
         }
     }
 }
@@ -285,18 +506,18 @@ where
     H: KeyHasher,
     T: TrieVar,
 {
-    fn try_encode(&self) -> Result<RawBytes> {
-        msgpack::to_vec(&MmBackendSerde::from(self)).c(d!())
+    fn try_encode(&self) -> EndeResult<RawBytes> {
+        msgpack::to_vec(&MmBackendSerde::from(self)).map_err(|e| EndeError::Codec(e.to_string()))
     }
 
     fn encode(&self) -> RawBytes {
         pnk!(self.try_encode())
     }
 
-    fn decode(bytes: &[u8]) -> Result<Self> {
+    fn decode(bytes: &[u8]) -> EndeResult<Self> {
         msgpack::from_slice::<MmBackendSerde<T>>(bytes)
-            .c(d!())
             .map(Self::from)
+            .map_err(|e| EndeError::Codec(e.to_string()))
     }
 }
 
@@ -308,4 +529,127 @@ mod test {
         println!("{:?}", KeccakHasher::hash(&[]));
         println!("{:?}", KeccakHasher::hash(&[0u8][..]));
     }
+
+    #[test]
+    fn hash_db_apply_batch() {
+        use super::*;
+        use hash_db::EMPTY_PREFIX;
+
+        let mut seq = TrieBackend::new(&mut Orphan::new(None)).unwrap();
+        let mut batch = TrieBackend::new(&mut Orphan::new(None)).unwrap();
+
+        let nodes = (0u8..10).map(|i| vec![i; 40]).collect::<Vec<_>>();
+        let keys = nodes
+            .iter()
+            .map(|n| HashDB::insert(&mut seq, EMPTY_PREFIX, n))
+            .collect::<Vec<_>>();
+        batch.apply_batch(
+            keys.iter()
+                .zip(nodes.iter())
+                .map(|(k, n)| BatchOp::Emplace(*k, EMPTY_PREFIX, n.clone())),
+        );
+
+        // remove twice, re-add once: rc goes 1 -> 0 -> 0 -> 1
+        for k in keys.iter().take(5) {
+            HashDB::remove(&mut seq, k, EMPTY_PREFIX);
+            HashDB::remove(&mut seq, k, EMPTY_PREFIX);
+        }
+        for (k, n) in keys.iter().zip(nodes.iter()).take(3) {
+            HashDB::emplace(&mut seq, *k, EMPTY_PREFIX, n.clone());
+        }
+        batch.apply_batch(
+            keys.iter()
+                .take(5)
+                .flat_map(|k| {
+                    [
+                        BatchOp::Remove(*k, EMPTY_PREFIX),
+                        BatchOp::Remove(*k, EMPTY_PREFIX),
+                    ]
+                })
+                .chain(
+                    keys.iter()
+                        .zip(nodes.iter())
+                        .take(3)
+                        .map(|(k, n)| BatchOp::Emplace(*k, EMPTY_PREFIX, n.clone())),
+                ),
+        );
+
+        for (i, k) in keys.iter().enumerate() {
+            let v = HashDB::get(&seq, k, EMPTY_PREFIX);
+            assert_eq!(v, HashDB::get(&batch, k, EMPTY_PREFIX));
+            assert_eq!(v.is_some(), !(3..5).contains(&i));
+        }
+    }
+
+    #[test]
+    fn hash_db_staged_batch() {
+        use super::*;
+        use hash_db::EMPTY_PREFIX;
+
+        let mut hdr = TrieBackend::new(&mut Orphan::new(None)).unwrap();
+        let node = vec![1u8; 40];
+        let k0 = HashDB::insert(&mut hdr, EMPTY_PREFIX, &node);
+
+        hdr.begin_batch();
+        let k1 = HashDB::insert(&mut hdr, EMPTY_PREFIX, &[2u8; 40]);
+        HashDB::remove(&mut hdr, &k0, EMPTY_PREFIX);
+
+        // visible to the backend itself, but not written yet
+        assert!(HashDB::contains(&hdr, &k1, EMPTY_PREFIX));
+        assert!(HashDB::get(&hdr, &k0, EMPTY_PREFIX).is_none());
+        let shadow = unsafe { hdr.shadow() };
+        assert!(!HashDB::contains(&shadow, &k1, EMPTY_PREFIX));
+        assert_eq!(
+            Some(&node),
+            HashDB::get(&shadow, &k0, EMPTY_PREFIX).as_ref()
+        );
+
+        hdr.commit_batch();
+        let shadow = unsafe { hdr.shadow() };
+        assert!(HashDB::contains(&shadow, &k1, EMPTY_PREFIX));
+        assert!(!HashDB::contains(&shadow, &k0, EMPTY_PREFIX));
+    }
+
+    #[test]
+    fn hash_db_node_cache() {
+        use super::*;
+        use hash_db::EMPTY_PREFIX;
+
+        let mut hdr = TrieBackend::new(&mut Orphan::new(None)).unwrap();
+        assert_eq!(DEFAULT_NODE_CACHE_CAP, hdr.node_cache_cap());
+
+        let node = vec![1u8; 40];
+        let k = HashDB::insert(&mut hdr, EMPTY_PREFIX, &node);
+        assert_eq!(Some(&node), HashDB::get(&hdr, &k, EMPTY_PREFIX).as_ref());
+
+        // cached nodes must be invalidated by writes
+        HashDB::remove(&mut hdr, &k, EMPTY_PREFIX);
+        assert!(HashDB::get(&hdr, &k, EMPTY_PREFIX).is_none());
+        assert!(!HashDB::contains(&hdr, &k, EMPTY_PREFIX));
+        HashDB::emplace(&mut hdr, k, EMPTY_PREFIX, node.clone());
+        assert!(HashDB::contains(&hdr, &k, EMPTY_PREFIX));
+        hdr.apply_batch([BatchOp::Remove(k, EMPTY_PREFIX)]);
+        assert!(HashDB::get(&hdr, &k, EMPTY_PREFIX).is_none());
+
+        hdr.set_node_cache_cap(0);
+        assert_eq!(0, hdr.node_cache_cap());
+        HashDB::emplace(&mut hdr, k, EMPTY_PREFIX, node.clone());
+        assert_eq!(Some(&node), HashDB::get(&hdr, &k, EMPTY_PREFIX).as_ref());
+        assert_eq!(0, unsafe { hdr.shadow() }.node_cache_cap());
+
+        // the decoded handles are attached to the cache of the live ones
+        let decoded = pnk!(TrieBackend::decode(&hdr.encode()));
+        decoded.set_node_cache_cap(7);
+        assert_eq!(7, hdr.node_cache_cap());
+        assert_eq!(
+            Some(&node),
+            HashDB::get(&decoded, &k, EMPTY_PREFIX).as_ref()
+        );
+        let encoded = hdr.encode();
+        drop((hdr, decoded));
+        assert_eq!(
+            DEFAULT_NODE_CACHE_CAP,
+            pnk!(TrieBackend::decode(&encoded)).node_cache_cap()
+        );
+    }
 }
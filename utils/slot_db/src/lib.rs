@@ -10,7 +10,7 @@ use std::{
     ops::Bound,
 };
 use vsdb::{
-    basic::mapx_ord::MapxOrdIter as LargeIter, KeyEnDeOrdered, MapxOrd,
+    basic::mapx_ord::MapxOrdIter as LargeIter, KeyEnDeOrdered, MapxOrd, Orphan,
 };
 
 type Slot = u64;
@@ -28,12 +28,16 @@ type Distance = i128;
 type PageSize = u16;
 type PageIndex = u32;
 
+// The default max number of entries that a slot can hold in memory
+const DEFAULT_CTNER_THRESHOLD: usize = 8;
+
 /// A `Skip List` like structure,
 /// designed to support fast paged queries and indexes
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(
     bound = "T: Clone + Ord + KeyEnDeOrdered + Serialize + de::DeserializeOwned"
 )]
+#[serde(try_from = "SlotDBRaw<T>")]
 pub struct SlotDB<T>
 where
     T: Clone + Ord + KeyEnDeOrdered + Serialize + de::DeserializeOwned,
@@ -55,6 +59,100 @@ where
     // if most scenes are under the reverse mode,
     // then swap the low-level logic
     swap_order: bool,
+
+    // A slot will be promoted to the disk-based container
+    // when its entries exceed this number, and be demoted
+    // back when its entries shrink to the half of it
+    #[serde(default = "default_ctner_threshold")]
+    ctner_threshold: usize,
+
+    // The tier configuration that the data was built with,
+    // it is persisted along with the data.
+    //
+    // `None` for the data created by older versions,
+    // it is created on the first write instead of during decoding
+    #[serde(default)]
+    header: Option<Orphan<TierHeader>>,
+}
+
+// The serialized form of `SlotDB`,
+// it will be validated against the persisted header
+#[derive(Deserialize)]
+#[serde(
+    bound = "T: Clone + Ord + KeyEnDeOrdered + Serialize + de::DeserializeOwned"
+)]
+struct SlotDBRaw<T>
+where
+    T: Clone + Ord + KeyEnDeOrdered + Serialize + de::DeserializeOwned,
+{
+    data: MapxOrd<Slot, DataCtner<T>>,
+    total: EntryCnt,
+    levels: Vec<Level>,
+    multiple_step: u64,
+    swap_order: bool,
+    #[serde(default = "default_ctner_threshold")]
+    ctner_threshold: usize,
+    #[serde(default)]
+    header: Option<Orphan<TierHeader>>,
+}
+
+impl<T> TryFrom<SlotDBRaw<T>> for SlotDB<T>
+where
+    T: Clone + Ord + KeyEnDeOrdered + Serialize + de::DeserializeOwned,
+{
+    type Error = Box<dyn RucError>;
+
+    fn try_from(raw: SlotDBRaw<T>) -> Result<Self> {
+        if raw.multiple_step < 2 {
+            return Err(eg!(
+                "Invalid tier capacity: {}, it must be at least 2",
+                raw.multiple_step
+            ));
+        }
+
+        // every tier must have been built with the same capacity
+        for (idx, l) in raw.levels.iter().enumerate() {
+            let expected = raw.multiple_step.checked_pow(1 + idx as u32);
+            if Some(l.floor_base) != expected {
+                return Err(eg!(
+                    "Tier {} does not match the tier capacity {}, floor base: {}",
+                    idx,
+                    raw.multiple_step,
+                    l.floor_base
+                ));
+            }
+        }
+
+        if let Some(h) = raw.header.as_ref().map(|h| h.get_value()) {
+            let found = TierHeader {
+                multiple_step: raw.multiple_step,
+                swap_order: raw.swap_order,
+            };
+            if h != found {
+                return Err(eg!(
+                    "Tier configuration mismatch, persisted: {:?}, found: {:?}",
+                    h,
+                    found
+                ));
+            }
+        }
+
+        Ok(Self {
+            data: raw.data,
+            total: raw.total,
+            levels: raw.levels,
+            multiple_step: raw.multiple_step,
+            swap_order: raw.swap_order,
+            ctner_threshold: raw.ctner_threshold,
+            header: raw.header,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+struct TierHeader {
+    multiple_step: u64,
+    swap_order: bool,
 }
 
 impl<T> SlotDB<T>
@@ -77,10 +175,37 @@ where
             levels: vec![],
             multiple_step,
             swap_order,
+            ctner_threshold: DEFAULT_CTNER_THRESHOLD,
+            header: Some(Orphan::new(TierHeader {
+                multiple_step,
+                swap_order,
+            })),
+        }
+    }
+
+    // The data created by older versions has no header,
+    // it is persisted on the first write
+    fn ensure_header(&mut self) {
+        if self.header.is_none() {
+            self.header = Some(Orphan::new(TierHeader {
+                multiple_step: self.multiple_step,
+                swap_order: self.swap_order,
+            }));
         }
     }
 
+    /// Set the max number of entries that a slot can hold in memory,
+    /// the default value is 8.
+    ///
+    /// Slots with more entries than this will be promoted to a disk-based container,
+    /// and they will be demoted back when the entries shrink to the half of this value.
+    pub fn set_ctner_threshold(&mut self, threshold: usize) {
+        self.ctner_threshold = threshold;
+    }
+
     pub fn insert(&mut self, mut slot: Slot, t: T) -> Result<()> {
+        self.ensure_header();
+
         if self.swap_order {
             slot = swap_order(slot);
         }
@@ -111,7 +236,12 @@ where
         };
 
         #[allow(clippy::unwrap_or_default)]
-        if self.data.entry(&slot).or_insert(DataCtner::new()).insert(t) {
+        if self
+            .data
+            .entry(&slot)
+            .or_insert(DataCtner::new())
+            .insert(t, self.ctner_threshold)
+        {
             self.levels.iter_mut().for_each(|l| {
                 let slot_floor = slot / l.floor_base * l.floor_base;
                 *l.data.entry(&slot_floor).or_insert(0) += 1;
@@ -123,6 +253,8 @@ where
     }
 
     pub fn remove(&mut self, mut slot: Slot, t: &T) {
+        self.ensure_header();
+
         if self.swap_order {
             slot = swap_order(slot);
         }
@@ -139,7 +271,7 @@ where
         }
 
         let (exist, empty) = if let Some(mut d) = self.data.get_mut(&slot) {
-            (d.remove(t), d.is_empty())
+            (d.remove(t, self.ctner_threshold), d.is_empty())
         } else {
             return;
         };
@@ -164,6 +296,8 @@ where
     }
 
     pub fn clear(&mut self) {
+        self.ensure_header();
+
         self.total = 0;
         self.data.clear();
 
@@ -225,6 +359,87 @@ where
         )
     }
 
+    /// Like `get_entries_by_page_slot`, but only the entries
+    /// that match the predicate are counted into the pages.
+    ///
+    /// NOTE: the predicate is applied to every scanned entry,
+    /// so it costs a scan from the start of the range to the target page.
+    pub fn get_entries_filtered(
+        &self,
+        slot_left_bound: Option<Slot>,  // Included
+        slot_right_bound: Option<Slot>, // Included
+        page_size: PageSize,
+        page_index: PageIndex, // start from 0
+        reverse_order: bool,
+        pred: impl Fn(&T) -> bool,
+    ) -> Vec<T> {
+        let mut ret = vec![];
+        alt!(0 == page_size, return ret);
+
+        let mut skip_n = page_size as usize * page_index as usize;
+        let take_n = page_size as usize;
+
+        self.for_each_within(
+            slot_left_bound,
+            slot_right_bound,
+            reverse_order,
+            |entry| {
+                if pred(&entry) {
+                    if 0 < skip_n {
+                        skip_n -= 1;
+                    } else {
+                        ret.push(entry);
+                    }
+                }
+                ret.len() < take_n
+            },
+        );
+
+        ret
+    }
+
+    // Visit the entries in the order of the pages,
+    // stop when `f` returns `false`
+    fn for_each_within(
+        &self,
+        slot_left_bound: Option<Slot>,  // Included
+        slot_right_bound: Option<Slot>, // Included
+        mut reverse_order: bool,
+        mut f: impl FnMut(T) -> bool,
+    ) {
+        let mut slot_min = slot_left_bound.unwrap_or(Slot::MIN);
+        let mut slot_max = slot_right_bound.unwrap_or(Slot::MAX);
+
+        if self.swap_order {
+            (slot_min, slot_max) =
+                (swap_order(slot_max), swap_order(slot_min));
+            reverse_order = !reverse_order;
+        }
+
+        alt!(slot_max < slot_min, return);
+
+        let slots = self.data.range(slot_min..=slot_max);
+        let slots: Box<dyn Iterator<Item = (Slot, DataCtner<T>)>> =
+            if reverse_order {
+                Box::new(slots.rev())
+            } else {
+                Box::new(slots)
+            };
+
+        for (_, entries) in slots {
+            let entries: Box<dyn Iterator<Item = T>> = if reverse_order {
+                Box::new(entries.iter().rev())
+            } else {
+                Box::new(entries.iter())
+            };
+            for entry in entries {
+                if !f(entry) {
+                    return;
+                }
+            }
+        }
+    }
+
     fn slot_entry_cnt(&self, slot: Slot) -> EntryCnt {
         self.data
             .get(&slot)
@@ -431,6 +646,26 @@ where
         }
     }
 
+    /// Count the entries that match the predicate within the slot range,
+    /// the result is consistent with the pages of `get_entries_filtered`.
+    ///
+    /// NOTE: unlike `total_by_slot`, the entries are scanned one by one.
+    pub fn count_if(
+        &self,
+        slot_start: Option<Slot>,
+        slot_end: Option<Slot>,
+        pred: impl Fn(&T) -> bool,
+    ) -> EntryCnt {
+        let mut cnt = 0;
+        self.for_each_within(slot_start, slot_end, false, |entry| {
+            if pred(&entry) {
+                cnt += 1;
+            }
+            true
+        });
+        cnt
+    }
+
     pub fn total(&self) -> EntryCnt {
         self.total_by_slot(None, None)
     }
@@ -476,9 +711,9 @@ where
         0 == self.len()
     }
 
-    fn insert(&mut self, t: T) -> bool {
+    fn insert(&mut self, t: T, threshold: usize) -> bool {
         if let Self::Small(i) = self {
-            if i.len() > 8 {
+            if i.len() > threshold {
                 *self = Self::Large(i.iter().fold(
                     MapxOrd::new(),
                     |mut acc, t| {
@@ -495,14 +730,26 @@ where
         }
     }
 
-    fn remove(&mut self, target: &T) -> bool {
-        match self {
+    fn remove(&mut self, target: &T, threshold: usize) -> bool {
+        let exist = match self {
             Self::Small(i) => i.remove(target),
             Self::Large(i) => i.remove(target).is_some(),
+        };
+
+        // Demote with a gap to the promotion point,
+        // avoid bouncing between the two kinds of containers
+        if let Self::Large(i) = self {
+            if i.len() <= threshold / 2 {
+                let small = i.iter().map(|(t, _)| t).collect();
+                i.clear();
+                *self = Self::Small(small);
+            }
         }
+
+        exist
     }
 
-    fn iter(&self) -> DataCtnerIter<T> {
+    fn iter(&self) -> DataCtnerIter<'_, T> {
         match self {
             Self::Small(i) => DataCtnerIter::Small(i.iter()),
             Self::Large(i) => DataCtnerIter::Large(i.iter()),
@@ -519,6 +766,7 @@ where
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 enum DataCtnerIter<'a, T>
 where
     T: Clone + Ord + KeyEnDeOrdered + Serialize + de::DeserializeOwned,
@@ -552,6 +800,10 @@ where
     }
 }
 
+fn default_ctner_threshold() -> usize {
+    DEFAULT_CTNER_THRESHOLD
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct Level {
     floor_base: u64,
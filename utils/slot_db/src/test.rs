@@ -1,5 +1,6 @@
 use super::*;
 use rand::random;
+use vsdb::ValueEnDe;
 
 #[test]
 fn workflow_normal() {
@@ -164,6 +165,39 @@ fn data_container() {
     db.clear();
 }
 
+#[test]
+fn data_container_threshold() {
+    let mut db = SlotDB::new(16, false);
+    db.set_ctner_threshold(64);
+
+    (0..60u32).for_each(|i| {
+        db.insert(0, i).unwrap();
+    });
+    assert!(matches!(db.data.first().unwrap().1, DataCtner::Small(_)));
+
+    (60..100u32).for_each(|i| {
+        db.insert(0, i).unwrap();
+    });
+    assert!(matches!(db.data.first().unwrap().1, DataCtner::Large(_)));
+
+    (0..60u32).for_each(|i| {
+        db.remove(0, &i);
+    });
+    assert!(matches!(db.data.first().unwrap().1, DataCtner::Large(_)));
+
+    (60..70u32).for_each(|i| {
+        db.remove(0, &i);
+    });
+    assert!(matches!(db.data.first().unwrap().1, DataCtner::Small(_)));
+    assert_eq!(db.total(), 30);
+    assert_eq!(
+        db.data.first().unwrap().1.iter().collect::<Vec<_>>(),
+        (70..100u32).collect::<Vec<_>>()
+    );
+
+    db.clear();
+}
+
 mod testdb {
     use super::*;
     use std::{
@@ -219,3 +253,88 @@ mod testdb {
         }
     }
 }
+
+#[test]
+fn tier_header_validation() {
+    let mut db = SlotDB::<u64>::new(16, true);
+    (0..100).for_each(|i| {
+        db.insert(i, i).unwrap();
+    });
+
+    let reloaded = pnk!(SlotDB::<u64>::decode(&db.encode()));
+    assert_eq!(100, reloaded.total());
+
+    // the tiers were built with another capacity
+    db.multiple_step = 8;
+    assert!(SlotDB::<u64>::decode(&db.encode()).is_err());
+    db.multiple_step = 1;
+    assert!(SlotDB::<u64>::decode(&db.encode()).is_err());
+
+    db.multiple_step = 16;
+    db.swap_order = false;
+    assert!(SlotDB::<u64>::decode(&db.encode()).is_err());
+    db.swap_order = true;
+
+    // the data of older versions, the header is created on the first write
+    let header = db.header.take();
+    let mut reloaded = pnk!(SlotDB::<u64>::decode(&db.encode()));
+    assert!(reloaded.header.is_none());
+    assert_eq!(100, reloaded.total());
+    reloaded.insert(100, 100).unwrap();
+    assert_eq!(
+        header.map(|h| h.get_value()),
+        reloaded.header.as_ref().map(|h| h.get_value())
+    );
+}
+
+#[test]
+fn filtered_pages() {
+    [false, true].into_iter().for_each(|swap| {
+        let mut db = SlotDB::<u64>::new(8, swap);
+        (0..1000).for_each(|i| {
+            db.insert(i / 10, i).unwrap();
+        });
+
+        let pred = |i: &u64| i.is_multiple_of(3);
+
+        assert_eq!(334, db.count_if(None, None, pred));
+        assert_eq!(33, db.count_if(Some(10), Some(19), pred));
+        assert_eq!(0, db.count_if(Some(20), Some(10), pred));
+
+        for reverse in [false, true] {
+            let all = db
+                .get_entries_by_page_slot(Some(10), Some(59), 500, 0, reverse)
+                .into_iter()
+                .filter(pred)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                all.len() as u64,
+                db.count_if(Some(10), Some(59), pred)
+            );
+
+            all.chunks(7).enumerate().for_each(|(idx, page)| {
+                let b = db.get_entries_filtered(
+                    Some(10),
+                    Some(59),
+                    7,
+                    idx as u32,
+                    reverse,
+                    pred,
+                );
+                assert_eq!(page, &b[..]);
+            });
+
+            let idx = all.len().div_ceil(7) as u32;
+            assert!(db
+                .get_entries_filtered(
+                    Some(10),
+                    Some(59),
+                    7,
+                    idx,
+                    reverse,
+                    pred
+                )
+                .is_empty());
+        }
+    });
+}
@@ -2,11 +2,13 @@
 #![deny(warnings)]
 #![allow(clippy::new_without_default)]
 
+mod proof;
 mod substrate_trie;
 
 #[cfg(test)]
 mod test;
 
+pub use proof::{verify_range_proof, RangeEntries, RangeProof};
 pub use vsdb::{RawBytes, RawKey, RawValue, ValueEnDe};
 
 use ruc::*;
@@ -15,7 +17,10 @@ use trie_db::{
     CError, DBValue, HashDB, Hasher as _, Trie, TrieHash, TrieItem, TrieIterator, TrieKeyItem,
     TrieMut,
 };
-use vsdb::{MapxOrdRawKey, Orphan};
+use vsdb::{
+    vsdb_ende::{Error as EndeError, Result as EndeResult},
+    MapxOrdRawKey, Orphan,
+};
 use vsdb_hash_db::{sp_hash_db::EMPTY_PREFIX, KeccakHasher as H, TrieBackend};
 
 type L = substrate_trie::LayoutV1<H>;
@@ -165,7 +170,10 @@ impl MptOnce {
             unsafe { parent_backend.shadow_backend() }.into_inner(),
         )))
         .c(d!())
-        .map(|b| Box::into_raw(Box::new(b)))?;
+        .map(|b| {
+            b.set_node_cache_cap(parent_backend.node_cache_cap());
+            Box::into_raw(Box::new(b))
+        })?;
 
         let mpt = MptMut::from_existing(unsafe { &mut *b }, root).c(d!())?;
 
@@ -206,7 +214,10 @@ impl MptOnce {
     /// consume the trie handler, and derive a new trie handler
     /// as a child of the current handler.
     pub fn commit(mut self) -> Result<Self> {
+        // the nodes are written by one batch of the backend
+        self.backend.begin_batch();
         let root = self.mpt.commit();
+        self.backend.commit_batch();
 
         // if self.header_set.contains_key(root) {
         //     return Err(eg!("the root value exists!"));
@@ -224,14 +235,20 @@ impl MptOnce {
         self.root
     }
 
+    /// Resize the node cache of the backend, `0` means disabling it,
+    /// the new capacity will be inherited by the handlers derived from `commit`.
+    pub fn set_node_cache_cap(&self, cap: usize) {
+        self.backend.set_node_cache_cap(cap);
+    }
+
     /// Derive a readonly handler of the trie.
-    pub fn ro_handle(&self, root: TrieRoot) -> Result<MptRo> {
+    pub fn ro_handle(&self, root: TrieRoot) -> Result<MptRo<'_>> {
         MptRo::from_existing(&self.backend, root).c(d!())
     }
 }
 
 impl ValueEnDe for MptOnce {
-    fn try_encode(&self) -> Result<RawBytes> {
+    fn try_encode(&self) -> EndeResult<RawBytes> {
         Ok(self.encode())
     }
 
@@ -244,17 +261,17 @@ impl ValueEnDe for MptOnce {
         .encode()
     }
 
-    fn decode(bytes: &[u8]) -> Result<Self> {
-        let [r, b, h] = <[Vec<u8>; 3]>::decode(bytes).c(d!())?;
+    fn decode(bytes: &[u8]) -> EndeResult<Self> {
+        let [r, b, h] = <[Vec<u8>; 3]>::decode(bytes)?;
 
-        alt!(H::LENGTH > r.len(), return Err(eg!("Invalid length")));
+        alt!(H::LENGTH > r.len(), return Err(EndeError::InvalidLength));
         let mut root = [0; H::LENGTH];
         root.copy_from_slice(&r[..H::LENGTH]);
 
-        let backend = TrieBackend::decode(&b).c(d!())?;
-        let header_set = HeaderSet::decode(&h).c(d!())?;
+        let backend = TrieBackend::decode(&b)?;
+        let header_set = HeaderSet::decode(&h)?;
 
-        Self::rederive(&backend, root, &header_set).c(d!())
+        Self::rederive(&backend, root, &header_set).map_err(|e| EndeError::Codec(e.to_string()))
     }
 }
 
@@ -332,7 +349,7 @@ impl<'a> MptMut<'a> {
         *self.trie.root()
     }
 
-    fn ro_handle(&self, root: TrieRoot) -> Result<MptRo> {
+    fn ro_handle(&self, root: TrieRoot) -> Result<MptRo<'_>> {
         MptRo::from_existing_dyn(self.trie.db(), root).c(d!())
     }
 }
@@ -0,0 +1,124 @@
+//!
+//! # Range proofs
+//!
+//! Prove that some consecutive entries are exactly the contents
+//! of a trie between two bounds, e.g. the chunks of a state sync.
+//!
+//! The proof is the set of the nodes visited by iterating the range,
+//! the verifier iterates the same range over these nodes only,
+//! so any missing, extra or modified entry leads to a different result
+//! or to a missing node.
+//!
+
+use super::{MptRo, RawKey, RawValue, TrieDBBuilder, TrieRoot, H};
+use hash_db::Prefix;
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use trie_db::{recorder::Recorder, DBValue, HashDBRef, Hasher as _, Trie};
+
+/// The entries within `[start, end]` of a trie, and the proof of them.
+pub type RangeEntries = Vec<(RawKey, RawValue)>;
+
+/// The encoded trie nodes that prove a range of entries.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RangeProof {
+    nodes: Vec<Vec<u8>>,
+}
+
+impl RangeProof {
+    /// The number of the nodes in the proof.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl<'a> MptRo<'a> {
+    /// Collect all the entries within `[start, end]`,
+    /// along with a proof that can be checked by `verify_range_proof`.
+    pub fn prove_range(&self, start: &[u8], end: &[u8]) -> Result<(RangeEntries, RangeProof)> {
+        let root = self.root();
+        let mut recorder = Recorder::<super::L>::new();
+
+        let entries = {
+            let trie = TrieDBBuilder::new(self.trie.db(), &root)
+                .with_recorder(&mut recorder)
+                .build();
+            collect_range(&trie, start, end).c(d!())?
+        };
+
+        let nodes = recorder
+            .drain()
+            .into_iter()
+            .map(|r| r.data)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        Ok((entries, RangeProof { nodes }))
+    }
+}
+
+/// Check that `entries` are exactly the contents within `[start, end]`
+/// of the trie with the given root.
+pub fn verify_range_proof(
+    root: &TrieRoot,
+    start: &[u8],
+    end: &[u8],
+    entries: &[(RawKey, RawValue)],
+    proof: &RangeProof,
+) -> Result<()> {
+    let db = ProofDB(
+        proof
+            .nodes
+            .iter()
+            .map(|n| (H::hash(n), n.clone()))
+            .collect(),
+    );
+
+    let trie = TrieDBBuilder::new(&db, root).build();
+    let proved = collect_range(&trie, start, end).c(d!("incomplete proof"))?;
+
+    if proved != entries {
+        return Err(eg!("the entries do not match the proof"));
+    }
+    Ok(())
+}
+
+// Iterate one more entry after `end` (if any),
+// to prove that there are no other entries within the range
+fn collect_range<T: Trie<super::L>>(trie: &T, start: &[u8], end: &[u8]) -> Result<RangeEntries> {
+    if start > end {
+        return Err(eg!("invalid range: start > end"));
+    }
+
+    let mut iter = trie.iter().c(d!())?;
+    iter.seek(start).c(d!())?;
+
+    let mut ret = vec![];
+    for i in iter {
+        let (k, v) = i.c(d!())?;
+        if k.as_slice() > end {
+            break;
+        }
+        ret.push((k, v));
+    }
+    Ok(ret)
+}
+
+// An in-memory node set built from a proof
+struct ProofDB(BTreeMap<TrieRoot, DBValue>);
+
+impl HashDBRef<H, DBValue> for ProofDB {
+    fn get(&self, key: &TrieRoot, _prefix: Prefix) -> Option<DBValue> {
+        self.0.get(key).cloned()
+    }
+
+    fn contains(&self, key: &TrieRoot, _prefix: Prefix) -> bool {
+        self.0.contains_key(key)
+    }
+}
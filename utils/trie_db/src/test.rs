@@ -218,3 +218,51 @@ fn trie_db_iter() {
     hdr.clear().unwrap();
     assert!(hdr.is_empty());
 }
+
+#[test]
+fn trie_db_range_proof() {
+    let mut s = MptStore::new();
+    let mut hdr = pnk!(s.trie_init(&[0]));
+
+    // the long values are stored as separate nodes
+    (0u8..200).step_by(2).for_each(|i| {
+        pnk!(hdr.insert(&[i, 0], &[i; 64]));
+    });
+    let hdr = hdr.commit().unwrap();
+    let root = hdr.root();
+    let ro_hdr = hdr.ro_handle(root).unwrap();
+
+    let (entries, proof) = pnk!(ro_hdr.prove_range(&[10], &[20, 0]));
+    assert_eq!(6, entries.len());
+    assert_eq!(vec![10, 0], entries[0].0);
+    assert_eq!(vec![20, 0], entries[5].0);
+    pnk!(verify_range_proof(&root, &[10], &[20, 0], &entries, &proof));
+
+    // no entries within the range
+    let (empty, empty_proof) = pnk!(ro_hdr.prove_range(&[11, 1], &[11, 2]));
+    assert!(empty.is_empty());
+    pnk!(verify_range_proof(
+        &root,
+        &[11, 1],
+        &[11, 2],
+        &empty,
+        &empty_proof
+    ));
+
+    // missing, extra and modified entries
+    assert!(verify_range_proof(&root, &[10], &[20, 0], &entries[1..], &proof).is_err());
+    let mut extra = entries.clone();
+    extra.insert(1, (vec![11, 0], vec![11; 64]));
+    assert!(verify_range_proof(&root, &[10], &[20, 0], &extra, &proof).is_err());
+    let mut modified = entries.clone();
+    modified[2].1 = vec![0; 64];
+    assert!(verify_range_proof(&root, &[10], &[20, 0], &modified, &proof).is_err());
+
+    // a wider range than the proved one
+    assert!(verify_range_proof(&root, &[10], &[30], &entries, &proof).is_err());
+
+    // another root
+    let (_, other) = pnk!(ro_hdr.prove_range(&[100], &[110]));
+    assert!(verify_range_proof(&root, &[10], &[20, 0], &entries, &other).is_err());
+    assert!(verify_range_proof(&[0; 32], &[10], &[20, 0], &entries, &proof).is_err());
+}
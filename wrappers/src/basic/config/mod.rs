@@ -0,0 +1,172 @@
+//!
+//! A store of named configuration fields,
+//! usually used through the typed stores generated by `#[derive(VsConfig)]`.
+//!
+//! NOTE:
+//! - Values will be encoded by some `serde`-like methods
+//! - Fields that have never been set are missing, the typed stores return their defaults
+//! - Values that can not be decoded into the requested type, e.g. after
+//!   the type of a field has been changed, are returned as `DecodeError`s
+//! - `update_all` writes the fields into a new generation,
+//!   and then switches the generation in one write,
+//!   so the fields are never observed half-updated, even after crashes
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::{basic::config::ConfigStore, ValueEnDe};
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut c = ConfigStore::new();
+//! assert!(c.get::<u32>("threads").unwrap().is_none());
+//!
+//! c.set("threads", &8u32);
+//! assert_eq!(Some(8u32), c.get("threads").unwrap());
+//!
+//! c.update_all([("threads", 16u32.encode())]);
+//! assert_eq!(Some(16u32), c.get("threads").unwrap());
+//!
+//! // the type of the field has been changed
+//! assert!(c.get::<String>("threads").is_err());
+//! ```
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::orphan::Orphan,
+    common::{
+        ende::{DecodeContext, DecodeError},
+        RawValue,
+    },
+    ValueEnDe,
+};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, mem::size_of, result::Result as StdResult};
+use vsdb_core::basic::mapx_raw::MapxRaw;
+
+type Generation = u64;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConfigStore {
+    // The generation that the fields are read from
+    generation: Orphan<Generation>,
+
+    // [ generation, big endian ][ field name ] => value
+    slots: MapxRaw,
+}
+
+impl ConfigStore {
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            generation: self.generation.shadow(),
+            slots: self.slots.shadow(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        ConfigStore {
+            generation: Orphan::new(0),
+            slots: MapxRaw::new(),
+        }
+    }
+
+    /// `Ok(None)` if the field has never been set.
+    #[inline(always)]
+    pub fn get<V: ValueEnDe>(&self, field: &str) -> StdResult<Option<V>, DecodeError> {
+        let key = slot_key(self.generation.get_value(), field);
+        self.slots
+            .get(&key)
+            .map(|v| {
+                <V as ValueEnDe>::decode(&v).ctx(self.slots.as_prefix_slice(), &key)
+            })
+            .transpose()
+    }
+
+    #[inline(always)]
+    pub fn get_raw(&self, field: &str) -> Option<RawValue> {
+        self.slots.get(slot_key(self.generation.get_value(), field))
+    }
+
+    #[inline(always)]
+    pub fn set<V: ValueEnDe>(&mut self, field: &str, value: &V) {
+        self.set_raw(field, value.encode());
+    }
+
+    #[inline(always)]
+    pub fn set_raw(&mut self, field: &str, value: impl AsRef<[u8]>) {
+        self.slots
+            .insert(slot_key(self.generation.get_value(), field), value);
+    }
+
+    /// The field will be missing after this call.
+    #[inline(always)]
+    pub fn remove(&mut self, field: &str) {
+        self.slots
+            .remove(slot_key(self.generation.get_value(), field));
+    }
+
+    /// Replace all the fields at once,
+    /// the fields that are not given will be missing after this call.
+    pub fn update_all<'a>(
+        &mut self,
+        fields: impl IntoIterator<Item = (&'a str, RawValue)>,
+    ) {
+        let old = self.generation.get_value();
+        let new = old.wrapping_add(1);
+
+        // leftovers of an interrupted update
+        self.remove_generation(new);
+
+        fields.into_iter().for_each(|(field, value)| {
+            self.slots.insert(slot_key(new, field), value);
+        });
+
+        self.generation.set_value(&new);
+        self.remove_generation(old);
+    }
+
+    /// All fields will be missing after this call.
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    #[inline(always)]
+    pub fn is_the_same_instance(&self, other_hdr: &Self) -> bool {
+        self.slots.is_the_same_instance(&other_hdr.slots)
+    }
+
+    fn remove_generation(&mut self, generation: Generation) {
+        let prefix = generation.to_be_bytes();
+        let keys = self
+            .slots
+            .range_keys(Cow::Borrowed(&prefix[..])..)
+            .take_while(|k| k.starts_with(&prefix))
+            .collect::<Vec<_>>();
+        keys.into_iter().for_each(|k| {
+            self.slots.remove(k);
+        });
+    }
+}
+
+impl Default for ConfigStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn slot_key(generation: Generation, field: &str) -> Vec<u8> {
+    let mut k = Vec::with_capacity(size_of::<Generation>() + field.len());
+    k.extend_from_slice(&generation.to_be_bytes());
+    k.extend_from_slice(field.as_bytes());
+    k
+}
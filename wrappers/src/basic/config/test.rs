@@ -0,0 +1,57 @@
+use super::*;
+use ruc::*;
+
+#[test]
+fn test_update_all() {
+    let mut c = ConfigStore::new();
+
+    c.set("a", &1u64);
+    c.set("b", &"b".to_owned());
+    assert_eq!(1u64, pnk!(c.get("a")).unwrap());
+    assert_eq!("b", pnk!(c.get::<String>("b")).unwrap());
+
+    c.update_all([("a", 2u64.encode())]);
+    assert_eq!(2u64, pnk!(c.get("a")).unwrap());
+    assert!(pnk!(c.get::<String>("b")).is_none());
+
+    // the old generation has been removed
+    assert_eq!(1, c.slots.iter().count());
+
+    let reloaded = pnk!(<ConfigStore as ValueEnDe>::decode(&c.encode()));
+    assert_eq!(2u64, pnk!(reloaded.get("a")).unwrap());
+
+    c.clear();
+    assert!(pnk!(c.get::<u64>("a")).is_none());
+}
+
+#[test]
+fn test_interrupted_update() {
+    let mut c = ConfigStore::new();
+    c.set("a", &1u64);
+
+    // fields written into the next generation before a crash
+    c.slots.insert(slot_key(1, "a"), 100u64.encode());
+    c.slots.insert(slot_key(1, "b"), 100u64.encode());
+    assert_eq!(1u64, pnk!(c.get("a")).unwrap());
+    assert!(pnk!(c.get::<u64>("b")).is_none());
+
+    c.update_all([("a", 2u64.encode())]);
+    assert_eq!(2u64, pnk!(c.get("a")).unwrap());
+    assert!(pnk!(c.get::<u64>("b")).is_none());
+}
+
+#[test]
+fn test_remove_and_decode_error() {
+    let mut c = ConfigStore::new();
+    c.set("a", &true);
+    c.set("b", &1u64);
+
+    // the type of `a` has been changed
+    let e = c.get::<u64>("a").unwrap_err();
+    assert_eq!(slot_key(0, "a").len(), e.key_len);
+    assert!(c.get_raw("a").is_some());
+
+    c.remove("a");
+    assert!(pnk!(c.get::<u64>("a")).is_none());
+    assert_eq!(1u64, pnk!(c.get("b")).unwrap());
+}
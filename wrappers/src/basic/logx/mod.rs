@@ -0,0 +1,516 @@
+//!
+//! An append-only log, optimized for sequential appends and range replays.
+//!
+//! NOTE:
+//! - Records are raw bytes, they will **NOT** be encoded in this structure
+//! - Every `SEGMENT_LEN` records are packed into one sealed segment,
+//!   so the index overhead is paid once per segment instead of per record
+//! - Records can be protected by CRC32 checksums, see `Logx::new_with_crc`
+//! - The length and the CRC setting are cached in memory after the first read,
+//!   a handle and its shadows share the cache, `recover` reloads it
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::basic::logx::Logx;
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut l = Logx::new_with_crc();
+//!
+//! assert_eq!(0, l.append(b"a"));
+//! assert_eq!(1, l.append(b"b"));
+//! assert_eq!(2, l.len());
+//! assert_eq!(b"b".to_vec(), l.get(1).unwrap().unwrap());
+//!
+//! for (i, r) in l.replay(1).map(|r| r.unwrap()) {
+//!     assert_eq!(1, i);
+//!     assert_eq!(b"b".to_vec(), r);
+//! }
+//!
+//! l.truncate(1).unwrap();
+//! assert_eq!(1, l.len());
+//! ```
+
+#[cfg(test)]
+mod test;
+
+use crate::common::{RawBytes, RawValue};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    mem::size_of,
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc,
+    },
+};
+use vsdb_core::basic::mapx_raw::{MapxRaw, MapxRawIter};
+
+/// The number of records in a sealed segment.
+pub const SEGMENT_LEN: u64 = 256;
+
+// The key spaces of the inner map,
+// sealed segments are placed before the open records
+const SPACE_SEALED: u8 = 0; // id of the first record => packed records
+const SPACE_OPEN: u8 = 1; // id => a single record that has not been sealed
+const SPACE_META: u8 = 2;
+
+const FLAG_CRC: u8 = 1;
+
+// [ length of the payload, u32 ][ crc32 of the payload, if enabled ][ payload ]
+const LEN_SIZE: usize = size_of::<u32>();
+const CRC_SIZE: usize = size_of::<u32>();
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(bound = "")]
+pub struct Logx {
+    inner: MapxRaw,
+    #[serde(skip)]
+    cache: Arc<LogxCache>,
+}
+
+// The unknown values are loaded from the inner map at the first read
+#[derive(Debug)]
+struct LogxCache {
+    len: AtomicU64,
+    crc: AtomicU8,
+}
+
+const LEN_UNKNOWN: u64 = u64::MAX;
+const CRC_UNKNOWN: u8 = u8::MAX;
+
+impl Default for LogxCache {
+    fn default() -> Self {
+        Self {
+            len: AtomicU64::new(LEN_UNKNOWN),
+            crc: AtomicU8::new(CRC_UNKNOWN),
+        }
+    }
+}
+
+// The deep copies load their own values
+impl Clone for LogxCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+// Compared by the inner maps
+impl PartialEq for LogxCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for LogxCache {}
+
+impl Logx {
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            cache: Arc::clone(&self.cache),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Do not use this API unless you know the internal details extremely well.
+    #[inline(always)]
+    pub unsafe fn from_bytes(s: impl AsRef<[u8]>) -> Self {
+        Self {
+            inner: MapxRaw::from_bytes(s),
+            cache: Arc::default(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Logx {
+            inner: MapxRaw::new(),
+            cache: Arc::default(),
+        }
+    }
+
+    /// Every record will carry a CRC32 checksum,
+    /// which is verified on reading; the setting is persisted.
+    #[inline(always)]
+    pub fn new_with_crc() -> Self {
+        let mut l = Self::new();
+        l.inner.insert([SPACE_META], [FLAG_CRC]);
+        l.cache.crc.store(1, Ordering::Relaxed);
+        l
+    }
+
+    #[inline(always)]
+    pub fn has_crc(&self) -> bool {
+        match self.cache.crc.load(Ordering::Relaxed) {
+            CRC_UNKNOWN => {
+                let crc = self.load_crc();
+                self.cache.crc.store(crc as u8, Ordering::Relaxed);
+                crc
+            }
+            crc => 0 != crc,
+        }
+    }
+
+    fn load_crc(&self) -> bool {
+        self.inner
+            .get([SPACE_META])
+            .map(|f| 0 != f[0] & FLAG_CRC)
+            .unwrap_or(false)
+    }
+
+    /// The number of records, it is also the id of the next one.
+    #[inline(always)]
+    pub fn len(&self) -> u64 {
+        match self.cache.len.load(Ordering::Relaxed) {
+            LEN_UNKNOWN => {
+                let len = self.load_len();
+                self.set_len(len);
+                len
+            }
+            len => len,
+        }
+    }
+
+    #[inline(always)]
+    fn set_len(&self, len: u64) {
+        self.cache.len.store(len, Ordering::Relaxed);
+    }
+
+    // Two reverse seeks, the open records and the last sealed segment
+    fn load_len(&self) -> u64 {
+        if let Some(k) = self
+            .inner
+            .range_keys(Cow::Owned(key(SPACE_OPEN, 0).to_vec())..=space_end(SPACE_OPEN))
+            .next_back()
+        {
+            return parse_id(&k) + 1;
+        }
+
+        self.inner
+            .range(..=space_end(SPACE_SEALED))
+            .next_back()
+            .map(|(k, v)| parse_id(&k) + crate::parse_int!(&v[..LEN_SIZE], u32) as u64)
+            .unwrap_or(0)
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+
+    /// Append a record and return its id.
+    pub fn append(&mut self, record: impl AsRef<[u8]>) -> u64 {
+        let id = self.len();
+        let frame = encode_frame(record.as_ref(), self.has_crc());
+        self.inner.insert(key(SPACE_OPEN, id), frame);
+        self.set_len(id + 1);
+
+        // the last record of a segment
+        if SEGMENT_LEN - 1 == id % SEGMENT_LEN {
+            self.seal(id + 1 - SEGMENT_LEN);
+        }
+
+        id
+    }
+
+    // Pack the open records of a full segment into one entry,
+    // an interrupted sealing will be finished by `recover`
+    fn seal(&mut self, first: u64) {
+        let mut seg = (SEGMENT_LEN as u32).to_be_bytes().to_vec();
+        self.inner
+            .range(open_range(first))
+            .for_each(|(_, frame)| seg.extend_from_slice(&frame));
+        self.inner.insert(key(SPACE_SEALED, first), seg);
+        self.inner.remove_range(open_range(first));
+    }
+
+    pub fn get(&self, id: u64) -> Result<Option<RawValue>> {
+        let crc = self.has_crc();
+
+        if let Some(frame) = self.inner.get(key(SPACE_OPEN, id)) {
+            return decode_frames(&frame, 1, crc)
+                .c(d!())
+                .map(|mut r| r.pop_front());
+        }
+
+        let first = id - id % SEGMENT_LEN;
+        match self.inner.get(key(SPACE_SEALED, first)) {
+            Some(seg) => decode_segment(&seg, crc)
+                .c(d!())
+                .map(|mut r| r.remove((id - first) as usize)),
+            None => Ok(None),
+        }
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> LogxIter<'_> {
+        self.replay(0)
+    }
+
+    /// Iterate over the records from the given id,
+    /// the replay stops after the first corrupted record.
+    pub fn replay(&self, from: u64) -> LogxIter<'_> {
+        LogxIter {
+            iter: self.inner.range(
+                Cow::Owned(key(SPACE_SEALED, from - from % SEGMENT_LEN).to_vec())
+                    ..=space_end(SPACE_OPEN),
+            ),
+            buf: VecDeque::new(),
+            from,
+            crc: self.has_crc(),
+            failed: false,
+        }
+    }
+
+    /// Drop the records whose ids are not less than `len`.
+    pub fn truncate(&mut self, len: u64) -> Result<()> {
+        if len >= self.len() {
+            return Ok(());
+        }
+
+        let first = len - len % SEGMENT_LEN;
+        if let Some(seg) = self.inner.get(key(SPACE_SEALED, first)) {
+            let crc = self.has_crc();
+            decode_segment(&seg, crc)
+                .c(d!())?
+                .into_iter()
+                .take((len - first) as usize)
+                .enumerate()
+                .for_each(|(i, r)| {
+                    self.inner.insert(
+                        key(SPACE_OPEN, first + i as u64),
+                        encode_frame(&r, crc),
+                    );
+                });
+        }
+
+        self.inner.remove_range(
+            Cow::Owned(key(SPACE_SEALED, first).to_vec())..=space_end(SPACE_SEALED),
+        );
+        self.inner.remove_range(
+            Cow::Owned(key(SPACE_OPEN, len).to_vec())..=space_end(SPACE_OPEN),
+        );
+        self.set_len(len);
+
+        Ok(())
+    }
+
+    /// Check the tail of the log after an unclean shutdown,
+    /// cut off the records from the first corrupted one,
+    /// and return the number of the dropped records.
+    ///
+    /// Only the last sealed segment and the open records are checked,
+    /// use `iter` to verify the whole log.
+    pub fn recover(&mut self) -> Result<u64> {
+        let old_len = self.load_len();
+        let crc = self.load_crc();
+        self.cache.crc.store(crc as u8, Ordering::Relaxed);
+
+        let last_sealed = self
+            .inner
+            .range(..=space_end(SPACE_SEALED))
+            .next_back()
+            .map(|(k, v)| (parse_id(&k), v));
+
+        let mut expected = 0;
+        if let Some((first, seg)) = last_sealed {
+            if decode_segment(&seg, crc).is_err() {
+                self.inner.remove_range(
+                    Cow::Owned(key(SPACE_SEALED, first).to_vec())
+                        ..=space_end(SPACE_OPEN),
+                );
+                let len = self.load_len();
+                self.set_len(len);
+                return Ok(old_len - len);
+            }
+            // the open records have been packed by an interrupted sealing
+            self.inner.remove_range(open_range(first));
+            expected = first + SEGMENT_LEN;
+        }
+
+        // the open records must be valid and contiguous
+        let bad = self
+            .inner
+            .range(Cow::Owned(key(SPACE_OPEN, 0).to_vec())..=space_end(SPACE_OPEN))
+            .enumerate()
+            .find(|(i, (k, frame))| {
+                parse_id(k) != expected + *i as u64
+                    || decode_frames(frame, 1, crc).is_err()
+            })
+            .map(|(i, _)| expected + i as u64);
+        if let Some(id) = bad {
+            self.inner.remove_range(
+                Cow::Owned(key(SPACE_OPEN, id).to_vec())..=space_end(SPACE_OPEN),
+            );
+        }
+
+        let len = self.load_len();
+        self.set_len(len);
+        Ok(old_len - len)
+    }
+
+    /// The CRC setting is kept.
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.remove_range(..=space_end(SPACE_OPEN));
+        self.set_len(0);
+    }
+
+    #[inline(always)]
+    pub fn is_the_same_instance(&self, other_hdr: &Self) -> bool {
+        self.inner.is_the_same_instance(&other_hdr.inner)
+    }
+}
+
+impl Default for Logx {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline(always)]
+fn key(space: u8, id: u64) -> [u8; 1 + size_of::<u64>()] {
+    let mut k = [space; 1 + size_of::<u64>()];
+    k[1..].copy_from_slice(&id.to_be_bytes());
+    k
+}
+
+// The last key of a key space, range bounds are always full keys
+#[inline(always)]
+fn space_end(space: u8) -> Cow<'static, [u8]> {
+    Cow::Owned(key(space, u64::MAX).to_vec())
+}
+
+#[inline(always)]
+fn parse_id(key: &[u8]) -> u64 {
+    crate::parse_int!(&key[1..], u64)
+}
+
+// The open records of the segment starting from `first`
+#[inline(always)]
+fn open_range(first: u64) -> Range<Cow<'static, [u8]>> {
+    Cow::Owned(key(SPACE_OPEN, first).to_vec())
+        ..Cow::Owned(key(SPACE_OPEN, first + SEGMENT_LEN).to_vec())
+}
+
+fn encode_frame(record: &[u8], crc: bool) -> RawBytes {
+    let mut frame = Vec::with_capacity(LEN_SIZE + CRC_SIZE + record.len());
+    frame.extend_from_slice(&(record.len() as u32).to_be_bytes());
+    if crc {
+        frame.extend_from_slice(&crc32fast::hash(record).to_be_bytes());
+    }
+    frame.extend_from_slice(record);
+    frame
+}
+
+// Decode exactly `cnt` frames, the checksums are verified if enabled
+fn decode_frames(mut bytes: &[u8], cnt: usize, crc: bool) -> Result<VecDeque<RawValue>> {
+    let mut ret = VecDeque::with_capacity(cnt);
+
+    for _ in 0..cnt {
+        let hdr_size = LEN_SIZE + if crc { CRC_SIZE } else { 0 };
+        if bytes.len() < hdr_size {
+            return Err(eg!("truncated record header"));
+        }
+        let len = crate::parse_int!(&bytes[..LEN_SIZE], u32) as usize;
+        if bytes.len() < hdr_size + len {
+            return Err(eg!("truncated record payload"));
+        }
+
+        let record = &bytes[hdr_size..hdr_size + len];
+        if crc
+            && crate::parse_int!(&bytes[LEN_SIZE..hdr_size], u32)
+                != crc32fast::hash(record)
+        {
+            return Err(eg!("checksum mismatch"));
+        }
+
+        ret.push_back(record.to_vec());
+        bytes = &bytes[hdr_size + len..];
+    }
+
+    if !bytes.is_empty() {
+        return Err(eg!("trailing bytes"));
+    }
+
+    Ok(ret)
+}
+
+#[inline(always)]
+fn decode_segment(seg: &[u8], crc: bool) -> Result<VecDeque<RawValue>> {
+    if seg.len() < LEN_SIZE {
+        return Err(eg!("truncated segment header"));
+    }
+    let cnt = crate::parse_int!(&seg[..LEN_SIZE], u32) as usize;
+    decode_frames(&seg[LEN_SIZE..], cnt, crc).c(d!())
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct LogxIter<'a> {
+    iter: MapxRawIter<'a>,
+    buf: VecDeque<(u64, RawValue)>,
+    from: u64,
+    crc: bool,
+    failed: bool,
+}
+
+impl<'a> Iterator for LogxIter<'a> {
+    type Item = Result<(u64, RawValue)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buf.is_empty() {
+            if self.failed {
+                return None;
+            }
+
+            let (k, v) = self.iter.next()?;
+            let first = parse_id(&k);
+            let records = if SPACE_SEALED == k[0] {
+                decode_segment(&v, self.crc)
+            } else {
+                decode_frames(&v, 1, self.crc)
+            };
+
+            match records {
+                Ok(records) => {
+                    let from = self.from;
+                    self.buf.extend(
+                        records
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, r)| (first + i as u64, r))
+                            .filter(|(id, _)| *id >= from),
+                    );
+                }
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e).c(d!(format!("record {}", first))));
+                }
+            }
+        }
+
+        self.buf.pop_front().map(Ok)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,130 @@
+use super::*;
+
+fn gen_record(i: u64) -> RawValue {
+    i.to_string().into_bytes()
+}
+
+#[test]
+fn test_append_get() {
+    let mut hdr = Logx::new();
+    assert!(!hdr.has_crc());
+
+    let max = 3 * SEGMENT_LEN + 10;
+    (0..max).for_each(|i| {
+        assert_eq!(i, hdr.append(gen_record(i)));
+    });
+    assert_eq!(max, hdr.len());
+
+    (0..max).for_each(|i| {
+        assert_eq!(gen_record(i), pnk!(pnk!(hdr.get(i))));
+    });
+    assert!(pnk!(hdr.get(max)).is_none());
+
+    // only the open records are indexed one by one
+    assert_eq!(3 + 10, hdr.inner.keys().count());
+
+    // the shadows share the cached length, the other handles load it
+    let shadow = unsafe { hdr.shadow() };
+    assert_eq!(max, hdr.append([]));
+    assert_eq!(max + 1, shadow.len());
+    let loaded = unsafe { Logx::from_bytes(hdr.as_bytes()) };
+    assert_eq!(max + 1, loaded.len());
+    assert!(!loaded.has_crc());
+
+    hdr.clear();
+    assert!(hdr.is_empty());
+    assert_eq!(0, hdr.append([]));
+}
+
+#[test]
+fn test_replay() {
+    let mut hdr = Logx::new_with_crc();
+    let max = 2 * SEGMENT_LEN + 5;
+    (0..max).for_each(|i| {
+        hdr.append(gen_record(i));
+    });
+
+    assert!(hdr
+        .iter()
+        .map(|r| pnk!(r))
+        .eq((0..max).map(|i| (i, gen_record(i)))));
+
+    let from = SEGMENT_LEN + 3;
+    assert!(hdr
+        .replay(from)
+        .map(|r| pnk!(r))
+        .eq((from..max).map(|i| (i, gen_record(i)))));
+    assert!(hdr.replay(max).next().is_none());
+}
+
+#[test]
+fn test_truncate() {
+    let mut hdr = Logx::new_with_crc();
+    let max = 3 * SEGMENT_LEN;
+    (0..max).for_each(|i| {
+        hdr.append(gen_record(i));
+    });
+
+    // in a sealed segment
+    pnk!(hdr.truncate(SEGMENT_LEN + 7));
+    assert_eq!(SEGMENT_LEN + 7, hdr.len());
+    assert!(pnk!(hdr.get(SEGMENT_LEN + 7)).is_none());
+    assert_eq!(
+        gen_record(SEGMENT_LEN + 6),
+        pnk!(pnk!(hdr.get(SEGMENT_LEN + 6)))
+    );
+
+    // sealed again
+    (SEGMENT_LEN + 7..max).for_each(|i| {
+        assert_eq!(i, hdr.append(gen_record(i)));
+    });
+    assert!(hdr
+        .iter()
+        .map(|r| pnk!(r))
+        .eq((0..max).map(|i| (i, gen_record(i)))));
+
+    pnk!(hdr.truncate(0));
+    assert!(hdr.is_empty());
+    assert!(hdr.has_crc());
+}
+
+#[test]
+fn test_corruption_and_recover() {
+    let mut hdr = Logx::new_with_crc();
+    let max = SEGMENT_LEN + 10;
+    (0..max).for_each(|i| {
+        hdr.append(gen_record(i));
+    });
+    assert_eq!(0, pnk!(hdr.recover()));
+
+    // a broken record in the open tail
+    let mut frame = pnk!(hdr.inner.get(key(SPACE_OPEN, SEGMENT_LEN + 5)));
+    *frame.last_mut().unwrap() ^= 1;
+    hdr.inner.insert(key(SPACE_OPEN, SEGMENT_LEN + 5), frame);
+
+    assert!(hdr.get(SEGMENT_LEN + 5).is_err());
+    let replayed = hdr.iter().collect::<Vec<_>>();
+    assert_eq!(SEGMENT_LEN as usize + 6, replayed.len());
+    assert!(replayed.last().unwrap().is_err());
+
+    assert_eq!(5, pnk!(hdr.recover()));
+    assert_eq!(SEGMENT_LEN + 5, hdr.len());
+    assert!(hdr.iter().all(|r| r.is_ok()));
+
+    // an interrupted sealing
+    (SEGMENT_LEN + 5..2 * SEGMENT_LEN).for_each(|i| {
+        hdr.append(gen_record(i));
+    });
+    let first = SEGMENT_LEN;
+    (first..2 * SEGMENT_LEN).for_each(|i| {
+        hdr.inner
+            .insert(key(SPACE_OPEN, i), encode_frame(&gen_record(i), true));
+    });
+    assert_eq!(0, pnk!(hdr.recover()));
+    // 2 sealed segments and the meta
+    assert_eq!(3, hdr.inner.keys().count());
+    assert!(hdr
+        .iter()
+        .map(|r| pnk!(r))
+        .eq((0..2 * SEGMENT_LEN).map(|i| (i, gen_record(i)))));
+}
@@ -5,6 +5,9 @@
 //!
 //! - Both keys and values will be encoded(serde) in this structure
 //!     - Both of them will be encoded by some `serde`-like methods
+//! - The iteration order is the byte order of the encoded keys
+//!     - It is the same in all storage engines, but not the order of the original keys
+//!     - Use `MapxOrd` if the original order of the keys is needed
 //!
 //! # Examples
 //!
@@ -16,9 +19,8 @@
 //!
 //! let mut l = Mapx::new();
 //!
-//! l.insert(1, 0);
 //! l.insert(&1, &0);
-//! l.insert(2, 0);
+//! l.insert(&2, &0);
 //!
 //! l.iter().for_each(|(k, v)| {
 //!     assert!(k >= 1);
@@ -43,14 +45,23 @@ use crate::{
             self, MapxOrdRawKey, MapxOrdRawKeyIter, MapxOrdRawKeyIterMut, ValueMut,
         },
     },
-    common::ende::{KeyEnDe, ValueEnDe},
+    common::{
+        ende::{decode_key, DecodeError, KeyEnDe, ValueEnDe},
+        PreBytes, SizeLimits, WriteError,
+    },
+    AccessStats, CacheAdapter, CompactionStats, QuotaGroup, Snapshot, TrashId,
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
+    io::{Read, Write},
     marker::PhantomData,
     ops::{Deref, DerefMut},
+    result::Result as StdResult,
+    sync::Arc,
+    time::{Duration, Instant},
 };
+use vsdb_core::basic::mapx_raw::MapxRawKeys;
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
@@ -105,6 +116,18 @@ where
         self.inner.get(key.encode())
     }
 
+    /// Like `get`, but return the decoding failure instead of panicking.
+    #[inline(always)]
+    pub fn try_get(&self, key: &K) -> StdResult<Option<V>, DecodeError> {
+        self.inner.try_get(key.encode())
+    }
+
+    /// Check `MapxRaw::get_with_snapshot` for details.
+    #[inline(always)]
+    pub fn get_with_snapshot(&self, snapshot: &Snapshot, key: &K) -> Option<V> {
+        self.inner.get_with_snapshot(snapshot, key.encode())
+    }
+
     #[inline(always)]
     pub fn get_mut(&mut self, key: &K) -> Option<ValueMut<'_, V>> {
         self.inner.get_mut(key.encode())
@@ -135,6 +158,40 @@ where
         self.inner.set_value(key.encode(), value);
     }
 
+    /// Like `insert`, but return an error instead of panicking
+    /// if the encoded key or value exceeds the size limits,
+    /// or the database is opened in read-only mode.
+    #[inline(always)]
+    pub fn try_insert(
+        &mut self,
+        key: &K,
+        value: &V,
+    ) -> StdResult<Option<V>, WriteError> {
+        self.inner.try_insert(key.encode(), value)
+    }
+
+    /// Check `MapxRaw::insert_nowait` for details.
+    #[inline(always)]
+    pub fn insert_nowait(
+        &mut self,
+        key: &K,
+        value: &V,
+    ) -> StdResult<Option<V>, WriteError> {
+        self.inner.insert_nowait(key.encode(), value)
+    }
+
+    #[inline(always)]
+    pub fn size_limits(&self) -> SizeLimits {
+        self.inner.size_limits()
+    }
+
+    /// Set the max sizes of the encoded keys and values,
+    /// see `MapxRaw::set_size_limits` for details.
+    #[inline(always)]
+    pub fn set_size_limits(&mut self, limits: SizeLimits) {
+        self.inner.set_size_limits(limits);
+    }
+
     #[inline(always)]
     pub fn entry(&mut self, key: &K) -> Entry<'_, V> {
         Entry {
@@ -143,8 +200,10 @@ where
         }
     }
 
+    /// Iterate in the byte order of the encoded keys,
+    /// which is deterministic across storage engines.
     #[inline(always)]
-    pub fn iter(&self) -> MapxIter<K, V> {
+    pub fn iter(&self) -> MapxIter<'_, K, V> {
         MapxIter {
             iter: self.inner.iter(),
             _p: PhantomData,
@@ -152,25 +211,35 @@ where
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxIterMut<K, V> {
+    pub fn iter_mut(&mut self) -> MapxIterMut<'_, K, V> {
         MapxIterMut {
             inner: self.inner.iter_mut(),
             _p: PhantomData,
         }
     }
 
+    /// Iterate over the keys only, the values are not decoded,
+    /// check `MapxRaw::keys` for details.
     #[inline(always)]
-    pub fn values(&self) -> MapxValues<V> {
+    pub fn keys(&self) -> MapxKeys<'_, K> {
+        MapxKeys {
+            ns: *self.inner.inner.as_prefix_slice(),
+            inner: self.inner.inner.keys(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn values(&self) -> MapxValues<'_, V> {
         MapxValues {
             inner: self.inner.iter(),
         }
     }
 
     #[inline(always)]
-    pub fn values_mut(&mut self) -> MapxValuesMut<V> {
+    pub fn values_mut(&mut self) -> MapxValuesMut<'_, V> {
         MapxValuesMut {
-            inner: self.inner.inner.iter_mut(),
-            _p: PhantomData,
+            inner: self.inner.iter_mut(),
         }
     }
 
@@ -179,6 +248,19 @@ where
         self.inner.remove(key.encode())
     }
 
+    /// Like `remove`, but return an error instead of panicking
+    /// if the database is opened in read-only mode.
+    #[inline(always)]
+    pub fn try_remove(&mut self, key: &K) -> StdResult<Option<V>, WriteError> {
+        self.inner.try_remove(key.encode())
+    }
+
+    /// Check `MapxRaw::remove_nowait` for details.
+    #[inline(always)]
+    pub fn remove_nowait(&mut self, key: &K) -> StdResult<Option<V>, WriteError> {
+        self.inner.remove_nowait(key.encode())
+    }
+
     #[inline(always)]
     pub fn unset_value(&mut self, key: &K) {
         self.inner.unset_value(key.encode());
@@ -189,10 +271,144 @@ where
         self.inner.clear();
     }
 
+    /// Check `MapxRaw::try_clear` for details.
+    #[inline(always)]
+    pub fn try_clear(&mut self) -> StdResult<(), WriteError> {
+        self.inner.try_clear()
+    }
+
     #[inline(always)]
     pub fn is_the_same_instance(&self, other_hdr: &Self) -> bool {
         self.inner.is_the_same_instance(&other_hdr.inner)
     }
+
+    /// Check `MapxRaw::clone_deep_to_new_instance` for details.
+    #[inline(always)]
+    pub fn clone_deep_to_new_instance(&self) -> Result<Self> {
+        self.inner
+            .clone_deep_to_new_instance()
+            .c(d!())
+            .map(|inner| Self {
+                inner,
+                _p: PhantomData,
+            })
+    }
+
+    /// Check `MapxOrdRawKey::export` for details.
+    #[inline(always)]
+    pub fn export(&self, writer: impl Write) -> Result<u64> {
+        self.inner.export(writer).c(d!())
+    }
+
+    /// Check `MapxOrdRawKey::import` for details.
+    #[inline(always)]
+    pub fn import(reader: impl Read) -> Result<Self> {
+        MapxOrdRawKey::import(reader).c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    /// Check `MapxRaw::content_digest` for details.
+    #[inline(always)]
+    pub fn content_digest(&self) -> u64 {
+        self.inner.content_digest()
+    }
+
+    /// Check `MapxRaw::enable_digest` for details.
+    #[inline(always)]
+    pub fn enable_digest(&self) -> StdResult<(), WriteError> {
+        self.inner.enable_digest()
+    }
+
+    /// Check `MapxRaw::disable_digest` for details.
+    #[inline(always)]
+    pub fn disable_digest(&self) -> StdResult<(), WriteError> {
+        self.inner.disable_digest()
+    }
+
+    /// Check `MapxRaw::compact` for details.
+    #[inline(always)]
+    pub fn compact(&self) -> StdResult<CompactionStats, WriteError> {
+        self.inner.compact()
+    }
+
+    /// Check `MapxRaw::seal` for details.
+    #[inline(always)]
+    pub fn seal(&self) -> Result<()> {
+        self.inner.seal().c(d!())
+    }
+
+    /// Check `MapxRaw::unseal` for details.
+    #[inline(always)]
+    pub fn unseal(&self) -> Result<()> {
+        self.inner.unseal().c(d!())
+    }
+
+    #[inline(always)]
+    pub fn is_sealed(&self) -> bool {
+        self.inner.is_sealed()
+    }
+
+    /// Check `MapxRaw::track_access` for details,
+    /// the key ranges are grouped by the encoded keys.
+    #[inline(always)]
+    pub fn track_access(&self, range_len: usize) {
+        self.inner.track_access(range_len)
+    }
+
+    #[inline(always)]
+    pub fn untrack_access(&self) {
+        self.inner.untrack_access()
+    }
+
+    #[inline(always)]
+    pub fn access_stats(&self) -> Option<AccessStats> {
+        self.inner.access_stats()
+    }
+
+    /// Check `MapxRaw::join_quota_group` for details.
+    #[inline(always)]
+    pub fn join_quota_group(&self, group: &QuotaGroup) {
+        self.inner.join_quota_group(group)
+    }
+
+    #[inline(always)]
+    pub fn leave_quota_group(&self) {
+        self.inner.leave_quota_group()
+    }
+
+    #[inline(always)]
+    pub fn quota_group(&self) -> Option<QuotaGroup> {
+        self.inner.quota_group()
+    }
+
+    /// Check `MapxRaw::bind_cache` for details.
+    #[inline(always)]
+    pub fn bind_cache(&self, adapter: Arc<dyn CacheAdapter>) {
+        self.inner.bind_cache(adapter)
+    }
+
+    #[inline(always)]
+    pub fn unbind_cache(&self) {
+        self.inner.unbind_cache()
+    }
+
+    /// Check `MapxRaw::soft_destroy` for details.
+    #[inline(always)]
+    pub fn soft_destroy(self, retention: Duration) -> Result<TrashId> {
+        self.inner.soft_destroy(retention).c(d!())
+    }
+
+    /// Restore an instance from the trash,
+    /// it must be soft-destroyed from the same type.
+    #[inline(always)]
+    pub fn from_trash(id: TrashId) -> Result<Self> {
+        MapxOrdRawKey::from_trash(id).c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
 }
 
 impl<K, V> Clone for Mapx<K, V> {
@@ -226,6 +442,31 @@ where
     _p: PhantomData<K>,
 }
 
+impl<'a, K, V> MapxIter<'a, K, V>
+where
+    K: KeyEnDe,
+    V: ValueEnDe,
+{
+    /// Check `MapxRawIter::take_bytes` for details.
+    #[inline(always)]
+    pub fn take_bytes(mut self, max_bytes: u64) -> Self {
+        self.iter = self.iter.take_bytes(max_bytes);
+        self
+    }
+
+    /// Check `MapxRawIter::deadline` for details.
+    #[inline(always)]
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.iter = self.iter.deadline(deadline);
+        self
+    }
+
+    #[inline(always)]
+    pub fn is_truncated(&self) -> bool {
+        self.iter.is_truncated()
+    }
+}
+
 impl<'a, K, V> Iterator for MapxIter<'a, K, V>
 where
     K: KeyEnDe,
@@ -233,9 +474,8 @@ where
 {
     type Item = (K, V);
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter
-            .next()
-            .map(|(k, v)| (<K as KeyEnDe>::decode(&k).unwrap(), v))
+        let ns = self.iter.ns;
+        self.iter.next().map(|(k, v)| (decode_key(&ns, &k), v))
     }
 }
 
@@ -245,9 +485,8 @@ where
     V: ValueEnDe,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.iter
-            .next_back()
-            .map(|(k, v)| (<K as KeyEnDe>::decode(&k).unwrap(), v))
+        let ns = self.iter.ns;
+        self.iter.next_back().map(|(k, v)| (decode_key(&ns, &k), v))
     }
 }
 
@@ -270,9 +509,11 @@ where
 {
     type Item = (K, ValueIterMut<'a, V>);
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner
-            .next()
-            .map(|(k, v)| (pnk!(<K as KeyEnDe>::decode(&k)), ValueIterMut { inner: v }))
+        let ns = self.inner.ns;
+        self.inner.next().map(|(k, v)| {
+            let k = decode_key(&ns, &k);
+            (k, ValueIterMut { inner: v })
+        })
     }
 }
 
@@ -282,9 +523,43 @@ where
     V: ValueEnDe,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner
-            .next_back()
-            .map(|(k, v)| (pnk!(<K as KeyEnDe>::decode(&k)), ValueIterMut { inner: v }))
+        let ns = self.inner.ns;
+        self.inner.next_back().map(|(k, v)| {
+            let k = decode_key(&ns, &k);
+            (k, ValueIterMut { inner: v })
+        })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct MapxKeys<'a, K>
+where
+    K: KeyEnDe,
+{
+    ns: PreBytes,
+    inner: MapxRawKeys<'a>,
+    _p: PhantomData<K>,
+}
+
+impl<'a, K> Iterator for MapxKeys<'a, K>
+where
+    K: KeyEnDe,
+{
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|k| decode_key(&self.ns, &k))
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for MapxKeys<'a, K>
+where
+    K: KeyEnDe,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|k| decode_key(&self.ns, &k))
     }
 }
 
@@ -302,6 +577,17 @@ where
     pub(crate) inner: mapx_ord_rawkey::ValueIterMut<'a, V>,
 }
 
+impl<'a, V> ValueIterMut<'a, V>
+where
+    V: ValueEnDe,
+{
+    /// Check `MapxRaw`'s `ValueIterMut::commit` for details.
+    #[inline(always)]
+    pub fn commit(&mut self) -> StdResult<(), WriteError> {
+        self.inner.commit()
+    }
+}
+
 impl<'a, V> Deref for ValueIterMut<'a, V>
 where
     V: ValueEnDe,
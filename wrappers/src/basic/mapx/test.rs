@@ -112,3 +112,140 @@ fn test_values_first_last() {
     let value = pnk!(hdr.values().next_back());
     assert_eq!(max - 1, value);
 }
+
+#[test]
+fn test_iter_order() {
+    let keys = (0..200)
+        .map(|i: usize| format!("{:x}", i * 7919))
+        .collect::<Vec<_>>();
+
+    let mut hdr_a: Mapx<String, usize> = Mapx::new();
+    let mut hdr_b: Mapx<String, usize> = Mapx::new();
+    keys.iter().enumerate().for_each(|(i, k)| {
+        hdr_a.insert(k, &i);
+    });
+    keys.iter().enumerate().rev().for_each(|(i, k)| {
+        hdr_b.insert(k, &i);
+    });
+
+    let encoded = hdr_a
+        .iter()
+        .map(|(k, _)| <String as crate::KeyEnDe>::encode(&k))
+        .collect::<Vec<_>>();
+    let mut sorted = encoded.clone();
+    sorted.sort();
+    assert_eq!(encoded, sorted);
+
+    assert!(hdr_a.iter().eq(hdr_b.iter()));
+    assert!(hdr_a.iter().rev().eq(hdr_b.iter().rev()));
+}
+
+#[test]
+fn test_fixed_bytes() {
+    use crate::{FixedBytes, KeyEnDe};
+
+    let k = FixedBytes([1u8; 32]);
+    let v = FixedBytes([2u8; 20]);
+    assert_eq!(32, <FixedBytes<32> as KeyEnDe>::encode(&k).len());
+    assert_eq!(20, <FixedBytes<20> as ValueEnDe>::encode(&v).len());
+    assert!(<FixedBytes<20> as ValueEnDe>::decode(&[0; 19]).is_err());
+
+    let mut hdr = Mapx::new();
+    hdr.insert(&k, &v);
+    assert_eq!(v, pnk!(hdr.get(&k)));
+    assert_eq!(k, pnk!(hdr.iter().next()).0);
+}
+
+#[test]
+fn test_clone_deep() {
+    let mut hdr: Mapx<usize, String> = Mapx::new();
+    (0..100).for_each(|i| {
+        hdr.insert(&i, &i.to_string());
+    });
+
+    let new_hdr = pnk!(hdr.clone_deep_to_new_instance());
+    assert!(!new_hdr.is_the_same_instance(&hdr));
+    assert!(hdr.iter().eq(new_hdr.iter()));
+
+    hdr.clear();
+    assert_eq!(100, new_hdr.len());
+    assert_eq!("99", pnk!(new_hdr.get(&99)));
+}
+
+#[test]
+fn test_snapshot() {
+    let mut hdr: Mapx<usize, usize> = Mapx::new();
+    hdr.insert(&1, &1);
+
+    let snapshot = crate::vsdb_snapshot();
+    hdr.insert(&1, &2);
+    hdr.insert(&2, &2);
+
+    assert_eq!(Some(1), hdr.get_with_snapshot(&snapshot, &1));
+    assert_eq!(None, hdr.get_with_snapshot(&snapshot, &2));
+    assert_eq!(Some(2), hdr.get(&1));
+}
+
+#[test]
+fn test_export_import() {
+    // more than one chunk
+    let mut hdr: Mapx<usize, String> = Mapx::new();
+    (0..3000).for_each(|i| {
+        hdr.insert(&i, &i.to_string());
+    });
+
+    let mut buf = vec![];
+    assert_eq!(3000, pnk!(hdr.export(&mut buf)));
+
+    let new_hdr: Mapx<usize, String> = pnk!(Mapx::import(&buf[..]));
+    assert!(!new_hdr.is_the_same_instance(&hdr));
+    assert_eq!(3000, new_hdr.len());
+    assert!(hdr.iter().eq(new_hdr.iter()));
+    assert_eq!(hdr.content_digest(), new_hdr.content_digest());
+
+    // corrupted
+    let idx = buf.len() / 2;
+    buf[idx] ^= 1;
+    assert!(Mapx::<usize, String>::import(&buf[..]).is_err());
+
+    // truncated
+    assert!(Mapx::<usize, String>::import(&buf[..idx]).is_err());
+}
+
+// The writes from other threads are not blocked by a slow writer
+#[test]
+fn test_export_with_concurrent_writes() {
+    struct SlowWriter(Vec<u8>, bool);
+
+    impl std::io::Write for SlowWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            // in the middle of the entries
+            if !self.1 && 1000 < self.0.len() {
+                self.1 = true;
+                std::thread::spawn(|| {
+                    let mut other: Mapx<usize, usize> = Mapx::new();
+                    other.insert(&0, &0);
+                })
+                .join()
+                .unwrap();
+            }
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut hdr: Mapx<usize, usize> = Mapx::new();
+    (0..3000).for_each(|i| {
+        hdr.insert(&i, &i);
+    });
+
+    let mut w = SlowWriter(vec![], false);
+    assert_eq!(3000, pnk!(hdr.export(&mut w)));
+    assert!(w.1);
+
+    let new_hdr: Mapx<usize, usize> = pnk!(Mapx::import(&w.0[..]));
+    assert!(hdr.iter().eq(new_hdr.iter()));
+}
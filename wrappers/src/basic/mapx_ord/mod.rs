@@ -18,9 +18,8 @@
 //!
 //! let mut l = MapxOrd::new();
 //!
-//! l.insert(1, 0);
 //! l.insert(&1, &0);
-//! l.insert(2, 0);
+//! l.insert(&2, &0);
 //!
 //! l.iter().for_each(|(k, v)| {
 //!     assert!(k >= 1);
@@ -39,20 +38,29 @@
 mod test;
 
 use crate::{
-    basic::mapx_ord_rawkey::{MapxOrdRawKey, MapxOrdRawKeyIter, ValueIterMut, ValueMut},
+    basic::mapx_ord_rawkey::{
+        MapxOrdRawKey, MapxOrdRawKeyIter, MapxOrdRawKeyIterMut, ValueIterMut, ValueMut,
+    },
     common::{
-        ende::{KeyEnDeOrdered, ValueEnDe},
-        RawKey,
+        ende::{
+            decode_ordered_key, decode_value, DecodeError, KeyEnDeOrdered, ValueEnDe,
+        },
+        PreBytes, RawKey, SizeLimits, WriteError,
     },
+    AccessStats, CacheAdapter, CompactionStats, QuotaGroup, Snapshot, TrashId,
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
+    io::{Read, Write},
     marker::PhantomData,
     ops::{Bound, RangeBounds},
+    result::Result as StdResult,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use vsdb_core::basic::mapx_raw;
+use vsdb_core::basic::mapx_raw::MapxRawKeys;
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
@@ -107,6 +115,18 @@ where
         self.inner.get(key.to_bytes())
     }
 
+    /// Like `get`, but return the decoding failure instead of panicking.
+    #[inline(always)]
+    pub fn try_get(&self, key: &K) -> StdResult<Option<V>, DecodeError> {
+        self.inner.try_get(key.to_bytes())
+    }
+
+    /// Check `MapxRaw::get_with_snapshot` for details.
+    #[inline(always)]
+    pub fn get_with_snapshot(&self, snapshot: &Snapshot, key: &K) -> Option<V> {
+        self.inner.get_with_snapshot(snapshot, key.to_bytes())
+    }
+
     #[inline(always)]
     pub fn get_mut(&mut self, key: &K) -> Option<ValueMut<'_, V>> {
         self.inner.get_mut(key.to_bytes())
@@ -121,14 +141,28 @@ where
     pub fn get_le(&self, key: &K) -> Option<(K, V)> {
         self.inner
             .get_le(key.to_bytes())
-            .map(|(k, v)| (pnk!(K::from_bytes(k)), v))
+            .map(|(k, v)| (decode_ordered_key(&self.ns(), k), v))
     }
 
     #[inline(always)]
     pub fn get_ge(&self, key: &K) -> Option<(K, V)> {
         self.inner
             .get_ge(key.to_bytes())
-            .map(|(k, v)| (pnk!(K::from_bytes(k)), v))
+            .map(|(k, v)| (decode_ordered_key(&self.ns(), k), v))
+    }
+
+    #[inline(always)]
+    pub fn get_lt(&self, key: &K) -> Option<(K, V)> {
+        self.inner
+            .get_lt(key.to_bytes())
+            .map(|(k, v)| (decode_ordered_key(&self.ns(), k), v))
+    }
+
+    #[inline(always)]
+    pub fn get_gt(&self, key: &K) -> Option<(K, V)> {
+        self.inner
+            .get_gt(key.to_bytes())
+            .map(|(k, v)| (decode_ordered_key(&self.ns(), k), v))
     }
 
     #[inline(always)]
@@ -164,6 +198,40 @@ where
         self.inner.insert(key.to_bytes(), value);
     }
 
+    /// Like `insert`, but return an error instead of panicking
+    /// if the encoded key or value exceeds the size limits,
+    /// or the database is opened in read-only mode.
+    #[inline(always)]
+    pub fn try_insert(
+        &mut self,
+        key: &K,
+        value: &V,
+    ) -> StdResult<Option<V>, WriteError> {
+        self.inner.try_insert(key.to_bytes(), value)
+    }
+
+    /// Check `MapxRaw::insert_nowait` for details.
+    #[inline(always)]
+    pub fn insert_nowait(
+        &mut self,
+        key: &K,
+        value: &V,
+    ) -> StdResult<Option<V>, WriteError> {
+        self.inner.insert_nowait(key.to_bytes(), value)
+    }
+
+    #[inline(always)]
+    pub fn size_limits(&self) -> SizeLimits {
+        self.inner.size_limits()
+    }
+
+    /// Set the max sizes of the encoded keys and values,
+    /// see `MapxRaw::set_size_limits` for details.
+    #[inline(always)]
+    pub fn set_size_limits(&mut self, limits: SizeLimits) {
+        self.inner.set_size_limits(limits);
+    }
+
     #[inline(always)]
     pub fn entry(&mut self, key: &K) -> Entry<'_, V> {
         Entry {
@@ -173,7 +241,7 @@ where
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> MapxOrdIter<K, V> {
+    pub fn iter(&self) -> MapxOrdIter<'_, K, V> {
         MapxOrdIter {
             inner: self.inner.iter(),
             _p: PhantomData,
@@ -181,25 +249,46 @@ where
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxOrdIterMut<K, V> {
+    pub fn iter_mut(&mut self) -> MapxOrdIterMut<'_, K, V> {
         MapxOrdIterMut {
-            inner: self.inner.inner.iter_mut(),
+            inner: self.inner.iter_mut(),
             _p: PhantomData,
         }
     }
 
+    /// Iterate over the keys only, the values are not decoded,
+    /// check `MapxRaw::keys` for details.
     #[inline(always)]
-    pub fn values(&self) -> MapxOrdValues<V> {
+    pub fn keys(&self) -> MapxOrdKeys<'_, K> {
+        MapxOrdKeys {
+            ns: self.ns(),
+            inner: self.inner.inner.keys(),
+            _p: PhantomData,
+        }
+    }
+
+    /// Check `MapxRaw::sample_keys` for details.
+    #[inline(always)]
+    pub fn sample_keys(&self, n: usize, seed: u64) -> Vec<K> {
+        self.inner
+            .inner
+            .sample_keys(n, seed)
+            .into_iter()
+            .map(|k| decode_ordered_key(&self.ns(), k))
+            .collect()
+    }
+
+    #[inline(always)]
+    pub fn values(&self) -> MapxOrdValues<'_, V> {
         MapxOrdValues {
             inner: self.inner.iter(),
         }
     }
 
     #[inline(always)]
-    pub fn values_mut(&mut self) -> MapxOrdValuesMut<V> {
+    pub fn values_mut(&mut self) -> MapxOrdValuesMut<'_, V> {
         MapxOrdValuesMut {
-            inner: self.inner.inner.iter_mut(),
-            _p: PhantomData,
+            inner: self.inner.iter_mut(),
         }
     }
 
@@ -241,7 +330,7 @@ where
         };
 
         MapxOrdIterMut {
-            inner: self.inner.inner.range_mut((l, h)),
+            inner: self.inner.range_mut((l, h)),
             _p: PhantomData,
         }
     }
@@ -261,20 +350,216 @@ where
         self.inner.remove(key.to_bytes())
     }
 
+    /// Like `remove`, but return an error instead of panicking
+    /// if the database is opened in read-only mode.
+    #[inline(always)]
+    pub fn try_remove(&mut self, key: &K) -> StdResult<Option<V>, WriteError> {
+        self.inner.try_remove(key.to_bytes())
+    }
+
+    /// Check `MapxRaw::remove_nowait` for details.
+    #[inline(always)]
+    pub fn remove_nowait(&mut self, key: &K) -> StdResult<Option<V>, WriteError> {
+        self.inner.remove_nowait(key.to_bytes())
+    }
+
     #[inline(always)]
     pub fn unset_value(&mut self, key: &K) {
         self.inner.remove(key.to_bytes());
     }
 
+    /// Remove all entries within the range,
+    /// in one batch if the backend supports it.
+    #[inline(always)]
+    pub fn remove_range<R: RangeBounds<K>>(&mut self, bounds: R) {
+        self.inner.inner.remove_range(Self::raw_bounds(bounds));
+    }
+
+    /// Check `MapxRaw::try_remove_range` for details.
+    #[inline(always)]
+    pub fn try_remove_range<R: RangeBounds<K>>(
+        &mut self,
+        bounds: R,
+    ) -> StdResult<(), WriteError> {
+        self.inner.inner.try_remove_range(Self::raw_bounds(bounds))
+    }
+
+    fn raw_bounds<R: RangeBounds<K>>(bounds: R) -> (RawBound, RawBound) {
+        let l = match bounds.start_bound() {
+            Bound::Included(lo) => Bound::Included(Cow::Owned(lo.to_bytes())),
+            Bound::Excluded(lo) => Bound::Excluded(Cow::Owned(lo.to_bytes())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let h = match bounds.end_bound() {
+            Bound::Included(hi) => Bound::Included(Cow::Owned(hi.to_bytes())),
+            Bound::Excluded(hi) => Bound::Excluded(Cow::Owned(hi.to_bytes())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        (l, h)
+    }
+
+    /// Keep only the entries for which `f` returns `true`.
+    #[inline(always)]
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &V) -> bool) {
+        let ns = self.ns();
+        self.inner.inner.retain(|k, v| {
+            f(
+                &decode_ordered_key(&ns, k.to_vec()),
+                &decode_value(&ns, k, v),
+            )
+        });
+    }
+
     #[inline(always)]
     pub fn clear(&mut self) {
         self.inner.clear();
     }
 
+    /// Check `MapxRaw::try_clear` for details.
+    #[inline(always)]
+    pub fn try_clear(&mut self) -> StdResult<(), WriteError> {
+        self.inner.try_clear()
+    }
+
+    #[inline(always)]
+    fn ns(&self) -> PreBytes {
+        *self.inner.inner.as_prefix_slice()
+    }
+
     #[inline(always)]
     pub fn is_the_same_instance(&self, other_hdr: &Self) -> bool {
         self.inner.is_the_same_instance(&other_hdr.inner)
     }
+
+    /// Check `MapxRaw::clone_deep_to_new_instance` for details.
+    #[inline(always)]
+    pub fn clone_deep_to_new_instance(&self) -> Result<Self> {
+        self.inner
+            .clone_deep_to_new_instance()
+            .c(d!())
+            .map(|inner| Self {
+                inner,
+                _p: PhantomData,
+            })
+    }
+
+    /// Check `MapxOrdRawKey::export` for details.
+    #[inline(always)]
+    pub fn export(&self, writer: impl Write) -> Result<u64> {
+        self.inner.export(writer).c(d!())
+    }
+
+    /// Check `MapxOrdRawKey::import` for details.
+    #[inline(always)]
+    pub fn import(reader: impl Read) -> Result<Self> {
+        MapxOrdRawKey::import(reader).c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    /// Check `MapxRaw::content_digest` for details.
+    #[inline(always)]
+    pub fn content_digest(&self) -> u64 {
+        self.inner.content_digest()
+    }
+
+    /// Check `MapxRaw::enable_digest` for details.
+    #[inline(always)]
+    pub fn enable_digest(&self) -> StdResult<(), WriteError> {
+        self.inner.enable_digest()
+    }
+
+    /// Check `MapxRaw::disable_digest` for details.
+    #[inline(always)]
+    pub fn disable_digest(&self) -> StdResult<(), WriteError> {
+        self.inner.disable_digest()
+    }
+
+    /// Check `MapxRaw::compact` for details.
+    #[inline(always)]
+    pub fn compact(&self) -> StdResult<CompactionStats, WriteError> {
+        self.inner.compact()
+    }
+
+    /// Check `MapxRaw::seal` for details.
+    #[inline(always)]
+    pub fn seal(&self) -> Result<()> {
+        self.inner.seal().c(d!())
+    }
+
+    /// Check `MapxRaw::unseal` for details.
+    #[inline(always)]
+    pub fn unseal(&self) -> Result<()> {
+        self.inner.unseal().c(d!())
+    }
+
+    #[inline(always)]
+    pub fn is_sealed(&self) -> bool {
+        self.inner.is_sealed()
+    }
+
+    /// Check `MapxRaw::track_access` for details,
+    /// the key ranges are grouped by the encoded keys.
+    #[inline(always)]
+    pub fn track_access(&self, range_len: usize) {
+        self.inner.track_access(range_len)
+    }
+
+    #[inline(always)]
+    pub fn untrack_access(&self) {
+        self.inner.untrack_access()
+    }
+
+    #[inline(always)]
+    pub fn access_stats(&self) -> Option<AccessStats> {
+        self.inner.access_stats()
+    }
+
+    /// Check `MapxRaw::join_quota_group` for details.
+    #[inline(always)]
+    pub fn join_quota_group(&self, group: &QuotaGroup) {
+        self.inner.join_quota_group(group)
+    }
+
+    #[inline(always)]
+    pub fn leave_quota_group(&self) {
+        self.inner.leave_quota_group()
+    }
+
+    #[inline(always)]
+    pub fn quota_group(&self) -> Option<QuotaGroup> {
+        self.inner.quota_group()
+    }
+
+    /// Check `MapxRaw::bind_cache` for details.
+    #[inline(always)]
+    pub fn bind_cache(&self, adapter: Arc<dyn CacheAdapter>) {
+        self.inner.bind_cache(adapter)
+    }
+
+    #[inline(always)]
+    pub fn unbind_cache(&self) {
+        self.inner.unbind_cache()
+    }
+
+    /// Check `MapxRaw::soft_destroy` for details.
+    #[inline(always)]
+    pub fn soft_destroy(self, retention: Duration) -> Result<TrashId> {
+        self.inner.soft_destroy(retention).c(d!())
+    }
+
+    /// Restore an instance from the trash,
+    /// it must be soft-destroyed from the same type.
+    #[inline(always)]
+    pub fn from_trash(id: TrashId) -> Result<Self> {
+        MapxOrdRawKey::from_trash(id).c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
 }
 
 impl<K, V> Clone for MapxOrd<K, V> {
@@ -299,6 +584,9 @@ where
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
+// A bound of the encoded keys
+type RawBound = Bound<Cow<'static, [u8]>>;
+
 pub struct MapxOrdIter<'a, K, V>
 where
     K: KeyEnDeOrdered,
@@ -308,6 +596,31 @@ where
     _p: PhantomData<K>,
 }
 
+impl<'a, K, V> MapxOrdIter<'a, K, V>
+where
+    K: KeyEnDeOrdered,
+    V: ValueEnDe,
+{
+    /// Check `MapxRawIter::take_bytes` for details.
+    #[inline(always)]
+    pub fn take_bytes(mut self, max_bytes: u64) -> Self {
+        self.inner = self.inner.take_bytes(max_bytes);
+        self
+    }
+
+    /// Check `MapxRawIter::deadline` for details.
+    #[inline(always)]
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.inner = self.inner.deadline(deadline);
+        self
+    }
+
+    #[inline(always)]
+    pub fn is_truncated(&self) -> bool {
+        self.inner.is_truncated()
+    }
+}
+
 impl<'a, K, V> Iterator for MapxOrdIter<'a, K, V>
 where
     K: KeyEnDeOrdered,
@@ -315,7 +628,10 @@ where
 {
     type Item = (K, V);
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|(k, v)| (pnk!(K::from_bytes(k)), v))
+        let ns = self.inner.ns;
+        self.inner
+            .next()
+            .map(|(k, v)| (decode_ordered_key(&ns, k), v))
     }
 }
 
@@ -325,9 +641,44 @@ where
     V: ValueEnDe,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
+        let ns = self.inner.ns;
         self.inner
             .next_back()
-            .map(|(k, v)| (pnk!(K::from_bytes(k)), v))
+            .map(|(k, v)| (decode_ordered_key(&ns, k), v))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct MapxOrdKeys<'a, K>
+where
+    K: KeyEnDeOrdered,
+{
+    ns: PreBytes,
+    inner: MapxRawKeys<'a>,
+    _p: PhantomData<K>,
+}
+
+impl<'a, K> Iterator for MapxOrdKeys<'a, K>
+where
+    K: KeyEnDeOrdered,
+{
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|k| decode_ordered_key(&self.ns, k))
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for MapxOrdKeys<'a, K>
+where
+    K: KeyEnDeOrdered,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|k| decode_ordered_key(&self.ns, k))
     }
 }
 
@@ -367,8 +718,7 @@ pub struct MapxOrdValuesMut<'a, V>
 where
     V: ValueEnDe,
 {
-    pub(crate) inner: mapx_raw::MapxRawIterMut<'a>,
-    pub(crate) _p: PhantomData<V>,
+    pub(crate) inner: MapxOrdRawKeyIterMut<'a, V>,
 }
 
 impl<'a, V> Iterator for MapxOrdValuesMut<'a, V>
@@ -377,10 +727,7 @@ where
 {
     type Item = ValueIterMut<'a, V>;
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|(_, v)| ValueIterMut {
-            value: pnk!(<V as ValueEnDe>::decode(&v)),
-            inner: v,
-        })
+        self.inner.next().map(|(_, v)| v)
     }
 }
 
@@ -389,10 +736,7 @@ where
     V: ValueEnDe,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner.next_back().map(|(_, v)| ValueIterMut {
-            value: pnk!(<V as ValueEnDe>::decode(&v)),
-            inner: v,
-        })
+        self.inner.next_back().map(|(_, v)| v)
     }
 }
 
@@ -404,8 +748,8 @@ where
     K: KeyEnDeOrdered,
     V: ValueEnDe,
 {
-    inner: mapx_raw::MapxRawIterMut<'a>,
-    _p: PhantomData<(K, V)>,
+    inner: MapxOrdRawKeyIterMut<'a, V>,
+    _p: PhantomData<K>,
 }
 
 impl<'a, K, V> Iterator for MapxOrdIterMut<'a, K, V>
@@ -415,15 +759,10 @@ where
 {
     type Item = (K, ValueIterMut<'a, V>);
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|(k, v)| {
-            (
-                pnk!(<K as KeyEnDeOrdered>::from_bytes(k)),
-                ValueIterMut {
-                    value: <V as ValueEnDe>::decode(&v).unwrap(),
-                    inner: v,
-                },
-            )
-        })
+        let ns = self.inner.ns;
+        self.inner
+            .next()
+            .map(|(k, v)| (decode_ordered_key(&ns, k), v))
     }
 }
 
@@ -433,15 +772,10 @@ where
     V: ValueEnDe,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner.next_back().map(|(k, v)| {
-            (
-                pnk!(<K as KeyEnDeOrdered>::from_bytes(k)),
-                ValueIterMut {
-                    value: <V as ValueEnDe>::decode(&v).unwrap(),
-                    inner: v,
-                },
-            )
-        })
+        let ns = self.inner.ns;
+        self.inner
+            .next_back()
+            .map(|(k, v)| (decode_ordered_key(&ns, k), v))
     }
 }
 
@@ -1,4 +1,5 @@
 use super::*;
+use crate::OrderedF64;
 use ruc::*;
 
 #[test]
@@ -114,3 +115,125 @@ fn test_values_first_last() {
     let value = pnk!(hdr.values().next_back());
     assert_eq!(max - 1, value);
 }
+
+#[test]
+fn test_size_limits() {
+    let mut hdr: MapxOrd<u64, String> = MapxOrd::new();
+    hdr.set_size_limits(SizeLimits {
+        max_key_len: None,
+        max_value_len: Some(8),
+    });
+
+    assert!(pnk!(hdr.try_insert(&1, &"a".to_owned())).is_none());
+    assert!(matches!(
+        hdr.try_insert(&2, &"a".repeat(100)),
+        Err(WriteError::ValueTooLarge { .. })
+    ));
+    assert_eq!(1, hdr.len());
+    assert!(hdr.get(&2).is_none());
+}
+
+#[test]
+fn test_remove_range_retain() {
+    let mut hdr: MapxOrd<i64, i64> = MapxOrd::new();
+    (-50..50).for_each(|i| {
+        hdr.insert(&i, &i);
+    });
+
+    hdr.remove_range(-50..-40);
+    assert_eq!(90, hdr.len());
+    assert_eq!(Some((-40, -40)), hdr.first());
+
+    hdr.remove_range(40..);
+    assert_eq!(80, hdr.len());
+    assert_eq!(Some((39, 39)), hdr.last());
+
+    hdr.retain(|k, v| k % 2 == 0 && *v < 30);
+    assert_eq!(35, hdr.len());
+    assert!(hdr.iter().all(|(k, v)| k % 2 == 0 && v < 30));
+
+    hdr.remove_range(..);
+    assert!(hdr.is_empty());
+    assert!(hdr.first().is_none());
+}
+
+#[test]
+fn test_float_keys() {
+    let mut hdr: MapxOrd<OrderedF64, usize> = MapxOrd::new();
+    [3.5, -2.0, 0.0, -100.25, 1e10, -0.5]
+        .into_iter()
+        .enumerate()
+        .for_each(|(i, f)| {
+            hdr.insert(&OrderedF64(f), &i);
+        });
+
+    let keys = hdr.iter().map(|(k, _)| k.0).collect::<Vec<_>>();
+    assert_eq!(keys, vec![-100.25, -2.0, -0.5, 0.0, 3.5, 1e10]);
+
+    let keys = hdr
+        .range(OrderedF64(-1.0)..OrderedF64(5.0))
+        .map(|(k, _)| k.0)
+        .collect::<Vec<_>>();
+    assert_eq!(keys, vec![-0.5, 0.0, 3.5]);
+}
+
+#[test]
+fn test_content_digest() {
+    let mut a = MapxOrd::new();
+    let mut b = MapxOrd::new();
+    pnk!(a.enable_digest());
+    (0..10u32).for_each(|i| {
+        a.insert(&i, &format!("{}", i));
+    });
+    (0..10u32).rev().for_each(|i| {
+        b.insert(&i, &format!("{}", i));
+    });
+    assert_eq!(a.content_digest(), b.content_digest());
+
+    *b.get_mut(&0).unwrap() = "x".to_owned();
+    assert_ne!(a.content_digest(), b.content_digest());
+    a.insert(&0, &"x".to_owned());
+    assert_eq!(a.content_digest(), b.content_digest());
+}
+
+#[test]
+fn test_keys() {
+    let mut hdr = MapxOrd::new();
+    (0..100u32).for_each(|i| {
+        hdr.insert(&i, &i);
+    });
+    assert!(hdr.keys().eq(0..100));
+    assert!(hdr.keys().rev().eq((0..100).rev()));
+}
+
+#[test]
+fn test_sample_keys() {
+    let mut hdr = MapxOrd::new();
+    (0..1000u32).for_each(|i| {
+        hdr.insert(&(i * 10), &i);
+    });
+
+    let sample = hdr.sample_keys(10, 0);
+    assert!(!sample.is_empty() && sample.len() <= 10);
+    assert!(sample.iter().all(|k| 0 == k % 10 && hdr.contains_key(k)));
+}
+
+#[test]
+fn test_budgeted_iter() {
+    let mut hdr = MapxOrd::new();
+    (0..100u32).for_each(|i| {
+        hdr.insert(&i, &i);
+    });
+
+    let mut it = hdr.iter().take_bytes(1);
+    assert!(it.next().is_none());
+    assert!(it.is_truncated());
+
+    let it = hdr.range(90..).take_bytes(u64::MAX);
+    assert!(it.map(|(k, _)| k).eq(90..100));
+
+    let it = hdr
+        .iter()
+        .deadline(std::time::Instant::now() + std::time::Duration::from_secs(60));
+    assert_eq!(100, it.count());
+}
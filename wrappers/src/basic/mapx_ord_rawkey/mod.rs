@@ -17,7 +17,6 @@
 //! let mut l = MapxOrdRawKey::new();
 //!
 //! l.insert(&[1], &0);
-//! l.insert(vec![1], 0);
 //! l.insert(&[2], &0);
 //!
 //! l.iter().for_each(|(_, v)| {
@@ -35,14 +34,26 @@
 #[cfg(test)]
 mod test;
 
-use crate::common::{ende::ValueEnDe, RawKey};
+use crate::common::{
+    ende::{decode_value, DecodeContext, DecodeError, ValueEnDe},
+    export, PreBytes, RawKey, SizeLimits, WriteError,
+};
+use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
+    io::{Read, Write},
     marker::PhantomData,
     ops::{Deref, DerefMut, RangeBounds},
+    result::Result as StdResult,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use vsdb_core::{
+    basic::mapx_raw::{self, MapxRaw, MapxRawIter, MapxRawKeys},
+    vsdb_trash_restore, AccessStats, CacheAdapter, CompactionStats, QuotaGroup,
+    Snapshot, TrashId,
 };
-use vsdb_core::basic::mapx_raw::{self, MapxRaw, MapxRawIter};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
@@ -93,15 +104,40 @@ where
 
     #[inline(always)]
     pub fn get(&self, key: impl AsRef<[u8]>) -> Option<V> {
+        let key = key.as_ref();
+        self.inner.get(key).map(|v| self.decode_value(key, &v))
+    }
+
+    /// Like `get`, but return the decoding failure instead of panicking.
+    #[inline(always)]
+    pub fn try_get(&self, key: impl AsRef<[u8]>) -> StdResult<Option<V>, DecodeError> {
+        let key = key.as_ref();
+        self.inner
+            .get(key)
+            .map(|v| <V as ValueEnDe>::decode(&v).ctx(self.inner.as_prefix_slice(), key))
+            .transpose()
+    }
+
+    /// Check `MapxRaw::get_with_snapshot` for details.
+    #[inline(always)]
+    pub fn get_with_snapshot(
+        &self,
+        snapshot: &Snapshot,
+        key: impl AsRef<[u8]>,
+    ) -> Option<V> {
+        let key = key.as_ref();
         self.inner
-            .get(key.as_ref())
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+            .get_with_snapshot(snapshot, key)
+            .map(|v| self.decode_value(key, &v))
     }
 
     #[inline(always)]
     pub fn get_mut(&mut self, key: impl AsRef<[u8]>) -> Option<ValueMut<'_, V>> {
-        self.inner.get_mut(key.as_ref()).map(|inner| ValueMut {
-            value: <V as ValueEnDe>::decode(&inner).unwrap(),
+        let key = key.as_ref();
+        let ns = *self.inner.as_prefix_slice();
+        self.inner.get_mut(key).map(|inner| ValueMut {
+            value: decode_value(&ns, key, &inner),
+            committed: true,
             inner,
         })
     }
@@ -111,6 +147,7 @@ where
         let v = value.encode();
         ValueMut {
             value,
+            committed: false,
             inner: self.inner.mock_value_mut(key, v),
         }
     }
@@ -122,16 +159,34 @@ where
 
     #[inline(always)]
     pub fn get_le(&self, key: impl AsRef<[u8]>) -> Option<(RawKey, V)> {
-        self.inner
-            .get_le(key.as_ref())
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        self.inner.get_le(key.as_ref()).map(|(k, v)| {
+            let v = self.decode_value(&k, &v);
+            (k, v)
+        })
     }
 
     #[inline(always)]
     pub fn get_ge(&self, key: impl AsRef<[u8]>) -> Option<(RawKey, V)> {
-        self.inner
-            .get_ge(key.as_ref())
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        self.inner.get_ge(key.as_ref()).map(|(k, v)| {
+            let v = self.decode_value(&k, &v);
+            (k, v)
+        })
+    }
+
+    #[inline(always)]
+    pub fn get_lt(&self, key: impl AsRef<[u8]>) -> Option<(RawKey, V)> {
+        self.inner.get_lt(key.as_ref()).map(|(k, v)| {
+            let v = self.decode_value(&k, &v);
+            (k, v)
+        })
+    }
+
+    #[inline(always)]
+    pub fn get_gt(&self, key: impl AsRef<[u8]>) -> Option<(RawKey, V)> {
+        self.inner.get_gt(key.as_ref()).map(|(k, v)| {
+            let v = self.decode_value(&k, &v);
+            (k, v)
+        })
     }
 
     #[inline(always)]
@@ -146,9 +201,10 @@ where
 
     #[inline(always)]
     pub fn insert(&mut self, key: impl AsRef<[u8]>, value: &V) -> Option<V> {
+        let key = key.as_ref();
         self.inner
-            .insert(key.as_ref(), value.encode())
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+            .insert(key, value.encode())
+            .map(|v| self.decode_value(key, &v))
     }
 
     /// # Safety
@@ -161,9 +217,10 @@ where
         key: impl AsRef<[u8]>,
         value: impl AsRef<[u8]>,
     ) -> Option<V> {
+        let key = key.as_ref();
         self.inner
-            .insert(key.as_ref(), value.as_ref())
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+            .insert(key, value.as_ref())
+            .map(|v| self.decode_value(key, &v))
     }
 
     #[inline(always)]
@@ -171,22 +228,71 @@ where
         self.inner.insert(key.as_ref(), value.encode());
     }
 
+    /// Like `insert`, but return an error instead of panicking
+    /// if the key or the encoded value exceeds the size limits,
+    /// or the database is opened in read-only mode.
+    #[inline(always)]
+    pub fn try_insert(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: &V,
+    ) -> StdResult<Option<V>, WriteError> {
+        let key = key.as_ref();
+        self.inner
+            .try_insert(key, value.encode())
+            .map(|v| v.map(|v| self.decode_value(key, &v)))
+    }
+
+    /// Check `MapxRaw::insert_nowait` for details.
+    #[inline(always)]
+    pub fn insert_nowait(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: &V,
+    ) -> StdResult<Option<V>, WriteError> {
+        let key = key.as_ref();
+        self.inner
+            .insert_nowait(key, value.encode())
+            .map(|v| v.map(|v| self.decode_value(key, &v)))
+    }
+
+    #[inline(always)]
+    pub fn size_limits(&self) -> SizeLimits {
+        self.inner.size_limits()
+    }
+
+    /// Set the max sizes of the encoded keys and values,
+    /// see `MapxRaw::set_size_limits` for details.
+    #[inline(always)]
+    pub fn set_size_limits(&mut self, limits: SizeLimits) {
+        self.inner.set_size_limits(limits);
+    }
+
     #[inline(always)]
     pub fn entry<'a>(&'a mut self, key: &'a [u8]) -> Entry<'a, V> {
         Entry { key, hdr: self }
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> MapxOrdRawKeyIter<V> {
+    pub fn iter(&self) -> MapxOrdRawKeyIter<'_, V> {
         MapxOrdRawKeyIter {
+            ns: *self.inner.as_prefix_slice(),
             inner: self.inner.iter(),
             _p: PhantomData,
         }
     }
 
+    /// Iterate over the keys only, the values are not decoded,
+    /// check `MapxRaw::keys` for details.
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxOrdRawKeyIterMut<V> {
+    pub fn keys(&self) -> MapxRawKeys<'_> {
+        self.inner.keys()
+    }
+
+    #[inline(always)]
+    pub fn iter_mut(&mut self) -> MapxOrdRawKeyIterMut<'_, V> {
         MapxOrdRawKeyIterMut {
+            ns: *self.inner.as_prefix_slice(),
             inner: self.inner.iter_mut(),
             _p: PhantomData,
         }
@@ -198,6 +304,7 @@ where
         bounds: R,
     ) -> MapxOrdRawKeyIter<'a, V> {
         MapxOrdRawKeyIter {
+            ns: *self.inner.as_prefix_slice(),
             inner: self.inner.range(bounds),
             _p: PhantomData,
         }
@@ -209,6 +316,7 @@ where
         bounds: R,
     ) -> MapxOrdRawKeyIterMut<'a, V> {
         MapxOrdRawKeyIterMut {
+            ns: *self.inner.as_prefix_slice(),
             inner: self.inner.range_mut(bounds),
             _p: PhantomData,
         }
@@ -226,9 +334,33 @@ where
 
     #[inline(always)]
     pub fn remove(&mut self, key: impl AsRef<[u8]>) -> Option<V> {
+        let key = key.as_ref();
+        self.inner.remove(key).map(|v| self.decode_value(key, &v))
+    }
+
+    /// Like `remove`, but return an error instead of panicking
+    /// if the database is opened in read-only mode.
+    #[inline(always)]
+    pub fn try_remove(
+        &mut self,
+        key: impl AsRef<[u8]>,
+    ) -> StdResult<Option<V>, WriteError> {
+        let key = key.as_ref();
+        self.inner
+            .try_remove(key)
+            .map(|v| v.map(|v| self.decode_value(key, &v)))
+    }
+
+    /// Check `MapxRaw::remove_nowait` for details.
+    #[inline(always)]
+    pub fn remove_nowait(
+        &mut self,
+        key: impl AsRef<[u8]>,
+    ) -> StdResult<Option<V>, WriteError> {
+        let key = key.as_ref();
         self.inner
-            .remove(key.as_ref())
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+            .remove_nowait(key)
+            .map(|v| v.map(|v| self.decode_value(key, &v)))
     }
 
     #[inline(always)]
@@ -241,10 +373,187 @@ where
         self.inner.clear();
     }
 
+    /// Check `MapxRaw::try_clear` for details.
+    #[inline(always)]
+    pub fn try_clear(&mut self) -> StdResult<(), WriteError> {
+        self.inner.try_clear()
+    }
+
+    #[inline(always)]
+    fn decode_value(&self, key: &[u8], v: &[u8]) -> V {
+        decode_value(self.inner.as_prefix_slice(), key, v)
+    }
+
     #[inline(always)]
     pub fn is_the_same_instance(&self, other_hdr: &Self) -> bool {
         self.inner.is_the_same_instance(&other_hdr.inner)
     }
+
+    /// Check `MapxRaw::clone_deep_to_new_instance` for details.
+    #[inline(always)]
+    pub fn clone_deep_to_new_instance(&self) -> Result<Self> {
+        self.inner
+            .clone_deep_to_new_instance()
+            .c(d!())
+            .map(|inner| Self {
+                inner,
+                _p: PhantomData,
+            })
+    }
+
+    /// Check `MapxRaw::bulk_load_sorted` for details.
+    #[inline(always)]
+    pub fn bulk_load_sorted(
+        &mut self,
+        entries: impl IntoIterator<Item = (RawKey, V)>,
+    ) -> Result<u64> {
+        self.inner
+            .bulk_load_sorted(entries.into_iter().map(|(k, v)| (k, v.encode())))
+            .c(d!())
+    }
+
+    /// Back up all the entries into the writer, as of one snapshot,
+    /// the data carries a checksum and the name of the codec.
+    ///
+    /// Return the number of the exported entries.
+    ///
+    /// NOTE: the entries are read in chunks, the writes from other threads
+    /// are blocked while each chunk is read, not while it is written out,
+    /// but they are serialized on the snapshot until the export is done.
+    #[inline(always)]
+    pub fn export(&self, writer: impl Write) -> Result<u64> {
+        export::export(&self.inner, writer).c(d!())
+    }
+
+    /// Restore the data of `export` into a new instance,
+    /// an error will be returned if the data is corrupted
+    /// or it is encoded by another codec.
+    #[inline(always)]
+    pub fn import(reader: impl Read) -> Result<Self> {
+        export::import(reader).c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    /// The number of the entries whose keys start with `prefix`,
+    /// and the total bytes of their keys and encoded values,
+    /// e.g. the usage of a tenant in a multi-tenant layout.
+    ///
+    /// NOTE: it is computed by a scan bounded by the prefix,
+    /// so the cost is proportional to the number of the matched entries.
+    pub fn prefix_stats(&self, prefix: impl AsRef<[u8]>) -> PrefixStats {
+        let prefix = prefix.as_ref();
+        self.inner
+            .range(Cow::Borrowed(prefix)..)
+            .take_while(|(k, _)| k.starts_with(prefix))
+            .fold(PrefixStats::default(), |mut s, (k, v)| {
+                s.count += 1;
+                s.bytes += (k.len() + v.len()) as u64;
+                s
+            })
+    }
+
+    /// Check `MapxRaw::content_digest` for details.
+    #[inline(always)]
+    pub fn content_digest(&self) -> u64 {
+        self.inner.content_digest()
+    }
+
+    /// Check `MapxRaw::enable_digest` for details.
+    #[inline(always)]
+    pub fn enable_digest(&self) -> StdResult<(), WriteError> {
+        self.inner.enable_digest()
+    }
+
+    /// Check `MapxRaw::disable_digest` for details.
+    #[inline(always)]
+    pub fn disable_digest(&self) -> StdResult<(), WriteError> {
+        self.inner.disable_digest()
+    }
+
+    /// Check `MapxRaw::compact` for details.
+    #[inline(always)]
+    pub fn compact(&self) -> StdResult<CompactionStats, WriteError> {
+        self.inner.compact()
+    }
+
+    /// Check `MapxRaw::seal` for details.
+    #[inline(always)]
+    pub fn seal(&self) -> Result<()> {
+        self.inner.seal().c(d!())
+    }
+
+    /// Check `MapxRaw::unseal` for details.
+    #[inline(always)]
+    pub fn unseal(&self) -> Result<()> {
+        self.inner.unseal().c(d!())
+    }
+
+    #[inline(always)]
+    pub fn is_sealed(&self) -> bool {
+        self.inner.is_sealed()
+    }
+
+    /// Check `MapxRaw::track_access` for details,
+    /// the key ranges are grouped by the encoded keys.
+    #[inline(always)]
+    pub fn track_access(&self, range_len: usize) {
+        self.inner.track_access(range_len)
+    }
+
+    #[inline(always)]
+    pub fn untrack_access(&self) {
+        self.inner.untrack_access()
+    }
+
+    #[inline(always)]
+    pub fn access_stats(&self) -> Option<AccessStats> {
+        self.inner.access_stats()
+    }
+
+    /// Check `MapxRaw::join_quota_group` for details.
+    #[inline(always)]
+    pub fn join_quota_group(&self, group: &QuotaGroup) {
+        self.inner.join_quota_group(group)
+    }
+
+    #[inline(always)]
+    pub fn leave_quota_group(&self) {
+        self.inner.leave_quota_group()
+    }
+
+    #[inline(always)]
+    pub fn quota_group(&self) -> Option<QuotaGroup> {
+        self.inner.quota_group()
+    }
+
+    /// Check `MapxRaw::bind_cache` for details.
+    #[inline(always)]
+    pub fn bind_cache(&self, adapter: Arc<dyn CacheAdapter>) {
+        self.inner.bind_cache(adapter)
+    }
+
+    #[inline(always)]
+    pub fn unbind_cache(&self) {
+        self.inner.unbind_cache()
+    }
+
+    /// Check `MapxRaw::soft_destroy` for details.
+    #[inline(always)]
+    pub fn soft_destroy(self, retention: Duration) -> Result<TrashId> {
+        self.inner.soft_destroy(retention).c(d!())
+    }
+
+    /// Restore an instance from the trash,
+    /// it must be soft-destroyed from the same type.
+    #[inline(always)]
+    pub fn from_trash(id: TrashId) -> Result<Self> {
+        vsdb_trash_restore(id).c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
 }
 
 impl<V> Clone for MapxOrdRawKey<V> {
@@ -274,15 +583,32 @@ where
     V: ValueEnDe,
 {
     value: V,
+    // no need to write again on dropping, until the value is borrowed mutably
+    committed: bool,
     inner: mapx_raw::ValueMut<'a>,
 }
 
+impl<'a, V> ValueMut<'a, V>
+where
+    V: ValueEnDe,
+{
+    /// Check `MapxRaw`'s `ValueMut::commit` for details.
+    pub fn commit(&mut self) -> StdResult<(), WriteError> {
+        *self.inner = self.value.encode();
+        self.inner.commit()?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
 impl<'a, V> Drop for ValueMut<'a, V>
 where
     V: ValueEnDe,
 {
     fn drop(&mut self) {
-        *self.inner = self.value.encode();
+        if !self.committed {
+            *self.inner = self.value.encode();
+        }
     }
 }
 
@@ -301,6 +627,7 @@ where
     V: ValueEnDe,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.committed = false;
         &mut self.value
     }
 }
@@ -333,20 +660,55 @@ where
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
+/// Check `MapxOrdRawKey::prefix_stats` for details.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrefixStats {
+    /// The number of the matched entries.
+    pub count: u64,
+    /// The total bytes of the keys and the encoded values.
+    pub bytes: u64,
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
 pub struct MapxOrdRawKeyIter<'a, V> {
+    pub(crate) ns: PreBytes,
     inner: MapxRawIter<'a>,
     _p: PhantomData<V>,
 }
 
+impl<'a, V> MapxOrdRawKeyIter<'a, V> {
+    /// Check `MapxRawIter::take_bytes` for details.
+    #[inline(always)]
+    pub fn take_bytes(mut self, max_bytes: u64) -> Self {
+        self.inner = self.inner.take_bytes(max_bytes);
+        self
+    }
+
+    /// Check `MapxRawIter::deadline` for details.
+    #[inline(always)]
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.inner = self.inner.deadline(deadline);
+        self
+    }
+
+    #[inline(always)]
+    pub fn is_truncated(&self) -> bool {
+        self.inner.is_truncated()
+    }
+}
+
 impl<'a, V> Iterator for MapxOrdRawKeyIter<'a, V>
 where
     V: ValueEnDe,
 {
     type Item = (RawKey, V);
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner
-            .next()
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        self.inner.next().map(|(k, v)| {
+            let v = decode_value(&self.ns, &k, &v);
+            (k, v)
+        })
     }
 }
 
@@ -355,9 +717,10 @@ where
     V: ValueEnDe,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner
-            .next_back()
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        self.inner.next_back().map(|(k, v)| {
+            let v = decode_value(&self.ns, &k, &v);
+            (k, v)
+        })
     }
 }
 
@@ -365,6 +728,7 @@ where
 /////////////////////////////////////////////////////////////////////////////
 
 pub struct MapxOrdRawKeyIterMut<'a, V> {
+    pub(crate) ns: PreBytes,
     inner: mapx_raw::MapxRawIterMut<'a>,
     _p: PhantomData<V>,
 }
@@ -376,13 +740,8 @@ where
     type Item = (RawKey, ValueIterMut<'a, V>);
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|(k, v)| {
-            (
-                k,
-                ValueIterMut {
-                    value: <V as ValueEnDe>::decode(&v).unwrap(),
-                    inner: v,
-                },
-            )
+            let value = decode_value(&self.ns, &k, &v);
+            (k, ValueIterMut::new(value, v))
         })
     }
 }
@@ -393,13 +752,8 @@ where
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|(k, v)| {
-            (
-                k,
-                ValueIterMut {
-                    value: <V as ValueEnDe>::decode(&v).unwrap(),
-                    inner: v,
-                },
-            )
+            let value = decode_value(&self.ns, &k, &v);
+            (k, ValueIterMut::new(value, v))
         })
     }
 }
@@ -413,15 +767,41 @@ where
     V: ValueEnDe,
 {
     pub(crate) value: V,
+    // no need to write again on dropping, until the value is borrowed mutably
+    committed: bool,
     pub(crate) inner: mapx_raw::ValueIterMut<'a>,
 }
 
+impl<'a, V> ValueIterMut<'a, V>
+where
+    V: ValueEnDe,
+{
+    #[inline(always)]
+    pub(crate) fn new(value: V, inner: mapx_raw::ValueIterMut<'a>) -> Self {
+        Self {
+            value,
+            committed: true,
+            inner,
+        }
+    }
+
+    /// Check `MapxRaw`'s `ValueIterMut::commit` for details.
+    pub fn commit(&mut self) -> StdResult<(), WriteError> {
+        *self.inner = self.value.encode();
+        self.inner.commit()?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
 impl<'a, V> Drop for ValueIterMut<'a, V>
 where
     V: ValueEnDe,
 {
     fn drop(&mut self) {
-        *self.inner = self.value.encode();
+        if !self.committed {
+            *self.inner = self.value.encode();
+        }
     }
 }
 
@@ -440,6 +820,7 @@ where
     V: ValueEnDe,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.committed = false;
         &mut self.value
     }
 }
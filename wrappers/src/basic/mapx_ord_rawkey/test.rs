@@ -124,3 +124,53 @@ fn test_values_first_last() {
     let (_, value) = pnk!(hdr.iter().next_back());
     assert_eq!(max - 1, value);
 }
+
+#[test]
+fn test_decode_error() {
+    let mut hdr = MapxOrdRawKey::<u64>::new();
+    let key = [7u8; 40];
+    unsafe { hdr.insert_encoded_value(key, [0xc1]) };
+
+    let ns = *hdr.inner.as_prefix_slice();
+    let e = hdr.try_get(key).unwrap_err();
+    assert_eq!(u64::from_be_bytes(ns), e.namespace);
+    assert_eq!(&key[..32], &e.key_excerpt[..]);
+    assert_eq!(40, e.key_len);
+    assert_eq!("u64", e.type_name);
+    assert!(e.to_string().contains(&"07".repeat(32)));
+
+    // the other readers panic with the same context
+    let broken = std::panic::catch_unwind(|| hdr.get(key));
+    assert!(broken.is_err());
+    let broken = std::panic::catch_unwind(|| hdr.iter().count());
+    assert!(broken.is_err());
+
+    // the healthy records are still readable
+    hdr.insert([1], &1);
+    assert_eq!(Some(1), hdr.try_get([1]).unwrap());
+    assert_eq!(None, hdr.try_get([2]).unwrap());
+}
+
+#[test]
+fn test_prefix_stats() {
+    let mut hdr: MapxOrdRawKey<u32> = MapxOrdRawKey::new();
+    let value_len = 4u32.encode().len() as u64;
+
+    (0u8..3).for_each(|tenant| {
+        (0..10u8).for_each(|i| {
+            hdr.insert([tenant, i], &(i as u32));
+        });
+    });
+    hdr.insert([1], &0);
+
+    assert_eq!(
+        PrefixStats {
+            count: 11,
+            bytes: 10 * (2 + value_len) + 1 + value_len,
+        },
+        hdr.prefix_stats([1])
+    );
+    assert_eq!(1, hdr.prefix_stats([1, 9]).count);
+    assert_eq!(0, hdr.prefix_stats([3]).count);
+    assert_eq!(31, hdr.prefix_stats([]).count);
+}
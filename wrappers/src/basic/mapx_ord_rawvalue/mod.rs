@@ -17,7 +17,6 @@
 //! let mut l = MapxOrdRawValue::new();
 //!
 //! l.insert(&1, &[0]);
-//! l.insert(1, Box::new([0]));
 //! l.insert(&2, &[0]);
 //!
 //! l.iter().for_each(|(k, v)| {
@@ -36,15 +35,24 @@
 #[cfg(test)]
 mod test;
 
-use crate::common::{ende::KeyEnDeOrdered, RawValue};
+use crate::common::{
+    ende::{decode_ordered_key, KeyEnDeOrdered},
+    PreBytes, RawValue, SizeLimits, WriteError,
+};
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
     marker::PhantomData,
     ops::{Bound, Deref, DerefMut, RangeBounds},
+    result::Result as StdResult,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use vsdb_core::{
+    basic::mapx_raw::{MapxRaw, MapxRawIter, MapxRawIterMut, MapxRawKeys, ValueIterMut},
+    vsdb_trash_restore, CacheAdapter, QuotaGroup, TrashId,
 };
-use vsdb_core::basic::mapx_raw::{MapxRaw, MapxRawIter, MapxRawIterMut, ValueIterMut};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
@@ -123,14 +131,28 @@ where
     pub fn get_le(&self, key: &K) -> Option<(K, RawValue)> {
         self.inner
             .get_le(key.to_bytes())
-            .map(|(k, v)| (pnk!(K::from_bytes(k)), v))
+            .map(|(k, v)| (decode_ordered_key(self.inner.as_prefix_slice(), k), v))
     }
 
     #[inline(always)]
     pub fn get_ge(&self, key: &K) -> Option<(K, RawValue)> {
         self.inner
             .get_ge(key.to_bytes())
-            .map(|(k, v)| (pnk!(K::from_bytes(k)), v))
+            .map(|(k, v)| (decode_ordered_key(self.inner.as_prefix_slice(), k), v))
+    }
+
+    #[inline(always)]
+    pub fn get_lt(&self, key: &K) -> Option<(K, RawValue)> {
+        self.inner
+            .get_lt(key.to_bytes())
+            .map(|(k, v)| (decode_ordered_key(self.inner.as_prefix_slice(), k), v))
+    }
+
+    #[inline(always)]
+    pub fn get_gt(&self, key: &K) -> Option<(K, RawValue)> {
+        self.inner
+            .get_gt(key.to_bytes())
+            .map(|(k, v)| (decode_ordered_key(self.inner.as_prefix_slice(), k), v))
     }
 
     #[inline(always)]
@@ -153,29 +175,66 @@ where
         self.inner.insert(key.to_bytes(), value.as_ref());
     }
 
+    /// Like `insert`, but return an error instead of panicking
+    /// if the encoded key or the value exceeds the size limits,
+    /// or the database is opened in read-only mode.
+    #[inline(always)]
+    pub fn try_insert(
+        &mut self,
+        key: &K,
+        value: impl AsRef<[u8]>,
+    ) -> StdResult<Option<RawValue>, WriteError> {
+        self.inner.try_insert(key.to_bytes(), value.as_ref())
+    }
+
+    #[inline(always)]
+    pub fn size_limits(&self) -> SizeLimits {
+        self.inner.size_limits()
+    }
+
+    /// Set the max sizes of the encoded keys and values,
+    /// see `MapxRaw::set_size_limits` for details.
+    #[inline(always)]
+    pub fn set_size_limits(&mut self, limits: SizeLimits) {
+        self.inner.set_size_limits(limits);
+    }
+
     #[inline(always)]
     pub fn entry(&mut self, key: K) -> Entry<'_, K> {
         Entry { key, hdr: self }
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> MapxOrdRawValueIter<K> {
+    pub fn iter(&self) -> MapxOrdRawValueIter<'_, K> {
         MapxOrdRawValueIter {
+            ns: *self.inner.as_prefix_slice(),
             inner: self.inner.iter(),
             _p: PhantomData,
         }
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxOrdRawValueIterMut<K> {
+    pub fn iter_mut(&mut self) -> MapxOrdRawValueIterMut<'_, K> {
         MapxOrdRawValueIterMut {
+            ns: *self.inner.as_prefix_slice(),
             inner: self.inner.iter_mut(),
             _p: PhantomData,
         }
     }
 
+    /// Iterate over the keys only, the values are not decoded,
+    /// check `MapxRaw::keys` for details.
+    #[inline(always)]
+    pub fn keys(&self) -> MapxOrdRawValueKeys<'_, K> {
+        MapxOrdRawValueKeys {
+            ns: *self.inner.as_prefix_slice(),
+            inner: self.inner.keys(),
+            _p: PhantomData,
+        }
+    }
+
     #[inline(always)]
-    pub fn values(&self) -> MapxOrdRawValueValues<K> {
+    pub fn values(&self) -> MapxOrdRawValueValues<'_, K> {
         MapxOrdRawValueValues {
             inner: self.inner.iter(),
             _p: PhantomData,
@@ -183,7 +242,7 @@ where
     }
 
     #[inline(always)]
-    pub fn values_mut(&mut self) -> MapxOrdRawValueValuesMut {
+    pub fn values_mut(&mut self) -> MapxOrdRawValueValuesMut<'_> {
         MapxOrdRawValueValuesMut {
             inner: self.inner.iter_mut(),
         }
@@ -193,7 +252,7 @@ where
     pub fn range<'a, R: RangeBounds<&'a K>>(
         &'a self,
         bounds: R,
-    ) -> MapxOrdRawValueIter<K> {
+    ) -> MapxOrdRawValueIter<'a, K> {
         let l = match bounds.start_bound() {
             Bound::Included(lo) => Bound::Included(Cow::Owned(lo.to_bytes())),
             Bound::Excluded(lo) => Bound::Excluded(Cow::Owned(lo.to_bytes())),
@@ -207,6 +266,7 @@ where
         };
 
         MapxOrdRawValueIter {
+            ns: *self.inner.as_prefix_slice(),
             inner: self.inner.range((l, h)),
             _p: PhantomData,
         }
@@ -216,7 +276,7 @@ where
     pub fn range_mut<'a, R: RangeBounds<&'a K>>(
         &'a mut self,
         bounds: R,
-    ) -> MapxOrdRawValueIterMut<K> {
+    ) -> MapxOrdRawValueIterMut<'a, K> {
         let l = match bounds.start_bound() {
             Bound::Included(lo) => Bound::Included(Cow::Owned(lo.to_bytes())),
             Bound::Excluded(lo) => Bound::Excluded(Cow::Owned(lo.to_bytes())),
@@ -230,6 +290,7 @@ where
         };
 
         MapxOrdRawValueIterMut {
+            ns: *self.inner.as_prefix_slice(),
             inner: self.inner.range_mut((l, h)),
             _p: PhantomData,
         }
@@ -250,20 +311,155 @@ where
         self.inner.remove(key.to_bytes())
     }
 
+    /// Like `remove`, but return an error instead of panicking
+    /// if the database is opened in read-only mode.
+    #[inline(always)]
+    pub fn try_remove(&mut self, key: &K) -> StdResult<Option<RawValue>, WriteError> {
+        self.inner.try_remove(key.to_bytes())
+    }
+
     #[inline(always)]
     pub fn unset_value(&mut self, key: &K) {
         self.inner.remove(key.to_bytes());
     }
 
+    /// Remove all entries within the range,
+    /// in one batch if the backend supports it.
+    #[inline(always)]
+    pub fn remove_range<'a, R: RangeBounds<&'a K>>(&mut self, bounds: R)
+    where
+        K: 'a,
+    {
+        self.inner.remove_range(Self::raw_bounds(bounds));
+    }
+
+    /// Check `MapxRaw::try_remove_range` for details.
+    #[inline(always)]
+    pub fn try_remove_range<'a, R: RangeBounds<&'a K>>(
+        &mut self,
+        bounds: R,
+    ) -> StdResult<(), WriteError>
+    where
+        K: 'a,
+    {
+        self.inner.try_remove_range(Self::raw_bounds(bounds))
+    }
+
+    fn raw_bounds<'a, R: RangeBounds<&'a K>>(bounds: R) -> (RawBound, RawBound)
+    where
+        K: 'a,
+    {
+        let l = match bounds.start_bound() {
+            Bound::Included(lo) => Bound::Included(Cow::Owned(lo.to_bytes())),
+            Bound::Excluded(lo) => Bound::Excluded(Cow::Owned(lo.to_bytes())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let h = match bounds.end_bound() {
+            Bound::Included(hi) => Bound::Included(Cow::Owned(hi.to_bytes())),
+            Bound::Excluded(hi) => Bound::Excluded(Cow::Owned(hi.to_bytes())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        (l, h)
+    }
+
+    /// Keep only the entries for which `f` returns `true`.
+    #[inline(always)]
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &[u8]) -> bool) {
+        let ns = *self.inner.as_prefix_slice();
+        self.inner
+            .retain(|k, v| f(&decode_ordered_key(&ns, k.to_vec()), v));
+    }
+
     #[inline(always)]
     pub fn clear(&mut self) {
         self.inner.clear();
     }
 
+    /// Check `MapxRaw::try_clear` for details.
+    #[inline(always)]
+    pub fn try_clear(&mut self) -> StdResult<(), WriteError> {
+        self.inner.try_clear()
+    }
+
     #[inline(always)]
     pub fn is_the_same_instance(&self, other_hdr: &Self) -> bool {
         self.inner.is_the_same_instance(&other_hdr.inner)
     }
+
+    /// Check `MapxRaw::clone_deep_to_new_instance` for details.
+    #[inline(always)]
+    pub fn clone_deep_to_new_instance(&self) -> Result<Self> {
+        self.inner
+            .clone_deep_to_new_instance()
+            .c(d!())
+            .map(|inner| Self {
+                inner,
+                _p: PhantomData,
+            })
+    }
+
+    /// Check `MapxRaw::content_digest` for details.
+    #[inline(always)]
+    pub fn content_digest(&self) -> u64 {
+        self.inner.content_digest()
+    }
+
+    /// Check `MapxRaw::enable_digest` for details.
+    #[inline(always)]
+    pub fn enable_digest(&self) -> StdResult<(), WriteError> {
+        self.inner.enable_digest()
+    }
+
+    /// Check `MapxRaw::disable_digest` for details.
+    #[inline(always)]
+    pub fn disable_digest(&self) -> StdResult<(), WriteError> {
+        self.inner.disable_digest()
+    }
+
+    /// Check `MapxRaw::join_quota_group` for details.
+    #[inline(always)]
+    pub fn join_quota_group(&self, group: &QuotaGroup) {
+        self.inner.join_quota_group(group)
+    }
+
+    #[inline(always)]
+    pub fn leave_quota_group(&self) {
+        self.inner.leave_quota_group()
+    }
+
+    #[inline(always)]
+    pub fn quota_group(&self) -> Option<QuotaGroup> {
+        self.inner.quota_group()
+    }
+
+    /// Check `MapxRaw::bind_cache` for details.
+    #[inline(always)]
+    pub fn bind_cache(&self, adapter: Arc<dyn CacheAdapter>) {
+        self.inner.bind_cache(adapter)
+    }
+
+    #[inline(always)]
+    pub fn unbind_cache(&self) {
+        self.inner.unbind_cache()
+    }
+
+    /// Check `MapxRaw::soft_destroy` for details.
+    #[inline(always)]
+    pub fn soft_destroy(self, retention: Duration) -> Result<TrashId> {
+        self.inner.soft_destroy(retention).c(d!())
+    }
+
+    /// Restore an instance from the trash,
+    /// it must be soft-destroyed from the same type.
+    #[inline(always)]
+    pub fn from_trash(id: TrashId) -> Result<Self> {
+        vsdb_trash_restore(id).c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
 }
 
 impl<K> Clone for MapxOrdRawValue<K> {
@@ -287,6 +483,9 @@ where
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
+// A bound of the encoded keys
+type RawBound = Bound<Cow<'static, [u8]>>;
+
 #[derive(Debug)]
 pub struct ValueMut<'a, K>
 where
@@ -367,17 +566,44 @@ pub struct MapxOrdRawValueIter<'a, K>
 where
     K: KeyEnDeOrdered,
 {
+    ns: PreBytes,
     inner: MapxRawIter<'a>,
     _p: PhantomData<K>,
 }
 
+impl<'a, K> MapxOrdRawValueIter<'a, K>
+where
+    K: KeyEnDeOrdered,
+{
+    /// Check `MapxRawIter::take_bytes` for details.
+    #[inline(always)]
+    pub fn take_bytes(mut self, max_bytes: u64) -> Self {
+        self.inner = self.inner.take_bytes(max_bytes);
+        self
+    }
+
+    /// Check `MapxRawIter::deadline` for details.
+    #[inline(always)]
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.inner = self.inner.deadline(deadline);
+        self
+    }
+
+    #[inline(always)]
+    pub fn is_truncated(&self) -> bool {
+        self.inner.is_truncated()
+    }
+}
+
 impl<'a, K> Iterator for MapxOrdRawValueIter<'a, K>
 where
     K: KeyEnDeOrdered,
 {
     type Item = (K, RawValue);
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|(k, v)| (pnk!(K::from_bytes(k)), v))
+        self.inner
+            .next()
+            .map(|(k, v)| (decode_ordered_key(&self.ns, k), v))
     }
 }
 
@@ -388,7 +614,41 @@ where
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner
             .next_back()
-            .map(|(k, v)| (pnk!(K::from_bytes(k)), v))
+            .map(|(k, v)| (decode_ordered_key(&self.ns, k), v))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct MapxOrdRawValueKeys<'a, K>
+where
+    K: KeyEnDeOrdered,
+{
+    ns: PreBytes,
+    inner: MapxRawKeys<'a>,
+    _p: PhantomData<K>,
+}
+
+impl<'a, K> Iterator for MapxOrdRawValueKeys<'a, K>
+where
+    K: KeyEnDeOrdered,
+{
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|k| decode_ordered_key(&self.ns, k))
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for MapxOrdRawValueKeys<'a, K>
+where
+    K: KeyEnDeOrdered,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|k| decode_ordered_key(&self.ns, k))
     }
 }
 
@@ -427,6 +687,7 @@ where
 /////////////////////////////////////////////////////////////////////////////
 
 pub struct MapxOrdRawValueIterMut<'a, K> {
+    pub(crate) ns: PreBytes,
     pub(crate) inner: MapxRawIterMut<'a>,
     pub(crate) _p: PhantomData<K>,
 }
@@ -439,7 +700,7 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         self.inner
             .next()
-            .map(|(k, v)| (pnk!(<K as KeyEnDeOrdered>::from_bytes(k)), v))
+            .map(|(k, v)| (decode_ordered_key(&self.ns, k), v))
     }
 }
 
@@ -450,7 +711,7 @@ where
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner
             .next_back()
-            .map(|(k, v)| (pnk!(<K as KeyEnDeOrdered>::from_bytes(k)), v))
+            .map(|(k, v)| (decode_ordered_key(&self.ns, k), v))
     }
 }
 
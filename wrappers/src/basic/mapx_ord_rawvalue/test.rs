@@ -98,3 +98,21 @@ fn test_first_last() {
     let val = pnk!(<usize as ValueEnDe>::decode(&value));
     assert_eq!(max - 1, val);
 }
+
+#[test]
+fn test_remove_range_retain() {
+    let mut hdr: MapxOrdRawValue<usize> = MapxOrdRawValue::new();
+    (0..100usize).for_each(|i| {
+        hdr.insert(&i, i.to_be_bytes());
+    });
+
+    hdr.remove_range(&10..=&19);
+    assert_eq!(90, hdr.len());
+    assert!(hdr.get(&10).is_none());
+    assert!(hdr.get(&19).is_none());
+    assert!(hdr.get(&20).is_some());
+
+    hdr.retain(|k, v| *k < 50 && v[7] % 2 == 0);
+    assert_eq!(20, hdr.len());
+    assert!(hdr.iter().all(|(k, _)| k < 50 && k % 2 == 0));
+}
@@ -2,6 +2,8 @@
 //! Unversioned functions.
 //!
 
+pub mod config;
+pub mod logx;
 pub mod mapx;
 pub mod mapx_ord;
 pub mod mapx_ord_rawkey;
@@ -14,7 +14,7 @@
 //!
 //! let mut l = Vecx::new();
 //!
-//! l.push(1);
+//! l.push(&1);
 //! for i in l.iter() {
 //!     assert_eq!(1, i);
 //! }
@@ -22,9 +22,13 @@
 //! l.pop();
 //! assert_eq!(l.len(), 0);
 //!
-//! l.insert(0, 1);
+//! l.insert(0, &1);
 //! assert_eq!(l.len(), 1);
 //!
+//! l.push_front(&2);
+//! assert_eq!(l.get(0), Some(2));
+//! assert_eq!(l.pop_front(), Some(2));
+//!
 //! l.clear();
 //! assert_eq!(l.len(), 0);
 //! ```
@@ -40,7 +44,16 @@ use crate::{
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, cmp::Ordering};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    ops::{Bound, RangeBounds},
+};
+
+// Elements are stored contiguously from the physical index of the first one,
+// which is persisted as the base offset of the instance, 0 by default;
+// `push_front` on a vector that starts from 0 moves it to this base.
+const FRONT_BASE: u64 = 1 << 63;
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
@@ -82,24 +95,60 @@ impl<T: ValueEnDe> Vecx<T> {
         }
     }
 
+    // The physical index of the first element
+    #[inline(always)]
+    fn head(&self) -> u64 {
+        self.inner.inner.base_offset()
+    }
+
+    // `None` if the index is out of the physical range
+    #[inline(always)]
+    fn physical_idx(&self, idx: usize) -> Option<u64> {
+        self.head().checked_add(idx as u64)
+    }
+
     #[inline(always)]
     pub fn get(&self, idx: usize) -> Option<T> {
-        self.inner.get((idx as u64).to_be_bytes())
+        self.inner.get(self.physical_idx(idx)?.to_be_bytes())
+    }
+
+    /// Read the elements within the range by one backend scan,
+    /// indexes beyond the length are ignored.
+    #[inline(always)]
+    pub fn get_range<R: RangeBounds<usize>>(&self, bounds: R) -> Vec<T> {
+        let head = self.head();
+
+        let l = match bounds.start_bound() {
+            Bound::Included(lo) => Bound::Included(Cow::Owned(
+                head.saturating_add(*lo as u64).to_be_bytes().to_vec(),
+            )),
+            Bound::Excluded(lo) => Bound::Excluded(Cow::Owned(
+                head.saturating_add(*lo as u64).to_be_bytes().to_vec(),
+            )),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let h = match bounds.end_bound() {
+            Bound::Included(hi) => Bound::Included(Cow::Owned(
+                head.saturating_add(*hi as u64).to_be_bytes().to_vec(),
+            )),
+            Bound::Excluded(hi) => Bound::Excluded(Cow::Owned(
+                head.saturating_add(*hi as u64).to_be_bytes().to_vec(),
+            )),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        self.inner.range((l, h)).map(|(_, v)| v).collect()
     }
 
     #[inline(always)]
     pub fn get_mut(&mut self, idx: usize) -> Option<ValueMut<'_, T>> {
-        self.inner.get_mut((idx as u64).to_be_bytes())
+        self.inner.get_mut(self.physical_idx(idx)?.to_be_bytes())
     }
 
     #[inline(always)]
     pub fn last(&self) -> Option<T> {
-        alt!(self.is_empty(), return None);
-        Some(
-            self.inner
-                .get((self.len() as u64 - 1).to_be_bytes())
-                .unwrap(),
-        )
+        self.iter().next_back()
     }
 
     #[inline(always)]
@@ -114,20 +163,65 @@ impl<T: ValueEnDe> Vecx<T> {
 
     #[inline(always)]
     pub fn push(&mut self, v: &T) {
-        self.inner.insert((self.len() as u64).to_be_bytes(), v);
+        let tail = self.head() + self.len() as u64;
+        self.inner.insert(tail.to_be_bytes(), v);
+    }
+
+    /// Prepend an element without shifting the others.
+    #[inline(always)]
+    pub fn push_front(&mut self, v: &T) {
+        let mut head = self.head();
+
+        if self.is_empty() {
+            head = FRONT_BASE;
+        } else if 0 == head {
+            self.rebase();
+            head = FRONT_BASE;
+        }
+
+        self.inner.insert((head - 1).to_be_bytes(), v);
+        self.inner.inner.set_base_offset(head - 1);
+    }
+
+    #[inline(always)]
+    pub fn pop_front(&mut self) -> Option<T> {
+        alt!(self.is_empty(), return None);
+        let head = self.head();
+        let ret = self.inner.remove(head.to_be_bytes());
+        self.inner.inner.set_base_offset(head + 1);
+        ret
+    }
+
+    // Move all elements to `FRONT_BASE`, this happens on the first `push_front`
+    // of a vector that has only been pushed from the back since its creation or clearing
+    fn rebase(&mut self) {
+        let shadow = unsafe { self.inner.shadow() };
+        let bound: Cow<[u8]> = Cow::Owned(FRONT_BASE.to_be_bytes().to_vec());
+        shadow.inner.range(..bound.clone()).for_each(|(i, v)| {
+            self.inner
+                .inner
+                .insert((FRONT_BASE + crate::parse_int!(i, u64)).to_be_bytes(), v);
+        });
+        self.inner.inner.remove_range(..bound);
+        self.inner.inner.set_base_offset(FRONT_BASE);
     }
 
     #[inline(always)]
     pub fn insert(&mut self, idx: usize, v: &T) {
-        let idx = idx as u64;
-        match (self.len() as u64).cmp(&idx) {
+        let head = self.head();
+        let tail = head + self.len() as u64;
+        // beyond the tail anyway
+        let idx = head.saturating_add(idx as u64);
+        match tail.cmp(&idx) {
             Ordering::Greater => {
                 let shadow = unsafe { self.inner.shadow() };
                 shadow
                     .range(
                         Cow::Borrowed(&idx.to_be_bytes()[..])
-                            ..Cow::Borrowed(&(self.len() as u64).to_be_bytes()[..]),
+                            ..Cow::Borrowed(&tail.to_be_bytes()[..]),
                     )
+                    // from the tail, or the next one will be overwritten before read
+                    .rev()
                     .for_each(|(i, iv)| {
                         self.inner
                             .insert((crate::parse_int!(i, u64) + 1).to_be_bytes(), &iv);
@@ -146,14 +240,16 @@ impl<T: ValueEnDe> Vecx<T> {
     #[inline(always)]
     pub fn pop(&mut self) -> Option<T> {
         alt!(self.is_empty(), return None);
-        self.inner.remove((self.len() as u64 - 1).to_be_bytes())
+        self.inner
+            .remove((self.head() + self.len() as u64 - 1).to_be_bytes())
     }
 
     #[inline(always)]
     pub fn remove(&mut self, idx: usize) -> T {
-        let idx = idx as u64;
-        if !self.is_empty() && idx < self.len() as u64 {
-            let last_idx = self.len() as u64 - 1;
+        if idx < self.len() {
+            let head = self.head();
+            let idx = head + idx as u64;
+            let last_idx = head + self.len() as u64 - 1;
             let ret = self.inner.remove(idx.to_be_bytes()).unwrap();
             let shadow = unsafe { self.inner.shadow() };
             shadow
@@ -170,9 +266,10 @@ impl<T: ValueEnDe> Vecx<T> {
 
     #[inline(always)]
     pub fn swap_remove(&mut self, idx: usize) -> T {
-        let idx = idx as u64;
-        if !self.is_empty() && idx < self.len() as u64 {
-            let last_idx = self.len() as u64 - 1;
+        if idx < self.len() {
+            let head = self.head();
+            let idx = head + idx as u64;
+            let last_idx = head + self.len() as u64 - 1;
             let ret = self.inner.remove(idx.to_be_bytes()).unwrap();
             if let Some(v) = self.inner.remove(last_idx.to_be_bytes()) {
                 self.inner.insert(idx.to_be_bytes(), &v);
@@ -185,18 +282,35 @@ impl<T: ValueEnDe> Vecx<T> {
     #[inline(always)]
     pub fn update(&mut self, idx: usize, v: &T) -> Option<T> {
         if idx < self.len() {
-            return self.inner.insert((idx as u64).to_be_bytes(), v);
+            return self
+                .inner
+                .insert((self.head() + idx as u64).to_be_bytes(), v);
         }
         panic!("out of index");
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> VecxIter<T> {
+    pub fn iter(&self) -> VecxIter<'_, T> {
         VecxIter(self.inner.iter())
     }
 
+    /// Iterate over the elements in batches of `chunk_size`,
+    /// the last batch may be shorter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> VecxIterMut<T> {
+    pub fn chunks(&self, chunk_size: usize) -> VecxChunks<'_, T> {
+        assert!(0 < chunk_size, "chunk size must be non-zero");
+        VecxChunks {
+            inner: self.iter(),
+            chunk_size,
+        }
+    }
+
+    #[inline(always)]
+    pub fn iter_mut(&mut self) -> VecxIterMut<'_, T> {
         VecxIterMut(self.inner.iter_mut())
     }
 
@@ -249,6 +363,26 @@ where
     }
 }
 
+pub struct VecxChunks<'a, T> {
+    inner: VecxIter<'a, T>,
+    chunk_size: usize,
+}
+
+impl<'a, T> Iterator for VecxChunks<'a, T>
+where
+    T: ValueEnDe,
+{
+    type Item = Vec<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self
+            .inner
+            .by_ref()
+            .take(self.chunk_size)
+            .collect::<Vec<_>>();
+        alt!(chunk.is_empty(), None, Some(chunk))
+    }
+}
+
 pub struct VecxIterMut<'a, T>(MapxOrdRawKeyIterMut<'a, T>);
 
 impl<'a, T> Iterator for VecxIterMut<'a, T>
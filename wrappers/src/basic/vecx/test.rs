@@ -180,3 +180,75 @@ fn write_out_of_index_7() {
     hdr.insert(0, &0);
     hdr.swap_remove(100);
 }
+
+#[test]
+fn test_get_range_chunks() {
+    let mut hdr = Vecx::new();
+    (0..100usize).for_each(|i| hdr.push(&i));
+
+    assert_eq!(hdr.get_range(10..20), (10..20).collect::<Vec<_>>());
+    assert_eq!(hdr.get_range(95..=99), (95..100).collect::<Vec<_>>());
+    assert_eq!(hdr.get_range(98..), vec![98, 99]);
+    assert_eq!(hdr.get_range(..3), vec![0, 1, 2]);
+    assert_eq!(hdr.get_range(90..200).len(), 10);
+    assert!(hdr.get_range(200..300).is_empty());
+
+    let chunks = hdr.chunks(30).collect::<Vec<_>>();
+    assert_eq!(4, chunks.len());
+    assert_eq!(chunks[0], (0..30).collect::<Vec<_>>());
+    assert_eq!(chunks[3], (90..100).collect::<Vec<_>>());
+    assert_eq!(100, hdr.chunks(7).map(|c| c.len()).sum::<usize>());
+}
+
+#[test]
+#[should_panic]
+fn chunks_zero_size() {
+    let hdr = Vecx::<usize>::new();
+    hdr.chunks(0);
+}
+
+#[test]
+fn test_deque() {
+    let mut hdr = Vecx::new();
+    (0..10usize).for_each(|i| hdr.push(&i));
+
+    // rebase from 0
+    (10..20usize).for_each(|i| hdr.push_front(&i));
+    assert_eq!(20, hdr.len());
+    assert_eq!(Some(19), hdr.get(0));
+    assert_eq!(Some(0), hdr.get(10));
+    assert_eq!(Some(9), hdr.last());
+    assert_eq!(vec![10, 0, 1], hdr.get_range(9..12));
+
+    hdr.insert(1, &100);
+    assert_eq!(Some(100), hdr.get(1));
+    assert_eq!(100, hdr.remove(1));
+    assert_eq!(19, hdr.swap_remove(0));
+    assert_eq!(Some(9), hdr.get(0));
+    hdr.update(0, &19);
+
+    for i in (10..20usize).rev() {
+        assert_eq!(Some(i), hdr.pop_front());
+    }
+    hdr.push(&9);
+    assert_eq!(
+        (0..10).collect::<Vec<usize>>(),
+        hdr.iter().collect::<Vec<_>>()
+    );
+
+    while hdr.pop_front().is_some() {}
+    assert!(hdr.is_empty());
+    hdr.push_front(&1);
+    hdr.push(&2);
+    assert_eq!(vec![1, 2], hdr.iter().collect::<Vec<usize>>());
+    assert!(hdr.get(usize::MAX).is_none());
+    assert!(hdr.get_range(usize::MAX - 1..).is_empty());
+    assert_eq!(vec![1, 2], hdr.clone().iter().collect::<Vec<usize>>());
+
+    // rebase again after clearing
+    hdr.clear();
+    hdr.push(&2);
+    hdr.push_front(&1);
+    assert_eq!(vec![1, 2], hdr.iter().collect::<Vec<usize>>());
+    assert_eq!(Some(1), hdr.get(0));
+}
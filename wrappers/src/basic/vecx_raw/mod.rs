@@ -25,6 +25,10 @@
 //! l.insert(0, &1u8.to_be_bytes());
 //! assert_eq!(l.len(), 1);
 //!
+//! l.push_front(&2u8.to_be_bytes());
+//! assert_eq!(&2u8.to_be_bytes(), &l.get(0).unwrap()[..]);
+//! assert_eq!(&2u8.to_be_bytes(), &l.pop_front().unwrap()[..]);
+//!
 //! l.clear();
 //! assert_eq!(l.len(), 0);
 //! ```
@@ -38,9 +42,14 @@ use crate::{
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
-use std::{cmp::Ordering, marker::PhantomData};
+use std::{borrow::Cow, cmp::Ordering, marker::PhantomData};
 use vsdb_core::basic::mapx_raw::MapxRawIter;
 
+// Elements are stored contiguously from the physical index of the first one,
+// which is persisted as the base offset of the instance, 0 by default;
+// `push_front` on a vector that starts from 0 moves it to this base.
+const FRONT_BASE: u64 = 1 << 63;
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
 pub struct VecxRaw {
@@ -81,14 +90,26 @@ impl VecxRaw {
         }
     }
 
+    // The physical index of the first element
+    #[inline(always)]
+    fn head(&self) -> u64 {
+        self.inner.inner.base_offset()
+    }
+
+    // `None` if the index is out of the physical range
+    #[inline(always)]
+    fn physical_idx(&self, idx: usize) -> Option<u64> {
+        self.head().checked_add(idx as u64)
+    }
+
     #[inline(always)]
     pub fn get(&self, idx: usize) -> Option<RawValue> {
-        self.inner.get(&(idx as u64))
+        self.inner.get(&self.physical_idx(idx)?)
     }
 
     #[inline(always)]
     pub fn get_mut(&mut self, idx: usize) -> Option<ValueMut<'_, u64>> {
-        let idx = idx as u64;
+        let idx = self.physical_idx(idx)?;
         self.inner
             .get(&idx)
             .map(|v| ValueMut::new(&mut self.inner, idx, v))
@@ -96,8 +117,7 @@ impl VecxRaw {
 
     #[inline(always)]
     pub fn last(&self) -> Option<RawValue> {
-        alt!(self.is_empty(), return None);
-        Some(self.inner.get(&(self.len() as u64 - 1)).unwrap())
+        self.iter().next_back()
     }
 
     #[inline(always)]
@@ -112,20 +132,61 @@ impl VecxRaw {
 
     #[inline(always)]
     pub fn push(&mut self, v: impl AsRef<[u8]>) {
-        self.inner.insert(&(self.len() as u64), v.as_ref());
+        let tail = self.head() + self.len() as u64;
+        self.inner.insert(&tail, v.as_ref());
+    }
+
+    /// Prepend an element without shifting the others.
+    #[inline(always)]
+    pub fn push_front(&mut self, v: impl AsRef<[u8]>) {
+        let mut head = self.head();
+
+        if self.is_empty() {
+            head = FRONT_BASE;
+        } else if 0 == head {
+            self.rebase();
+            head = FRONT_BASE;
+        }
+
+        self.inner.insert(&(head - 1), v.as_ref());
+        self.inner.inner.set_base_offset(head - 1);
+    }
+
+    #[inline(always)]
+    pub fn pop_front(&mut self) -> Option<RawValue> {
+        alt!(self.is_empty(), return None);
+        let head = self.head();
+        let ret = self.inner.remove(&head);
+        self.inner.inner.set_base_offset(head + 1);
+        ret
+    }
+
+    // Move all elements to `FRONT_BASE`, this happens on the first `push_front`
+    // of a vector that has only been pushed from the back since its creation or clearing
+    fn rebase(&mut self) {
+        let shadow = unsafe { self.inner.shadow() };
+        shadow.range(..&FRONT_BASE).for_each(|(i, v)| {
+            self.inner.insert(&(FRONT_BASE + i), &v);
+        });
+        self.inner
+            .inner
+            .remove_range(..Cow::Owned(FRONT_BASE.to_be_bytes().to_vec()));
+        self.inner.inner.set_base_offset(FRONT_BASE);
     }
 
     #[inline(always)]
     pub fn insert(&mut self, idx: usize, v: impl AsRef<[u8]>) {
-        let idx = idx as u64;
-        match (self.len() as u64).cmp(&idx) {
+        let head = self.head();
+        let tail = head + self.len() as u64;
+        // beyond the tail anyway
+        let idx = head.saturating_add(idx as u64);
+        match tail.cmp(&idx) {
             Ordering::Greater => {
                 let shadow = unsafe { self.inner.shadow() };
-                shadow
-                    .range(&idx..&(self.len() as u64))
-                    .for_each(|(i, iv)| {
-                        self.inner.insert(&(i + 1), &iv);
-                    });
+                // from the tail, or the next one will be overwritten before read
+                shadow.range(&idx..&tail).rev().for_each(|(i, iv)| {
+                    self.inner.insert(&(i + 1), &iv);
+                });
                 self.inner.insert(&idx, v.as_ref());
             }
             Ordering::Equal => {
@@ -140,14 +201,15 @@ impl VecxRaw {
     #[inline(always)]
     pub fn pop(&mut self) -> Option<RawValue> {
         alt!(self.is_empty(), return None);
-        self.inner.remove(&(self.len() as u64 - 1))
+        self.inner.remove(&(self.head() + self.len() as u64 - 1))
     }
 
     #[inline(always)]
     pub fn remove(&mut self, idx: usize) -> RawValue {
-        let idx = idx as u64;
-        if !self.is_empty() && idx < self.len() as u64 {
-            let last_idx = self.len() as u64 - 1;
+        if idx < self.len() {
+            let head = self.head();
+            let idx = head + idx as u64;
+            let last_idx = head + self.len() as u64 - 1;
             let ret = self.inner.remove(&idx).unwrap();
             let shadow = unsafe { self.inner.shadow() };
             for (i, v) in shadow.range(&(1 + idx)..) {
@@ -161,9 +223,10 @@ impl VecxRaw {
 
     #[inline(always)]
     pub fn swap_remove(&mut self, idx: usize) -> RawValue {
-        let idx = idx as u64;
-        if !self.is_empty() && idx < self.len() as u64 {
-            let last_idx = self.len() as u64 - 1;
+        if idx < self.len() {
+            let head = self.head();
+            let idx = head + idx as u64;
+            let last_idx = head + self.len() as u64 - 1;
             let ret = self.inner.remove(&idx).unwrap();
             if let Some(v) = self.inner.remove(&last_idx) {
                 self.inner.insert(&idx, &v);
@@ -176,21 +239,22 @@ impl VecxRaw {
     #[inline(always)]
     pub fn update(&mut self, idx: usize, v: impl AsRef<[u8]>) -> Option<RawValue> {
         if idx < self.len() {
-            return self.inner.insert(&(idx as u64), v.as_ref());
+            return self.inner.insert(&(self.head() + idx as u64), v.as_ref());
         }
         panic!("out of index");
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> VecxRawIter {
+    pub fn iter(&self) -> VecxRawIter<'_> {
         VecxRawIter {
             iter: self.inner.inner.iter(),
         }
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> VecxRawIterMut {
+    pub fn iter_mut(&mut self) -> VecxRawIterMut<'_> {
         VecxRawIterMut {
+            ns: *self.inner.inner.as_prefix_slice(),
             inner: self.inner.inner.iter_mut(),
             _p: PhantomData,
         }
@@ -204,3 +204,37 @@ fn write_out_of_index_7() {
     hdr.insert(0, &gen_sample(0));
     hdr.swap_remove(100);
 }
+
+#[test]
+fn test_deque() {
+    let mut hdr = VecxRaw::new();
+    (0..10).for_each(|i| hdr.push(gen_sample(i)));
+
+    // rebase from 0
+    (10..20).for_each(|i| hdr.push_front(gen_sample(i)));
+    assert_eq!(20, hdr.len());
+    assert_eq!(gen_sample(19).to_vec(), pnk!(hdr.get(0)));
+    assert_eq!(gen_sample(0).to_vec(), pnk!(hdr.get(10)));
+    assert_eq!(gen_sample(9).to_vec(), pnk!(hdr.last()));
+
+    assert_eq!(gen_sample(19).to_vec(), hdr.swap_remove(0));
+    assert_eq!(gen_sample(9).to_vec(), pnk!(hdr.get(0)));
+    hdr.update(0, gen_sample(19));
+
+    for i in (10..20).rev() {
+        assert_eq!(gen_sample(i).to_vec(), pnk!(hdr.pop_front()));
+    }
+    assert_eq!(
+        (0..9).map(|i| gen_sample(i).to_vec()).collect::<Vec<_>>(),
+        hdr.iter().collect::<Vec<_>>()
+    );
+    assert!(hdr.get(usize::MAX).is_none());
+    assert!(hdr.get_mut(usize::MAX).is_none());
+
+    // rebase again after clearing
+    hdr.clear();
+    hdr.push(gen_sample(2));
+    hdr.push_front(gen_sample(1));
+    assert_eq!(gen_sample(1).to_vec(), pnk!(hdr.get(0)));
+    assert_eq!(gen_sample(2).to_vec(), pnk!(hdr.get(1)));
+}
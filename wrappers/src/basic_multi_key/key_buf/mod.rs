@@ -0,0 +1,170 @@
+//!
+//! A reusable buffer for building the keys of multi-key maps.
+//!
+//! NOTE:
+//! - All sub-keys share one buffer, which can be reused across calls
+//! - The typed multi-key maps build their keys in a per-thread `KeyBuf`
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::basic_multi_key::{key_buf::KeyBuf, mapx_raw::MapxRawMk};
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut m = MapxRawMk::new(3);
+//! let mut kb = KeyBuf::new();
+//!
+//! for i in 0u8..3 {
+//!     kb.clear().push([1]).push([2]).push([i]);
+//!     m.insert(&kb.keys(), &[i]).unwrap();
+//! }
+//!
+//! kb.clear().push([1]).push([2]).push([0]);
+//! assert_eq!(m.get(&kb.keys()), Some(vec![0]));
+//! ```
+
+#[cfg(test)]
+mod test;
+
+use crate::common::ende::KeyEnDe;
+use ruc::*;
+use std::{cell::RefCell, ops::Deref};
+
+/// The max number of sub-keys that can be borrowed by `KeyBuf::keys`
+/// without allocations.
+pub const MAX_SUB_KEYS: usize = 8;
+
+thread_local! {
+    // Shared by the typed multi-key maps, whose reads only borrow the maps
+    static KEY_BUF: RefCell<KeyBuf> = RefCell::new(KeyBuf::new());
+}
+
+// Run `f` with the cleared per-thread buffer,
+// a temporary one is used if it is being used by an outer call
+#[inline(always)]
+pub(crate) fn with_key_buf<R>(f: impl FnOnce(&mut KeyBuf) -> R) -> R {
+    KEY_BUF.with(|kb| match kb.try_borrow_mut() {
+        Ok(mut kb) => f(kb.clear()),
+        Err(_) => f(&mut KeyBuf::new()),
+    })
+}
+
+/// Sub-keys are appended into one buffer,
+/// so no allocations happen once the buffer is large enough.
+#[derive(Clone, Debug, Default)]
+pub struct KeyBuf {
+    buf: Vec<u8>,
+    // the end offset of each sub-key
+    ends: Vec<usize>,
+}
+
+impl KeyBuf {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline(always)]
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(bytes),
+            ends: Vec::with_capacity(MAX_SUB_KEYS),
+        }
+    }
+
+    /// Drop all sub-keys, the allocated memory is kept.
+    #[inline(always)]
+    pub fn clear(&mut self) -> &mut Self {
+        self.buf.clear();
+        self.ends.clear();
+        self
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, sub_key: impl AsRef<[u8]>) -> &mut Self {
+        self.buf.extend_from_slice(sub_key.as_ref());
+        self.ends.push(self.buf.len());
+        self
+    }
+
+    /// Encode a typed sub-key into the buffer.
+    #[inline(always)]
+    pub fn push_key<K: KeyEnDe>(&mut self, sub_key: &K) -> &mut Self {
+        sub_key.encode_into(&mut self.buf);
+        self.ends.push(self.buf.len());
+        self
+    }
+
+    /// Drop the last sub-key.
+    #[inline(always)]
+    pub fn pop(&mut self) -> &mut Self {
+        if self.ends.pop().is_some() {
+            self.buf.truncate(self.start(self.ends.len()));
+        }
+        self
+    }
+
+    /// The number of sub-keys.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn get(&self, idx: usize) -> Option<&[u8]> {
+        alt!(
+            idx < self.len(),
+            Some(&self.buf[self.start(idx)..self.ends[idx]]),
+            None
+        )
+    }
+
+    /// The sub-keys in the form accepted by the multi-key maps,
+    /// a vector is allocated only if there are more than `MAX_SUB_KEYS` sub-keys.
+    #[inline(always)]
+    pub fn keys(&self) -> SubKeys<'_> {
+        let mut keys = SubKeys {
+            inline: [&[]; MAX_SUB_KEYS],
+            spilled: vec![],
+            cnt: self.len(),
+        };
+        if MAX_SUB_KEYS < self.len() {
+            keys.spilled = (0..self.len()).map(|i| self.get(i).unwrap()).collect();
+        } else {
+            (0..self.len()).for_each(|i| {
+                keys.inline[i] = self.get(i).unwrap();
+            });
+        }
+        keys
+    }
+
+    #[inline(always)]
+    fn start(&self, idx: usize) -> usize {
+        alt!(0 == idx, 0, self.ends[idx - 1])
+    }
+}
+
+/// Borrowed sub-keys of a `KeyBuf`, it derefs to `[&[u8]]`.
+pub struct SubKeys<'a> {
+    inline: [&'a [u8]; MAX_SUB_KEYS],
+    spilled: Vec<&'a [u8]>,
+    cnt: usize,
+}
+
+impl<'a> Deref for SubKeys<'a> {
+    type Target = [&'a [u8]];
+    fn deref(&self) -> &Self::Target {
+        alt!(
+            MAX_SUB_KEYS < self.cnt,
+            &self.spilled,
+            &self.inline[..self.cnt]
+        )
+    }
+}
@@ -0,0 +1,84 @@
+use super::*;
+use crate::basic_multi_key::{mapx_raw::MapxRawMk, mapx_rawkey::MapxRawKeyMk};
+use ruc::*;
+
+#[test]
+fn test_key_buf() {
+    let mut kb = KeyBuf::with_capacity(16);
+    assert!(kb.is_empty());
+    assert!(kb.keys().is_empty());
+
+    kb.push([1]).push([]).push([2, 3]);
+    assert_eq!(3, kb.len());
+    assert_eq!(Some(&[1][..]), kb.get(0));
+    assert_eq!(Some(&[][..]), kb.get(1));
+    assert_eq!(Some(&[2, 3][..]), kb.get(2));
+    assert!(kb.get(3).is_none());
+    assert_eq!(&[&[1][..], &[][..], &[2, 3][..]][..], &*kb.keys());
+
+    kb.pop().push([4]);
+    assert_eq!(&[&[1][..], &[][..], &[4][..]][..], &*kb.keys());
+
+    kb.clear();
+    assert!(kb.is_empty());
+    kb.pop();
+    assert!(kb.is_empty());
+}
+
+#[test]
+fn test_key_buf_spill() {
+    let mut kb = KeyBuf::new();
+    (0..=MAX_SUB_KEYS).for_each(|i| {
+        kb.push([i as u8]);
+    });
+    let keys = kb.keys();
+    assert_eq!(1 + MAX_SUB_KEYS, keys.len());
+    assert_eq!(&[MAX_SUB_KEYS as u8][..], keys[MAX_SUB_KEYS]);
+
+    kb.pop();
+    assert_eq!(MAX_SUB_KEYS, kb.keys().len());
+}
+
+#[test]
+fn test_key_buf_typed() {
+    let mut kb = KeyBuf::new();
+    kb.push_key(&1u32)
+        .push_key(&"a".to_owned())
+        .push_key(&vec![2u8]);
+    assert_eq!(Some(&1u32.encode()[..]), kb.get(0));
+    assert_eq!(Some(&"a".to_owned().encode()[..]), kb.get(1));
+    assert_eq!(Some(&vec![2u8].encode()[..]), kb.get(2));
+
+    // the per-thread buffer is cleared before reusing
+    with_key_buf(|kb| {
+        kb.push([1]);
+        // used by an outer call, a temporary one is given
+        with_key_buf(|inner| assert!(inner.is_empty()));
+    });
+    with_key_buf(|kb| assert!(kb.is_empty()));
+}
+
+#[test]
+fn test_key_buf_with_maps() {
+    let mut raw = MapxRawMk::new(3);
+    let mut typed = MapxRawKeyMk::new(3);
+    let mut kb = KeyBuf::new();
+
+    for i in 0u8..10 {
+        kb.clear().push([i]).push([i, i]).push([i, i, i]);
+        assert!(pnk!(raw.insert(&kb.keys(), &[i])).is_none());
+        assert!(pnk!(typed.insert(&kb.keys(), &i)).is_none());
+    }
+
+    for i in 0u8..10 {
+        kb.clear().push([i]).push([i, i]).push([i, i, i]);
+        assert_eq!(Some(vec![i]), raw.get(&kb.keys()));
+        assert_eq!(Some(i), typed.get(&kb.keys()));
+
+        // batch removal by the key prefix
+        kb.pop();
+        assert!(pnk!(raw.remove(&kb.keys())).is_none());
+        kb.push([i, i, i]);
+        assert!(raw.get(&kb.keys()).is_none());
+    }
+}
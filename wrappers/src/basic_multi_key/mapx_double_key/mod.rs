@@ -9,7 +9,10 @@
 mod test;
 
 use crate::{
-    basic_multi_key::mapx_raw::MapxRawMk,
+    basic_multi_key::{
+        key_buf::with_key_buf,
+        mapx_raw::{decode_located, MapxRawMk},
+    },
     common::ende::{KeyEnDe, ValueEnDe},
 };
 use ruc::*;
@@ -57,11 +60,13 @@ where
 
     #[inline(always)]
     pub fn get(&self, key: &(&K1, &K2)) -> Option<V> {
-        let k1 = key.0.encode();
-        let k2 = key.1.encode();
-        self.inner
-            .get(&[&k1, &k2])
-            .map(|v| pnk!(ValueEnDe::decode(&v)))
+        with_key_buf(|kb| {
+            kb.push_key(key.0).push_key(key.1);
+            let key = kb.keys();
+            self.inner
+                .get_located(&key)
+                .map(|v| decode_located(&key, v))
+        })
     }
 
     #[inline(always)]
@@ -98,25 +103,27 @@ where
 
     #[inline(always)]
     pub fn insert(&mut self, key: &(&K1, &K2), value: &V) -> Option<V> {
-        let k1 = key.0.encode();
-        let k2 = key.1.encode();
         let v = value.encode();
-        pnk!(self.inner.insert(&[&k1, &k2], &v))
-            .map(|old_v| pnk!(ValueEnDe::decode(&old_v)))
+        with_key_buf(|kb| {
+            kb.push_key(key.0).push_key(key.1);
+            let key = kb.keys();
+            pnk!(self.inner.insert_located(&key, &v))
+                .map(|old_v| decode_located(&key, old_v))
+        })
     }
 
     /// Support batch removal.
     #[inline(always)]
     pub fn remove(&mut self, key: &(&K1, Option<&K2>)) -> Option<V> {
-        let k1 = key.0.encode();
-        let k2 = key.1.map(|k2| k2.encode());
-        let k = if let Some(k2) = k2.as_ref() {
-            vec![&k1[..], &k2[..]]
-        } else {
-            vec![&k1[..]]
-        };
-        pnk!(self.inner.remove(k.as_slice()))
-            .map(|old_v| pnk!(ValueEnDe::decode(&old_v)))
+        with_key_buf(|kb| {
+            kb.push_key(key.0);
+            if let Some(k2) = key.1 {
+                kb.push_key(k2);
+            }
+            let key = kb.keys();
+            pnk!(self.inner.remove_located(&key))
+                .map(|old_v| decode_located(&key, old_v))
+        })
     }
 
     #[inline(always)]
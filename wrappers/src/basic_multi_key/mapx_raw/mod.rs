@@ -9,7 +9,11 @@
 mod test;
 
 use crate::{
-    common::{ende::ValueEnDe, RawKey, RawValue},
+    basic_multi_key::key_buf::KeyBuf,
+    common::{
+        ende::{decode_value, ValueEnDe},
+        PreBytes, RawValue,
+    },
     MapxRaw,
 };
 use ruc::*;
@@ -52,6 +56,12 @@ impl MapxRawMk {
 
     #[inline(always)]
     pub fn get(&self, key: &[&[u8]]) -> Option<RawValue> {
+        self.get_located(key).map(|(_, v)| v)
+    }
+
+    // Like `get`, but the prefix of the innermost instance,
+    // which the value belongs to, is also returned for decoding
+    pub(crate) fn get_located(&self, key: &[&[u8]]) -> Option<(PreBytes, RawValue)> {
         if key.len() != self.key_size as usize {
             return None;
         }
@@ -60,9 +70,9 @@ impl MapxRawMk {
         for (idx, k) in key.iter().enumerate() {
             if let Some(v) = hdr.get(k) {
                 if 1 + idx == self.key_size as usize {
-                    return Some(v);
+                    return Some((*hdr.as_prefix_slice(), v));
                 } else {
-                    hdr = pnk!(ValueEnDe::decode(&v));
+                    hdr = decode_value(hdr.as_prefix_slice(), k, &v);
                 }
             } else {
                 return None;
@@ -107,6 +117,16 @@ impl MapxRawMk {
 
     #[inline(always)]
     pub fn insert(&mut self, key: &[&[u8]], value: &[u8]) -> Result<Option<RawValue>> {
+        self.insert_located(key, value)
+            .map(|ret| ret.map(|(_, v)| v))
+    }
+
+    // Like `insert`, but the prefix of the innermost instance is also returned
+    pub(crate) fn insert_located(
+        &mut self,
+        key: &[&[u8]],
+        value: &[u8],
+    ) -> Result<Option<(PreBytes, RawValue)>> {
         if key.len() != self.key_size as usize {
             return Err(eg!("Incorrect key size"));
         }
@@ -116,9 +136,10 @@ impl MapxRawMk {
         let mut hdr = unsafe { self.inner.shadow() };
         for (idx, k) in key.iter().enumerate() {
             if 1 + idx == self.key_size as usize {
-                ret = hdr.insert(k, value);
+                ret = hdr.insert(k, value).map(|v| (*hdr.as_prefix_slice(), v));
                 break;
             } else {
+                let ns = *hdr.as_prefix_slice();
                 let mut new_hdr = None;
                 let f = || {
                     new_hdr.replace(MapxRaw::new());
@@ -128,7 +149,7 @@ impl MapxRawMk {
                 let h = if let Some(h) = new_hdr {
                     h
                 } else {
-                    pnk!(ValueEnDe::decode(mutv.as_ref()))
+                    decode_value(&ns, k, mutv.as_ref())
                 };
                 drop(mutv);
                 hdr = h;
@@ -141,6 +162,14 @@ impl MapxRawMk {
     /// Support batch removal.
     #[inline(always)]
     pub fn remove(&mut self, key: &[&[u8]]) -> Result<Option<RawValue>> {
+        self.remove_located(key).map(|ret| ret.map(|(_, v)| v))
+    }
+
+    // Like `remove`, but the prefix of the innermost instance is also returned
+    pub(crate) fn remove_located(
+        &mut self,
+        key: &[&[u8]],
+    ) -> Result<Option<(PreBytes, RawValue)>> {
         // Support batch removal from key path.
         if key.len() > self.key_size as usize {
             return Err(eg!("Incorrect key size"));
@@ -151,7 +180,7 @@ impl MapxRawMk {
             if let Some(v) = hdr.get(k) {
                 // NOTE: use `key.len()` instead of `self.key_size`
                 if 1 + idx == key.len() {
-                    let ret = hdr.remove(k);
+                    let ret = hdr.remove(k).map(|v| (*hdr.as_prefix_slice(), v));
                     // NOTE: use `self.key_size` instead of `key.len()`
                     if 1 + idx == self.key_size as usize {
                         return Ok(ret);
@@ -159,7 +188,7 @@ impl MapxRawMk {
                         return Ok(None);
                     }
                 } else {
-                    hdr = pnk!(ValueEnDe::decode(&v));
+                    hdr = decode_value(hdr.as_prefix_slice(), k, &v);
                 }
             } else {
                 return Ok(None);
@@ -202,7 +231,7 @@ impl MapxRawMk {
         F: FnMut(&[&[u8]], &[u8]) -> Result<()>,
     {
         let key_size = self.key_size() as usize;
-        let mut key_buf = vec![RawKey::default(); key_size];
+        let mut key_buf = KeyBuf::new();
         let mut hdr = unsafe { self.inner.shadow() };
         let mut depth = key_size;
 
@@ -211,15 +240,11 @@ impl MapxRawMk {
         } else {
             for (idx, k) in key_prefix.iter().enumerate() {
                 if let Some(v) = hdr.get(k) {
-                    key_buf[idx] = k.to_vec();
+                    key_buf.push(k);
                     if 1 + idx == key_size {
-                        let key = key_buf
-                            .iter()
-                            .map(|sub_k| sub_k.as_ref())
-                            .collect::<Vec<_>>();
-                        return op(key.as_slice(), &v).c(d!());
+                        return op(&key_buf.keys(), &v).c(d!());
                     } else {
-                        hdr = pnk!(ValueEnDe::decode(&v));
+                        hdr = decode_value(hdr.as_prefix_slice(), k, &v);
                         depth -= 1;
                     }
                 } else {
@@ -229,35 +254,34 @@ impl MapxRawMk {
             }
         };
 
-        self.recursive_walk(hdr, key_buf.as_mut_slice(), depth as u32, op)
+        self.recursive_walk(hdr, &mut key_buf, depth as u32, op)
             .c(d!())
     }
 
+    // The sub-keys of the upper levels are kept in `key_buf`,
+    // the sub-key of the current level is pushed and popped for each entry
     fn recursive_walk<F>(
         &self,
         hdr: MapxRaw,
-        key_buf: &mut [RawKey],
+        key_buf: &mut KeyBuf,
         depth: u32,
         op: &mut F,
     ) -> Result<()>
     where
         F: FnMut(&[&[u8]], &[u8]) -> Result<()>,
     {
-        let idx = (self.key_size() - depth) as usize;
         if 1 == depth {
             for (k, v) in hdr.iter() {
-                key_buf[idx] = k;
-                let key = key_buf
-                    .iter()
-                    .map(|sub_k| sub_k.as_ref())
-                    .collect::<Vec<_>>();
-                op(key.as_slice(), &v[..]).c(d!())?;
+                key_buf.push(&k);
+                op(&key_buf.keys(), &v[..]).c(d!())?;
+                key_buf.pop();
             }
         } else {
             for (k, v) in hdr.iter() {
-                key_buf[idx] = k;
-                let hdr = pnk!(ValueEnDe::decode(&v));
+                key_buf.push(&k);
+                let hdr = decode_value(hdr.as_prefix_slice(), &k, &v);
                 self.recursive_walk(hdr, key_buf, depth - 1, op).c(d!())?;
+                key_buf.pop();
             }
         }
 
@@ -284,7 +308,7 @@ impl MapxRawMk {
         V: ValueEnDe,
     {
         let key_size = self.key_size() as usize;
-        let mut key_buf = vec![RawKey::default(); key_size];
+        let mut key_buf = KeyBuf::new();
         let mut hdr = unsafe { self.inner.shadow() };
         let mut depth = key_size;
 
@@ -293,15 +317,12 @@ impl MapxRawMk {
         } else {
             for (idx, k) in key_prefix.iter().enumerate() {
                 if let Some(v) = hdr.get(k) {
-                    key_buf[idx] = k.to_vec();
+                    key_buf.push(k);
                     if 1 + idx == key_size {
-                        let key = key_buf
-                            .iter()
-                            .map(|sub_k| sub_k.as_ref())
-                            .collect::<Vec<_>>();
-                        return op(key.as_slice(), &pnk!(ValueEnDe::decode(&v))).c(d!());
+                        let v = decode_value(hdr.as_prefix_slice(), k, &v);
+                        return op(&key_buf.keys(), &v).c(d!());
                     } else {
-                        hdr = pnk!(ValueEnDe::decode(&v));
+                        hdr = decode_value(hdr.as_prefix_slice(), k, &v);
                         depth -= 1;
                     }
                 } else {
@@ -311,14 +332,14 @@ impl MapxRawMk {
             }
         };
 
-        self.recursive_walk_typed_value(hdr, key_buf.as_mut_slice(), depth as u32, op)
+        self.recursive_walk_typed_value(hdr, &mut key_buf, depth as u32, op)
             .c(d!())
     }
 
     fn recursive_walk_typed_value<V, F>(
         &self,
         hdr: MapxRaw,
-        key_buf: &mut [RawKey],
+        key_buf: &mut KeyBuf,
         depth: u32,
         op: &mut F,
     ) -> Result<()>
@@ -326,22 +347,20 @@ impl MapxRawMk {
         F: FnMut(&[&[u8]], &V) -> Result<()>,
         V: ValueEnDe,
     {
-        let idx = (self.key_size() - depth) as usize;
         if 1 == depth {
             for (k, v) in hdr.iter() {
-                key_buf[idx] = k;
-                let key = key_buf
-                    .iter()
-                    .map(|sub_k| sub_k.as_ref())
-                    .collect::<Vec<_>>();
-                op(key.as_slice(), &pnk!(ValueEnDe::decode(&v))).c(d!())?;
+                key_buf.push(&k);
+                let v = decode_value(hdr.as_prefix_slice(), &k, &v);
+                op(&key_buf.keys(), &v).c(d!())?;
+                key_buf.pop();
             }
         } else {
             for (k, v) in hdr.iter() {
-                key_buf[idx] = k;
-                let hdr = pnk!(ValueEnDe::decode(&v));
+                key_buf.push(&k);
+                let hdr = decode_value(hdr.as_prefix_slice(), &k, &v);
                 self.recursive_walk_typed_value(hdr, key_buf, depth - 1, op)
                     .c(d!())?;
+                key_buf.pop();
             }
         }
 
@@ -355,6 +374,16 @@ impl MapxRawMk {
     // pub fn iter_mut_op_typed_value_with_key_prefix
 }
 
+// Decode a value returned by the `*_located` methods,
+// it is keyed by the last sub-key in the innermost instance
+#[inline(always)]
+pub(crate) fn decode_located<V: ValueEnDe>(
+    key: &[&[u8]],
+    (ns, v): (PreBytes, RawValue),
+) -> V {
+    decode_value(&ns, key.last().copied().unwrap_or_default(), &v)
+}
+
 #[derive(Debug)]
 pub struct ValueMut<'a> {
     hdr: &'a mut MapxRawMk,
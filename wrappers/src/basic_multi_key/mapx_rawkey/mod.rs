@@ -8,7 +8,10 @@
 #[cfg(test)]
 mod test;
 
-use crate::{basic_multi_key::mapx_raw::MapxRawMk, common::ende::ValueEnDe};
+use crate::{
+    basic_multi_key::mapx_raw::{decode_located, MapxRawMk},
+    common::ende::ValueEnDe,
+};
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -48,7 +51,7 @@ impl<V: ValueEnDe> MapxRawKeyMk<V> {
 
     #[inline(always)]
     pub fn get(&self, key: &[&[u8]]) -> Option<V> {
-        self.inner.get(key).map(|v| pnk!(ValueEnDe::decode(&v)))
+        self.inner.get_located(key).map(|v| decode_located(key, v))
     }
 
     #[inline(always)]
@@ -88,18 +91,18 @@ impl<V: ValueEnDe> MapxRawKeyMk<V> {
     pub fn insert(&mut self, key: &[&[u8]], value: &V) -> Result<Option<V>> {
         let v = value.encode();
         self.inner
-            .insert(key, &v)
+            .insert_located(key, &v)
             .c(d!())
-            .map(|v| v.map(|old_v| pnk!(ValueEnDe::decode(&old_v))))
+            .map(|v| v.map(|old_v| decode_located(key, old_v)))
     }
 
     /// Support batch removal.
     #[inline(always)]
     pub fn remove(&mut self, key: &[&[u8]]) -> Result<Option<V>> {
         self.inner
-            .remove(key)
+            .remove_located(key)
             .c(d!())
-            .map(|v| v.map(|old_v| pnk!(ValueEnDe::decode(&old_v))))
+            .map(|v| v.map(|old_v| decode_located(key, old_v)))
     }
 
     #[inline(always)]
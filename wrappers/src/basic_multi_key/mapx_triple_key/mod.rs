@@ -9,7 +9,10 @@
 mod test;
 
 use crate::{
-    basic_multi_key::mapx_raw::MapxRawMk,
+    basic_multi_key::{
+        key_buf::with_key_buf,
+        mapx_raw::{decode_located, MapxRawMk},
+    },
     common::ende::{KeyEnDe, ValueEnDe},
 };
 use ruc::*;
@@ -58,12 +61,13 @@ where
 
     #[inline(always)]
     pub fn get(&self, key: &(&K1, &K2, &K3)) -> Option<V> {
-        let k1 = key.0.encode();
-        let k2 = key.1.encode();
-        let k3 = key.2.encode();
-        self.inner
-            .get(&[&k1, &k2, &k3])
-            .map(|v| pnk!(ValueEnDe::decode(&v)))
+        with_key_buf(|kb| {
+            kb.push_key(key.0).push_key(key.1).push_key(key.2);
+            let key = kb.keys();
+            self.inner
+                .get_located(&key)
+                .map(|v| decode_located(&key, v))
+        })
     }
 
     #[inline(always)]
@@ -103,33 +107,30 @@ where
 
     #[inline(always)]
     pub fn insert(&mut self, key: &(&K1, &K2, &K3), value: &V) -> Option<V> {
-        let k1 = key.0.encode();
-        let k2 = key.1.encode();
-        let k3 = key.2.encode();
         let v = value.encode();
-        pnk!(self.inner.insert(&[&k1, &k2, &k3], &v))
-            .map(|old_v| pnk!(ValueEnDe::decode(&old_v)))
+        with_key_buf(|kb| {
+            kb.push_key(key.0).push_key(key.1).push_key(key.2);
+            let key = kb.keys();
+            pnk!(self.inner.insert_located(&key, &v))
+                .map(|old_v| decode_located(&key, old_v))
+        })
     }
 
     /// Support batch removal.
     #[inline(always)]
     pub fn remove(&mut self, key: &(&K1, Option<(&K2, Option<&K3>)>)) -> Option<V> {
-        let k1 = key.0.encode();
-        let k2_k3 = key
-            .1
-            .map(|(k2, k3)| (k2.encode(), k3.map(|k3| k3.encode())));
-        let k = if let Some((k2, k3)) = k2_k3.as_ref() {
-            let mut res = vec![&k1[..], &k2[..]];
-            if let Some(k3) = k3.as_ref() {
-                res.push(&k3[..]);
+        with_key_buf(|kb| {
+            kb.push_key(key.0);
+            if let Some((k2, k3)) = key.1 {
+                kb.push_key(k2);
+                if let Some(k3) = k3 {
+                    kb.push_key(k3);
+                }
             }
-            res
-        } else {
-            vec![&k1[..]]
-        };
-
-        pnk!(self.inner.remove(k.as_slice()))
-            .map(|old_v| pnk!(ValueEnDe::decode(&old_v)))
+            let key = kb.keys();
+            pnk!(self.inner.remove_located(&key))
+                .map(|old_v| decode_located(&key, old_v))
+        })
     }
 
     #[inline(always)]
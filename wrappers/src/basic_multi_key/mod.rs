@@ -2,6 +2,7 @@
 //! Multi-Key kinds of basic structures.
 //!
 
+pub mod key_buf;
 pub mod mapx_double_key;
 pub mod mapx_raw;
 pub mod mapx_rawkey;
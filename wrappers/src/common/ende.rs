@@ -4,557 +4,365 @@
 //! (en)Encode and (de)Decode
 //!
 
-use super::RawBytes;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+
+use super::{Pre, PreBytes, RawBytes};
 use ruc::*;
 use std::{
-    fmt,
-    mem::{size_of, transmute},
+    any, error, fmt,
+    ops::{Deref, DerefMut},
+    result::Result as StdResult,
+};
+
+// Defined in `vsdb_ende`, so the types implementing them work with both crates
+pub use vsdb_ende::{
+    KeyDe, KeyEn, KeyEnDe, KeyEnDeOrdered, OrderedF32, OrderedF64, ValueDe, ValueEn,
+    ValueEnDe,
 };
 
+use vsdb_ende::{Error as EndeError, Result as EndeResult};
+
 #[cfg(feature = "serde_ende")]
 use serde::{de::DeserializeOwned, Serialize};
 
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-/// Methods used to encode the KEY.
-pub trait KeyEn: Sized {
-    /// Encode original key type to bytes.
-    fn try_encode_key(&self) -> Result<RawBytes>;
+/// Values whose layout may change over time.
+#[cfg(feature = "serde_ende")]
+pub trait SchemaEvolve: Serialize + DeserializeOwned {
+    /// The version of the current layout.
+    const SCHEMA_VERSION: u32;
 
-    fn encode_key(&self) -> RawBytes {
-        pnk!(self.try_encode_key())
-    }
+    /// Rebuild a value from the bytes written by an older layout,
+    /// the version 0 is the layout of the values written without `Evolving`.
+    fn migrate(old_version: u32, bytes: &[u8]) -> Result<Self>;
 }
 
-/// Methods used to decode the KEY.
-pub trait KeyDe: Sized {
-    /// Decode from bytes to the original key type.
-    fn decode_key(bytes: &[u8]) -> Result<Self>;
-}
+// The first byte of the version header, no codec output starts with it:
+// it is invalid in UTF-8, and it is a complete value in msgpack,
+// so an encoded value starting with it can not be longer than it
+#[cfg(feature = "serde_ende")]
+const SCHEMA_HEADER_MARKER: u8 = 0xff;
 
-/// Methods used to encode and decode the KEY.
-pub trait KeyEnDe: Sized {
-    /// Encode original key type to bytes.
-    fn try_encode(&self) -> Result<RawBytes>;
+#[cfg(feature = "serde_ende")]
+const SCHEMA_HEADER_SIZE: usize = 1 + size_of::<u32>();
+
+/// A value stored along with its schema version.
+///
+/// Values written by older layouts are migrated when they are read,
+/// and they will be stored in the current layout on the next write.
+///
+/// The values stored without the version header, e.g. the ones written
+/// before adopting this wrapper, are passed to `SchemaEvolve::migrate`
+/// as the version 0.
+#[cfg(feature = "serde_ende")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Evolving<V>(pub V);
 
-    fn encode(&self) -> RawBytes {
-        pnk!(self.try_encode())
+#[cfg(feature = "serde_ende")]
+impl<V: SchemaEvolve> ValueEn for Evolving<V> {
+    fn try_encode_value(&self) -> EndeResult<RawBytes> {
+        let mut ret = vec![SCHEMA_HEADER_MARKER];
+        ret.extend_from_slice(&V::SCHEMA_VERSION.to_be_bytes());
+        vsdb_ende::encode_into(&self.0, &mut ret)?;
+        Ok(ret)
     }
-
-    /// Decode from bytes to the original key type.
-    fn decode(bytes: &[u8]) -> Result<Self>;
 }
 
-/// Methods used to encode the VALUE.
-pub trait ValueEn: Sized {
-    /// Encode original key type to bytes.
-    fn try_encode_value(&self) -> Result<RawBytes>;
+#[cfg(feature = "serde_ende")]
+impl<V: SchemaEvolve> ValueDe for Evolving<V> {
+    fn decode_value(bytes: &[u8]) -> EndeResult<Self> {
+        let (ver, bytes) =
+            if bytes.len() >= SCHEMA_HEADER_SIZE && SCHEMA_HEADER_MARKER == bytes[0] {
+                let (hdr, bytes) = bytes.split_at(SCHEMA_HEADER_SIZE);
+                (u32::from_be_bytes(hdr[1..].try_into().unwrap()), bytes)
+            } else {
+                (0, bytes)
+            };
+
+        if V::SCHEMA_VERSION < ver {
+            return Err(EndeError::Codec(format!(
+                "unsupported schema version: {}, current: {}",
+                ver,
+                V::SCHEMA_VERSION
+            )));
+        }
 
-    fn encode_value(&self) -> RawBytes {
-        pnk!(self.try_encode_value())
+        if V::SCHEMA_VERSION == ver {
+            vsdb_ende::decode(bytes).map(Self)
+        } else {
+            V::migrate(ver, bytes)
+                .map(Self)
+                .map_err(|e| EndeError::Codec(e.to_string()))
+        }
     }
 }
 
-/// Methods used to decode the VALUE.
-pub trait ValueDe: Sized {
-    /// Decode from bytes to the original key type.
-    fn decode_value(bytes: &[u8]) -> Result<Self>;
-}
-
-/// Methods used to encode and decode the VALUE.
-pub trait ValueEnDe: Sized {
-    /// Encode original key type to bytes.
-    fn try_encode(&self) -> Result<RawBytes>;
-
-    fn encode(&self) -> RawBytes {
-        pnk!(self.try_encode())
+#[cfg(feature = "serde_ende")]
+impl<V> Deref for Evolving<V> {
+    type Target = V;
+    fn deref(&self) -> &Self::Target {
+        &self.0
     }
-
-    /// Decode from bytes to the original key type.
-    fn decode(bytes: &[u8]) -> Result<Self>;
 }
 
 #[cfg(feature = "serde_ende")]
-impl<T: Serialize> KeyEn for T {
-    #[cfg(feature = "json_codec")]
-    fn try_encode_key(&self) -> Result<RawBytes> {
-        serde_json::to_vec(self).c(d!())
-    }
-
-    #[cfg(feature = "msgpack_codec")]
-    fn try_encode_key(&self) -> Result<RawBytes> {
-        msgpack::to_vec(self).c(d!())
+impl<V> DerefMut for Evolving<V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
     }
 }
 
 #[cfg(feature = "serde_ende")]
-impl<T: DeserializeOwned> KeyDe for T {
-    #[cfg(feature = "json_codec")]
-    fn decode_key(bytes: &[u8]) -> Result<Self> {
-        serde_json::from_slice(bytes).c(d!())
-    }
-
-    #[cfg(feature = "msgpack_codec")]
-    fn decode_key(bytes: &[u8]) -> Result<Self> {
-        msgpack::from_slice(bytes).c(d!())
+impl<V> From<V> for Evolving<V> {
+    fn from(v: V) -> Self {
+        Self(v)
     }
 }
 
-#[cfg(feature = "serde_ende")]
-impl<T: Serialize> ValueEn for T {
-    #[cfg(feature = "json_codec")]
-    fn try_encode_value(&self) -> Result<RawBytes> {
-        serde_json::to_vec(self).c(d!())
-    }
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
 
-    #[cfg(feature = "msgpack_codec")]
-    fn try_encode_value(&self) -> Result<RawBytes> {
-        msgpack::to_vec(self).c(d!())
-    }
+// Long keys are truncated in the error messages
+const KEY_EXCERPT_LEN: usize = 32;
+
+/// The context of a decoding failure,
+/// used to locate the broken record in a large instance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    /// The prefix of the instance that the record belongs to.
+    pub namespace: Pre,
+    /// The leading bytes of the key.
+    pub key_excerpt: RawBytes,
+    /// The full length of the key.
+    pub key_len: usize,
+    /// The type that the bytes were decoded into.
+    pub type_name: &'static str,
+    /// The error reported by the codec.
+    pub reason: String,
 }
 
-#[cfg(feature = "serde_ende")]
-impl<T: DeserializeOwned> ValueDe for T {
-    #[cfg(feature = "json_codec")]
-    fn decode_value(bytes: &[u8]) -> Result<Self> {
-        serde_json::from_slice(bytes).c(d!())
+impl DecodeError {
+    fn new<T>(namespace: &PreBytes, key: &[u8], reason: String) -> Self {
+        Self::with_excerpt::<T>(namespace, excerpt(key), key.len(), reason)
+    }
+
+    fn with_excerpt<T>(
+        namespace: &PreBytes,
+        excerpt: &[u8],
+        key_len: usize,
+        reason: String,
+    ) -> Self {
+        Self {
+            namespace: Pre::from_be_bytes(*namespace),
+            key_excerpt: excerpt.to_vec(),
+            key_len,
+            type_name: any::type_name::<T>(),
+            reason,
+        }
     }
+}
 
-    #[cfg(feature = "msgpack_codec")]
-    fn decode_value(bytes: &[u8]) -> Result<Self> {
-        msgpack::from_slice(bytes).c(d!())
-    }
+#[inline(always)]
+fn excerpt(key: &[u8]) -> &[u8] {
+    &key[..key.len().min(KEY_EXCERPT_LEN)]
 }
 
-impl<T: KeyEn + KeyDe> KeyEnDe for T {
-    fn try_encode(&self) -> Result<RawBytes> {
-        <Self as KeyEn>::try_encode_key(self).c(d!())
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to decode `{}`, namespace: {}, key({} bytes): 0x",
+            self.type_name, self.namespace, self.key_len
+        )?;
+        for b in self.key_excerpt.iter() {
+            write!(f, "{:02x}", b)?;
+        }
+        if self.key_len > self.key_excerpt.len() {
+            write!(f, "..")?;
+        }
+        write!(f, ", reason: {}", self.reason)
     }
+}
 
-    fn encode(&self) -> RawBytes {
-        <Self as KeyEn>::encode_key(self)
-    }
+impl error::Error for DecodeError {}
 
-    fn decode(bytes: &[u8]) -> Result<Self> {
-        <Self as KeyDe>::decode_key(bytes).c(d!())
-    }
+/// Attach the location of the record to decoding failures.
+pub trait DecodeContext<T> {
+    /// `namespace` is the prefix of the instance,
+    /// `key` is the raw key of the record being decoded.
+    fn ctx(self, namespace: &PreBytes, key: &[u8]) -> StdResult<T, DecodeError>;
 }
 
-impl<T: ValueEn + ValueDe> ValueEnDe for T {
-    fn try_encode(&self) -> Result<RawBytes> {
-        <Self as ValueEn>::try_encode_value(self).c(d!())
-    }
-
-    fn encode(&self) -> RawBytes {
-        <Self as ValueEn>::encode_value(self)
+impl<T> DecodeContext<T> for Result<T> {
+    fn ctx(self, namespace: &PreBytes, key: &[u8]) -> StdResult<T, DecodeError> {
+        self.map_err(|e| DecodeError::new::<T>(namespace, key, e.to_string()))
     }
+}
 
-    fn decode(bytes: &[u8]) -> Result<Self> {
-        <Self as ValueDe>::decode_value(bytes).c(d!())
+impl<T> DecodeContext<T> for EndeResult<T> {
+    fn ctx(self, namespace: &PreBytes, key: &[u8]) -> StdResult<T, DecodeError> {
+        self.map_err(|e| DecodeError::new::<T>(namespace, key, e.to_string()))
     }
 }
 
-/////////////////////////////////////////////////////////////////////////////
-/////////////////////////////////////////////////////////////////////////////
-
-#[cfg(not(feature = "serde_ende"))]
-impl<T: KeyEnDeOrdered> KeyEn for T {
-    fn try_encode_key(&self) -> Result<RawBytes> {
-        Ok(self.encode_key())
-    }
+// The records of the instance `ns` are decoded by the helpers below,
+// the failures panic with the locations of the records
 
-    fn encode_key(&self) -> RawBytes {
-        <T as KeyEnDeOrdered>::to_bytes(self)
-    }
+#[inline(always)]
+pub(crate) fn decode_key<K: KeyEnDe>(ns: &PreBytes, key: &[u8]) -> K {
+    pnk!(K::decode(key).ctx(ns, key))
 }
 
-#[cfg(not(feature = "serde_ende"))]
-impl<T: KeyEnDeOrdered> KeyDe for T {
-    fn decode_key(bytes: &[u8]) -> Result<Self> {
-        <T as KeyEnDeOrdered>::from_slice(bytes).c(d!())
-    }
+// The key is moved into the decoded one if possible,
+// so only an excerpt of it is kept for the failures
+#[inline(always)]
+pub(crate) fn decode_ordered_key<K: KeyEnDeOrdered>(ns: &PreBytes, key: RawBytes) -> K {
+    let mut buf = [0; KEY_EXCERPT_LEN];
+    let excerpt_len = excerpt(&key).len();
+    buf[..excerpt_len].copy_from_slice(excerpt(&key));
+    let key_len = key.len();
+    pnk!(K::from_bytes(key).map_err(|e| {
+        DecodeError::with_excerpt::<K>(ns, &buf[..excerpt_len], key_len, e.to_string())
+    }))
 }
 
-macro_rules! impl_v_ende {
-    ($t: ty) => {
-        #[cfg(not(feature = "serde_ende"))]
-        impl ValueEnDe for $t {
-            fn try_encode(&self) -> Result<RawBytes> {
-                Ok(self.encode())
-            }
-            fn encode(&self) -> RawBytes {
-                self.as_bytes().into()
-            }
-            fn decode(bytes: &[u8]) -> Result<Self> {
-                unsafe { Ok(<$t>::from_bytes(bytes)) }
-            }
-        }
-    };
-    (@$t: ty) => {
-        #[cfg(not(feature = "serde_ende"))]
-        impl<K: KeyEnDeOrdered> ValueEnDe for $t {
-            fn try_encode(&self) -> Result<RawBytes> {
-                Ok(self.encode())
-            }
-            fn encode(&self) -> RawBytes {
-                self.as_bytes().into()
-            }
-            fn decode(bytes: &[u8]) -> Result<Self> {
-                unsafe { Ok(<$t>::from_bytes(bytes)) }
-            }
-        }
-    };
-    (^$t: ty) => {
-        #[cfg(not(feature = "serde_ende"))]
-        impl<V: ValueEnDe> ValueEnDe for $t {
-            fn try_encode(&self) -> Result<RawBytes> {
-                Ok(self.encode())
-            }
-            fn encode(&self) -> RawBytes {
-                self.as_bytes().into()
-            }
-            fn decode(bytes: &[u8]) -> Result<Self> {
-                unsafe { Ok(<$t>::from_bytes(bytes)) }
-            }
-        }
-    };
-    (~$t: ty) => {
-        #[cfg(not(feature = "serde_ende"))]
-        impl<K: KeyEnDeOrdered, V: ValueEnDe> ValueEnDe for $t {
-            fn try_encode(&self) -> Result<RawBytes> {
-                Ok(self.encode())
-            }
-            fn encode(&self) -> RawBytes {
-                self.as_bytes().into()
-            }
-            fn decode(bytes: &[u8]) -> Result<Self> {
-                unsafe { Ok(<$t>::from_bytes(bytes)) }
-            }
-        }
-    };
+#[inline(always)]
+pub(crate) fn decode_value<V: ValueEnDe>(ns: &PreBytes, key: &[u8], value: &[u8]) -> V {
+    pnk!(V::decode(value).ctx(ns, key))
 }
 
-impl_v_ende!(vsdb_core::MapxRaw);
-impl_v_ende!(crate::basic::vecx_raw::VecxRaw);
-impl_v_ende!(~crate::basic::mapx::Mapx<K, V>);
-impl_v_ende!(~crate::basic::mapx_ord::MapxOrd<K, V>);
-impl_v_ende!(^crate::basic::vecx::Vecx<V>);
-impl_v_ende!(^crate::basic::orphan::Orphan<V>);
-impl_v_ende!(^crate::basic::mapx_ord_rawkey::MapxOrdRawKey<V>);
-impl_v_ende!(@crate::basic::mapx_ord_rawvalue::MapxOrdRawValue<K>);
-
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-/// For keys that their serialized order keep consistent with their original format.
-/// When using this kind of keys, we can do some ordered operations, such as: `get_le/get_be ...`
-pub trait KeyEnDeOrdered: Clone + Eq + Ord + fmt::Debug {
-    /// &key => bytes
-    fn to_bytes(&self) -> RawBytes;
+/// Fixed-size bytes, e.g. hashes and addresses.
+///
+/// Both as keys and values, they are stored as they are,
+/// no length prefixes or other codec overheads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedBytes<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> FixedBytes<N> {
+    #[inline(always)]
+    fn from_raw(bytes: &[u8]) -> EndeResult<Self> {
+        <[u8; N]>::try_from(bytes)
+            .map(Self)
+            .map_err(|_| EndeError::InvalidLength)
+    }
+}
 
-    /// key => bytes
-    fn into_bytes(self) -> RawBytes {
-        self.to_bytes()
+#[cfg(feature = "serde_ende")]
+impl<const N: usize> KeyEn for FixedBytes<N> {
+    fn try_encode_key(&self) -> EndeResult<RawBytes> {
+        Ok(self.0.to_vec())
     }
 
-    /// &bytes => key
-    fn from_slice(b: &[u8]) -> Result<Self>;
+    fn encode_key(&self) -> RawBytes {
+        self.0.to_vec()
+    }
 
-    /// bytes => key
-    fn from_bytes(b: RawBytes) -> Result<Self> {
-        Self::from_slice(&b)
+    fn encode_key_into(&self, buf: &mut RawBytes) {
+        buf.extend_from_slice(&self.0);
     }
 }
 
-impl KeyEnDeOrdered for RawBytes {
-    #[inline(always)]
-    fn to_bytes(&self) -> RawBytes {
-        self.clone()
+#[cfg(feature = "serde_ende")]
+impl<const N: usize> KeyDe for FixedBytes<N> {
+    fn decode_key(bytes: &[u8]) -> EndeResult<Self> {
+        Self::from_raw(bytes)
     }
+}
 
-    #[inline(always)]
-    fn into_bytes(self) -> RawBytes {
-        self
+impl<const N: usize> ValueEn for FixedBytes<N> {
+    fn try_encode_value(&self) -> EndeResult<RawBytes> {
+        Ok(self.0.to_vec())
     }
 
-    #[inline(always)]
-    fn from_slice(b: &[u8]) -> Result<Self> {
-        Ok(b.to_vec())
+    fn encode_value(&self) -> RawBytes {
+        self.0.to_vec()
     }
+}
 
-    #[inline(always)]
-    fn from_bytes(b: RawBytes) -> Result<Self> {
-        Ok(b.to_vec())
+impl<const N: usize> ValueDe for FixedBytes<N> {
+    fn decode_value(bytes: &[u8]) -> EndeResult<Self> {
+        Self::from_raw(bytes)
     }
 }
 
-impl KeyEnDeOrdered for Box<[u8]> {
+impl<const N: usize> KeyEnDeOrdered for FixedBytes<N> {
     #[inline(always)]
     fn to_bytes(&self) -> RawBytes {
-        self.clone().to_vec()
+        self.0.to_vec()
     }
 
     #[inline(always)]
-    fn into_bytes(self) -> RawBytes {
-        self.to_vec()
+    fn write_bytes(&self, buf: &mut RawBytes) {
+        buf.extend_from_slice(&self.0);
     }
 
     #[inline(always)]
-    fn from_slice(b: &[u8]) -> Result<Self> {
-        Ok(b.to_vec().into())
+    fn from_slice(b: &[u8]) -> EndeResult<Self> {
+        Self::from_raw(b)
     }
+}
 
-    #[inline(always)]
-    fn from_bytes(b: RawBytes) -> Result<Self> {
-        Ok(b.into())
+impl<const N: usize> Default for FixedBytes<N> {
+    fn default() -> Self {
+        Self([0; N])
     }
 }
 
-impl KeyEnDeOrdered for String {
-    #[inline(always)]
-    fn to_bytes(&self) -> RawBytes {
-        self.as_bytes().to_vec()
+impl<const N: usize> Deref for FixedBytes<N> {
+    type Target = [u8; N];
+    fn deref(&self) -> &Self::Target {
+        &self.0
     }
+}
 
-    #[inline(always)]
-    fn into_bytes(self) -> RawBytes {
-        self.into_bytes()
+impl<const N: usize> DerefMut for FixedBytes<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
     }
+}
 
-    #[inline(always)]
-    fn from_slice(b: &[u8]) -> Result<Self> {
-        String::from_utf8(b.to_owned()).c(d!())
+impl<const N: usize> AsRef<[u8]> for FixedBytes<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
     }
+}
 
-    #[inline(always)]
-    fn from_bytes(b: RawBytes) -> Result<Self> {
-        String::from_utf8(b).c(d!())
+impl<const N: usize> From<[u8; N]> for FixedBytes<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        Self(bytes)
     }
 }
 
-macro_rules! impl_type {
-    ($int: ty) => {
-        impl KeyEnDeOrdered for $int {
-            #[inline(always)]
-            fn to_bytes(&self) -> RawBytes {
-                self.wrapping_sub(<$int>::MIN).to_be_bytes().to_vec()
-            }
-            #[inline(always)]
-            fn from_slice(b: &[u8]) -> Result<Self> {
-                <[u8; size_of::<$int>()]>::try_from(b)
-                    .c(d!())
-                    .map(|bytes| <$int>::from_be_bytes(bytes).wrapping_add(<$int>::MIN))
-            }
-        }
-    };
-    (@$int: ty) => {
-        #[allow(clippy::unsound_collection_transmute)]
-        impl KeyEnDeOrdered for Vec<$int> {
-            #[inline(always)]
-            fn to_bytes(&self) -> RawBytes {
-                self.iter()
-                    .map(|i| i.wrapping_sub(<$int>::MIN).to_be_bytes())
-                    .flatten()
-                    .collect::<Vec<_>>()
-            }
-            #[inline(always)]
-            fn into_bytes(mut self) -> RawBytes {
-                for i in 0..self.len() {
-                    self[i] = self[i].wrapping_sub(<$int>::MIN).to_be();
-                }
-                unsafe {
-                    let v = transmute::<Vec<$int>, RawBytes>(self);
-                    v
-                }
-            }
-            #[inline(always)]
-            fn from_slice(b: &[u8]) -> Result<Self> {
-                if 0 != b.len() % size_of::<$int>() {
-                    return Err(eg!("invalid bytes"));
-                }
-                b.chunks(size_of::<$int>())
-                    .map(|i| {
-                        <[u8; size_of::<$int>()]>::try_from(i).c(d!()).map(|bytes| {
-                            <$int>::from_be_bytes(bytes).wrapping_add(<$int>::MIN)
-                        })
-                    })
-                    .collect()
-            }
-            #[inline(always)]
-            fn from_bytes(b: RawBytes) -> Result<Self> {
-                if 0 != b.len() % size_of::<$int>() {
-                    return Err(eg!("invalid bytes"));
-                }
-                let mut ret = unsafe {
-                    let mut v = transmute::<Vec<u8>, Vec<$int>>(b);
-                    v.set_len(v.len() / size_of::<$int>());
-                    v
-                };
-                for i in 0..ret.len() {
-                    ret[i] = <$int>::from_be(ret[i]).wrapping_add(<$int>::MIN);
-                }
-                Ok(ret)
-            }
-        }
-    };
-    (^$int: ty) => {
-        impl KeyEnDeOrdered for Box<[$int]> {
-            #[inline(always)]
-            fn to_bytes(&self) -> RawBytes {
-                KeyEnDeOrdered::to_bytes(&self.to_vec())
-            }
-            #[inline(always)]
-            fn into_bytes(self) -> RawBytes {
-                KeyEnDeOrdered::into_bytes(self.to_vec())
-            }
-            #[inline(always)]
-            fn from_slice(b: &[u8]) -> Result<Self> {
-                <Vec<$int> as KeyEnDeOrdered>::from_slice(b).map(|b| b.into())
-            }
-            #[inline(always)]
-            fn from_bytes(b: RawBytes) -> Result<Self> {
-                <Vec<$int> as KeyEnDeOrdered>::from_bytes(b).map(|b| b.into())
-            }
-        }
-    };
-    ($int: ty, $siz: expr) => {
-        impl KeyEnDeOrdered for [$int; $siz] {
-            #[inline(always)]
-            fn to_bytes(&self) -> RawBytes {
-                self.iter()
-                    .map(|i| i.wrapping_sub(<$int>::MIN).to_be_bytes())
-                    .flatten()
-                    .collect::<Vec<_>>()
-            }
-            #[inline(always)]
-            fn from_slice(b: &[u8]) -> Result<Self> {
-                if 0 != b.len() % size_of::<$int>() {
-                    return Err(eg!("invalid bytes"));
-                }
-                if $siz != b.len() / size_of::<$int>() {
-                    return Err(eg!("invalid bytes"));
-                }
-                let mut res = [0; $siz];
-                b.chunks(size_of::<$int>())
-                    .enumerate()
-                    .for_each(|(idx, i)| {
-                        res[idx] = <[u8; size_of::<$int>()]>::try_from(i)
-                            .map(|bytes| {
-                                <$int>::from_be_bytes(bytes).wrapping_add(<$int>::MIN)
-                            })
-                            .unwrap();
-                    });
-                Ok(res)
-            }
-        }
-    };
-    (%$hash: ty) => {
-        impl KeyEnDeOrdered for $hash {
-            #[inline(always)]
-            fn to_bytes(&self) -> RawBytes {
-                self.as_bytes().to_vec()
-            }
-            #[inline(always)]
-            fn from_slice(b: &[u8]) -> Result<Self> {
-                if b.len() != <$hash>::len_bytes() {
-                    return Err(eg!("length mismatch"));
-                }
-                Ok(<$hash>::from_slice(b))
-            }
-        }
-    };
-    (~$big_uint: ty) => {
-        impl KeyEnDeOrdered for $big_uint {
-            #[inline(always)]
-            fn to_bytes(&self) -> RawBytes {
-                let mut r = vec![];
-                self.to_big_endian(&mut r);
-                r
-            }
-            #[inline(always)]
-            fn from_slice(b: &[u8]) -> Result<Self> {
-                Ok(<$big_uint>::from_big_endian(b))
-            }
-        }
-    };
+impl<const N: usize> From<FixedBytes<N>> for [u8; N] {
+    fn from(bytes: FixedBytes<N>) -> Self {
+        bytes.0
+    }
 }
 
-macro_rules! impl_all {
-    ($t: ty) => {
-        impl_type!($t);
-    };
-    ($t: ty, $($tt: ty),+) => {
-        impl_all!($t);
-        impl_all!($($tt), +);
-    };
-    (@$t: ty) => {
-        impl_type!(@$t);
-    };
-    (@$t: ty, $(@$tt: ty),+) => {
-        impl_all!(@$t);
-        impl_all!($(@$tt), +);
-    };
-    (^$t: ty) => {
-        impl_type!(^$t);
-    };
-    (^$t: ty, $(^$tt: ty),+) => {
-        impl_all!(^$t);
-        impl_all!($(^$tt), +);
-    };
-    (%$t: ty) => {
-        impl_type!(%$t);
-    };
-    (%$t: ty, $(%$tt: ty),+) => {
-        impl_all!(%$t);
-        impl_all!($(%$tt), +);
-    };
-    (~$t: ty) => {
-        impl_type!(~$t);
-    };
-    (~$t: ty, $(~$tt: ty),+) => {
-        impl_all!(~$t);
-        impl_all!($(~$tt), +);
-    };
-}
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
 
-impl_all!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
-impl_all!(
-    @i8, @i16, @i32, @i64, @i128, @isize, @u16, @u32, @u64, @u128, @usize
+// Without `serde`, the collections are stored as their own handle bytes
+vsdb_ende::impl_value_ende_by_bytes!(crate::basic::vecx_raw::VecxRaw);
+vsdb_ende::impl_value_ende_by_bytes!(
+    [K: KeyEnDeOrdered, V: ValueEnDe] crate::basic::mapx::Mapx<K, V>
 );
-impl_all!(
-    ^i8, ^i16, ^i32, ^i64, ^i128, ^isize, ^u16, ^u32, ^u64, ^u128, ^usize
+vsdb_ende::impl_value_ende_by_bytes!(
+    [K: KeyEnDeOrdered, V: ValueEnDe] crate::basic::mapx_ord::MapxOrd<K, V>
 );
-
-macro_rules! impl_array {
-    ($i: expr) => {
-        impl_type!(i8, $i);
-        impl_type!(i16, $i);
-        impl_type!(i32, $i);
-        impl_type!(i64, $i);
-        impl_type!(i128, $i);
-        impl_type!(isize, $i);
-        impl_type!(u8, $i);
-        impl_type!(u16, $i);
-        impl_type!(u32, $i);
-        impl_type!(u64, $i);
-        impl_type!(u128, $i);
-        impl_type!(usize, $i);
-    };
-    ($i: expr, $($ii: expr),+) => {
-        impl_array!($i);
-        impl_array!($($ii), +);
-    };
-}
-
-impl_array!(
-    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-    24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44,
-    45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65,
-    66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86,
-    87, 88, 89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99, 100, 101, 102, 103, 104, 105,
-    106, 107, 108, 109, 110, 111, 112, 113, 114, 115, 116, 117, 118, 119, 120, 121, 122,
-    123, 124, 125, 126, 127, 128
+vsdb_ende::impl_value_ende_by_bytes!([V: ValueEnDe] crate::basic::vecx::Vecx<V>);
+vsdb_ende::impl_value_ende_by_bytes!([V: ValueEnDe] crate::basic::orphan::Orphan<V>);
+vsdb_ende::impl_value_ende_by_bytes!(
+    [V: ValueEnDe] crate::basic::mapx_ord_rawkey::MapxOrdRawKey<V>
+);
+vsdb_ende::impl_value_ende_by_bytes!(
+    [K: KeyEnDeOrdered] crate::basic::mapx_ord_rawvalue::MapxOrdRawValue<K>
 );
 
 /////////////////////////////////////////////////////////////////////////////
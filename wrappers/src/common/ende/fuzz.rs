@@ -0,0 +1,205 @@
+//!
+//! # fuzz
+//!
+//! Generators and round-trip checks of the codecs,
+//! to be plugged into the existing fuzz harnesses, e.g. `cargo fuzz`.
+//!
+//! The values are generated by [Arbitrary] from the raw bytes given by the fuzzers,
+//! so any type implementing it, e.g. by `#[derive(Arbitrary)]`, can be checked,
+//! and `corpus` generates the seed inputs deterministically,
+//! so any failure can be reproduced by the same seed.
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::ende::fuzz::{corpus, fuzz_builtin, fuzz_key, fuzz_value};
+//!
+//! for data in corpus(7, 100, 64) {
+//!     fuzz_builtin(&data).unwrap();
+//!     fuzz_key::<u64>(&data).unwrap();
+//!     fuzz_value::<(u32, String)>(&data).unwrap();
+//! }
+//! ```
+//!
+
+pub use arbitrary::{self, Arbitrary, Unstructured};
+
+use super::{FixedBytes, KeyEnDe, KeyEnDeOrdered, OrderedF32, OrderedF64, ValueEnDe};
+use crate::common::RawBytes;
+use ruc::*;
+use std::fmt;
+
+// the ordered floats implement `Arbitrary` in `vsdb_ende`
+impl<'a, const N: usize> Arbitrary<'a> for FixedBytes<N> {
+    #[inline(always)]
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        <[u8; N]>::arbitrary(u).map(Self)
+    }
+
+    #[inline(always)]
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <[u8; N]>::size_hint(depth)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+/// Generate `cnt` inputs of at most `max_len` bytes,
+/// the same seed always generates the same inputs.
+pub fn corpus(seed: u64, cnt: usize, max_len: usize) -> Vec<RawBytes> {
+    let mut state = seed;
+    (0..cnt)
+        .map(|_| {
+            let n = splitmix64(&mut state) % (max_len as u64 + 1);
+            (0..n).map(|_| splitmix64(&mut state) as u8).collect()
+        })
+        .collect()
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+/// `K::decode(&key.encode()) == key`
+pub fn check_key_roundtrip<K: KeyEnDe + PartialEq + fmt::Debug>(key: &K) -> Result<()> {
+    let decoded = K::decode(&key.try_encode().c(d!())?).c(d!())?;
+    if &decoded != key {
+        return Err(eg!("key round-trip mismatch: {:?} => {:?}", key, decoded));
+    }
+    Ok(())
+}
+
+/// `V::decode(&value.encode()) == value`
+pub fn check_value_roundtrip<V: ValueEnDe + PartialEq + fmt::Debug>(
+    value: &V,
+) -> Result<()> {
+    let decoded = V::decode(&value.try_encode().c(d!())?).c(d!())?;
+    if &decoded != value {
+        return Err(eg!(
+            "value round-trip mismatch: {:?} => {:?}",
+            value,
+            decoded
+        ));
+    }
+    Ok(())
+}
+
+/// Both keys survive the round-trip,
+/// and their encoded bytes are in the same order as themselves.
+pub fn check_key_order<K: KeyEnDeOrdered>(a: &K, b: &K) -> Result<()> {
+    let (ea, eb) = (a.to_bytes(), b.to_bytes());
+    for (k, e) in [(a, &ea), (b, &eb)] {
+        let decoded = K::from_slice(e).c(d!())?;
+        if &decoded != k {
+            return Err(eg!("key round-trip mismatch: {:?} => {:?}", k, decoded));
+        }
+    }
+    if a.cmp(b) != ea.cmp(&eb) {
+        return Err(eg!("key order mismatch: {:?} vs {:?}", a, b));
+    }
+    Ok(())
+}
+
+/// Check the round-trip of a key generated from `data`,
+/// `data` itself is also decoded, which may fail but must not panic.
+///
+/// Nothing is checked if `data` is not enough to generate a key.
+pub fn fuzz_key<K>(data: &[u8]) -> Result<()>
+where
+    K: KeyEnDe + for<'a> Arbitrary<'a> + PartialEq + fmt::Debug,
+{
+    let _ = K::decode(data);
+    match Unstructured::new(data).arbitrary::<K>() {
+        Ok(k) => check_key_roundtrip(&k).c(d!()),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Check the round-trip of a value generated from `data`,
+/// `data` itself is also decoded, which may fail but must not panic.
+///
+/// Nothing is checked if `data` is not enough to generate a value.
+pub fn fuzz_value<V>(data: &[u8]) -> Result<()>
+where
+    V: ValueEnDe + for<'a> Arbitrary<'a> + PartialEq + fmt::Debug,
+{
+    let _ = V::decode(data);
+    match Unstructured::new(data).arbitrary::<V>() {
+        Ok(v) => check_value_roundtrip(&v).c(d!()),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Check the order of two keys generated from `data`,
+/// `data` itself is also decoded, which may fail but must not panic.
+///
+/// Nothing is checked if `data` is not enough to generate two keys.
+pub fn fuzz_ordered_key<K>(data: &[u8]) -> Result<()>
+where
+    K: KeyEnDeOrdered + for<'a> Arbitrary<'a>,
+{
+    let _ = K::from_slice(data);
+    let mut u = Unstructured::new(data);
+    match (u.arbitrary::<K>(), u.arbitrary::<K>()) {
+        (Ok(a), Ok(b)) => check_key_order(&a, &b).c(d!()),
+        _ => Ok(()),
+    }
+}
+
+/// Run all the checks on the builtin codecs.
+pub fn fuzz_builtin(data: &[u8]) -> Result<()> {
+    macro_rules! check {
+        ($f: ident, $($t: ty),+) => {
+            $($f::<$t>(data).c(d!("{}", stringify!($t)))?;)+
+        };
+    }
+
+    check!(
+        fuzz_ordered_key,
+        i8,
+        i32,
+        i128,
+        u16,
+        u64,
+        usize,
+        OrderedF32,
+        OrderedF64,
+        String,
+        RawBytes,
+        Box<[u8]>,
+        Vec<i64>,
+        Box<[u32]>,
+        [u8; 32],
+        [i16; 4],
+        FixedBytes<32>
+    );
+
+    check!(fuzz_key, i64, u64, String, RawBytes, FixedBytes<32>);
+    check!(fuzz_value, FixedBytes<32>);
+
+    #[cfg(feature = "serde_ende")]
+    check!(
+        fuzz_value,
+        bool,
+        i64,
+        u64,
+        String,
+        RawBytes,
+        Vec<u32>,
+        Option<u16>,
+        (u32, String)
+    );
+
+    #[cfg(not(feature = "serde_ende"))]
+    check!(fuzz_value, [u8; 32]);
+
+    Ok(())
+}
@@ -0,0 +1,214 @@
+//!
+//! # Logical export
+//!
+//! Back up a single instance without touching the others in the same base dir.
+//!
+//! The layout:
+//! - `[ MAGIC ][ FORMAT_VERSION ][ length of the codec name, u8 ][ codec name ]`
+//! - `[ TAG_ENTRY ][ key length, u32 ][ key ][ value length, u32 ][ value ]`, repeated
+//! - `[ TAG_END ][ number of the entries, u64 ][ crc32 of all the previous bytes, u32 ]`
+//!
+//! The integers are in big endian,
+//! the keys and the values are written as they are stored, in the order of the keys.
+//!
+
+use crate::common::{RawKey, RawValue};
+use crc32fast::Hasher;
+use ruc::*;
+use std::{
+    borrow::Cow,
+    io::{Read, Write},
+    iter,
+    ops::Bound,
+};
+use vsdb_core::{basic::mapx_raw::MapxRaw, vsdb_snapshot};
+
+const MAGIC: [u8; 8] = *b"VSDBEXP\0";
+const FORMAT_VERSION: u8 = 1;
+
+const TAG_ENTRY: u8 = 1;
+const TAG_END: u8 = 0;
+
+// The number of the entries read under one snapshot iterator
+const EXPORT_CHUNK_SIZE: usize = 1024;
+
+// The data can only be decoded by the same codec
+#[cfg(feature = "serde_ende")]
+const CODEC_NAME: &str = vsdb_ende::CODEC_NAME;
+#[cfg(not(feature = "serde_ende"))]
+const CODEC_NAME: &str = "";
+
+/// Write all the entries as of one snapshot,
+/// return the number of the exported entries.
+pub(crate) fn export(map: &MapxRaw, writer: impl Write) -> Result<u64> {
+    let mut w = CrcWriter {
+        inner: writer,
+        hasher: Hasher::new(),
+    };
+
+    w.write_all(&MAGIC).c(d!())?;
+    w.write_all(&[FORMAT_VERSION, CODEC_NAME.len() as u8])
+        .c(d!())?;
+    w.write_all(CODEC_NAME.as_bytes()).c(d!())?;
+
+    // The writes from other threads wait for the snapshot iterators,
+    // so the entries are read in chunks, and no iterator is held
+    // while writing to the writer, which may be arbitrarily slow
+    let snapshot = vsdb_snapshot();
+    let mut n = 0u64;
+    let mut last: Option<RawKey> = None;
+    loop {
+        let start = last
+            .take()
+            .map_or(Bound::Unbounded, |k| Bound::Excluded(Cow::Owned(k)));
+        let chunk = map
+            .range_with_snapshot(&snapshot, (start, Bound::Unbounded))
+            .take(EXPORT_CHUNK_SIZE)
+            .collect::<Vec<_>>();
+        let done = chunk.len() < EXPORT_CHUNK_SIZE;
+
+        for (k, v) in chunk {
+            w.write_all(&[TAG_ENTRY]).c(d!())?;
+            write_bytes(&mut w, &k).c(d!())?;
+            write_bytes(&mut w, &v).c(d!())?;
+            n += 1;
+            last = Some(k);
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    w.write_all(&[TAG_END]).c(d!())?;
+    w.write_all(&n.to_be_bytes()).c(d!())?;
+    let crc = w.hasher.clone().finalize();
+    w.inner.write_all(&crc.to_be_bytes()).c(d!())?;
+    w.inner.flush().c(d!())?;
+
+    Ok(n)
+}
+
+/// Load the exported entries into a new instance,
+/// the loaded entries are removed if the data is corrupted.
+pub(crate) fn import(reader: impl Read) -> Result<MapxRaw> {
+    let mut r = CrcReader {
+        inner: reader,
+        hasher: Hasher::new(),
+    };
+
+    let mut magic = [0; MAGIC.len()];
+    r.read_exact(&mut magic).c(d!())?;
+    if MAGIC != magic {
+        return Err(eg!("not an exported vsdb instance"));
+    }
+
+    let [version, codec_len] = read_array(&mut r).c(d!())?;
+    if FORMAT_VERSION != version {
+        return Err(eg!("unsupported format version: {}", version));
+    }
+
+    let mut codec = vec![0; codec_len as usize];
+    r.read_exact(&mut codec).c(d!())?;
+    if CODEC_NAME.as_bytes() != codec {
+        return Err(eg!(
+            "codec mismatch, exported: {}, found: {}",
+            String::from_utf8_lossy(&codec),
+            CODEC_NAME
+        ));
+    }
+
+    let mut err = None;
+    let entries = iter::from_fn(|| match read_entry(&mut r) {
+        Ok(entry) => entry,
+        Err(e) => {
+            err = Some(e);
+            None
+        }
+    });
+
+    let mut map = MapxRaw::new();
+    let ret = map.bulk_load_sorted(entries).c(d!()).and_then(|n| {
+        if let Some(e) = err {
+            return Err(e);
+        }
+        let cnt = u64::from_be_bytes(read_array(&mut r).c(d!())?);
+        let crc = r.hasher.clone().finalize();
+        let expected = u32::from_be_bytes(read_array(&mut r.inner).c(d!())?);
+        if crc != expected {
+            return Err(eg!("checksum mismatch"));
+        }
+        if cnt != n {
+            return Err(eg!("entry count mismatch, exported: {}, found: {}", cnt, n));
+        }
+        Ok(())
+    });
+
+    if let Err(e) = ret {
+        map.clear();
+        return Err(e);
+    }
+    Ok(map)
+}
+
+fn write_bytes(w: &mut impl Write, bytes: &[u8]) -> Result<()> {
+    let len = u32::try_from(bytes.len()).c(d!())?;
+    w.write_all(&len.to_be_bytes()).c(d!())?;
+    w.write_all(bytes).c(d!())
+}
+
+fn read_bytes(r: &mut impl Read) -> Result<Vec<u8>> {
+    let len = u32::from_be_bytes(read_array(r).c(d!())?);
+    let mut bytes = vec![0; len as usize];
+    r.read_exact(&mut bytes).c(d!())?;
+    Ok(bytes)
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> Result<[u8; N]> {
+    let mut buf = [0; N];
+    r.read_exact(&mut buf).c(d!())?;
+    Ok(buf)
+}
+
+// `None` means that the end tag has been read
+fn read_entry(r: &mut impl Read) -> Result<Option<(RawKey, RawValue)>> {
+    match read_array(r).c(d!())? {
+        [TAG_ENTRY] => {
+            let k = read_bytes(r).c(d!())?;
+            let v = read_bytes(r).c(d!())?;
+            Ok(Some((k, v)))
+        }
+        [TAG_END] => Ok(None),
+        [tag] => Err(eg!("invalid tag: {}", tag)),
+    }
+}
+
+struct CrcWriter<W> {
+    inner: W,
+    hasher: Hasher,
+}
+
+impl<W: Write> Write for CrcWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct CrcReader<R> {
+    inner: R,
+    hasher: Hasher,
+}
+
+impl<R: Read> Read for CrcReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
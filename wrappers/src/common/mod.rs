@@ -3,5 +3,6 @@
 //!
 
 pub mod ende;
+pub(crate) mod export;
 
 pub use vsdb_core::common::*;
@@ -1,5 +1,6 @@
 pub mod raw;
 pub mod rawkey;
+pub mod typed;
 
 pub type DagMapId = [u8];
 
@@ -14,7 +15,8 @@ pub fn gen_dag_map_id_num() -> u128 {
         meta_path.push("id_num");
 
         match fs::read(&meta_path) {
-            Ok(m) => Mutex::new(ValueEnDe::decode(&m).unwrap()),
+            Ok(m) => Mutex::new(pnk!(ValueEnDe::decode(&m)
+                .c(d!("corrupted ID file: {}", meta_path.display())))),
             Err(e) => match e.kind() {
                 ErrorKind::NotFound => {
                     let i = Orphan::new(0);
@@ -5,10 +5,15 @@ use crate::{DagMapId, MapxOrdRawKey, MapxRaw, Orphan};
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
     collections::HashSet,
-    ops::{Deref, DerefMut},
+    marker::PhantomData,
+    ops::{Bound, Deref, DerefMut},
+};
+use vsdb_core::{
+    basic::mapx_raw,
+    common::{PreBytes, RawBytes},
 };
-use vsdb_core::{basic::mapx_raw, common::RawBytes};
 
 type DagHead = DagMapRaw;
 
@@ -62,7 +67,16 @@ impl DagMapRaw {
         self.children.is_empty()
     }
 
+    #[inline(always)]
     pub fn get(&self, key: impl AsRef<[u8]>) -> Option<RawBytes> {
+        self.get_located(key).map(|(_, v)| v)
+    }
+
+    // Like `get`, but also return the prefix of the layer that holds the value
+    pub(crate) fn get_located(
+        &self,
+        key: impl AsRef<[u8]>,
+    ) -> Option<(PreBytes, RawBytes)> {
         let key = key.as_ref();
 
         let mut hdr = self;
@@ -70,7 +84,7 @@ impl DagMapRaw {
 
         loop {
             if let Some(v) = hdr.data.get(key) {
-                return alt!(v.is_empty(), None, Some(v));
+                return alt!(v.is_empty(), None, Some((hdr.ns(), v)));
             }
             if let Some(p) = hdr.parent.get_value() {
                 hdr_owned = p;
@@ -81,6 +95,31 @@ impl DagMapRaw {
         }
     }
 
+    /// Iterate over the merged view of this instance and all its ancestors,
+    /// the value in the nearest layer wins, and removed keys are skipped.
+    pub fn iter(&self) -> DagMapRawIter<'_> {
+        let mut layers = vec![unsafe { self.data.shadow() }];
+        let mut parent = self.parent.get_value();
+        while let Some(p) = parent {
+            layers.push(unsafe { p.data.shadow() });
+            parent = p.parent.get_value();
+        }
+
+        let heads = layers.iter().map(|l| l.iter().next()).collect();
+
+        DagMapRawIter {
+            layers,
+            heads,
+            _p: PhantomData,
+        }
+    }
+
+    // The prefix of the data of this layer
+    #[inline(always)]
+    pub(crate) fn ns(&self) -> PreBytes {
+        *self.data.as_prefix_slice()
+    }
+
     #[inline(always)]
     pub fn get_mut(&mut self, key: impl AsRef<[u8]>) -> Option<ValueMut<'_>> {
         self.data.get_mut(key.as_ref()).map(|inner| ValueMut {
@@ -103,6 +142,21 @@ impl DagMapRaw {
         self.data.insert(key.as_ref(), [])
     }
 
+    /// Apply the writes to this instance in one batch of the backend,
+    /// the `None` values remove the keys.
+    #[inline(always)]
+    pub fn write_batch<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        ops: impl IntoIterator<Item = (K, Option<V>)>,
+    ) {
+        // the removed keys are covered by empty values, like `remove`
+        let ops = ops.into_iter().collect::<Vec<_>>();
+        self.data.write_batch(
+            ops.iter()
+                .map(|(k, v)| (k, Some(v.as_ref().map_or(&[][..], |v| v.as_ref())))),
+        );
+    }
+
     /// Return the new head of mainline,
     /// all instances should have been committed!
     #[inline(always)]
@@ -213,6 +267,57 @@ impl DagMapRaw {
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
+pub struct DagMapRawIter<'a> {
+    // from the current instance to the genesis
+    layers: Vec<MapxRaw>,
+    // the next entry of each layer
+    heads: Vec<Option<(RawBytes, RawBytes)>>,
+    _p: PhantomData<&'a DagMapRaw>,
+}
+
+impl<'a> DagMapRawIter<'a> {
+    // Like `next`, but also return the prefix of the layer that holds the value
+    pub(crate) fn next_located(&mut self) -> Option<(PreBytes, RawBytes, RawBytes)> {
+        loop {
+            // `min_by_key` returns the first one on ties, that is the nearest layer
+            let (ns, (k, v)) = self
+                .layers
+                .iter()
+                .zip(self.heads.iter())
+                .filter_map(|(l, h)| h.as_ref().map(|h| (l, h)))
+                .min_by_key(|(_, (k, _))| k)
+                .map(|(l, h)| (*l.as_prefix_slice(), h.clone()))?;
+
+            for (layer, head) in self.layers.iter().zip(self.heads.iter_mut()) {
+                if matches!(head, Some((hk, _)) if hk == &k) {
+                    *head = layer
+                        .range((
+                            Bound::Excluded(Cow::Borrowed(&k[..])),
+                            Bound::Unbounded,
+                        ))
+                        .next();
+                }
+            }
+
+            if !v.is_empty() {
+                return Some((ns, k, v));
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for DagMapRawIter<'a> {
+    type Item = (RawBytes, RawBytes);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_located().map(|(_, k, v)| (k, v))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
 #[derive(Debug)]
 pub struct ValueMut<'a> {
     value: RawBytes,
@@ -93,3 +93,33 @@ fn dagmapraw_functions() {
         0u8.to_be_bytes()
     );
 }
+
+#[test]
+fn dagmapraw_iter() {
+    let mut i0 = DagMapRaw::new(&mut Orphan::new(None)).unwrap();
+    i0.insert("k0", "v0");
+    i0.insert("k1", "v1");
+    i0.insert("k3", "v3");
+    let mut i0 = Orphan::new(Some(i0));
+
+    let mut i1 = DagMapRaw::new(&mut i0).unwrap();
+    i1.insert("k1", "v1x");
+    i1.insert("k2", "v2");
+    i1.remove("k3");
+
+    let res = i1
+        .iter()
+        .map(|(k, v)| (String::from_utf8(k).unwrap(), String::from_utf8(v).unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        res,
+        vec![
+            ("k0".to_owned(), "v0".to_owned()),
+            ("k1".to_owned(), "v1x".to_owned()),
+            ("k2".to_owned(), "v2".to_owned()),
+        ]
+    );
+
+    // the parent is not affected by the overlay
+    assert_eq!(i0.get_value().unwrap().iter().count(), 3);
+}
@@ -1,7 +1,11 @@
 #[cfg(test)]
 mod test;
 
-use crate::{dagmap::raw, DagMapId, DagMapRaw, Orphan, ValueEnDe};
+use crate::{
+    common::{ende::decode_value, PreBytes, RawKey},
+    dagmap::raw,
+    DagMapId, DagMapRaw, Orphan, ValueEnDe,
+};
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -68,27 +72,59 @@ where
 
     #[inline(always)]
     pub fn get(&self, key: impl AsRef<[u8]>) -> Option<V> {
-        self.inner.get(key).map(|v| V::decode(&v).unwrap())
+        let key = key.as_ref();
+        self.inner
+            .get_located(key)
+            .map(|(ns, v)| decode_value(&ns, key, &v))
+    }
+
+    /// Iterate over the merged view of this instance and all its ancestors,
+    /// see [DagMapRaw::iter](crate::DagMapRaw::iter).
+    #[inline(always)]
+    pub fn iter(&self) -> DagMapRawKeyIter<'_, V> {
+        DagMapRawKeyIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
     }
 
     #[inline(always)]
     pub fn get_mut(&mut self, key: impl AsRef<[u8]>) -> Option<ValueMut<'_, V>> {
-        self.inner.get_mut(key.as_ref()).map(|inner| ValueMut {
-            value: <V as ValueEnDe>::decode(&inner).unwrap(),
+        let key = key.as_ref();
+        let ns = self.inner.ns();
+        self.inner.get_mut(key).map(|inner| ValueMut {
+            value: decode_value(&ns, key, &inner),
             inner,
         })
     }
 
     #[inline(always)]
     pub fn insert(&mut self, key: impl AsRef<[u8]>, value: &V) -> Option<V> {
+        let key = key.as_ref();
+        let ns = self.inner.ns();
         self.inner
             .insert(key, value.encode())
-            .map(|v| V::decode(&v).unwrap())
+            .map(|v| decode_value(&ns, key, &v))
     }
 
     #[inline(always)]
     pub fn remove(&mut self, key: impl AsRef<[u8]>) -> Option<V> {
-        self.inner.remove(key).map(|v| V::decode(&v).unwrap())
+        let key = key.as_ref();
+        let ns = self.inner.ns();
+        self.inner.remove(key).map(|v| decode_value(&ns, key, &v))
+    }
+
+    /// Apply the writes to this instance in one batch of the backend,
+    /// the `None` values remove the keys.
+    #[inline(always)]
+    pub fn write_batch<'a>(
+        &mut self,
+        ops: impl IntoIterator<Item = (impl AsRef<[u8]>, Option<&'a V>)>,
+    ) where
+        V: 'a,
+    {
+        self.inner
+            .write_batch(ops.into_iter().map(|(k, v)| (k, v.map(|v| v.encode()))));
     }
 
     /// Return the new head of mainline,
@@ -127,6 +163,39 @@ where
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
+pub struct DagMapRawKeyIter<'a, V> {
+    inner: raw::DagMapRawIter<'a>,
+    _p: PhantomData<V>,
+}
+
+impl<'a, V> DagMapRawKeyIter<'a, V>
+where
+    V: ValueEnDe,
+{
+    // Like `next`, but also return the prefix of the layer that holds the value
+    pub(crate) fn next_located(&mut self) -> Option<(PreBytes, RawKey, V)> {
+        self.inner.next_located().map(|(ns, k, v)| {
+            let v = decode_value(&ns, &k, &v);
+            (ns, k, v)
+        })
+    }
+}
+
+impl<'a, V> Iterator for DagMapRawKeyIter<'a, V>
+where
+    V: ValueEnDe,
+{
+    type Item = (RawKey, V);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_located().map(|(_, k, v)| (k, v))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
 #[derive(Debug)]
 pub struct ValueMut<'a, V>
 where
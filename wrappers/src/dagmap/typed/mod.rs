@@ -0,0 +1,154 @@
+#[cfg(test)]
+mod test;
+
+use crate::{
+    common::ende::decode_key,
+    dagmap::rawkey::{DagMapRawKeyIter, ValueMut},
+    DagMapId, DagMapRaw, DagMapRawKey, KeyEnDe, Orphan, ValueEnDe,
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+type DagHead<K, V> = DagMap<K, V>;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct DagMap<K, V> {
+    inner: DagMapRawKey<V>,
+    _p: PhantomData<K>,
+}
+
+impl<K, V> DagMap<K, V>
+where
+    K: KeyEnDe,
+    V: ValueEnDe,
+{
+    #[inline(always)]
+    pub fn new(raw_parent: &mut Orphan<Option<DagMapRaw>>) -> Result<Self> {
+        DagMapRawKey::new(raw_parent).c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> DagMapRaw {
+        self.inner.into_inner()
+    }
+
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow_inner(&self) -> DagMapRaw {
+        self.inner.shadow_inner()
+    }
+
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> DagMap<K, V> {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_dead(&self) -> bool {
+        self.inner.is_dead()
+    }
+
+    #[inline(always)]
+    pub fn no_children(&self) -> bool {
+        self.inner.no_children()
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.inner.get(key.encode())
+    }
+
+    #[inline(always)]
+    pub fn get_mut(&mut self, key: &K) -> Option<ValueMut<'_, V>> {
+        self.inner.get_mut(key.encode())
+    }
+
+    #[inline(always)]
+    pub fn insert(&mut self, key: &K, value: &V) -> Option<V> {
+        self.inner.insert(key.encode(), value)
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.inner.remove(key.encode())
+    }
+
+    /// Iterate over the merged view of this instance and all its ancestors,
+    /// see [DagMapRaw::iter](crate::DagMapRaw::iter).
+    #[inline(always)]
+    pub fn iter(&self) -> DagMapIter<'_, K, V> {
+        DagMapIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    /// Return the new head of mainline,
+    /// all instances should have been committed!
+    #[inline(always)]
+    pub fn prune(self) -> Result<DagHead<K, V>> {
+        self.inner.prune().c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    /// Drop children that are in the `targets` list
+    #[inline(always)]
+    pub fn prune_children_include(&mut self, include_targets: &[impl AsRef<DagMapId>]) {
+        self.inner.prune_children_include(include_targets);
+    }
+
+    /// Drop children that are not in the `exclude_targets` list
+    #[inline(always)]
+    pub fn prune_children_exclude(&mut self, exclude_targets: &[impl AsRef<DagMapId>]) {
+        self.inner.prune_children_exclude(exclude_targets);
+    }
+
+    #[inline(always)]
+    pub fn destroy(&mut self) {
+        self.inner.destroy();
+    }
+
+    #[inline(always)]
+    pub fn is_the_same_instance(&self, other_hdr: &Self) -> bool {
+        self.inner.is_the_same_instance(&other_hdr.inner)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct DagMapIter<'a, K, V> {
+    inner: DagMapRawKeyIter<'a, V>,
+    _p: PhantomData<K>,
+}
+
+impl<'a, K, V> Iterator for DagMapIter<'a, K, V>
+where
+    K: KeyEnDe,
+    V: ValueEnDe,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_located()
+            .map(|(ns, k, v)| (decode_key(&ns, &k), v))
+    }
+}
@@ -0,0 +1,32 @@
+use super::*;
+
+#[test]
+fn dagmap_functions() {
+    let mut i0 = DagMap::<u32, String>::new(&mut Orphan::new(None)).unwrap();
+    i0.insert(&0, &"v0".to_owned());
+    i0.insert(&1, &"v1".to_owned());
+    assert_eq!(i0.get(&0).unwrap(), "v0");
+    assert!(i0.get(&2).is_none());
+    let mut i0_raw = Orphan::new(Some(i0.into_inner()));
+
+    let mut i1 = DagMap::<u32, String>::new(&mut i0_raw).unwrap();
+    i1.insert(&2, &"v2".to_owned());
+    assert!(i1.get_mut(&1).is_none()); // not in the local layer
+    i1.insert(&1, &"v1".to_owned());
+    *i1.get_mut(&1).unwrap() = "v1x".to_owned();
+    i1.remove(&0);
+    assert!(i1.get(&0).is_none());
+    assert_eq!(i1.get(&1).unwrap(), "v1x");
+    assert_eq!(i1.get(&2).unwrap(), "v2");
+
+    assert_eq!(
+        i1.iter().collect::<Vec<_>>(),
+        vec![(1, "v1x".to_owned()), (2, "v2".to_owned())]
+    );
+
+    let head = pnk!(i1.prune());
+    assert_eq!(
+        head.iter().collect::<Vec<_>>(),
+        vec![(1, "v1x".to_owned()), (2, "v2".to_owned())]
+    );
+}
@@ -0,0 +1,162 @@
+//!
+//! A safe alternative of the `shadow` pattern for concurrent readers.
+//!
+//! A single `WriteHandle` owns all mutations, and any number of `ReadHandle`s
+//! observe the snapshot published by the last `publish()`.
+//!
+//! Like `evmap`, two copies of the inner structure are kept:
+//! - the writer applies its operations on the pending copy, and logs them
+//! - `publish` swaps the two copies, and replays the log on the old one
+//!
+//! NOTE:
+//! - The inner structure will be cloned once at creation, that is a full copy for vsdb structures
+//! - Operations will be applied twice, so they must be deterministic
+//! - `publish` will wait for the readers that still hold a guard of the old copy
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::{handle::WriteHandle, Mapx};
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut w = WriteHandle::new(Mapx::<u32, u32>::new());
+//! let r = w.reader();
+//!
+//! w.write(|m| {
+//!     m.insert(&1, &1);
+//! });
+//! assert!(r.read().get(&1).is_none());
+//!
+//! w.publish();
+//! assert_eq!(r.read().get(&1), Some(1));
+//! ```
+//!
+
+#[cfg(test)]
+mod test;
+
+use parking_lot::{RwLock, RwLockReadGuard};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+type Op<T> = Box<dyn FnMut(&mut T) + Send>;
+
+struct Shared<T> {
+    copies: [RwLock<T>; 2],
+    // index of the published copy
+    active: AtomicUsize,
+}
+
+/// The only entry for mutations.
+pub struct WriteHandle<T> {
+    shared: Arc<Shared<T>>,
+    // operations that have not been applied on the published copy
+    oplog: Vec<Op<T>>,
+}
+
+impl<T: Clone> WriteHandle<T> {
+    #[inline(always)]
+    pub fn new(inner: T) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                copies: [RwLock::new(inner.clone()), RwLock::new(inner)],
+                active: AtomicUsize::new(0),
+            }),
+            oplog: vec![],
+        }
+    }
+}
+
+impl<T> WriteHandle<T> {
+    /// Create a new reader of the published snapshot.
+    #[inline(always)]
+    pub fn reader(&self) -> ReadHandle<T> {
+        ReadHandle {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Apply an operation on the pending copy,
+    /// it will be visible to the readers after the next `publish`.
+    pub fn write(&mut self, mut op: impl FnMut(&mut T) + Send + 'static) {
+        op(&mut self.shared.copies[self.pending_idx()].write());
+        self.oplog.push(Box::new(op));
+    }
+
+    /// Read the pending copy, that is the view of the writer.
+    #[inline(always)]
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.shared.copies[self.pending_idx()].read()
+    }
+
+    /// Make all written operations visible to the readers.
+    pub fn publish(&mut self) {
+        let pending = self.pending_idx();
+        self.shared.active.store(pending, Ordering::Release);
+
+        // wait for the readers of the old copy
+        let mut old = self.shared.copies[1 - pending].write();
+        self.oplog.drain(..).for_each(|mut op| op(&mut old));
+    }
+
+    /// The number of operations that have not been published.
+    #[inline(always)]
+    pub fn pending_cnt(&self) -> usize {
+        self.oplog.len()
+    }
+
+    #[inline(always)]
+    fn pending_idx(&self) -> usize {
+        1 - self.shared.active.load(Ordering::Acquire)
+    }
+}
+
+impl<T> fmt::Debug for WriteHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteHandle")
+            .field("pending_cnt", &self.oplog.len())
+            .finish()
+    }
+}
+
+/// A cheap cloneable observer of the published snapshot.
+pub struct ReadHandle<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> ReadHandle<T> {
+    /// Read the published snapshot,
+    /// it stays consistent as long as the guard is held.
+    #[inline(always)]
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        loop {
+            let idx = self.shared.active.load(Ordering::Acquire);
+            let guard = self.shared.copies[idx].read();
+            // the writer may have switched the copies before we got the lock
+            if idx == self.shared.active.load(Ordering::Acquire) {
+                return guard;
+            }
+        }
+    }
+}
+
+impl<T> Clone for ReadHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> fmt::Debug for ReadHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadHandle").finish()
+    }
+}
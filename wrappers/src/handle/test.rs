@@ -0,0 +1,74 @@
+use super::*;
+use crate::{DagMapRaw, Mapx, Orphan};
+use std::thread;
+
+#[test]
+fn handle_publish() {
+    let mut w = WriteHandle::new(Mapx::<u64, u64>::new());
+    let r = w.reader();
+
+    (0..100).for_each(|i| {
+        w.write(move |m| {
+            m.insert(&i, &i);
+        });
+    });
+    assert_eq!(100, w.pending_cnt());
+    assert_eq!(100, w.read().len());
+    assert!(r.read().is_empty());
+
+    w.publish();
+    assert_eq!(0, w.pending_cnt());
+    assert_eq!(100, r.read().len());
+    assert_eq!(100, w.read().len());
+
+    w.write(|m| {
+        m.remove(&0);
+    });
+    assert_eq!(Some(0), r.read().get(&0));
+    w.publish();
+    assert!(r.read().get(&0).is_none());
+    assert!(w.read().get(&0).is_none());
+}
+
+#[test]
+fn handle_concurrent_readers() {
+    let mut w = WriteHandle::new(Orphan::new(0u64));
+
+    let readers = (0..4)
+        .map(|_| {
+            let r = w.reader();
+            thread::spawn(move || {
+                let mut last = 0;
+                (0..1000).for_each(|_| {
+                    let v = r.read().get_value();
+                    // never go backwards, always even
+                    assert!(last <= v);
+                    assert_eq!(0, v % 2);
+                    last = v;
+                });
+            })
+        })
+        .collect::<Vec<_>>();
+
+    (0..100).for_each(|_| {
+        w.write(|o| *o.get_mut() += 1);
+        w.write(|o| *o.get_mut() += 1);
+        w.publish();
+    });
+
+    readers.into_iter().for_each(|r| r.join().unwrap());
+    assert_eq!(200, w.reader().read().get_value());
+}
+
+#[test]
+fn handle_dagmap() {
+    let mut w = WriteHandle::new(DagMapRaw::new(&mut Orphan::new(None)).unwrap());
+    let r = w.reader();
+
+    w.write(|d| {
+        d.insert("k", "v");
+    });
+    assert!(r.read().get("k").is_none());
+    w.publish();
+    assert_eq!(r.read().get("k").unwrap(), b"v");
+}
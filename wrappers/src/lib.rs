@@ -7,17 +7,43 @@ pub mod basic;
 pub mod basic_multi_key;
 pub mod common;
 pub mod dagmap;
+pub mod handle;
+pub mod migrate;
 
 pub use basic::{
-    mapx::Mapx, mapx_ord::MapxOrd, mapx_ord_rawkey::MapxOrdRawKey,
-    mapx_ord_rawvalue::MapxOrdRawValue, orphan::Orphan, vecx::Vecx, vecx_raw::VecxRaw,
+    config::ConfigStore, logx::Logx, mapx::Mapx, mapx_ord::MapxOrd,
+    mapx_ord_rawkey::MapxOrdRawKey, mapx_ord_rawvalue::MapxOrdRawValue, orphan::Orphan,
+    vecx::Vecx, vecx_raw::VecxRaw,
 };
 
-pub use dagmap::{raw::DagMapRaw, rawkey::DagMapRawKey, DagMapId};
+pub use handle::{ReadHandle, WriteHandle};
+
+pub use dagmap::{raw::DagMapRaw, rawkey::DagMapRawKey, typed::DagMap, DagMapId};
 
 pub use common::{
-    ende::{KeyDe, KeyEn, KeyEnDe, KeyEnDeOrdered, ValueDe, ValueEn, ValueEnDe},
+    ende::{
+        DecodeContext, DecodeError, FixedBytes, KeyDe, KeyEn, KeyEnDe, KeyEnDeOrdered,
+        OrderedF32, OrderedF64, ValueDe, ValueEn, ValueEnDe,
+    },
     NULL,
 };
 
+#[cfg(feature = "serde_ende")]
+pub use common::ende::{Evolving, SchemaEvolve};
+
+#[cfg(feature = "fuzz")]
+pub mod ende {
+    //! The fuzz helpers of the codecs, see [fuzz](crate::common::ende::fuzz).
+    pub use crate::common::ende::fuzz;
+}
+
+#[cfg(feature = "derive")]
+pub use vsdb_derive::VsConfig;
+
+// used by the code generated by the derive macros
+#[doc(hidden)]
+pub use serde;
+
 pub use vsdb_core::{self, *};
+
+pub use vsdb_ende;
@@ -0,0 +1,194 @@
+//!
+//! # Key migrations
+//!
+//! Re-key a structure for schema changes, e.g. widening an ID type,
+//! all entries are streamed into a new instance with the transformed keys.
+//!
+//! The source is left untouched until the new instance has been fully written:
+//! - On success, the source is cleared and the new instance is returned
+//! - On failure, e.g. two old keys are mapped to the same new key,
+//!   the partially written instance is cleared and the source keeps all its data
+//!
+//! The progress is recorded in a journal on disk, keyed by the source instance,
+//! so an interrupted migration, e.g. by a crash, is resolved by the next call
+//! on the same source:
+//! - If the copying had not finished, the partial instance is cleared
+//!   and the migration starts over
+//! - If the copying had finished, the source is cleared
+//!   and the recorded new instance is returned, `f` is not called again
+//!
+//! NOTE:
+//! - The values are copied as raw bytes, they are not decoded
+//! - Until the next call, an interrupted migration keeps both the source
+//!   and the partial or complete new instance on disk
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::{migrate::migrate_keys, MapxOrd};
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut old: MapxOrd<u32, String> = MapxOrd::new();
+//! old.insert(&1, &"a".to_owned());
+//!
+//! let new: MapxOrd<u64, String> = migrate_keys(&mut old, |k| k as u64).unwrap();
+//!
+//! assert_eq!(new.get(&1).unwrap(), "a");
+//! assert!(old.is_empty());
+//! ```
+//!
+
+use crate::{
+    basic::mapx_ord_rawkey::MapxOrdRawKey,
+    common::{
+        ende::{KeyEnDe, KeyEnDeOrdered, ValueEnDe},
+        RawKey,
+    },
+    Mapx, MapxOrd,
+};
+use parking_lot::Mutex;
+use ruc::*;
+use std::sync::LazyLock;
+use vsdb_core::{vsdb_with_namespace_label, MapxRaw, SizeLimits};
+
+// The journal has a fixed prefix derived from this label,
+// so it is found again after restarting
+const JOURNAL_LABEL: &str = "vsdb::migrate::journal";
+
+// source instance => [ new instance ][ phase ]
+static JOURNAL: LazyLock<Mutex<MapxRaw>> =
+    LazyLock::new(|| Mutex::new(vsdb_with_namespace_label(JOURNAL_LABEL, MapxRaw::new)));
+
+const PHASE_COPYING: u8 = 0;
+const PHASE_COPIED: u8 = 1;
+
+/// Re-key a `MapxOrd`, see the module docs for details.
+pub fn migrate_keys<K, K2, V>(
+    map: &mut MapxOrd<K, V>,
+    mut f: impl FnMut(K) -> K2,
+) -> Result<MapxOrd<K2, V>>
+where
+    K: KeyEnDeOrdered,
+    K2: KeyEnDeOrdered,
+    V: ValueEnDe,
+{
+    let limits = map.size_limits();
+    migrate(map.as_bytes(), limits, |k| {
+        K::from_bytes(k).c(d!()).map(|k| f(k).to_bytes())
+    })
+    .map(|new| {
+        let mut new = unsafe { MapxOrd::from_bytes(new.as_bytes()) };
+        new.set_size_limits(limits);
+        new
+    })
+}
+
+/// Re-key a `Mapx`, see the module docs for details.
+pub fn migrate_mapx_keys<K, K2, V>(
+    map: &mut Mapx<K, V>,
+    mut f: impl FnMut(K) -> K2,
+) -> Result<Mapx<K2, V>>
+where
+    K: KeyEnDe,
+    K2: KeyEnDe,
+    V: ValueEnDe,
+{
+    let limits = map.size_limits();
+    migrate(map.as_bytes(), limits, |k| {
+        <K as KeyEnDe>::decode(&k).c(d!()).map(|k| f(k).encode())
+    })
+    .map(|new| {
+        let mut new = unsafe { Mapx::from_bytes(new.as_bytes()) };
+        new.set_size_limits(limits);
+        new
+    })
+}
+
+/// Re-key a `MapxOrdRawKey`, see the module docs for details,
+/// the transformation can fail, the error will be returned as is.
+pub fn migrate_raw_keys<V>(
+    map: &mut MapxOrdRawKey<V>,
+    f: impl FnMut(RawKey) -> Result<RawKey>,
+) -> Result<MapxOrdRawKey<V>>
+where
+    V: ValueEnDe,
+{
+    let limits = map.size_limits();
+    migrate(map.as_bytes(), limits, f).map(|new| {
+        let mut new = unsafe { MapxOrdRawKey::from_bytes(new.as_bytes()) };
+        new.set_size_limits(limits);
+        new
+    })
+}
+
+// The caller holds the source mutably, so nothing else writes to it
+fn migrate(
+    src: &[u8],
+    limits: SizeLimits,
+    mut f: impl FnMut(RawKey) -> Result<RawKey>,
+) -> Result<MapxRaw> {
+    let mut src = unsafe { MapxRaw::from_bytes(src) };
+
+    let rec = JOURNAL.lock().get(src.as_bytes());
+    if let Some(rec) = rec {
+        let (new, phase) = rec.split_at(rec.len() - 1);
+        let mut new = unsafe { MapxRaw::from_bytes(new) };
+        if PHASE_COPIED == phase[0] {
+            return finish(&mut src, new).c(d!());
+        }
+        // roll back the interrupted copying
+        new.try_clear().map_err(|e| eg!(e))?;
+        forget(&src).c(d!())?;
+    }
+
+    let mut new = MapxRaw::new();
+    new.set_size_limits(limits);
+    record(&src, &new, PHASE_COPYING).c(d!())?;
+
+    let copied = src.iter().try_for_each(|(k, v)| {
+        let k = f(k).c(d!())?;
+        if new.contains_key(&k) {
+            return Err(eg!("key collision: {:?}", k));
+        }
+        new.try_insert(k, v).map(|_| ()).map_err(|e| eg!(e))
+    });
+
+    match copied {
+        Ok(()) => {
+            record(&src, &new, PHASE_COPIED).c(d!())?;
+            finish(&mut src, new).c(d!())
+        }
+        Err(e) => {
+            // the journal is kept if this fails, the next call will clear it again
+            new.try_clear().map_err(|e| eg!(e)).c(d!())?;
+            forget(&src).c(d!())?;
+            Err(e)
+        }
+    }
+}
+
+fn finish(src: &mut MapxRaw, new: MapxRaw) -> Result<MapxRaw> {
+    src.try_clear().map_err(|e| eg!(e))?;
+    forget(src).c(d!())?;
+    Ok(new)
+}
+
+fn record(src: &MapxRaw, new: &MapxRaw, phase: u8) -> Result<()> {
+    let mut rec = new.as_bytes().to_vec();
+    rec.push(phase);
+    JOURNAL
+        .lock()
+        .try_insert(src.as_bytes(), rec)
+        .map(|_| ())
+        .map_err(|e| eg!(e))
+}
+
+fn forget(src: &MapxRaw) -> Result<()> {
+    JOURNAL
+        .lock()
+        .try_remove(src.as_bytes())
+        .map(|_| ())
+        .map_err(|e| eg!(e))
+}
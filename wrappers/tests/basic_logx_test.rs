@@ -0,0 +1,36 @@
+use ruc::*;
+use vsdb::{basic::logx::Logx, vsdb_set_base_dir, ValueEnDe};
+
+#[test]
+fn basic_cases() {
+    let cnt = 1000;
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let hdr = {
+        let mut hdr = Logx::new_with_crc();
+        (0..cnt).for_each(|i: u64| {
+            assert_eq!(i, hdr.append(i.to_be_bytes()));
+        });
+        <Logx as ValueEnDe>::encode(&hdr)
+    };
+
+    let mut reloaded = pnk!(<Logx as ValueEnDe>::decode(&hdr));
+    assert!(reloaded.has_crc());
+    assert_eq!(cnt, reloaded.len());
+    assert_eq!(0, pnk!(reloaded.recover()));
+
+    reloaded
+        .replay(cnt / 2)
+        .map(|r| pnk!(r))
+        .zip(cnt / 2..)
+        .for_each(|((i, r), j)| {
+            assert_eq!(i, j);
+            assert_eq!(&r[..], &j.to_be_bytes()[..]);
+        });
+
+    pnk!(reloaded.truncate(cnt / 3));
+    assert_eq!(cnt / 3, reloaded.iter().count() as u64);
+}
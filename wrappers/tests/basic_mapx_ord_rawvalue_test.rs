@@ -5,7 +5,7 @@ use vsdb::{basic::mapx_ord_rawvalue::MapxOrdRawValue, vsdb_set_base_dir, ValueEn
 #[test]
 fn basic_cases() {
     let cnt = 200;
-    info_omit!(vsdb_set_base_dir(&format!(
+    info_omit!(vsdb_set_base_dir(format!(
         "/tmp/vsdb_testing/{}",
         rand::random::<u64>()
     )));
@@ -21,14 +21,14 @@ fn basic_cases() {
         (0usize..cnt)
             .map(|i| (i, i.to_be_bytes()))
             .for_each(|(i, b)| {
-                hdr_i.entry(i).or_insert(b.clone());
-                assert_eq!(1 + i as usize, hdr_i.len());
+                hdr_i.entry(i).or_insert(b);
+                assert_eq!(1 + i, hdr_i.len());
                 assert_eq!(&hdr_i.get(&i).unwrap()[..], &b[..]);
                 assert_eq!(&hdr_i.remove(&i).unwrap()[..], &b);
-                assert_eq!(i as usize, hdr_i.len());
+                assert_eq!(i, hdr_i.len());
                 assert!(hdr_i.get(&i).is_none());
-                assert!(hdr_i.insert(&i, &b).is_none());
-                assert!(hdr_i.insert(&i, &b).is_some());
+                assert!(hdr_i.insert(&i, b).is_none());
+                assert!(hdr_i.insert(&i, b).is_some());
             });
 
         assert_eq!(cnt, hdr_i.len());
@@ -56,10 +56,10 @@ fn basic_cases() {
     reloaded.clear();
     assert!(reloaded.is_empty());
 
-    reloaded.insert(&1, &1usize.to_be_bytes());
-    reloaded.insert(&10, &10usize.to_be_bytes());
-    reloaded.insert(&100, &100usize.to_be_bytes());
-    reloaded.insert(&1000, &1000usize.to_be_bytes());
+    reloaded.insert(&1, 1usize.to_be_bytes());
+    reloaded.insert(&10, 10usize.to_be_bytes());
+    reloaded.insert(&100, 100usize.to_be_bytes());
+    reloaded.insert(&1000, 1000usize.to_be_bytes());
 
     assert!(reloaded.range(&0..&1).next().is_none());
 
@@ -97,4 +97,13 @@ fn basic_cases() {
         &100usize.to_be_bytes()[..],
         &reloaded.get_le(&101).unwrap().1[..]
     );
+
+    assert_eq!(
+        &100usize.to_be_bytes()[..],
+        &reloaded.get_gt(&99).unwrap().1[..]
+    );
+    assert_eq!(
+        &100usize.to_be_bytes()[..],
+        &reloaded.get_lt(&101).unwrap().1[..]
+    );
 }
@@ -19,7 +19,7 @@ fn gen_sample(idx: usize) -> SampleBlock {
 #[test]
 fn basic_cases() {
     let cnt = 200;
-    info_omit!(vsdb_set_base_dir(&format!(
+    info_omit!(vsdb_set_base_dir(format!(
         "/tmp/vsdb_testing/{}",
         rand::random::<u64>()
     )));
@@ -34,10 +34,10 @@ fn basic_cases() {
 
         (0..cnt).map(|i| (i, gen_sample(i))).for_each(|(i, b)| {
             hdr_i.entry(&i).or_insert(b.clone());
-            assert_eq!(1 + i as usize, hdr_i.len());
+            assert_eq!(1 + i, hdr_i.len());
             assert_eq!(pnk!(hdr_i.get(&i)).idx, i);
             assert_eq!(hdr_i.remove(&i), Some(b.clone()));
-            assert_eq!(i as usize, hdr_i.len());
+            assert_eq!(i, hdr_i.len());
             assert!(hdr_i.get(&i).is_none());
             assert!(hdr_i.insert(&i, &b).is_none());
             assert!(hdr_i.insert(&i, &b).is_some());
@@ -89,6 +89,11 @@ fn basic_cases() {
     assert_eq!(100, reloaded.get_ge(&100).unwrap().1.idx);
     assert_eq!(100, reloaded.get_le(&100).unwrap().1.idx);
     assert_eq!(100, reloaded.get_le(&101).unwrap().1.idx);
+
+    assert_eq!(1000, reloaded.get_gt(&100).unwrap().1.idx);
+    assert!(reloaded.get_gt(&1000).is_none());
+    assert_eq!(10, reloaded.get_lt(&100).unwrap().1.idx);
+    assert!(reloaded.get_lt(&1).is_none());
 }
 
 #[test]
@@ -3,7 +3,7 @@ use vsdb::{basic_multi_key::mapx_double_key::MapxDk, vsdb_set_base_dir};
 
 #[test]
 fn basic_cases() {
-    info_omit!(vsdb_set_base_dir(&format!(
+    info_omit!(vsdb_set_base_dir(format!(
         "/tmp/vsdb_testing/{}",
         rand::random::<u64>()
     )));
@@ -5,7 +5,7 @@ const NIL: &[u8] = &[];
 
 #[test]
 fn basic_cases() {
-    info_omit!(vsdb_set_base_dir(&format!(
+    info_omit!(vsdb_set_base_dir(format!(
         "/tmp/vsdb_testing/{}",
         rand::random::<u64>()
     )));
@@ -106,6 +106,5 @@ fn basic_cases() {
     // cnt += 0
     pnk!(map.iter_op_with_key_prefix(&mut op, &[&[111], &[12], &[13], &[15]]));
 
-    drop(op);
     assert_eq!(cnt, 10);
 }
@@ -8,7 +8,7 @@ fn gen_sample(idx: usize) -> RawBytes {
 #[test]
 fn basic_cases() {
     let cnt = 200;
-    info_omit!(vsdb_set_base_dir(&format!(
+    info_omit!(vsdb_set_base_dir(format!(
         "/tmp/vsdb_testing/{}",
         rand::random::<u64>()
     )));
@@ -23,8 +23,8 @@ fn basic_cases() {
 
         (0..cnt).map(|i| (i, gen_sample(i))).for_each(|(i, b)| {
             hdr.push(&b);
-            assert_eq!(1 + i as usize, hdr.len());
-            assert_eq!(pnk!(hdr.get(i as usize)), b);
+            assert_eq!(1 + i, hdr.len());
+            assert_eq!(pnk!(hdr.get(i)), b);
             assert_eq!(pnk!(hdr.last()), b);
         });
 
@@ -41,7 +41,7 @@ fn basic_cases() {
 
     assert_eq!(cnt, reloaded.len());
 
-    reloaded.update(0, &gen_sample(100 * cnt)).unwrap();
+    reloaded.update(0, gen_sample(100 * cnt)).unwrap();
     assert_eq!(cnt, reloaded.len());
     *reloaded.get_mut(0).unwrap() = gen_sample(999 * cnt);
     assert_eq!(reloaded.get(0).unwrap(), gen_sample(999 * cnt));
@@ -55,29 +55,29 @@ fn basic_cases() {
 
 #[test]
 fn write() {
-    info_omit!(vsdb_set_base_dir(&format!(
+    info_omit!(vsdb_set_base_dir(format!(
         "/tmp/vsdb_testing/{}",
         rand::random::<u64>()
     )));
 
     let mut hdr = VecxRaw::new();
 
-    hdr.insert(0, &gen_sample(0));
+    hdr.insert(0, gen_sample(0));
     assert_eq!(1, hdr.len());
-    hdr.insert(0, &gen_sample(0));
+    hdr.insert(0, gen_sample(0));
     assert_eq!(2, hdr.len());
 
-    hdr.update(0, &gen_sample(1));
+    hdr.update(0, gen_sample(1));
     assert_eq!(gen_sample(1), hdr.get(0).unwrap());
-    hdr.update(1, &gen_sample(1));
+    hdr.update(1, gen_sample(1));
     assert_eq!(gen_sample(1), hdr.get(1).unwrap());
 
-    hdr.push(&gen_sample(2));
+    hdr.push(gen_sample(2));
     assert_eq!(gen_sample(1), hdr.swap_remove(0));
     assert_eq!(2, hdr.len());
     assert_eq!(gen_sample(2), hdr.get(0).unwrap());
 
-    hdr.push(&gen_sample(3));
+    hdr.push(gen_sample(3));
     assert_eq!(gen_sample(2), hdr.remove(0));
     assert_eq!(2, hdr.len());
     assert_eq!(gen_sample(3), hdr.get(1).unwrap());
@@ -18,7 +18,7 @@ fn gen_sample(idx: usize) -> SampleBlock {
 #[test]
 fn basic_cases() {
     let cnt = 200;
-    info_omit!(vsdb_set_base_dir(&format!(
+    info_omit!(vsdb_set_base_dir(format!(
         "/tmp/vsdb_testing/{}",
         rand::random::<u64>()
     )));
@@ -32,8 +32,8 @@ fn basic_cases() {
 
         (0..cnt).map(|i| (i, gen_sample(i))).for_each(|(i, b)| {
             hdr.push(&b);
-            assert_eq!(1 + i as usize, hdr.len());
-            assert_eq!(pnk!(hdr.get(i as usize)), b);
+            assert_eq!(1 + i, hdr.len());
+            assert_eq!(pnk!(hdr.get(i)), b);
             assert_eq!(pnk!(hdr.last()), b);
         });
 
@@ -64,7 +64,7 @@ fn basic_cases() {
 
 #[test]
 fn write() {
-    info_omit!(vsdb_set_base_dir(&format!(
+    info_omit!(vsdb_set_base_dir(format!(
         "/tmp/vsdb_testing/{}",
         rand::random::<u64>()
     )));
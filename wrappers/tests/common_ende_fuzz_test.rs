@@ -0,0 +1,34 @@
+#![cfg(feature = "fuzz")]
+
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use vsdb::ende::fuzz::{corpus, fuzz_builtin, fuzz_value, Arbitrary, Unstructured};
+
+#[derive(Serialize, Deserialize, Arbitrary, Debug, PartialEq)]
+struct Account {
+    balance: u64,
+    name: String,
+    tags: Vec<u16>,
+}
+
+#[test]
+fn builtin_codecs() {
+    assert_eq!(corpus(1, 10, 32), corpus(1, 10, 32));
+    assert_ne!(corpus(1, 10, 32), corpus(2, 10, 32));
+
+    for data in corpus(0, 500, 256) {
+        pnk!(fuzz_builtin(&data));
+    }
+    pnk!(fuzz_builtin(&[]));
+}
+
+#[test]
+fn custom_types() {
+    for data in corpus(0, 500, 256) {
+        pnk!(fuzz_value::<Account>(&data));
+    }
+
+    let mut u = Unstructured::new(&[1, 2]);
+    assert_eq!(0x0201, pnk!(u.arbitrary::<u16>()));
+    assert!(u.is_empty());
+}
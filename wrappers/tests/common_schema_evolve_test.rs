@@ -0,0 +1,82 @@
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use vsdb::{vsdb_set_base_dir, Evolving, Mapx, SchemaEvolve, ValueEnDe};
+
+#[derive(Serialize, Deserialize, Default, Debug, Eq, PartialEq, Clone)]
+struct AccountV1 {
+    balance: u64,
+}
+
+impl SchemaEvolve for AccountV1 {
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn migrate(old_version: u32, _bytes: &[u8]) -> Result<Self> {
+        Err(eg!("unknown schema version: {}", old_version))
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Eq, PartialEq, Clone)]
+struct AccountV2 {
+    balance: u64,
+    nonce: u64,
+}
+
+impl SchemaEvolve for AccountV2 {
+    const SCHEMA_VERSION: u32 = 2;
+
+    fn migrate(old_version: u32, bytes: &[u8]) -> Result<Self> {
+        match old_version {
+            // the values written before adopting `Evolving` have the V1 layout
+            0 | 1 => {
+                let old = <AccountV1 as ValueEnDe>::decode(bytes).c(d!())?;
+                Ok(Self {
+                    balance: old.balance,
+                    nonce: 0,
+                })
+            }
+            v => Err(eg!("unknown schema version: {}", v)),
+        }
+    }
+}
+
+#[test]
+fn schema_evolve() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut old = Mapx::<u32, Evolving<AccountV1>>::new();
+    (0..10u32).for_each(|i| {
+        old.insert(&i, &AccountV1 { balance: i as u64 }.into());
+    });
+
+    let mut new = pnk!(Mapx::<u32, Evolving<AccountV2>>::decode(&old.encode()));
+    (0..10u32).for_each(|i| {
+        let v = pnk!(new.get(&i));
+        assert_eq!(i as u64, v.balance);
+        assert_eq!(0, v.nonce);
+    });
+
+    // rewritten in the new layout
+    new.insert(
+        &0,
+        &AccountV2 {
+            balance: 100,
+            nonce: 1,
+        }
+        .into(),
+    );
+    assert_eq!(1, pnk!(new.get(&0)).nonce);
+
+    assert!(Evolving::<AccountV2>::decode(&[0]).is_err());
+    assert!(Evolving::<AccountV2>::decode(&[0xff, 0, 0, 0, 3, 0]).is_err());
+
+    // written without the version header
+    let mut legacy = Mapx::<u32, AccountV1>::new();
+    legacy.insert(&1, &AccountV1 { balance: 1 });
+    let new = pnk!(Mapx::<u32, Evolving<AccountV2>>::decode(&legacy.encode()));
+    let v = pnk!(new.get(&1));
+    assert_eq!(1, v.balance);
+    assert_eq!(0, v.nonce);
+}
@@ -0,0 +1,93 @@
+use ruc::*;
+use std::panic::{self, AssertUnwindSafe};
+use vsdb::{
+    migrate::{migrate_keys, migrate_mapx_keys},
+    vsdb_set_base_dir, Mapx, MapxOrd,
+};
+
+#[test]
+fn basic_cases() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let cnt = 1000;
+
+    let mut old: MapxOrd<u32, u32> = MapxOrd::new();
+    (0..cnt).for_each(|i| {
+        old.insert(&i, &i);
+    });
+
+    let new: MapxOrd<u64, u32> =
+        pnk!(migrate_keys(&mut old, |k| ((k as u64) << 32) | k as u64));
+    assert!(old.is_empty());
+    assert_eq!(cnt as usize, new.len());
+    new.iter().zip(0..cnt).for_each(|((k, v), i)| {
+        assert_eq!(k, ((i as u64) << 32) | i as u64);
+        assert_eq!(v, i);
+    });
+
+    let mut old: Mapx<u32, String> = Mapx::new();
+    (0..cnt).for_each(|i| {
+        old.insert(&i, &i.to_string());
+    });
+
+    let new: Mapx<String, String> = pnk!(migrate_mapx_keys(&mut old, |k| k.to_string()));
+    assert!(old.is_empty());
+    (0..cnt).for_each(|i| {
+        assert_eq!(new.get(&i.to_string()).unwrap(), i.to_string());
+    });
+}
+
+#[test]
+fn collision() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut old: MapxOrd<u32, u32> = MapxOrd::new();
+    (0..100).for_each(|i| {
+        old.insert(&i, &i);
+    });
+
+    assert!(migrate_keys(&mut old, |k| k / 2).is_err());
+
+    // the source is untouched
+    assert_eq!(100, old.len());
+    (0..100).for_each(|i| {
+        assert_eq!(old.get(&i).unwrap(), i);
+    });
+}
+
+#[test]
+fn interrupted() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut old: MapxOrd<u32, u32> = MapxOrd::new();
+    (0..100).for_each(|i| {
+        old.insert(&i, &i);
+    });
+
+    // stopped in the middle of the copying
+    assert!(panic::catch_unwind(AssertUnwindSafe(|| {
+        migrate_keys(&mut old, |k| {
+            assert_ne!(50, k);
+            k as u64
+        })
+    }))
+    .is_err());
+    assert_eq!(100, old.len());
+
+    // the partial instance is rolled back, and the migration starts over
+    let new: MapxOrd<u64, u32> = pnk!(migrate_keys(&mut old, |k| k as u64));
+    assert!(old.is_empty());
+    assert_eq!(100, new.len());
+    (0..100).for_each(|i| {
+        assert_eq!(new.get(&(i as u64)).unwrap(), i);
+    });
+}